@@ -0,0 +1,121 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt, stream};
+use rustls_acme::AcmeConfig;
+use rustls_acme::caches::DirCache;
+use rustls_acme::futures_rustls::server::TlsStream;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::compat::Compat;
+
+/// Connection served once its TLS handshake completed
+type TlsConnection = Compat<TlsStream<Compat<TcpStream>>>;
+
+/// Where and for which names certificates are requested
+pub struct AcmeSettings {
+    /// Names the certificate is issued for, the first being its subject
+    pub domains: Vec<String>,
+    /// Contacts registered with the ACME account, such as `mailto:ops@example.com`
+    pub contacts: Vec<String>,
+    /// Directory URL of the ACME server, Let's Encrypt or an internal CA
+    pub directory: String,
+    /// Where the account key and certificates are kept across restarts
+    pub cache_dir: PathBuf,
+}
+
+/// TLS listener serving a certificate it obtains and renews over ACME.
+///
+/// Certificates are validated with the TLS-ALPN-01 challenge on the listener
+/// itself, so the ACME server must reach it on port 443 of every domain. Until
+/// the first certificate is issued, or loaded from the cache, handshakes fail
+pub struct AcmeListener {
+    incoming: Pin<Box<dyn Stream<Item = io::Result<TlsConnection>> + Send>>,
+    local_addr: SocketAddr,
+}
+
+impl AcmeListener {
+    /// Serves TLS on `listener`, handshaking connections concurrently while
+    /// driving certificate orders and renewals in the background of `accept`
+    pub fn new(listener: TcpListener, settings: AcmeSettings) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let tcp = Box::pin(stream::unfold(listener, |listener| async move {
+            let accepted = listener.accept().await.map(|(stream, _)| stream);
+            Some((accepted, listener))
+        }));
+        let incoming = AcmeConfig::new(settings.domains)
+            .contact(settings.contacts)
+            .directory(settings.directory)
+            .cache(DirCache::new(settings.cache_dir))
+            .state()
+            .tokio_incoming(tcp, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+        Ok(AcmeListener {
+            incoming: Box::pin(incoming),
+            local_addr,
+        })
+    }
+}
+
+impl axum::serve::Listener for AcmeListener {
+    type Io = TlsConnection;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            match self.incoming.next().await {
+                Some(Ok(tls)) => {
+                    // Connections whose peer is already gone are dropped
+                    if let Ok(peer) = tls.get_ref().get_ref().0.get_ref().peer_addr() {
+                        return (tls, peer);
+                    }
+                }
+                Some(Err(e)) => {
+                    // Like axum's own listener, back off on errors such as running out of sockets
+                    eprintln!("Failed to accept a connection: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                }
+                None => unreachable!("the TCP listener never runs out of connections"),
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::serve::Listener;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_failed_handshakes_are_skipped() {
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = tcp.local_addr().unwrap();
+        let mut listener = AcmeListener::new(
+            tcp,
+            AcmeSettings {
+                domains: vec!["registry.example.com".to_string()],
+                contacts: Vec::new(),
+                // Nothing listens here, so no certificate is ever issued
+                directory: "http://127.0.0.1:9/directory".to_string(),
+                cache_dir: std::env::temp_dir()
+                    .join(format!("xolotl-acme-{}", uuid::Uuid::new_v4())),
+            },
+        )
+        .unwrap();
+        assert_eq!(listener.local_addr().unwrap(), address);
+
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), listener.accept())
+                .await
+                .is_err()
+        );
+    }
+}
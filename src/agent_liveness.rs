@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::InstanceId;
+use crate::model::instance_state::InstanceState;
+use crate::model::service_registry::{RegistryError, ServiceRegistry};
+use crate::registry::agent_hub::AgentHub;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Keeps instances held by connected agents alive and marks those of agents
+/// gone for longer than `grace` millis down at time `at`, returning the latter
+pub async fn check_liveness(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    agents: &Arc<RwLock<AgentHub>>,
+    at: u64,
+    grace: u64,
+) -> Vec<InstanceId> {
+    let mut agents = agents.write().await;
+    let liveness = agents.sweep(at, grace);
+    let mut registry = registry.write().await;
+
+    for id in &liveness.alive {
+        if let Err(RegistryError::NotFound) = registry.heartbeat_instance(id) {
+            agents.release(id);
+        }
+    }
+
+    let mut lost = Vec::new();
+    for id in liveness.lost {
+        match registry.set_state(&id, InstanceState::Down) {
+            Ok(_) => lost.push(id),
+            Err(RegistryError::NotFound) => {}
+            Err(e) => eprintln!("Failed to mark instance {} down: {:?}", id, e),
+        }
+    }
+    lost
+}
+
+/// Runs `check_liveness` periodically for the lifetime of the process
+pub fn spawn_agent_liveness(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    agents: Arc<RwLock<AgentHub>>,
+    clock: SharedClock,
+    grace: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for id in check_liveness(&registry, &agents, clock.now(), grace).await {
+                println!("Marked instance {} down after its agent disconnected", id);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::VirtualClock;
+    use crate::model::service_registry::{ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_disconnected_agent_instances_go_down() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry: Arc<RwLock<dyn ServiceRegistry>> = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let entry = ServiceEntry::new(
+            "worker".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        registry.write().await.register(entry.clone()).unwrap();
+
+        let agents = Arc::new(RwLock::new(AgentHub::new()));
+        let (connection, _) = agents.write().await.connect("node-1", None).unwrap();
+        agents.write().await.hold("node-1", [entry.id.clone()]);
+
+        clock.advance(Duration::from_secs(60));
+        assert!(
+            check_liveness(&registry, &agents, 1_060_000, 30_000)
+                .await
+                .is_empty()
+        );
+        assert_eq!(registry.read().await.list()[0].last_heartbeat, 1_060_000);
+
+        agents
+            .write()
+            .await
+            .disconnect("node-1", connection, 1_060_000);
+        assert!(
+            check_liveness(&registry, &agents, 1_080_000, 30_000)
+                .await
+                .is_empty()
+        );
+        assert_eq!(
+            check_liveness(&registry, &agents, 1_090_000, 30_000).await,
+            [entry.id]
+        );
+        assert_eq!(registry.read().await.list()[0].state, InstanceState::Down);
+    }
+}
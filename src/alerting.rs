@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::model::alert::{AlertEvaluator, AlertRule};
+use crate::model::clock::SharedClock;
+use crate::model::min_instances::MinimumWatcher;
+use crate::model::service_change::ServiceChangeWatcher;
+use crate::model::service_registry::{HealthPolicy, ServiceRegistry};
+use crate::notifier::{Notification, Notifier};
+use crate::registry::expectation_store::ExpectationStore;
+use crate::registry::profile_store::ProfileStore;
+
+const EVALUATION_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Alert rules and the notifiers every alert is sent to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct AlertingConfig {
+    #[serde(default)]
+    pub notifiers: Vec<Notifier>,
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+    /// Percentage of the silence allowed before an instance is unhealthy at which
+    /// notifiers are warned, 80 when not set
+    #[serde(default)]
+    pub heartbeat_warning_percent: Option<u8>,
+}
+
+impl AlertingConfig {
+    /// Loads rules and notifiers from a YAML or JSON file
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read alert rules {}: {}", path, e))?;
+        let config: AlertingConfig = serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse alert rules {}: {}", path, e))?;
+        if config
+            .heartbeat_warning_percent
+            .is_some_and(|percent| percent == 0 || percent >= 100)
+        {
+            return Err(format!(
+                "heartbeat_warning_percent in {} must be between 1 and 99",
+                path
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Sends a notification to every notifier, logging failures
+pub(crate) async fn notify(
+    http: &reqwest::Client,
+    notifiers: &[Notifier],
+    notification: Notification<'_>,
+) {
+    println!("{}", notification.summary());
+    for notifier in notifiers {
+        if let Err(e) = notifier.send(http, notification).await {
+            eprintln!("Failed to send notification: {}", e);
+        }
+    }
+}
+
+/// Evaluates the rules periodically for the lifetime of the process, sending
+/// every alert that starts or stops firing and every instance that is about to
+/// become unhealthy, becomes unhealthy or is removed to each notifier
+pub fn spawn_alerting(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    clock: SharedClock,
+    policy: HealthPolicy,
+    config: AlertingConfig,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut evaluator = AlertEvaluator::new(config.rules);
+        let mut watcher = ServiceChangeWatcher::default();
+        let mut ticker = tokio::time::interval(EVALUATION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let (entries, events) = {
+                let registry = registry.read().await;
+                (registry.list(), registry.events(watcher.last_event_index()))
+            };
+            let at = clock.now();
+
+            for alert in evaluator.evaluate(&entries, &policy, at) {
+                notify(&http, &config.notifiers, Notification::Alert(&alert)).await;
+            }
+            for change in watcher.observe(&entries, &events, &policy, at) {
+                notify(&http, &config.notifiers, Notification::Change(&change)).await;
+            }
+        }
+    })
+}
+
+/// Checks the expected services periodically for the lifetime of the process,
+/// sending every expectation that is violated or restored to each notifier
+pub fn spawn_expectations(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    expectations: Arc<RwLock<ExpectationStore>>,
+    clock: SharedClock,
+    policy: HealthPolicy,
+    notifiers: Vec<Notifier>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut ticker = tokio::time::interval(EVALUATION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let entries = registry.read().await.list();
+            let changes = expectations
+                .write()
+                .await
+                .observe(&entries, &policy, clock.now());
+            for change in changes {
+                notify(&http, &notifiers, Notification::Expectation(&change)).await;
+            }
+        }
+    })
+}
+
+/// Checks the minimum instances of every profile periodically for the lifetime
+/// of the process, sending every service that falls below or recovers to each notifier
+pub fn spawn_min_instances(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    profiles: Arc<RwLock<ProfileStore>>,
+    clock: SharedClock,
+    policy: HealthPolicy,
+    notifiers: Vec<Notifier>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut watcher = MinimumWatcher::default();
+        let mut ticker = tokio::time::interval(EVALUATION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let entries = registry.read().await.list();
+            let profiles = profiles.read().await.list();
+            for change in watcher.observe(&entries, &profiles, &policy, clock.now()) {
+                notify(&http, &notifiers, Notification::Minimum(&change)).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: AlertingConfig = serde_yaml::from_str(
+            r#"
+notifiers:
+  - type: webhook
+    url: http://localhost/hook
+rules:
+  - name: checkout-capacity
+    service_name: checkout
+    environment: prod
+    healthy_below: 2
+    for: 5m
+  - name: search-missing
+    service_name: search
+    environment: prod
+    instances_below: 1
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.notifiers.len(), 1);
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[1].for_millis, 0);
+    }
+}
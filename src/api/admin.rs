@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::admission::admission_routes;
+use crate::api::auth::RequireAdmin;
+use crate::api::chaos::chaos_routes;
+use crate::api::guardrails::guardrails_routes;
+use crate::api::handoff::handoff_routes;
+use crate::api::ndjson::{self, NDJSON};
+use crate::api::read_only::read_only_routes;
+use crate::api::tokens::tokens_routes;
+use crate::model::identifiers::InstanceId;
+use crate::model::service_registry::{RegistryReadHandle, ServiceEntry, ServiceRegistry};
+use crate::registry::resolve_cache::{ResolveCache, ResolveCacheStats};
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Json,
+    Ndjson,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum ImportMode {
+    #[default]
+    Merge,
+    Replace,
+}
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    mode: ImportMode,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize, Default)]
+struct ImportReport {
+    mode: ImportMode,
+    dry_run: bool,
+    created: usize,
+    updated: usize,
+    removed: usize,
+    errors: Vec<String>,
+}
+
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/export", get(export_registry))
+        .route("/import", post(import_registry))
+        .nest("/chaos", chaos_routes())
+        .nest("/admission", admission_routes())
+        .nest("/guardrails", guardrails_routes())
+        .nest("/read-only", read_only_routes())
+        .nest("/handoff", handoff_routes())
+        .nest("/tokens", tokens_routes())
+        .route("/resolve-cache", get(get_resolve_cache))
+}
+
+async fn get_resolve_cache(
+    _admin: RequireAdmin,
+    State(resolve_cache): State<Arc<ResolveCache>>,
+) -> Json<ResolveCacheStats> {
+    Json(resolve_cache.stats())
+}
+
+async fn export_registry(
+    _admin: RequireAdmin,
+    State(registry): State<RegistryReadHandle>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let registry = registry.read().await;
+    let entries = registry.list();
+
+    match query.format {
+        ExportFormat::Json => Json(entries).into_response(),
+        ExportFormat::Ndjson => ndjson::stream(entries),
+    }
+}
+
+async fn import_registry(
+    _admin: RequireAdmin,
+    State(registry): State<Arc<RwLock<dyn ServiceRegistry>>>,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, Json<ImportReport>) {
+    let mut report = ImportReport {
+        mode: query.mode,
+        dry_run: query.dry_run,
+        ..Default::default()
+    };
+
+    let is_ndjson = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(NDJSON));
+    let entries = match parse_entries(&body, is_ndjson) {
+        Ok(entries) => entries,
+        Err(error) => {
+            report.errors.push(error);
+            return (StatusCode::BAD_REQUEST, Json(report));
+        }
+    };
+
+    report.errors = validate_entries(&entries);
+    if !report.errors.is_empty() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(report));
+    }
+
+    let mut registry = registry.write().await;
+    let existing: HashSet<InstanceId> = registry.list().into_iter().map(|e| e.id).collect();
+    let incoming: HashSet<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+
+    report.updated = entries.iter().filter(|e| existing.contains(&e.id)).count();
+    report.created = entries.len() - report.updated;
+    if query.mode == ImportMode::Replace {
+        report.removed = existing
+            .iter()
+            .filter(|id| !incoming.contains(id.as_str()))
+            .count();
+    }
+
+    if query.dry_run {
+        return (StatusCode::OK, Json(report));
+    }
+
+    let to_remove: Vec<&InstanceId> = match query.mode {
+        ImportMode::Replace => existing.iter().collect(),
+        ImportMode::Merge => existing
+            .iter()
+            .filter(|id| incoming.contains(id.as_str()))
+            .collect(),
+    };
+    for id in to_remove {
+        let _ = registry.deregister_instance(id);
+    }
+    for entry in entries {
+        if let Err(e) = registry.register(entry) {
+            report.errors.push(format!("{:?}", e));
+        }
+    }
+
+    let status = if report.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    (status, Json(report))
+}
+
+fn parse_entries(body: &[u8], is_ndjson: bool) -> Result<Vec<ServiceEntry>, String> {
+    if !is_ndjson {
+        return serde_json::from_slice(body).map_err(|e| format!("Invalid JSON: {}", e));
+    }
+
+    let body = std::str::from_utf8(body).map_err(|e| format!("Invalid UTF-8: {}", e))?;
+    body.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line).map_err(|e| format!("Line {}: {}", number + 1, e))
+        })
+        .collect()
+}
+
+fn validate_entries(entries: &[ServiceEntry]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (position, entry) in entries.iter().enumerate() {
+        // Identifiers are already validated while parsing
+        if entry.address_str().trim().is_empty() {
+            errors.push(format!("Entry {}: address must not be empty", position));
+        }
+        if !seen.insert(entry.id.as_str()) {
+            errors.push(format!("Entry {}: duplicate id {}", position, entry.id));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_address::ServiceAddress;
+    use crate::model::service_registry::{HealthPolicy, RegistryReader, RegistryWriter, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request},
+    };
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn create_entry(name: &str, env: &str) -> ServiceEntry {
+        ServiceEntry::new(
+            name.parse().unwrap(),
+            env.parse().unwrap(),
+            format!("http://{}.{}:8080", name, env),
+            HashMap::new(),
+            now(),
+        )
+    }
+
+    async fn create_test_app(
+        entries: Vec<ServiceEntry>,
+    ) -> (Router, Arc<RwLock<InMemoryRegistry>>) {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for entry in entries {
+            registry.write().await.register(entry).unwrap();
+        }
+        let state = AppState::new(registry.clone(), HealthPolicy::default(), None);
+        (admin_routes().with_state(state), registry)
+    }
+
+    async fn send(app: Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn import_request(uri: &str, content_type: &str, body: String) -> Request<Body> {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("content-type", content_type)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_export_json() {
+        let entry = create_entry("payments", "prod");
+        let (app, _) = create_test_app(vec![entry.clone()]).await;
+
+        let request = Request::builder()
+            .uri("/export")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app, request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let exported: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(exported[0]["id"], entry.id.as_str());
+        assert_eq!(exported[0]["address"]["value"], "http://payments.prod:8080");
+    }
+
+    #[tokio::test]
+    async fn test_export_ndjson() {
+        let (app, _) =
+            create_test_app(vec![create_entry("a", "dev"), create_entry("b", "dev")]).await;
+
+        let request = Request::builder()
+            .uri("/export?format=ndjson")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(app, request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            assert!(serde_json::from_str::<ServiceEntry>(line).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_merge() {
+        let existing = create_entry("payments", "prod");
+        let mut updated = existing.clone();
+        updated.address =
+            crate::model::service_address::ServiceAddress::String("http://moved:9090".to_string());
+        let (app, registry) = create_test_app(vec![existing]).await;
+
+        let body = serde_json::to_string(&vec![updated, create_entry("search", "prod")]).unwrap();
+        let (status, body) = send(app, import_request("/import", "application/json", body)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let report: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["created"], 1);
+        assert_eq!(report["updated"], 1);
+        assert_eq!(report["removed"], 0);
+
+        let registry = registry.read().await;
+        assert_eq!(registry.list().len(), 2);
+        assert_eq!(
+            registry.resolve(&"payments".parse().unwrap(), &"prod".parse().unwrap())[0]
+                .address_str(),
+            "http://moved:9090"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_replace_ndjson() {
+        let (app, registry) = create_test_app(vec![create_entry("old", "dev")]).await;
+
+        let body = [create_entry("new", "dev"), create_entry("newer", "dev")]
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (status, body) = send(app, import_request("/import?mode=replace", NDJSON, body)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let report: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["created"], 2);
+        assert_eq!(report["removed"], 1);
+
+        let registry = registry.read().await;
+        assert!(
+            registry
+                .resolve(&"old".parse().unwrap(), &"dev".parse().unwrap())
+                .is_empty()
+        );
+        assert_eq!(registry.list().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_dry_run_does_not_mutate() {
+        let (app, registry) = create_test_app(vec![create_entry("old", "dev")]).await;
+
+        let body = serde_json::to_string(&vec![create_entry("new", "dev")]).unwrap();
+        let (status, body) = send(
+            app,
+            import_request(
+                "/import?mode=replace&dry_run=true",
+                "application/json",
+                body,
+            ),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let report: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["dry_run"], true);
+        assert_eq!(report["created"], 1);
+        assert_eq!(report["removed"], 1);
+
+        let registry = registry.read().await;
+        assert_eq!(
+            registry
+                .resolve(&"old".parse().unwrap(), &"dev".parse().unwrap())
+                .len(),
+            1
+        );
+        assert!(
+            registry
+                .resolve(&"new".parse().unwrap(), &"dev".parse().unwrap())
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_validation_errors() {
+        let (app, registry) = create_test_app(vec![]).await;
+
+        let mut invalid = create_entry("payments", "prod");
+        invalid.address = ServiceAddress::String(String::new());
+        let duplicate = create_entry("search", "prod");
+        let body = serde_json::to_string(&vec![invalid, duplicate.clone(), duplicate]).unwrap();
+
+        let (status, body) = send(app, import_request("/import", "application/json", body)).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let report: Value = serde_json::from_str(&body).unwrap();
+        let errors = report["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].as_str().unwrap().contains("address"));
+        assert!(errors[1].as_str().unwrap().contains("duplicate id"));
+        assert!(registry.read().await.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_malformed_body() {
+        let (app, _) = create_test_app(vec![]).await;
+
+        let (status, body) = send(
+            app,
+            import_request("/import", NDJSON, "{\"id\": 1}\n".to_string()),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let report: Value = serde_json::from_str(&body).unwrap();
+        assert!(report["errors"][0].as_str().unwrap().starts_with("Line 1"));
+    }
+
+    #[tokio::test]
+    async fn test_requires_admin_token() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(
+            registry,
+            HealthPolicy::default(),
+            Some("secret".to_string()),
+        );
+        let app = admin_routes().with_state(state);
+
+        let request = Request::builder()
+            .uri("/export")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send(app, request).await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
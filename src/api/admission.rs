@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::admission::{AdmissionController, AdmissionStats, WritePriority};
+use crate::model::service_registry::RegistryError;
+
+/// Shared write admission queue, only present when the server runs with `--max-concurrent-writes`
+pub type AdmissionHandle = Option<Arc<AdmissionController>>;
+
+pub fn admission_routes() -> Router<AppState> {
+    Router::new().route("/", get(get_admission))
+}
+
+async fn get_admission(
+    _admin: RequireAdmin,
+    State(admission): State<AdmissionHandle>,
+) -> Result<Json<AdmissionStats>, RegistryError> {
+    let admission = admission.ok_or_else(|| {
+        RegistryError::Conflict(
+            "Admission control is disabled, start the server with --max-concurrent-writes"
+                .to_string(),
+        )
+    })?;
+    Ok(Json(admission.stats()))
+}
+
+/// Priority of a request, `None` for reads and admin requests, which are never queued
+fn priority(method: &Method, path: &str) -> Option<WritePriority> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) || path.starts_with("/admin")
+    {
+        return None;
+    }
+    if *method == Method::DELETE
+        || path.starts_with("/services/heartbeat")
+        || (path.starts_with("/services/instances/") && path.ends_with("/state"))
+        || path.ends_with("/confirm-deletion")
+    {
+        return Some(WritePriority::High);
+    }
+    Some(WritePriority::Normal)
+}
+
+/// Middleware holding writes back while the registry is saturated, letting
+/// deregistrations and state changes through ahead of registrations
+pub async fn admit_writes(
+    State(admission): State<Arc<AdmissionController>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(priority) = priority(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    match admission.admit(priority).await {
+        Ok(_permit) => next.run(request).await,
+        Err(stats) => RegistryError::Overloaded(format!(
+            "{} writes are queued, retry later",
+            stats.queued_high + stats.queued_normal
+        ))
+        .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use crate::testing::TestServer;
+    use axum::http::StatusCode;
+    use serde_json::{Value, json};
+    use tokio::sync::RwLock;
+
+    #[test]
+    fn test_priority() {
+        assert_eq!(priority(&Method::GET, "/services"), None);
+        assert_eq!(priority(&Method::POST, "/admin/import"), None);
+        assert_eq!(
+            priority(&Method::POST, "/services"),
+            Some(WritePriority::Normal)
+        );
+        assert_eq!(
+            priority(&Method::DELETE, "/services/instances/a1"),
+            Some(WritePriority::High)
+        );
+        assert_eq!(
+            priority(&Method::PUT, "/services/instances/a1/state"),
+            Some(WritePriority::High)
+        );
+        assert_eq!(
+            priority(&Method::POST, "/services/checkout/confirm-deletion"),
+            Some(WritePriority::High)
+        );
+        assert_eq!(
+            priority(&Method::PUT, "/services/heartbeat/batch"),
+            Some(WritePriority::High)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_saturated_registry_rejects_registrations() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None).with_admission(1, 0);
+        let server = TestServer::with_state(state).await;
+        let client = reqwest::Client::new();
+
+        let controller = server.state().admission.clone().unwrap();
+        let running = controller.admit(WritePriority::High).await.unwrap();
+        let response = client
+            .post(format!("{}/services", server.url()))
+            .json(&json!({
+                "service_name": "payments",
+                "environment": "prod",
+                "address": "http://10.0.0.1:8080"
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["error_code"], "overloaded");
+        drop(running);
+
+        let stats: Value = client
+            .get(format!("{}/admin/admission", server.url()))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(stats["rejected"], 1);
+        assert_eq!(stats["in_flight"], 0);
+    }
+}
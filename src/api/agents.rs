@@ -0,0 +1,528 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{
+        Path, Query, State, WebSocketUpgrade,
+        ws::{Message, WebSocket},
+    },
+    http::StatusCode,
+    response::Response,
+    routing::{get, post},
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::api::AppState;
+use crate::api::auth::{AdminToken, Principal, RequireAdmin};
+use crate::model::agent::{AgentMessage, Directive, ServerMessage};
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::InstanceId;
+use crate::model::payload_signing::{SigningHandle, SigningSecrets};
+use crate::model::service_registry::{RegistryError, ServiceRegistry};
+use crate::registry::agent_hub::AgentHub;
+
+/// What keeps the instances an agent heartbeats alive
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum LivenessMode {
+    /// Their heartbeats, as for any other instance
+    #[default]
+    Heartbeat,
+    /// The channel itself, until it has been closed for longer than the grace period
+    Connection,
+}
+
+#[derive(Deserialize)]
+struct ConnectQuery {
+    node: String,
+    #[serde(default)]
+    liveness: LivenessMode,
+}
+
+pub fn agents_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_agents))
+        .route("/ws", get(connect_agent))
+        .route("/{node}/directives", post(send_directive))
+}
+
+async fn list_agents(State(agents): State<Arc<RwLock<AgentHub>>>) -> Json<Vec<String>> {
+    Json(agents.read().await.nodes())
+}
+
+async fn send_directive(
+    _admin: RequireAdmin,
+    State(agents): State<Arc<RwLock<AgentHub>>>,
+    Path(node): Path<String>,
+    Json(directive): Json<Directive>,
+) -> Result<StatusCode, RegistryError> {
+    agents.read().await.send(&node, directive)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Opens the channel of an agent. Once the server runs with an admin token, agents
+/// must present it or a caller token, and only that credential may take over the node
+#[allow(clippy::too_many_arguments)]
+async fn connect_agent(
+    State(registry): State<Arc<RwLock<dyn ServiceRegistry>>>,
+    State(agents): State<Arc<RwLock<AgentHub>>>,
+    State(clock): State<SharedClock>,
+    State(signing): State<SigningHandle>,
+    State(AdminToken(admin_token)): State<AdminToken>,
+    principal: Principal,
+    Query(query): Query<ConnectQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, RegistryError> {
+    if query.node.trim().is_empty() {
+        return Err(RegistryError::Validation(
+            "node must not be empty".to_string(),
+        ));
+    }
+    if admin_token.is_some() && principal == Principal::Anonymous {
+        return Err(RegistryError::Unauthorized(
+            "Agents must present an admin or caller token".to_string(),
+        ));
+    }
+    let identity = principal.label();
+    let (connection, directives) = agents
+        .write()
+        .await
+        .connect(&query.node, identity.clone())?;
+
+    let channel = AgentChannel {
+        node: query.node.clone(),
+        identity,
+        liveness: query.liveness,
+        connection,
+    };
+    let failed = (agents.clone(), query.node, connection, clock.clone());
+    Ok(upgrade
+        .on_failed_upgrade(move |_| {
+            let (agents, node, connection, clock) = failed;
+            tokio::spawn(async move {
+                agents
+                    .write()
+                    .await
+                    .disconnect(&node, connection, clock.now())
+            });
+        })
+        .on_upgrade(move |socket| {
+            serve_agent(
+                socket, channel, directives, registry, agents, clock, signing,
+            )
+        }))
+}
+
+/// Channel of a node, already registered with the hub
+struct AgentChannel {
+    node: String,
+    identity: Option<String>,
+    liveness: LivenessMode,
+    connection: u64,
+}
+
+/// Relays heartbeats from the agent and directives to it until either side closes
+async fn serve_agent(
+    mut socket: WebSocket,
+    channel: AgentChannel,
+    mut directives: UnboundedReceiver<Directive>,
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    agents: Arc<RwLock<AgentHub>>,
+    clock: SharedClock,
+    signing: SigningHandle,
+) {
+    let node = channel.node;
+
+    loop {
+        let replies = tokio::select! {
+            directive = directives.recv() => match directive {
+                Some(directive) => vec![ServerMessage::Directive(directive)],
+                // Replaced by a newer connection from the same node
+                None => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let (mut replies, accepted) = handle_message(&text, &registry, signing.as_deref()).await;
+                    if channel.liveness == LivenessMode::Connection {
+                        let (owned, foreign) = owned_by(&registry, accepted, &channel.identity).await;
+                        agents.write().await.hold(&node, owned);
+                        replies.extend(foreign.into_iter().map(|id| ServerMessage::Error {
+                            message: format!("Instance {} was registered with other credentials, it is not held by this node", id),
+                        }));
+                    }
+                    replies
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        for reply in replies {
+            let text = serde_json::to_string(&reply).expect("Server messages serialize");
+            if socket.send(Message::Text(text.into())).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    agents
+        .write()
+        .await
+        .disconnect(&node, channel.connection, clock.now());
+}
+
+/// Splits `instances` into those registered with the credential `identity` and the others
+async fn owned_by(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    instances: Vec<InstanceId>,
+    identity: &Option<String>,
+) -> (Vec<InstanceId>, Vec<InstanceId>) {
+    let registry = registry.read().await;
+    instances.into_iter().partition(|id| {
+        registry
+            .get(id)
+            .is_some_and(|entry| entry.registered_by == *identity)
+    })
+}
+
+/// Applies a message from an agent, returning the replies and the instances it refreshed.
+/// Socket messages carry no signature, so instances of services with a signing
+/// secret must be heartbeated over HTTP
+async fn handle_message(
+    text: &str,
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    signing: Option<&SigningSecrets>,
+) -> (Vec<ServerMessage>, Vec<InstanceId>) {
+    let message: AgentMessage = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = ServerMessage::Error {
+                message: e.to_string(),
+            };
+            return (vec![error], Vec::new());
+        }
+    };
+
+    match message {
+        AgentMessage::Heartbeat { instance_ids } => {
+            let mut registry = registry.write().await;
+            let mut accepted = Vec::new();
+            let mut replies = Vec::new();
+            for instance_id in instance_ids {
+                if let Some(secrets) = signing
+                    && let Some(entry) = registry.get(&instance_id)
+                    && secrets.requires_signature(&entry.service_name)
+                {
+                    replies.push(ServerMessage::Error {
+                        message: format!(
+                            "Heartbeats of {} must be signed, send them over HTTP",
+                            entry.service_name
+                        ),
+                    });
+                    continue;
+                }
+                match registry.heartbeat_instance(&instance_id) {
+                    Ok(()) => accepted.push(instance_id),
+                    // The registry lost the instance, e.g. after a restart or garbage collection
+                    Err(RegistryError::NotFound) => {
+                        replies.push(ServerMessage::Directive(Directive::Reregister {
+                            instance_id,
+                        }))
+                    }
+                    Err(e) => replies.push(ServerMessage::Error {
+                        message: e.to_string(),
+                    }),
+                }
+            }
+            replies.insert(
+                0,
+                ServerMessage::Ack {
+                    accepted: accepted.len(),
+                },
+            );
+            (replies, accepted)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::Clock;
+    use crate::model::service_registry::{HealthPolicy, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use crate::testing::TestServer;
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::{connect_async, tungstenite};
+
+    type Socket = tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >;
+
+    async fn connect(server: &TestServer, query: &str) -> Socket {
+        connect_with(server, query, None).await.unwrap()
+    }
+
+    async fn connect_with(
+        server: &TestServer,
+        query: &str,
+        token: Option<&str>,
+    ) -> Result<Socket, tungstenite::Error> {
+        let url = format!("{}/agents/ws?{}", server.url(), query).replacen("http", "ws", 1);
+        let mut request = url.into_client_request().unwrap();
+        if let Some(token) = token {
+            request.headers_mut().insert(
+                "authorization",
+                format!("Bearer {}", token).parse().unwrap(),
+            );
+        }
+        connect_async(request).await.map(|(socket, _)| socket)
+    }
+
+    fn refused_with(result: Result<Socket, tungstenite::Error>) -> StatusCode {
+        match result {
+            Err(tungstenite::Error::Http(response)) => response.status(),
+            _ => panic!("Expected the connection to be refused"),
+        }
+    }
+
+    async fn next_json(socket: &mut Socket) -> Value {
+        loop {
+            match socket.next().await.unwrap().unwrap() {
+                tungstenite::Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+                _ => continue,
+            }
+        }
+    }
+
+    async fn wait_for_nodes(server: &TestServer, expected: usize) {
+        for _ in 0..50 {
+            if server.state().agents.read().await.nodes().len() == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Expected {} connected agents", expected);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_over_socket() {
+        let server = TestServer::simulated().await;
+        let entry = ServiceEntry::new(
+            "worker".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        server
+            .registry()
+            .write()
+            .await
+            .register(entry.clone())
+            .unwrap();
+        server.advance(Duration::from_secs(10));
+
+        let mut socket = connect(&server, "node=node-1").await;
+        let heartbeat = json!({ "type": "heartbeat", "instance_ids": [entry.id, "deadbeef"] });
+        socket
+            .send(tungstenite::Message::text(heartbeat.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            next_json(&mut socket).await,
+            json!({ "type": "ack", "accepted": 1 })
+        );
+        assert_eq!(
+            next_json(&mut socket).await,
+            json!({ "type": "directive", "directive": "reregister", "instance_id": "deadbeef" })
+        );
+        let now = server.clock().unwrap().now();
+        assert_eq!(server.registry().read().await.list()[0].last_heartbeat, now);
+    }
+
+    #[tokio::test]
+    async fn test_signed_services_are_not_heartbeated_over_socket() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None)
+            .with_signing_secrets("sensors:s3cret".parse().unwrap());
+        let server = TestServer::with_state(state).await;
+        let entry = ServiceEntry::new(
+            "sensors".parse().unwrap(),
+            "edge".parse().unwrap(),
+            "http://10.0.0.7:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        server
+            .registry()
+            .write()
+            .await
+            .register(entry.clone())
+            .unwrap();
+
+        let mut socket = connect(&server, "node=node-1").await;
+        let heartbeat = json!({ "type": "heartbeat", "instance_ids": [entry.id] });
+        socket
+            .send(tungstenite::Message::text(heartbeat.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            next_json(&mut socket).await,
+            json!({ "type": "ack", "accepted": 0 })
+        );
+        assert_eq!(next_json(&mut socket).await["type"], "error");
+        assert_eq!(
+            server.registry().read().await.list()[0].last_heartbeat,
+            entry.last_heartbeat
+        );
+    }
+
+    #[tokio::test]
+    async fn test_directives_reach_connected_agent() {
+        let server = TestServer::start().await;
+        let client = reqwest::Client::new();
+        let mut socket = connect(&server, "node=node-1").await;
+        wait_for_nodes(&server, 1).await;
+
+        let nodes: Value = client
+            .get(format!("{}/agents", server.url()))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(nodes, json!(["node-1"]));
+
+        let drain = json!({ "directive": "drain", "instance_id": "a1b2c3" });
+        let response = client
+            .post(format!("{}/agents/node-1/directives", server.url()))
+            .json(&drain)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            next_json(&mut socket).await,
+            json!({ "type": "directive", "directive": "drain", "instance_id": "a1b2c3" })
+        );
+
+        let response = client
+            .post(format!("{}/agents/node-2/directives", server.url()))
+            .json(&drain)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        socket.close(None).await.unwrap();
+        wait_for_nodes(&server, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_connection_liveness_holds_instances() {
+        let server = TestServer::simulated().await;
+        let entry = ServiceEntry::new(
+            "worker".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        server
+            .registry()
+            .write()
+            .await
+            .register(entry.clone())
+            .unwrap();
+
+        let mut socket = connect(&server, "node=node-1&liveness=connection").await;
+        let heartbeat = json!({ "type": "heartbeat", "instance_ids": [entry.id] });
+        socket
+            .send(tungstenite::Message::text(heartbeat.to_string()))
+            .await
+            .unwrap();
+        next_json(&mut socket).await;
+
+        let agents = &server.state().agents;
+        let now = server.clock().unwrap().now();
+        let liveness = agents.write().await.sweep(now, 30_000);
+        assert_eq!(liveness.alive, std::slice::from_ref(&entry.id));
+
+        socket.close(None).await.unwrap();
+        wait_for_nodes(&server, 0).await;
+        let liveness = agents.write().await.sweep(now + 30_000, 30_000);
+        assert_eq!(liveness.lost, [entry.id]);
+    }
+
+    #[tokio::test]
+    async fn test_agents_hold_only_their_own_instances() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let callers = HashMap::from([
+            ("worker".to_string(), "w-token".to_string()),
+            ("intruder".to_string(), "i-token".to_string()),
+        ]);
+        let state = AppState::new(registry, HealthPolicy::default(), Some("root".to_string()))
+            .with_callers(&callers);
+        let server = TestServer::with_state(state).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/services", server.url()))
+            .bearer_auth("w-token")
+            .json(&json!({
+                "service_name": "worker",
+                "environment": "prod",
+                "address": "http://10.0.0.1:8080",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let owned = server.registry().read().await.list()[0].clone();
+        assert_eq!(owned.registered_by.as_deref(), Some("caller:worker"));
+        let foreign = ServiceEntry::new(
+            "billing".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.2:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        server
+            .registry()
+            .write()
+            .await
+            .register(foreign.clone())
+            .unwrap();
+
+        let query = "node=node-1&liveness=connection";
+        let anonymous = connect_with(&server, query, None).await;
+        assert_eq!(refused_with(anonymous), StatusCode::UNAUTHORIZED);
+
+        let mut socket = connect_with(&server, query, Some("w-token")).await.unwrap();
+        let heartbeat = json!({ "type": "heartbeat", "instance_ids": [owned.id, foreign.id] });
+        socket
+            .send(tungstenite::Message::text(heartbeat.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_json(&mut socket).await,
+            json!({ "type": "ack", "accepted": 2 })
+        );
+        assert_eq!(next_json(&mut socket).await["type"], "error");
+        let liveness = server.state().agents.write().await.sweep(0, 30_000);
+        assert_eq!(liveness.alive, [owned.id]);
+
+        // Another credential cannot take the node over
+        let intruder = connect_with(&server, query, Some("i-token")).await;
+        assert_eq!(refused_with(intruder), StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{StatusCode, header::AUTHORIZATION, request::Parts},
+};
+use ring::digest::{SHA256, digest};
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::impersonation::Impersonation;
+use crate::model::service_registry::RegistryError;
+use crate::model::spiffe::SpiffeId;
+use crate::registry::token_store::TokenStore;
+
+/// Token operators must present for administrative actions, no token means they are open
+#[derive(Clone, Default)]
+pub struct AdminToken(pub Option<Arc<str>>);
+
+/// Tokens identifying calling services, mapped to the name intentions know them by
+#[derive(Clone, Default)]
+pub struct CallerTokens(pub Arc<HashMap<String, String>>);
+
+impl CallerTokens {
+    /// Builds the directory from caller names and their tokens
+    pub fn new(callers: &HashMap<String, String>) -> Self {
+        let tokens = callers
+            .iter()
+            .map(|(name, token)| (token.clone(), name.clone()))
+            .collect();
+        CallerTokens(Arc::new(tokens))
+    }
+}
+
+pub(crate) fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Name of the service making the request, when it presented a caller token
+/// or an admin acts on its behalf with `X-Act-As`
+pub struct Caller(pub Option<String>);
+
+/// Header in which the proxy terminating mTLS forwards the client certificate,
+/// unset when SPIFFE identities are not taken from requests
+#[derive(Clone, Default)]
+pub struct SpiffeHeader(pub Option<Arc<str>>);
+
+/// SPIFFE ID of the workload making the request, as forwarded by the proxy
+/// terminating its mTLS connection
+pub struct PeerIdentity(pub Option<SpiffeId>);
+
+impl<S> FromRequestParts<S> for PeerIdentity
+where
+    SpiffeHeader: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = RegistryError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let SpiffeHeader(header) = SpiffeHeader::from_ref(state);
+        let Some(value) = header.and_then(|header| parts.headers.get(&*header).cloned()) else {
+            return Ok(PeerIdentity(None));
+        };
+        let value = value.to_str().map_err(|_| {
+            RegistryError::Validation("The forwarded client certificate is not ASCII".to_string())
+        })?;
+        SpiffeId::from_forwarded(value)
+            .map(|id| PeerIdentity(Some(id)))
+            .map_err(RegistryError::Validation)
+    }
+}
+
+impl<S> FromRequestParts<S> for Caller
+where
+    CallerTokens: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(impersonation) = parts.extensions.get::<Impersonation>() {
+            return Ok(Caller(Some(impersonation.acting_as.clone())));
+        }
+        let CallerTokens(tokens) = CallerTokens::from_ref(state);
+        Ok(Caller(
+            bearer_token(parts).and_then(|token| tokens.get(token).cloned()),
+        ))
+    }
+}
+
+/// Credential a request was made with, for tying what it creates to whoever created it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Principal {
+    /// No admin or caller token, only possible on servers without `--admin-token`
+    /// or for requests that leave their token off
+    Anonymous,
+    /// The admin token or one issued through `/admin/tokens`
+    Admin,
+    /// A caller token, by the name intentions know the service by
+    Caller(String),
+}
+
+impl Principal {
+    /// How the principal is recorded on what it creates, `None` when anonymous
+    pub fn label(&self) -> Option<String> {
+        match self {
+            Principal::Anonymous => None,
+            Principal::Admin => Some("admin".to_string()),
+            Principal::Caller(name) => Some(format!("caller:{}", name)),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Principal
+where
+    AdminToken: FromRef<S>,
+    CallerTokens: FromRef<S>,
+    Arc<RwLock<TokenStore>>: FromRef<S>,
+    SharedClock: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(impersonation) = parts.extensions.get::<Impersonation>() {
+            return Ok(Principal::Caller(impersonation.acting_as.clone()));
+        }
+        let Some(token) = bearer_token(parts) else {
+            return Ok(Principal::Anonymous);
+        };
+        if is_admin_token(token, state).await {
+            return Ok(Principal::Admin);
+        }
+        let CallerTokens(callers) = CallerTokens::from_ref(state);
+        Ok(callers
+            .get(token)
+            .map_or(Principal::Anonymous, |name| Principal::Caller(name.clone())))
+    }
+}
+
+/// Compares a presented token with the expected one in time independent of where
+/// they differ, by comparing their digests without stopping at the first difference
+pub(crate) fn tokens_match(token: &str, expected: &str) -> bool {
+    let token = digest(&SHA256, token.as_bytes());
+    let expected = digest(&SHA256, expected.as_bytes());
+    token
+        .as_ref()
+        .iter()
+        .zip(expected.as_ref())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// Whether `token` is the admin token or a valid issued one
+async fn is_admin_token<S>(token: &str, state: &S) -> bool
+where
+    AdminToken: FromRef<S>,
+    Arc<RwLock<TokenStore>>: FromRef<S>,
+    SharedClock: FromRef<S>,
+{
+    let AdminToken(expected) = AdminToken::from_ref(state);
+    let Some(expected) = expected else {
+        return false;
+    };
+    if tokens_match(token, &expected) {
+        return true;
+    }
+    let tokens = Arc::<RwLock<TokenStore>>::from_ref(state);
+    let now = SharedClock::from_ref(state).now();
+    tokens.write().await.authenticate(token, now)
+}
+
+/// Extractor guarding administrative handlers behind `Authorization: Bearer <admin token>`,
+/// either the token the server started with or one issued through `/admin/tokens`
+pub struct RequireAdmin;
+
+impl<S> FromRequestParts<S> for RequireAdmin
+where
+    AdminToken: FromRef<S>,
+    Arc<RwLock<TokenStore>>: FromRef<S>,
+    SharedClock: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AdminToken(expected) = AdminToken::from_ref(state);
+        if expected.is_none() {
+            return Ok(RequireAdmin);
+        }
+
+        match bearer_token(parts) {
+            Some(token) if is_admin_token(token, state).await => Ok(RequireAdmin),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::system_clock;
+    use axum::http::Request;
+
+    #[derive(Clone)]
+    struct TestState {
+        admin_token: AdminToken,
+        tokens: Arc<RwLock<TokenStore>>,
+        clock: SharedClock,
+    }
+
+    impl FromRef<TestState> for AdminToken {
+        fn from_ref(state: &TestState) -> Self {
+            state.admin_token.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Arc<RwLock<TokenStore>> {
+        fn from_ref(state: &TestState) -> Self {
+            state.tokens.clone()
+        }
+    }
+
+    impl FromRef<TestState> for SharedClock {
+        fn from_ref(state: &TestState) -> Self {
+            state.clock.clone()
+        }
+    }
+
+    fn test_state(expected: Option<&str>) -> TestState {
+        TestState {
+            admin_token: AdminToken(expected.map(Arc::from)),
+            tokens: Arc::default(),
+            clock: system_clock(),
+        }
+    }
+
+    async fn check_with(state: &TestState, header: Option<&str>) -> Result<(), StatusCode> {
+        let mut builder = Request::builder();
+        if let Some(header) = header {
+            builder = builder.header(AUTHORIZATION, header);
+        }
+        let (mut parts, _) = builder.body(()).unwrap().into_parts();
+
+        RequireAdmin::from_request_parts(&mut parts, state)
+            .await
+            .map(|_| ())
+    }
+
+    async fn check(expected: Option<&str>, header: Option<&str>) -> Result<(), StatusCode> {
+        check_with(&test_state(expected), header).await
+    }
+
+    #[tokio::test]
+    async fn test_issued_token() {
+        let state = test_state(Some("secret"));
+        let issued = state.tokens.write().await.issue("ci", None, 0);
+        let header = format!("Bearer {}", issued.secret);
+        assert!(check_with(&state, Some(&header)).await.is_ok());
+
+        state
+            .tokens
+            .write()
+            .await
+            .revoke(&issued.token.id, 0)
+            .unwrap();
+        assert_eq!(
+            check_with(&state, Some(&header)).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_caller_from_token() {
+        let state = CallerTokens::new(&HashMap::from([("web".to_string(), "w-token".to_string())]));
+        let identify = |header: &str| {
+            let (mut parts, _) = Request::builder()
+                .header(AUTHORIZATION, header)
+                .body(())
+                .unwrap()
+                .into_parts();
+            let state = state.clone();
+            async move {
+                let Ok(Caller(name)) = Caller::from_request_parts(&mut parts, &state).await;
+                name
+            }
+        };
+
+        assert_eq!(identify("Bearer w-token").await.as_deref(), Some("web"));
+        assert_eq!(identify("Bearer other").await, None);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[tokio::test]
+    async fn test_open_without_token() {
+        assert!(check(None, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_valid_token() {
+        assert!(check(Some("secret"), Some("Bearer secret")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_missing_or_wrong_token() {
+        assert_eq!(
+            check(Some("secret"), None).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check(Some("secret"), Some("Bearer nope")).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            check(Some("secret"), Some("secret")).await,
+            Err(StatusCode::UNAUTHORIZED)
+        );
+    }
+}
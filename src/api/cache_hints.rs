@@ -0,0 +1,149 @@
+use axum::{
+    http::{HeaderValue, header::CACHE_CONTROL},
+    response::{IntoResponseParts, ResponseParts},
+};
+use serde::Serialize;
+
+use crate::model::identifiers::ServiceName;
+use crate::model::service_registry::RegistryReader;
+use crate::registry::profile_store::ProfileStore;
+
+/// Carries the registry index the response reflects, so clients can tell
+/// whether a cached answer is still current
+pub const XOLOTL_INDEX: &str = "x-xolotl-index";
+
+/// Seconds clients may cache discovery results of services without a `cache_ttl_seconds` profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheTtl(pub u64);
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        CacheTtl(5)
+    }
+}
+
+/// How long a discovery response is safe to cache and which modification it reflects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CacheHints {
+    /// Seconds the response is safe to cache
+    pub max_age: u64,
+    /// Index of the last registry event the response reflects
+    pub index: u64,
+}
+
+impl CacheHints {
+    /// Hints for the instances of one service
+    pub fn for_service(
+        registry: &dyn RegistryReader,
+        profiles: &ProfileStore,
+        default: CacheTtl,
+        service_name: &ServiceName,
+    ) -> Self {
+        CacheHints {
+            max_age: profiles
+                .get(service_name)
+                .and_then(|profile| profile.cache_ttl_seconds)
+                .unwrap_or(default.0),
+            index: registry.modify_index(service_name),
+        }
+    }
+
+    /// Hints for a response covering the whole registry
+    pub fn for_registry(registry: &dyn RegistryReader, default: CacheTtl) -> Self {
+        CacheHints {
+            max_age: default.0,
+            index: registry.last_index(),
+        }
+    }
+
+    /// Hints for a response combining both, valid as long as either part is
+    pub fn combine(self, other: CacheHints) -> Self {
+        CacheHints {
+            max_age: self.max_age.min(other.max_age),
+            index: self.index.max(other.index),
+        }
+    }
+}
+
+impl IntoResponseParts for CacheHints {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut parts: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let headers = parts.headers_mut();
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_str(&format!("max-age={}", self.max_age))
+                .expect("Formatted numbers are valid header values"),
+        );
+        headers.insert(XOLOTL_INDEX, HeaderValue::from(self.index));
+        Ok(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_profile::ServiceProfile;
+    use crate::model::service_registry::{RegistryWriter, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_hints() {
+        let mut registry = InMemoryRegistry::new();
+        for name in ["payments", "search"] {
+            registry
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    "prod".parse().unwrap(),
+                    "http://10.0.0.1:8080".to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+        let mut profiles = ProfileStore::new();
+        profiles.put(
+            "payments".parse().unwrap(),
+            ServiceProfile {
+                cache_ttl_seconds: Some(60),
+                ..ServiceProfile::default()
+            },
+        );
+
+        let payments = CacheHints::for_service(
+            &registry,
+            &profiles,
+            CacheTtl::default(),
+            &"payments".parse().unwrap(),
+        );
+        let search = CacheHints::for_service(
+            &registry,
+            &profiles,
+            CacheTtl::default(),
+            &"search".parse().unwrap(),
+        );
+
+        assert_eq!(
+            payments,
+            CacheHints {
+                max_age: 60,
+                index: 1
+            }
+        );
+        assert_eq!(
+            payments.combine(search),
+            CacheHints {
+                max_age: 5,
+                index: 2
+            }
+        );
+        assert_eq!(
+            CacheHints::for_registry(&registry, CacheTtl(30)),
+            CacheHints {
+                max_age: 30,
+                index: 2
+            }
+        );
+    }
+}
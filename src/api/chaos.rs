@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Request, State},
+    http::{Method, StatusCode, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::chaos::{ChaosController, ChaosRule};
+use crate::model::service_registry::RegistryError;
+
+/// Shared fault injector, only present when the server runs with `--chaos`
+pub type ChaosHandle = Option<Arc<RwLock<ChaosController>>>;
+
+#[derive(Deserialize)]
+struct ChaosRuleRequest {
+    route: String,
+    #[serde(flatten)]
+    rule: ChaosRule,
+}
+
+#[derive(Serialize)]
+struct ChaosResponse {
+    seed: u64,
+    rules: BTreeMap<String, ChaosRule>,
+}
+
+pub fn chaos_routes() -> Router<AppState> {
+    Router::new().route("/", get(get_chaos).put(put_chaos_rule).delete(clear_chaos))
+}
+
+fn enabled(chaos: ChaosHandle) -> Result<Arc<RwLock<ChaosController>>, RegistryError> {
+    chaos.ok_or_else(|| {
+        RegistryError::Conflict("Chaos mode is disabled, start the server with --chaos".to_string())
+    })
+}
+
+async fn get_chaos(
+    _admin: RequireAdmin,
+    State(chaos): State<ChaosHandle>,
+) -> Result<Json<ChaosResponse>, RegistryError> {
+    let chaos = enabled(chaos)?;
+    let chaos = chaos.read().await;
+
+    Ok(Json(ChaosResponse {
+        seed: chaos.seed(),
+        rules: chaos.rules().clone(),
+    }))
+}
+
+async fn put_chaos_rule(
+    _admin: RequireAdmin,
+    State(chaos): State<ChaosHandle>,
+    Json(payload): Json<ChaosRuleRequest>,
+) -> Result<Json<String>, RegistryError> {
+    let chaos = enabled(chaos)?;
+    if !payload.route.starts_with('/') {
+        return Err(RegistryError::Validation(
+            "route must start with '/'".to_string(),
+        ));
+    }
+    payload.rule.validate().map_err(RegistryError::Validation)?;
+
+    let message = format!("Injecting faults below {}", payload.route);
+    chaos.write().await.set_rule(payload.route, payload.rule);
+
+    Ok(Json(message))
+}
+
+async fn clear_chaos(
+    _admin: RequireAdmin,
+    State(chaos): State<ChaosHandle>,
+) -> Result<Json<String>, RegistryError> {
+    enabled(chaos)?.write().await.clear();
+
+    Ok(Json("Stopped injecting faults".to_string()))
+}
+
+/// Middleware delaying, failing or replaying responses according to the chaos rules.
+/// Admin routes are left alone so faults can always be turned off.
+pub async fn inject_faults(
+    State(chaos): State<Arc<RwLock<ChaosController>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    if path.starts_with("/admin") {
+        return next.run(request).await;
+    }
+
+    let Some(decision) = chaos.write().await.decide(&path) else {
+        return next.run(request).await;
+    };
+    tokio::time::sleep(decision.latency).await;
+
+    if decision.fail {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error_code": "internal_error", "message": "Injected fault" })),
+        )
+            .into_response();
+    }
+
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let uri = request.uri().to_string();
+    if decision.stale
+        && let Some(body) = chaos.read().await.recall(&uri)
+    {
+        return ([(CONTENT_TYPE, "application/json")], body.to_vec()).into_response();
+    }
+
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    chaos.write().await.remember(&uri, &bytes);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_app;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn send_request(
+        app: Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn create_test_app(seed: Option<u64>) -> Router {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let mut state = AppState::new(registry, HealthPolicy::default(), None);
+        if let Some(seed) = seed {
+            state = state.with_chaos(seed);
+        }
+        create_app(state)
+    }
+
+    #[tokio::test]
+    async fn test_chaos_disabled() {
+        let app = create_test_app(None);
+
+        let (status, response) = send_request(app, Method::GET, "/admin/chaos", None).await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(response["error_code"], "conflict");
+    }
+
+    #[tokio::test]
+    async fn test_injected_errors_and_stale_reads() {
+        let app = create_test_app(Some(42));
+
+        let rule = json!({ "route": "/services", "error_rate": 1.0 });
+        let (status, _) = send_request(app.clone(), Method::PUT, "/admin/chaos", Some(rule)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, response) = send_request(app.clone(), Method::GET, "/services", None).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response["message"], "Injected fault");
+
+        // Other routes are untouched
+        let (status, _) = send_request(app.clone(), Method::GET, "/events", None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let rule = json!({ "route": "/services", "stale_rate": 1.0 });
+        send_request(app.clone(), Method::PUT, "/admin/chaos", Some(rule)).await;
+        let (_, response) = send_request(app.clone(), Method::GET, "/services", None).await;
+        assert_eq!(response, json!([]));
+
+        let registration = json!({
+            "service_name": "payments",
+            "environment": "prod",
+            "address": "http://localhost:8080"
+        });
+        let (status, _) =
+            send_request(app.clone(), Method::POST, "/services", Some(registration)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The list is served from before the registration
+        let (_, response) = send_request(app.clone(), Method::GET, "/services", None).await;
+        assert_eq!(response, json!([]));
+
+        let (status, _) = send_request(app.clone(), Method::DELETE, "/admin/chaos", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, response) = send_request(app, Method::GET, "/services", None).await;
+        assert_eq!(response.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_rule() {
+        let app = create_test_app(Some(42));
+
+        let rule = json!({ "route": "/services", "error_rate": 2.0 });
+        let (status, _) = send_request(app, Method::PUT, "/admin/chaos", Some(rule)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
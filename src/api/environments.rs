@@ -0,0 +1,483 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Response,
+    routing::{get, post, put},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::api::services::{DeregistrationChecks, guard_deregistration};
+use crate::api::tabular::Tabular;
+use crate::model::clock::SharedClock;
+use crate::model::environment::{EnvironmentFreeze, EnvironmentRecord, EnvironmentStatus};
+use crate::model::guardrail::Guardrail;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{
+    HealthPolicy, RegistryError, RegistryReadHandle, RegistryReader, ServiceRegistry,
+};
+use crate::registry::environment_store::EnvironmentStore;
+use crate::registry::profile_store::ProfileStore;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentRequest {
+    name: Environment,
+    /// Seconds after which the environment is archived and its instances removed
+    ttl_seconds: Option<u64>,
+    /// Unix time in millis at which the environment expires, instead of `ttl_seconds`
+    expires_at: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FreezeRequest {
+    /// Shown in the errors of refused writes, e.g. an incident or change ticket
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DeleteQuery {
+    #[serde(default)]
+    cascade: bool,
+    /// Removes services of the environment even below the minimum instances of their profile
+    #[serde(default)]
+    force: bool,
+}
+
+/// Columns of the environment listing rendered as CSV
+const ENVIRONMENT_COLUMNS: &[&str] = &[
+    "name",
+    "declared",
+    "instances",
+    "status",
+    "created_at",
+    "expires_at",
+    "archived_at",
+];
+
+#[derive(Serialize)]
+struct EnvironmentResponse {
+    name: Environment,
+    /// `false` for environments that only exist because instances were registered in them
+    declared: bool,
+    instances: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<EnvironmentStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frozen: Option<EnvironmentFreeze>,
+}
+
+impl EnvironmentResponse {
+    fn new(name: Environment, record: Option<&EnvironmentRecord>, instances: usize) -> Self {
+        EnvironmentResponse {
+            name,
+            declared: record.is_some(),
+            instances,
+            status: record.map(|record| record.status),
+            created_at: record.map(|record| record.created_at),
+            expires_at: record.and_then(|record| record.expires_at),
+            archived_at: record.and_then(|record| record.archived_at),
+            frozen: None,
+        }
+    }
+
+    fn with_freeze(mut self, freeze: Option<&EnvironmentFreeze>) -> Self {
+        self.frozen = freeze.cloned();
+        self
+    }
+}
+
+pub fn environments_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_environments).post(create_environment))
+        .route("/{name}", get(get_environment).delete(delete_environment))
+        .route("/{name}/archive", post(archive_environment))
+        .route(
+            "/{name}/freeze",
+            put(freeze_environment).delete(unfreeze_environment),
+        )
+}
+
+/// Number of instances in every environment that has any
+fn instance_counts(registry: &dyn RegistryReader) -> BTreeMap<Environment, usize> {
+    let mut counts = BTreeMap::new();
+    for entry in registry.list() {
+        *counts.entry(entry.environment).or_default() += 1;
+    }
+    counts
+}
+
+/// Services with instances in `environment`
+pub(crate) fn services_in(
+    registry: &dyn RegistryReader,
+    environment: &Environment,
+) -> BTreeSet<ServiceName> {
+    registry
+        .list()
+        .into_iter()
+        .filter(|entry| &entry.environment == environment)
+        .map(|entry| entry.service_name)
+        .collect()
+}
+
+async fn list_environments(
+    State(registry): State<RegistryReadHandle>,
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    accepted: Tabular,
+) -> Response {
+    let counts = instance_counts(&*registry.read().await);
+    let environments = environments.read().await;
+
+    let names: BTreeSet<Environment> = environments
+        .list()
+        .into_iter()
+        .map(|record| record.name)
+        .chain(counts.keys().cloned())
+        .chain(environments.frozen().cloned())
+        .collect();
+    let rows: Vec<EnvironmentResponse> = names
+        .into_iter()
+        .map(|name| {
+            let instances = counts.get(&name).copied().unwrap_or_default();
+            let record = environments.get(&name);
+            let freeze = environments.freeze_of(&name);
+            EnvironmentResponse::new(name, record, instances).with_freeze(freeze)
+        })
+        .collect();
+    accepted.render(&rows, ENVIRONMENT_COLUMNS)
+}
+
+async fn get_environment(
+    State(registry): State<RegistryReadHandle>,
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    Path(name): Path<Environment>,
+) -> Result<Json<EnvironmentResponse>, RegistryError> {
+    let instances = instance_counts(&*registry.read().await)
+        .get(&name)
+        .copied()
+        .unwrap_or_default();
+    let environments = environments.read().await;
+    let record = environments.get(&name);
+    let freeze = environments.freeze_of(&name);
+    if record.is_none() && freeze.is_none() && instances == 0 {
+        return Err(RegistryError::NotFound);
+    }
+    Ok(Json(
+        EnvironmentResponse::new(name, record, instances).with_freeze(freeze),
+    ))
+}
+
+async fn create_environment(
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    State(clock): State<SharedClock>,
+    Json(payload): Json<EnvironmentRequest>,
+) -> Result<(StatusCode, Json<EnvironmentResponse>), RegistryError> {
+    let at = clock.now();
+    if payload.ttl_seconds == Some(0) {
+        return Err(RegistryError::Validation(
+            "ttl_seconds must be greater than zero".to_string(),
+        ));
+    }
+    if payload.ttl_seconds.is_some_and(|seconds| {
+        seconds
+            .checked_mul(1000)
+            .and_then(|ttl| at.checked_add(ttl))
+            .is_none()
+    }) {
+        return Err(RegistryError::Validation(
+            "ttl_seconds is out of range".to_string(),
+        ));
+    }
+    let mut record = EnvironmentRecord::new(payload.name, payload.ttl_seconds, at);
+    match (payload.ttl_seconds, payload.expires_at) {
+        (Some(_), Some(_)) => {
+            return Err(RegistryError::Validation(
+                "Give either ttl_seconds or expires_at, not both".to_string(),
+            ));
+        }
+        (None, Some(expires_at)) if expires_at <= at => {
+            return Err(RegistryError::Validation(
+                "expires_at must be in the future".to_string(),
+            ));
+        }
+        (None, Some(expires_at)) => record = record.with_expiry(expires_at),
+        _ => {}
+    }
+    environments.write().await.create(record.clone())?;
+    Ok((
+        StatusCode::CREATED,
+        Json(EnvironmentResponse::new(
+            record.name.clone(),
+            Some(&record),
+            0,
+        )),
+    ))
+}
+
+async fn archive_environment(
+    _admin: RequireAdmin,
+    State(registry): State<RegistryReadHandle>,
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    State(clock): State<SharedClock>,
+    Path(name): Path<Environment>,
+) -> Result<Json<EnvironmentResponse>, RegistryError> {
+    let record = environments.write().await.archive(&name, clock.now())?;
+    let instances = instance_counts(&*registry.read().await)
+        .get(&name)
+        .copied()
+        .unwrap_or_default();
+    Ok(Json(EnvironmentResponse::new(
+        name,
+        Some(&record),
+        instances,
+    )))
+}
+
+/// Refuses registrations and deregistrations in an environment until it is unfrozen,
+/// the environment does not need to be declared
+async fn freeze_environment(
+    _admin: RequireAdmin,
+    State(registry): State<RegistryReadHandle>,
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    State(clock): State<SharedClock>,
+    Path(name): Path<Environment>,
+    Json(payload): Json<FreezeRequest>,
+) -> Json<EnvironmentResponse> {
+    let freeze = EnvironmentFreeze {
+        frozen_at: clock.now(),
+        reason: payload.reason,
+    };
+    let mut environments = environments.write().await;
+    environments.freeze(name.clone(), freeze);
+    let instances = instance_counts(&*registry.read().await)
+        .get(&name)
+        .copied()
+        .unwrap_or_default();
+    let record = environments.get(&name);
+    let freeze = environments.freeze_of(&name);
+    Json(EnvironmentResponse::new(name, record, instances).with_freeze(freeze))
+}
+
+async fn unfreeze_environment(
+    _admin: RequireAdmin,
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    Path(name): Path<Environment>,
+) -> Result<StatusCode, RegistryError> {
+    environments.write().await.unfreeze(&name)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Removes an environment, refusing while it has instances unless `cascade`
+/// is set, in which case they are deregistered first. Each service is checked
+/// like its own deregistration, and nothing is removed unless all of them pass
+#[allow(clippy::too_many_arguments)]
+async fn delete_environment(
+    _admin: RequireAdmin,
+    State(registry): State<Arc<RwLock<dyn ServiceRegistry>>>,
+    State(environments): State<Arc<RwLock<EnvironmentStore>>>,
+    State(guardrail): State<Arc<RwLock<Guardrail>>>,
+    State(profiles): State<Arc<RwLock<ProfileStore>>>,
+    State(policy): State<HealthPolicy>,
+    State(clock): State<SharedClock>,
+    Path(name): Path<Environment>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, RegistryError> {
+    let mut registry = registry.write().await;
+    let services = services_in(&*registry, &name);
+    {
+        let environments = environments.read().await;
+        if environments.get(&name).is_none() && services.is_empty() {
+            return Err(RegistryError::NotFound);
+        }
+        if !services.is_empty() {
+            environments.check_unfrozen(&name)?;
+        }
+    }
+    if !services.is_empty() && !query.cascade {
+        return Err(RegistryError::Conflict(format!(
+            "Environment {} still has instances of {} services, delete with cascade=true to remove them",
+            name,
+            services.len()
+        )));
+    }
+
+    // Previews every service before counting any against the guardrail, so a
+    // refused service does not leave the others half removed
+    let at = clock.now();
+    for dry_run in [true, false] {
+        for service_name in &services {
+            let checks = DeregistrationChecks {
+                environments: &environments,
+                profiles: &profiles,
+                policy: &policy,
+                force: query.force,
+                replacements: &[],
+            };
+            guard_deregistration(
+                &guardrail,
+                checks,
+                &*registry,
+                service_name,
+                |entry| entry.environment == name,
+                at,
+                dry_run,
+            )
+            .await?;
+        }
+    }
+    for service_name in &services {
+        registry.deregister(service_name, Some(&name))?;
+    }
+    let _ = environments.write().await.remove(&name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_profile::ServiceProfile;
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request},
+    };
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    async fn send(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_environment_lifecycle() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for environment in ["pr-42", "dev"] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    "web".parse().unwrap(),
+                    environment.parse().unwrap(),
+                    "http://10.0.0.1:8080".to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+        let app = environments_routes().with_state(AppState::new(
+            registry.clone(),
+            HealthPolicy::default(),
+            None,
+        ));
+
+        let (status, created) = send(
+            &app,
+            Method::POST,
+            "/",
+            Some(json!({ "name": "pr-42", "ttl_seconds": 3600 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["status"], "active");
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/",
+            Some(json!({ "name": "pr-43", "ttl_seconds": u64::MAX })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(&app, Method::POST, "/", Some(json!({ "name": "pr-42" }))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (_, listed) = send(&app, Method::GET, "/", None).await;
+        assert_eq!(listed[0]["name"], "dev");
+        assert_eq!(listed[0]["declared"], false);
+        assert_eq!(listed[1]["name"], "pr-42");
+        assert_eq!(listed[1]["declared"], true);
+        assert_eq!(listed[1]["instances"], 1);
+
+        let (status, archived) = send(&app, Method::POST, "/pr-42/archive", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(archived["status"], "archived");
+
+        let (status, _) = send(&app, Method::DELETE, "/pr-42", None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = send(&app, Method::DELETE, "/pr-42?cascade=true", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(registry.read().await.list().len(), 1);
+        let (status, _) = send(&app, Method::GET, "/pr-42", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_cascade_checks_each_service() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for name in ["web", "api"] {
+            // Heartbeating since it registered, so it counts as healthy
+            let mut entry = ServiceEntry::new(
+                name.parse().unwrap(),
+                "pr-42".parse().unwrap(),
+                "http://10.0.0.1:8080".to_string(),
+                HashMap::new(),
+                now() - 1_000,
+            );
+            entry.last_heartbeat = now();
+            registry.write().await.register(entry).unwrap();
+        }
+        let state = AppState::new(registry.clone(), HealthPolicy::default(), None);
+        state.profiles.write().await.put(
+            "web".parse().unwrap(),
+            ServiceProfile {
+                min_instances: [("pr-42".parse().unwrap(), 1)].into(),
+                ..ServiceProfile::default()
+            },
+        );
+        let app = environments_routes().with_state(state);
+
+        let (status, response) = send(&app, Method::DELETE, "/pr-42?cascade=true", None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(
+            response["message"]
+                .as_str()
+                .unwrap()
+                .contains("minimum of 1")
+        );
+        assert_eq!(registry.read().await.list().len(), 2);
+
+        let (status, _) = send(&app, Method::DELETE, "/pr-42?cascade=true&force=true", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(registry.read().await.list().is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::model::service_registry::RegistryError;
+
+/// Body returned with every failed registry operation
+#[derive(Serialize)]
+struct ErrorResponse {
+    error_code: &'static str,
+    message: String,
+}
+
+impl RegistryError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            RegistryError::AlreadyExists | RegistryError::Conflict(_) => StatusCode::CONFLICT,
+            RegistryError::NotFound => StatusCode::NOT_FOUND,
+            RegistryError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            RegistryError::Validation(_) => StatusCode::BAD_REQUEST,
+            RegistryError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            RegistryError::StorageUnavailable(_)
+            | RegistryError::Overloaded(_)
+            | RegistryError::ReadOnly(_) => StatusCode::SERVICE_UNAVAILABLE,
+            RegistryError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            RegistryError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for RegistryError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status.is_server_error() {
+            eprintln!("Registry operation failed: {}", self);
+        }
+
+        let body = ErrorResponse {
+            error_code: self.error_code(),
+            message: self.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    async fn render(error: RegistryError) -> (StatusCode, Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_error_response_body() {
+        let (status, body) = render(RegistryError::NotFound).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error_code"], "not_found");
+        assert_eq!(body["message"], "Not found");
+
+        let (status, body) =
+            render(RegistryError::StorageUnavailable("disk full".to_string())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error_code"], "storage_unavailable");
+        assert_eq!(body["message"], "Storage unavailable: disk full");
+    }
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(
+            RegistryError::AlreadyExists.status_code(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            RegistryError::Validation("bad".to_string()).status_code(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            RegistryError::QuotaExceeded("limit".to_string()).status_code(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            RegistryError::Timeout.status_code(),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    routing::get,
+};
+use serde::Deserialize;
+
+use crate::api::AppState;
+use crate::model::registry_event::RegistryEvent;
+use crate::model::service_registry::RegistryReadHandle;
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    since: Option<u64>,
+}
+
+pub fn events_routes() -> Router<AppState> {
+    Router::new().route("/", get(list_events))
+}
+
+async fn list_events(
+    State(registry): State<RegistryReadHandle>,
+    Query(query): Query<EventsQuery>,
+) -> Json<Vec<RegistryEvent>> {
+    let registry = registry.read().await;
+    Json(registry.events(query.since.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance_expiry::mark_expiring;
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::service_registry::{
+        HealthPolicy, RegistryWriter, ServiceEntry, ServiceRegistry, now,
+    };
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::Value;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    async fn get_events(state: AppState, uri: &str) -> (StatusCode, Value) {
+        let app = events_routes().with_state(state);
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_list_events() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry.clone(), HealthPolicy::default(), None);
+
+        for name in ["first", "second"] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    "dev".parse().unwrap(),
+                    "http://localhost:8080".to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+
+        let (status, response) = get_events(state.clone(), "/").await;
+        assert_eq!(status, StatusCode::OK);
+        let events = response.as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["kind"], "Registered");
+        assert_eq!(events[0]["service_name"], "first");
+
+        let (_, response) = get_events(state, "/?since=1").await;
+        let events = response.as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["service_name"], "second");
+    }
+
+    #[tokio::test]
+    async fn test_expiring_instances_recorded() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let policy = HealthPolicy::default();
+        let state = AppState::new(registry.clone(), policy, None);
+        registry
+            .write()
+            .await
+            .register(ServiceEntry::new(
+                "checkout".parse().unwrap(),
+                "prod".parse().unwrap(),
+                "http://localhost:8080".to_string(),
+                HashMap::new(),
+                clock.now(),
+            ))
+            .unwrap();
+
+        clock.advance(Duration::from_millis(policy.unhealthy_after * 9 / 10));
+        let shared: Arc<RwLock<dyn ServiceRegistry>> = registry;
+        mark_expiring(&shared, &policy, 80, &mut HashSet::new(), clock.now()).await;
+
+        let (_, response) = get_events(state, "/?since=1").await;
+        let events = response.as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["kind"], "Expiring");
+        assert_eq!(events[0]["service_name"], "checkout");
+        assert_eq!(events[0]["detail"], "silent for 90% of the time allowed");
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::clock::SharedClock;
+use crate::model::expectation::{Expectation, ExpectationStatus};
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{HealthPolicy, RegistryError, RegistryReadHandle};
+use crate::model::stale_report::parse_age;
+use crate::registry::expectation_store::ExpectationStore;
+
+#[derive(Deserialize)]
+struct ExpectationRequest {
+    /// Only count instances in this environment, any environment by default
+    environment: Option<Environment>,
+    /// How long the service may go without a healthy instance, e.g. `10m`
+    window: String,
+}
+
+pub fn expectations_routes() -> Router<AppState> {
+    Router::new().route("/", get(list_expectations)).route(
+        "/{name}",
+        get(get_expectation)
+            .put(put_expectation)
+            .delete(delete_expectation),
+    )
+}
+
+async fn statuses(
+    registry: &RegistryReadHandle,
+    expectations: &Arc<RwLock<ExpectationStore>>,
+    policy: &HealthPolicy,
+    at: u64,
+) -> Vec<ExpectationStatus> {
+    let entries = registry.read().await.list();
+    expectations.read().await.statuses(&entries, policy, at)
+}
+
+async fn list_expectations(
+    State(registry): State<RegistryReadHandle>,
+    State(expectations): State<Arc<RwLock<ExpectationStore>>>,
+    State(policy): State<HealthPolicy>,
+    State(clock): State<SharedClock>,
+) -> Json<Vec<ExpectationStatus>> {
+    Json(statuses(&registry, &expectations, &policy, clock.now()).await)
+}
+
+async fn get_expectation(
+    State(registry): State<RegistryReadHandle>,
+    State(expectations): State<Arc<RwLock<ExpectationStore>>>,
+    State(policy): State<HealthPolicy>,
+    State(clock): State<SharedClock>,
+    Path(name): Path<ServiceName>,
+) -> Result<Json<ExpectationStatus>, RegistryError> {
+    statuses(&registry, &expectations, &policy, clock.now())
+        .await
+        .into_iter()
+        .find(|status| status.expectation.service_name == name)
+        .map(Json)
+        .ok_or(RegistryError::NotFound)
+}
+
+/// Declares that a service should always have a healthy instance
+async fn put_expectation(
+    _admin: RequireAdmin,
+    State(expectations): State<Arc<RwLock<ExpectationStore>>>,
+    State(clock): State<SharedClock>,
+    Path(name): Path<ServiceName>,
+    Json(payload): Json<ExpectationRequest>,
+) -> Result<StatusCode, RegistryError> {
+    let window = parse_age(&payload.window).map_err(RegistryError::Validation)?;
+    expectations.write().await.put(Expectation::new(
+        name,
+        payload.environment,
+        window,
+        clock.now(),
+    ));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_expectation(
+    _admin: RequireAdmin,
+    State(expectations): State<Arc<RwLock<ExpectationStore>>>,
+    Path(name): Path<ServiceName>,
+) -> Result<StatusCode, RegistryError> {
+    expectations.write().await.remove(&name)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{body::Body, http::Request};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_expectation_lifecycle() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None);
+        let app = expectations_routes().with_state(state);
+
+        let request = Request::builder()
+            .method("PUT")
+            .uri("/billing")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"environment": "prod", "window": "0s"}"#))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed[0]["service_name"], "billing");
+        assert_eq!(listed[0]["violated"], true);
+
+        let request = Request::builder()
+            .method("DELETE")
+            .uri("/billing")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let request = Request::builder()
+            .uri("/billing")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::clock::SharedClock;
+use crate::model::guardrail::{Guardrail, GuardrailTrip};
+use crate::model::identifiers::ServiceName;
+use crate::model::service_registry::RegistryError;
+
+pub fn guardrails_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_trips))
+        .route("/{name}/confirm", post(confirm_trip))
+}
+
+async fn list_trips(
+    _admin: RequireAdmin,
+    State(guardrail): State<Arc<RwLock<Guardrail>>>,
+) -> Json<Vec<GuardrailTrip>> {
+    Json(guardrail.read().await.trips())
+}
+
+/// Lets the deregistrations of a tripped service through for the rest of the window
+async fn confirm_trip(
+    _admin: RequireAdmin,
+    State(guardrail): State<Arc<RwLock<Guardrail>>>,
+    State(clock): State<SharedClock>,
+    Path(name): Path<ServiceName>,
+) -> Result<StatusCode, RegistryError> {
+    guardrail.write().await.confirm(&name, clock.now())?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{Json, Router, extract::State, routing::post};
+use tokio::sync::Notify;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::api::read_only::ReadOnly;
+use crate::model::service_registry::{RegistryReadHandle, ServiceEntry};
+
+/// How long a server keeps answering after handing off, for the new process to
+/// start listening on the shared port before this one stops
+const HANDOFF_GRACE: Duration = Duration::from_secs(1);
+
+/// Tells the server to stop accepting connections and exit once the ones
+/// in flight are answered
+#[derive(Clone, Default)]
+pub struct Shutdown(Arc<Notify>);
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.0.notify_one();
+    }
+
+    /// Resolves once the shutdown is triggered, even if it was triggered before
+    pub async fn triggered(&self) {
+        self.0.notified().await;
+    }
+}
+
+pub fn handoff_routes() -> Router<AppState> {
+    Router::new().route("/", post(hand_off))
+}
+
+/// Hands the registry to the process taking this one's place: stops taking
+/// writes so the export stays complete, returns it, and shuts down shortly after
+async fn hand_off(
+    _admin: RequireAdmin,
+    State(registry): State<RegistryReadHandle>,
+    State(read_only): State<ReadOnly>,
+    State(shutdown): State<Shutdown>,
+) -> Json<Vec<ServiceEntry>> {
+    read_only.set(true);
+    let entries = registry.read().await.list();
+    println!(
+        "Handing off {} instances to the new process and shutting down",
+        entries.len()
+    );
+    tokio::spawn(async move {
+        tokio::time::sleep(HANDOFF_GRACE).await;
+        shutdown.trigger();
+    });
+    Json(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TestServer;
+
+    #[tokio::test]
+    async fn test_hand_off() {
+        let server = TestServer::start().await;
+        server
+            .register("checkout", "prod", "http://10.0.0.1:8080", &[])
+            .await;
+        let shutdown = server.state().shutdown.clone();
+
+        let export = server.client().hand_off().await.unwrap();
+
+        assert_eq!(export.as_array().unwrap().len(), 1);
+        assert!(server.state().read_only.is_enabled());
+        tokio::time::timeout(std::time::Duration::from_secs(5), shutdown.triggered())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderValue, Method, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ring::digest;
+use tokio::sync::RwLock;
+
+use crate::api::buffer_body;
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::RegistryError;
+use crate::registry::idempotency_store::{
+    Claim, IdempotencyRecord, IdempotencyStore, StoredResponse,
+};
+
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on responses replayed from an earlier request with the same key
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Digest of the `Authorization` header, scoping keys to the caller so a
+/// response is never replayed to someone presenting other credentials
+fn credential(headers: &HeaderMap) -> String {
+    let authorization = headers
+        .get(AUTHORIZATION)
+        .map(HeaderValue::as_bytes)
+        .unwrap_or_default();
+    digest::digest(&digest::SHA256, authorization)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn fingerprint(method: &Method, uri: &str, credential: &str, body: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    method.as_str().hash(&mut hasher);
+    uri.hash(&mut hasher);
+    credential.hash(&mut hasher);
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Releases a claimed key unless disarmed once the response is stored, so a
+/// handler that panics or a client that disconnects does not block retries
+struct ClaimGuard {
+    store: Arc<RwLock<IdempotencyStore>>,
+    key: Option<String>,
+}
+
+impl ClaimGuard {
+    fn disarm(mut self) {
+        self.key = None;
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        if let Ok(mut store) = self.store.try_write() {
+            store.release(&key);
+        } else if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let store = self.store.clone();
+            runtime.spawn(async move { store.write().await.release(&key) });
+        }
+    }
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let mut response = Response::new(Body::from(stored.body));
+    *response.status_mut() = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let headers = response.headers_mut();
+    if let Some(content_type) = stored
+        .content_type
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+/// Middleware answering retries of a mutating request carrying an `Idempotency-Key`
+/// with the response of the first attempt instead of applying the request again.
+/// Server errors are not remembered, so the request can be retried after them.
+pub async fn remember_idempotent(
+    State(store): State<Arc<RwLock<IdempotencyStore>>>,
+    State(clock): State<SharedClock>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mutating = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    let key = match request.headers().get(IDEMPOTENCY_KEY) {
+        Some(key) if mutating => match key.to_str() {
+            Ok(key) if !key.is_empty() => key.to_string(),
+            _ => {
+                return RegistryError::Validation("Invalid Idempotency-Key header".to_string())
+                    .into_response();
+            }
+        },
+        _ => return next.run(request).await,
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match buffer_body(body).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let credential = credential(&parts.headers);
+    let fingerprint = fingerprint(&parts.method, &parts.uri.to_string(), &credential, &body);
+    let scoped_key = format!("{}/{}", credential, key);
+
+    let claim = store
+        .write()
+        .await
+        .claim(&scoped_key, fingerprint, clock.now());
+    match claim {
+        Claim::New => {}
+        Claim::Existing(IdempotencyRecord::Completed(stored)) => return replay(stored),
+        Claim::Existing(IdempotencyRecord::InFlight) => {
+            return RegistryError::Conflict(format!(
+                "A request with Idempotency-Key {} is still in progress",
+                key
+            ))
+            .into_response();
+        }
+        Claim::Mismatch => {
+            return RegistryError::Validation(format!(
+                "Idempotency-Key {} was already used for a different request",
+                key
+            ))
+            .into_response();
+        }
+    }
+
+    let guard = ClaimGuard {
+        store: store.clone(),
+        key: Some(scoped_key.clone()),
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if response.status().is_server_error() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    store.write().await.complete(
+        &scoped_key,
+        StoredResponse {
+            status: parts.status.as_u16(),
+            content_type: parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            body: body.to_vec(),
+        },
+        clock.now(),
+    );
+    guard.disarm();
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AppState;
+    use crate::create_app;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::routing::post;
+    use axum::{Router, middleware};
+    use serde_json::{Value, json};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn register_request(key: &str, address: &str) -> Request<Body> {
+        let payload = json!({
+            "service_name": "payments",
+            "environment": "prod",
+            "address": address
+        });
+        Request::builder()
+            .method(Method::POST)
+            .uri("/services")
+            .header("content-type", "application/json")
+            .header("idempotency-key", key)
+            .body(Body::from(payload.to_string()))
+            .unwrap()
+    }
+
+    async fn send_request(app: Router, request: Request<Body>) -> (StatusCode, bool, Value) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let replayed = response.headers().contains_key(IDEMPOTENT_REPLAYED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            replayed,
+            serde_json::from_slice(&body).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_retried_registration_is_applied_once() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let app = create_app(AppState::new(
+            registry.clone(),
+            HealthPolicy::default(),
+            None,
+        ));
+
+        let (status, replayed, first) = send_request(
+            app.clone(),
+            register_request("retry-1", "http://localhost:8080"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!replayed);
+
+        let (status, replayed, second) = send_request(
+            app.clone(),
+            register_request("retry-1", "http://localhost:8080"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(replayed);
+        assert_eq!(first, second);
+
+        let (_, _, services) = send_request(
+            app.clone(),
+            Request::builder()
+                .uri("/services")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(services.as_array().unwrap().len(), 1);
+
+        let (status, _, response) =
+            send_request(app, register_request("retry-1", "http://localhost:9090")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(response["error_code"], "validation_failed");
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_request_releases_key() {
+        let state = AppState::new(
+            Arc::new(RwLock::new(InMemoryRegistry::new())),
+            HealthPolicy::default(),
+            None,
+        );
+        // The first attempt never completes, as when its client disconnects
+        let attempted = Arc::new(AtomicBool::new(false));
+        let app = Router::new()
+            .route(
+                "/services",
+                post(move || async move {
+                    if !attempted.swap(true, Ordering::SeqCst) {
+                        std::future::pending::<()>().await;
+                    }
+                    "registered"
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                remember_idempotent,
+            ))
+            .with_state(state);
+
+        let first = app
+            .clone()
+            .oneshot(register_request("retry-1", "http://localhost:8080"));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), first)
+                .await
+                .is_err()
+        );
+
+        let (status, replayed, _) =
+            send_request(app, register_request("retry-1", "http://localhost:8080")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!replayed);
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_to_credentials() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let app = create_app(AppState::new(
+            registry,
+            HealthPolicy::default(),
+            Some("root".to_string()),
+        ));
+        let issue = |authorization: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/admin/tokens")
+                .header("content-type", "application/json")
+                .header("idempotency-key", "issue-1")
+                .header("authorization", authorization)
+                .body(Body::from(json!({ "label": "ci" }).to_string()))
+                .unwrap()
+        };
+
+        let (status, _, issued) = send_request(app.clone(), issue("Bearer root")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, replayed, _) = send_request(app.clone(), issue("Bearer root")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(replayed);
+
+        // The same key and body from another caller never see the stored secret
+        let (status, replayed, response) = send_request(app, issue("Bearer guess")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(!replayed);
+        assert!(response.get("secret").is_none());
+        assert!(issued["secret"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_refused() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let app = create_app(AppState::new(registry, HealthPolicy::default(), None));
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/services")
+            .header("content-type", "application/json")
+            .header("idempotency-key", "large-1")
+            .body(Body::from(vec![b' '; crate::api::MAX_BODY_BYTES + 1]))
+            .unwrap();
+        let (status, _, _) = send_request(app, request).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::sync::RwLock;
+
+use crate::api::auth::{AdminToken, bearer_token, tokens_match};
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::ServiceName;
+use crate::model::impersonation::{ACT_AS_HEADER, Impersonation};
+use crate::model::service_registry::RegistryError;
+use crate::registry::token_store::TokenStore;
+
+/// Middleware letting admins act on behalf of a service with `X-Act-As`, for
+/// break-glass changes without the service's own credentials. The request is
+/// treated as coming from that service, and every registry event it records
+/// names both the service and the admin credential behind it
+pub async fn act_as(
+    State(AdminToken(admin_token)): State<AdminToken>,
+    State(tokens): State<Arc<RwLock<TokenStore>>>,
+    State(clock): State<SharedClock>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(value) = request.headers().get(ACT_AS_HEADER) else {
+        return next.run(request).await;
+    };
+    let acting_as = match value.to_str().map(str::parse::<ServiceName>) {
+        Ok(Ok(service_name)) => service_name.to_string(),
+        _ => {
+            return RegistryError::Validation(format!("{} must name a service", ACT_AS_HEADER))
+                .into_response();
+        }
+    };
+    // Without an admin token anyone could act as any service without a trace of who did
+    let Some(admin_token) = admin_token else {
+        return RegistryError::Conflict(format!(
+            "{} can only be used when the server runs with --admin-token",
+            ACT_AS_HEADER
+        ))
+        .into_response();
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let admin = match bearer_token(&parts) {
+        Some(token) if tokens_match(token, &admin_token) => "admin-token".to_string(),
+        Some(token) => match tokens.write().await.identify(token, clock.now()) {
+            Some(issued) => issued.id,
+            None => return unauthorized(),
+        },
+        None => return unauthorized(),
+    };
+
+    let impersonation = Impersonation { acting_as, admin };
+    println!(
+        "Admin {} acting as {}: {} {}",
+        impersonation.admin, impersonation.acting_as, parts.method, parts.uri
+    );
+    parts.extensions.insert(impersonation.clone());
+    impersonation
+        .scope(next.run(Request::from_parts(parts, body)))
+        .await
+}
+
+fn unauthorized() -> Response {
+    RegistryError::Unauthorized(format!(
+        "Only admin tokens may act on behalf of a service with {}",
+        ACT_AS_HEADER
+    ))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AppState;
+    use crate::create_app;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::body::Body;
+    use axum::http::{Method, StatusCode};
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    async fn send(
+        app: &axum::Router,
+        method: Method,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        let response = app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_deregister_on_behalf_of_service() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), Some("root".to_string()));
+        let tokens = state.tokens.clone();
+        let app = create_app(state);
+
+        let registration = json!({
+            "service_name": "payments",
+            "environment": "prod",
+            "address": "http://10.0.0.1:8080",
+        });
+        let (status, _) = send(&app, Method::POST, "/services", &[], Some(registration)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Only admin credentials may act as a service
+        let (status, _) = send(
+            &app,
+            Method::DELETE,
+            "/services/payments",
+            &[(ACT_AS_HEADER, "payments")],
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(
+            &app,
+            Method::DELETE,
+            "/services/payments",
+            &[
+                (ACT_AS_HEADER, "not a service"),
+                ("authorization", "Bearer root"),
+            ],
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let issued = tokens.write().await.issue("on-call", None, 0);
+        let bearer = format!("Bearer {}", issued.secret);
+        let (status, _) = send(
+            &app,
+            Method::DELETE,
+            "/services/payments",
+            &[(ACT_AS_HEADER, "payments"), ("authorization", &bearer)],
+            None,
+        )
+        .await;
+        assert!(status.is_success());
+
+        let (_, events) = send(&app, Method::GET, "/events", &[], None).await;
+        let events = events.as_array().unwrap();
+        assert!(events[0].get("impersonation").is_none());
+        assert_eq!(events[1]["kind"], "Deregistered");
+        assert_eq!(events[1]["impersonation"]["acting_as"], "payments");
+        assert_eq!(events[1]["impersonation"]["admin"], issued.token.id);
+    }
+
+    #[tokio::test]
+    async fn test_refused_without_admin_token() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let app = create_app(AppState::new(registry, HealthPolicy::default(), None));
+
+        let (status, _) = send(
+            &app,
+            Method::GET,
+            "/services",
+            &[(ACT_AS_HEADER, "payments")],
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
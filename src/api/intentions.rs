@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{delete, get, put},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::clock::SharedClock;
+use crate::model::intention::{Intention, IntentionAction};
+use crate::model::service_registry::RegistryError;
+use crate::registry::intention_store::IntentionStore;
+
+#[derive(Deserialize)]
+struct IntentionRequest {
+    source: String,
+    destination: String,
+    action: IntentionAction,
+}
+
+#[derive(Deserialize)]
+struct IntentionCheckQuery {
+    src: String,
+    dst: String,
+}
+
+#[derive(Serialize)]
+struct IntentionCheckResponse {
+    source: String,
+    destination: String,
+    allowed: bool,
+    matched: Option<Intention>,
+}
+
+pub fn intentions_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_intentions))
+        .route("/", put(upsert_intention))
+        .route("/check", get(check_intention))
+        .route("/{source}/{destination}", delete(delete_intention))
+}
+
+async fn list_intentions(State(store): State<Arc<RwLock<IntentionStore>>>) -> Json<Vec<Intention>> {
+    let store = store.read().await;
+    Json(store.list())
+}
+
+async fn upsert_intention(
+    _admin: RequireAdmin,
+    State(store): State<Arc<RwLock<IntentionStore>>>,
+    State(clock): State<SharedClock>,
+    Json(payload): Json<IntentionRequest>,
+) -> Result<Json<String>, RegistryError> {
+    if payload.source.is_empty() || payload.destination.is_empty() {
+        return Err(RegistryError::Validation(
+            "Source and destination must not be empty".to_string(),
+        ));
+    }
+
+    let mut store = store.write().await;
+    let message = format!(
+        "Intention from {} to {} set to {:?}",
+        payload.source, payload.destination, payload.action
+    );
+    store.upsert(Intention {
+        source: payload.source,
+        destination: payload.destination,
+        action: payload.action,
+        created_at: clock.now(),
+    });
+
+    Ok(Json(message))
+}
+
+async fn check_intention(
+    State(store): State<Arc<RwLock<IntentionStore>>>,
+    Query(query): Query<IntentionCheckQuery>,
+) -> Json<IntentionCheckResponse> {
+    let store = store.read().await;
+    let matched = store.lookup(&query.src, &query.dst).cloned();
+
+    Json(IntentionCheckResponse {
+        allowed: store.is_allowed(&query.src, &query.dst),
+        source: query.src,
+        destination: query.dst,
+        matched,
+    })
+}
+
+async fn delete_intention(
+    _admin: RequireAdmin,
+    State(store): State<Arc<RwLock<IntentionStore>>>,
+    Path((source, destination)): Path<(String, String)>,
+) -> Result<Json<String>, RegistryError> {
+    let mut store = store.write().await;
+    store.remove(&source, &destination)?;
+
+    Ok(Json(format!(
+        "Successfully deleted intention from {} to {}",
+        source, destination
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    fn create_test_app() -> Router {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        intentions_routes().with_state(AppState::new(registry, HealthPolicy::default(), None))
+    }
+
+    async fn send_request(app: Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap_or(json!({}));
+        (status, json)
+    }
+
+    fn put_request(payload: Value) -> Request<Body> {
+        Request::builder()
+            .method(Method::PUT)
+            .uri("/")
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap()
+    }
+
+    fn get_request(uri: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_check_without_intentions_allows() {
+        let app = create_test_app();
+
+        let (status, response) = send_request(app, get_request("/check?src=web&dst=db")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response["allowed"], true);
+        assert_eq!(response["matched"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_put_and_check_deny() {
+        let app = create_test_app();
+
+        let payload = json!({ "source": "web", "destination": "db", "action": "deny" });
+        let (status, _) = send_request(app.clone(), put_request(payload)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, response) = send_request(app, get_request("/check?src=web&dst=db")).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response["allowed"], false);
+        assert_eq!(response["matched"]["action"], "deny");
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_default_deny() {
+        let app = create_test_app();
+
+        for payload in [
+            json!({ "source": "*", "destination": "*", "action": "deny" }),
+            json!({ "source": "web", "destination": "db", "action": "allow" }),
+        ] {
+            send_request(app.clone(), put_request(payload)).await;
+        }
+
+        let (_, response) = send_request(app.clone(), get_request("/check?src=web&dst=db")).await;
+        assert_eq!(response["allowed"], true);
+
+        let (_, response) = send_request(app, get_request("/check?src=api&dst=db")).await;
+        assert_eq!(response["allowed"], false);
+    }
+
+    #[tokio::test]
+    async fn test_put_invalid_action() {
+        let app = create_test_app();
+
+        let payload = json!({ "source": "web", "destination": "db", "action": "maybe" });
+        let (status, _) = send_request(app, put_request(payload)).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_put_empty_source() {
+        let app = create_test_app();
+
+        let payload = json!({ "source": "", "destination": "db", "action": "allow" });
+        let (status, _) = send_request(app, put_request(payload)).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_check_missing_params() {
+        let app = create_test_app();
+
+        let (status, _) = send_request(app, get_request("/check?src=web")).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_and_delete() {
+        let app = create_test_app();
+
+        let payload = json!({ "source": "web", "destination": "db", "action": "allow" });
+        send_request(app.clone(), put_request(payload)).await;
+
+        let (status, response) = send_request(app.clone(), get_request("/")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.as_array().unwrap().len(), 1);
+
+        let delete_request = Request::builder()
+            .method(Method::DELETE)
+            .uri("/web/db")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send_request(app.clone(), delete_request).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, response) = send_request(app, get_request("/")).await;
+        assert_eq!(response, json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_delete_not_found() {
+        let app = create_test_app();
+
+        let request = Request::builder()
+            .method(Method::DELETE)
+            .uri("/web/db")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send_request(app, request).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
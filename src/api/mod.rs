@@ -1 +1,497 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::body::{Body, Bytes};
+use axum::extract::FromRef;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tokio::sync::RwLock;
+
+use crate::dns_fallback::DnsFallback;
+use crate::model::address_rewrite::AddressRewrites;
+use crate::model::admission::AdmissionController;
+use crate::model::chaos::ChaosController;
+use crate::model::clock::{SharedClock, system_clock};
+use crate::model::guardrail::{Guardrail, GuardrailLimit};
+use crate::model::heartbeat_pacing::{HeartbeatPacer, PacerHandle};
+use crate::model::ownership::OwnerPolicy;
+use crate::model::payload_signing::{SigningHandle, SigningSecrets};
+use crate::model::selfcheck::SelfCheckReport;
+use crate::model::service_registry::{
+    HealthPolicy, RecoveryWindow, RegistryReadHandle, ServiceRegistry,
+};
+use crate::model::tag_masking::TagMasking;
+use crate::model::tag_schema::TagSchema;
+use crate::registry::agent_hub::AgentHub;
+use crate::registry::deletion_store::DeletionStore;
+use crate::registry::environment_store::EnvironmentStore;
+use crate::registry::expectation_store::ExpectationStore;
+use crate::registry::history_store::HistoryStore;
+use crate::registry::idempotency_store::IdempotencyStore;
+use crate::registry::intention_store::IntentionStore;
+use crate::registry::profile_store::ProfileStore;
+use crate::registry::resolve_cache::ResolveCache;
+use crate::registry::service_meta_store::ServiceMetaStore;
+use crate::registry::snapshot_store::SnapshotStore;
+use crate::registry::token_store::TokenStore;
+
+pub mod admin;
+pub mod admission;
+pub mod agents;
+pub mod auth;
+pub mod cache_hints;
+pub mod chaos;
+pub mod environments;
+pub mod error;
+pub mod events;
+pub mod expectations;
+pub mod guardrails;
+pub mod handoff;
+pub mod idempotency;
+pub mod impersonation;
+pub mod intentions;
+pub mod ndjson;
+pub mod owners;
+pub mod profiles;
+pub mod read_only;
+pub mod reports;
+pub mod resolve;
+pub mod routing_config;
+pub mod search;
+pub mod selfcheck;
 pub mod services;
+pub mod signing;
+pub mod sync;
+pub mod tabular;
+pub mod tokens;
+pub mod ui;
+pub mod validation;
+pub mod view;
+
+/// Largest request body middleware buffers, the same as the default limit of the
+/// `Json` extractor, which middleware runs ahead of
+pub const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Reads a request body in middleware, answering `413` past `MAX_BODY_BYTES`.
+/// Reading only fails past the limit or once the client is gone, when the
+/// response no longer matters
+pub(crate) async fn buffer_body(body: Body) -> Result<Bytes, Response> {
+    axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())
+}
+
+/// Shared state handed to every route, handlers extract the parts they need
+#[derive(Clone)]
+pub struct AppState {
+    pub registry: Arc<RwLock<dyn ServiceRegistry>>,
+    pub intentions: Arc<RwLock<IntentionStore>>,
+    pub service_meta: Arc<RwLock<ServiceMetaStore>>,
+    pub profiles: Arc<RwLock<ProfileStore>>,
+    pub environments: Arc<RwLock<EnvironmentStore>>,
+    pub history: Arc<RwLock<HistoryStore>>,
+    pub idempotency: Arc<RwLock<IdempotencyStore>>,
+    pub selfcheck: Arc<RwLock<SelfCheckReport>>,
+    pub agents: Arc<RwLock<AgentHub>>,
+    pub guardrail: Arc<RwLock<Guardrail>>,
+    pub deletions: Arc<RwLock<DeletionStore>>,
+    pub expectations: Arc<RwLock<ExpectationStore>>,
+    pub snapshots: Arc<RwLock<SnapshotStore>>,
+    pub tokens: Arc<RwLock<TokenStore>>,
+    pub resolve_cache: Arc<ResolveCache>,
+    pub health_policy: HealthPolicy,
+    pub owner_policy: OwnerPolicy,
+    pub tag_schema: TagSchema,
+    pub recovery: RecoveryWindow,
+    pub admin_token: auth::AdminToken,
+    pub caller_tokens: auth::CallerTokens,
+    pub spiffe_header: auth::SpiffeHeader,
+    pub chaos: chaos::ChaosHandle,
+    pub admission: admission::AdmissionHandle,
+    pub heartbeat_pacer: PacerHandle,
+    pub signing: SigningHandle,
+    pub clock: SharedClock,
+    pub dns_fallback: DnsFallback,
+    pub address_rewrites: Arc<AddressRewrites>,
+    pub tag_masking: Arc<TagMasking>,
+    pub cache_ttl: cache_hints::CacheTtl,
+    pub read_only: read_only::ReadOnly,
+    pub sync_epoch: sync::SyncEpoch,
+    pub shutdown: handoff::Shutdown,
+}
+
+impl AppState {
+    pub fn new(
+        registry: Arc<RwLock<dyn ServiceRegistry>>,
+        health_policy: HealthPolicy,
+        admin_token: Option<String>,
+    ) -> Self {
+        AppState {
+            registry,
+            intentions: Arc::new(RwLock::new(IntentionStore::new())),
+            service_meta: Arc::new(RwLock::new(ServiceMetaStore::new())),
+            profiles: Arc::new(RwLock::new(ProfileStore::new())),
+            environments: Arc::new(RwLock::new(EnvironmentStore::new())),
+            history: Arc::new(RwLock::new(HistoryStore::new())),
+            idempotency: Arc::new(RwLock::new(IdempotencyStore::default())),
+            selfcheck: Arc::new(RwLock::new(SelfCheckReport::default())),
+            agents: Arc::new(RwLock::new(AgentHub::new())),
+            guardrail: Arc::new(RwLock::new(Guardrail::default())),
+            deletions: Arc::new(RwLock::new(DeletionStore::default())),
+            expectations: Arc::new(RwLock::new(ExpectationStore::new())),
+            snapshots: Arc::new(RwLock::new(SnapshotStore::new())),
+            tokens: Arc::new(RwLock::new(TokenStore::default())),
+            resolve_cache: Arc::new(ResolveCache::new(0)),
+            health_policy,
+            owner_policy: OwnerPolicy::default(),
+            tag_schema: TagSchema::default(),
+            recovery: RecoveryWindow::default(),
+            admin_token: auth::AdminToken(admin_token.map(Arc::from)),
+            caller_tokens: auth::CallerTokens::default(),
+            spiffe_header: auth::SpiffeHeader::default(),
+            chaos: None,
+            admission: None,
+            heartbeat_pacer: None,
+            signing: None,
+            clock: system_clock(),
+            dns_fallback: DnsFallback::default(),
+            address_rewrites: Arc::default(),
+            tag_masking: Arc::default(),
+            cache_ttl: cache_hints::CacheTtl::default(),
+            read_only: read_only::ReadOnly::default(),
+            sync_epoch: sync::SyncEpoch::default(),
+            shutdown: handoff::Shutdown::default(),
+        }
+    }
+
+    pub fn with_owner_policy(mut self, owner_policy: OwnerPolicy) -> Self {
+        self.owner_policy = owner_policy;
+        self
+    }
+
+    pub fn with_tag_schema(mut self, tag_schema: TagSchema) -> Self {
+        self.tag_schema = tag_schema;
+        self
+    }
+
+    /// Enables fault injection, seeded so runs can be reproduced
+    pub fn with_chaos(mut self, seed: u64) -> Self {
+        self.chaos = Some(Arc::new(RwLock::new(ChaosController::new(seed))));
+        self
+    }
+
+    /// Runs at most `max_concurrent` writes at once, queueing up to `max_queued`
+    /// normal priority writes behind deregistrations and state changes
+    pub fn with_admission(mut self, max_concurrent: usize, max_queued: usize) -> Self {
+        self.admission = Some(Arc::new(AdmissionController::new(
+            max_concurrent,
+            max_queued,
+        )));
+        self
+    }
+
+    /// Suggests heartbeat intervals to clients, stretched while more than
+    /// `capacity` heartbeats arrive per second
+    pub fn with_heartbeat_capacity(mut self, capacity: u64) -> Self {
+        self.heartbeat_pacer = Some(Arc::new(HeartbeatPacer::new(capacity)));
+        self
+    }
+
+    /// Requires registrations and heartbeats of the services in `secrets` to be
+    /// signed with their shared secret
+    pub fn with_signing_secrets(mut self, secrets: SigningSecrets) -> Self {
+        self.signing = Some(Arc::new(secrets));
+        self
+    }
+
+    /// Flags bursts of deregistrations of one service over `limit`, refusing
+    /// them until an admin confirms when `block` is set
+    /// Makes `DELETE /services/{name}` hand out a token that must be posted back
+    /// within `timeout` millis before the service is deleted
+    pub fn with_deletion_confirmation(mut self, timeout: u64) -> Self {
+        self.deletions = Arc::new(RwLock::new(DeletionStore::new(timeout)));
+        self
+    }
+
+    pub fn with_guardrail(mut self, limit: GuardrailLimit, block: bool) -> Self {
+        self.guardrail = Arc::new(RwLock::new(Guardrail::new(limit, block)));
+        self
+    }
+
+    pub fn with_recovery(mut self, recovery: RecoveryWindow) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Remembers idempotency keys for `ttl` millis
+    pub fn with_idempotency_ttl(mut self, ttl: u64) -> Self {
+        self.idempotency = Arc::new(RwLock::new(IdempotencyStore::new(ttl)));
+        self
+    }
+
+    /// Reads the time from `clock`, which should be the clock the registry was built with
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Resolves unknown services through DNS instead of answering `404`
+    pub fn with_dns_fallback(mut self, enabled: bool) -> Self {
+        self.dns_fallback = DnsFallback { enabled };
+        self
+    }
+
+    /// Identifies calling services by the tokens in `callers`, keyed by caller name
+    pub fn with_callers(mut self, callers: &HashMap<String, String>) -> Self {
+        self.caller_tokens = auth::CallerTokens::new(callers);
+        self
+    }
+
+    /// Records the SPIFFE ID a terminating proxy forwards in `header`, either a
+    /// bare ID or an `x-forwarded-client-cert` value, on every registration
+    pub fn with_spiffe_header(mut self, header: &str) -> Self {
+        self.spiffe_header = auth::SpiffeHeader(Some(Arc::from(header.to_ascii_lowercase())));
+        self
+    }
+
+    /// Rewrites addresses in resolution responses for callers that cannot reach them as registered
+    pub fn with_address_rewrites(mut self, address_rewrites: AddressRewrites) -> Self {
+        self.address_rewrites = Arc::new(address_rewrites);
+        self
+    }
+
+    /// Suggests clients cache discovery results for `seconds` unless a profile says otherwise
+    pub fn with_cache_ttl(mut self, seconds: u64) -> Self {
+        self.cache_ttl = cache_hints::CacheTtl(seconds);
+        self
+    }
+
+    /// Serves repeated resolutions of a service from a cache for up to `millis`,
+    /// 0 resolves every request against the registry
+    pub fn with_resolve_cache(mut self, millis: u64) -> Self {
+        self.resolve_cache = Arc::new(ResolveCache::new(millis));
+        self
+    }
+
+    /// Starts refusing every change made through the API, until an admin turns it off
+    pub fn with_read_only(self, enabled: bool) -> Self {
+        self.read_only.set(enabled);
+        self
+    }
+
+    /// Redacts sensitive tags in discovery responses for callers that may not unmask them
+    pub fn with_tag_masking(mut self, tag_masking: TagMasking) -> Self {
+        self.tag_masking = Arc::new(tag_masking);
+        self
+    }
+}
+
+impl FromRef<AppState> for Arc<AddressRewrites> {
+    fn from_ref(state: &AppState) -> Self {
+        state.address_rewrites.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<TagMasking> {
+    fn from_ref(state: &AppState) -> Self {
+        state.tag_masking.clone()
+    }
+}
+
+impl FromRef<AppState> for cache_hints::CacheTtl {
+    fn from_ref(state: &AppState) -> Self {
+        state.cache_ttl
+    }
+}
+
+impl FromRef<AppState> for Arc<ResolveCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.resolve_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for read_only::ReadOnly {
+    fn from_ref(state: &AppState) -> Self {
+        state.read_only.clone()
+    }
+}
+
+impl FromRef<AppState> for sync::SyncEpoch {
+    fn from_ref(state: &AppState) -> Self {
+        state.sync_epoch.clone()
+    }
+}
+
+impl FromRef<AppState> for handoff::Shutdown {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+impl FromRef<AppState> for DnsFallback {
+    fn from_ref(state: &AppState) -> Self {
+        state.dns_fallback
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<dyn ServiceRegistry>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.registry.clone()
+    }
+}
+
+impl FromRef<AppState> for RegistryReadHandle {
+    fn from_ref(state: &AppState) -> Self {
+        RegistryReadHandle::new(state.registry.clone())
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<IntentionStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.intentions.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<ServiceMetaStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.service_meta.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<ProfileStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.profiles.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<EnvironmentStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.environments.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<HistoryStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.history.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<IdempotencyStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.idempotency.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<SelfCheckReport>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.selfcheck.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<DeletionStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.deletions.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<ExpectationStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.expectations.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<AgentHub>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.agents.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<SnapshotStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.snapshots.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<TokenStore>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.tokens.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RwLock<Guardrail>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.guardrail.clone()
+    }
+}
+
+impl FromRef<AppState> for HealthPolicy {
+    fn from_ref(state: &AppState) -> Self {
+        state.health_policy
+    }
+}
+
+impl FromRef<AppState> for OwnerPolicy {
+    fn from_ref(state: &AppState) -> Self {
+        state.owner_policy.clone()
+    }
+}
+
+impl FromRef<AppState> for TagSchema {
+    fn from_ref(state: &AppState) -> Self {
+        state.tag_schema.clone()
+    }
+}
+
+impl FromRef<AppState> for RecoveryWindow {
+    fn from_ref(state: &AppState) -> Self {
+        state.recovery
+    }
+}
+
+impl FromRef<AppState> for SharedClock {
+    fn from_ref(state: &AppState) -> Self {
+        state.clock.clone()
+    }
+}
+
+impl FromRef<AppState> for auth::CallerTokens {
+    fn from_ref(state: &AppState) -> Self {
+        state.caller_tokens.clone()
+    }
+}
+
+impl FromRef<AppState> for auth::SpiffeHeader {
+    fn from_ref(state: &AppState) -> Self {
+        state.spiffe_header.clone()
+    }
+}
+
+impl FromRef<AppState> for auth::AdminToken {
+    fn from_ref(state: &AppState) -> Self {
+        state.admin_token.clone()
+    }
+}
+
+impl FromRef<AppState> for chaos::ChaosHandle {
+    fn from_ref(state: &AppState) -> Self {
+        state.chaos.clone()
+    }
+}
+
+impl FromRef<AppState> for PacerHandle {
+    fn from_ref(state: &AppState) -> Self {
+        state.heartbeat_pacer.clone()
+    }
+}
+
+impl FromRef<AppState> for SigningHandle {
+    fn from_ref(state: &AppState) -> Self {
+        state.signing.clone()
+    }
+}
+
+impl FromRef<AppState> for admission::AdmissionHandle {
+    fn from_ref(state: &AppState) -> Self {
+        state.admission.clone()
+    }
+}
@@ -0,0 +1,27 @@
+use axum::{
+    body::{Body, Bytes},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use futures::stream;
+use serde::Serialize;
+
+pub const NDJSON: &str = "application/x-ndjson";
+
+/// Streams `items` as one JSON document per line, with chunked transfer encoding.
+/// Each item is only serialized once the client is ready for it, so large
+/// listings are never held in memory as a single document
+pub fn stream<I>(items: I) -> Response
+where
+    I: IntoIterator,
+    I::IntoIter: Send + 'static,
+    I::Item: Serialize,
+{
+    let lines = stream::iter(items.into_iter().map(|item| {
+        serde_json::to_vec(&item).map(|mut line| {
+            line.push(b'\n');
+            Bytes::from(line)
+        })
+    }));
+    ([(CONTENT_TYPE, NDJSON)], Body::from_stream(lines)).into_response()
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    response::Response,
+    routing::get,
+};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::tabular::Tabular;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::ownership::OWNER_TAG;
+use crate::model::service_meta::ServiceMeta;
+use crate::model::service_registry::RegistryReadHandle;
+use crate::registry::service_meta_store::ServiceMetaStore;
+
+#[derive(Serialize)]
+struct OwnedService {
+    service_name: ServiceName,
+    environment: Environment,
+    instances: usize,
+    meta: Option<ServiceMeta>,
+}
+
+pub fn owners_routes() -> Router<AppState> {
+    Router::new().route("/{team}/services", get(list_owned_services))
+}
+
+/// Lists the services owned by a team, either through the owner tag of their
+/// instances or through the owner of their metadata document
+async fn list_owned_services(
+    State(registry): State<RegistryReadHandle>,
+    State(meta_store): State<Arc<RwLock<ServiceMetaStore>>>,
+    Path(team): Path<String>,
+    accepted: Tabular,
+) -> Response {
+    let registry = registry.read().await;
+    let meta_store = meta_store.read().await;
+
+    let mut owned: BTreeMap<(ServiceName, Environment), usize> = BTreeMap::new();
+    for entry in registry.list() {
+        let key = (entry.service_name, entry.environment);
+        if entry.tags.get(OWNER_TAG) == Some(&team) {
+            *owned.entry(key).or_default() += 1;
+        }
+    }
+    for key in meta_store.owned_by(&team) {
+        let instances = registry.resolve(&key.0, &key.1).len();
+        owned.entry(key).or_insert(instances);
+    }
+
+    let rows: Vec<OwnedService> = owned
+        .into_iter()
+        .map(|((service_name, environment), instances)| OwnedService {
+            meta: meta_store.get(&service_name, &environment).cloned(),
+            service_name,
+            environment,
+            instances,
+        })
+        .collect();
+    accepted.render(&rows, &["service_name", "environment", "instances"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_list_owned_services() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry.clone(), HealthPolicy::default(), None);
+
+        for (name, owner) in [
+            ("payments", "team-a"),
+            ("payments", "team-a"),
+            ("search", "team-b"),
+        ] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    "prod".parse().unwrap(),
+                    "http://localhost:8080".to_string(),
+                    HashMap::from([(OWNER_TAG.to_string(), owner.to_string())]),
+                    now(),
+                ))
+                .unwrap();
+        }
+        state.service_meta.write().await.put(
+            "billing".parse().unwrap(),
+            "prod".parse().unwrap(),
+            ServiceMeta {
+                owner: Some("team-a".to_string()),
+                ..ServiceMeta::default()
+            },
+        );
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("/team-a/services")
+            .body(Body::empty())
+            .unwrap();
+        let response = owners_routes()
+            .with_state(state)
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let services: Value = serde_json::from_slice(&body).unwrap();
+        let services = services.as_array().unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0]["service_name"], "billing");
+        assert_eq!(services[0]["instances"], 0);
+        assert_eq!(services[0]["meta"]["owner"], "team-a");
+        assert_eq!(services[1]["service_name"], "payments");
+        assert_eq!(services[1]["instances"], 2);
+    }
+}
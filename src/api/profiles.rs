@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    routing::get,
+};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::identifiers::ServiceName;
+use crate::model::resolution_script;
+use crate::model::service_profile::ServiceProfile;
+use crate::model::service_registry::RegistryError;
+use crate::registry::profile_store::ProfileStore;
+
+#[derive(Serialize)]
+struct ProfileResponse {
+    service_name: ServiceName,
+    #[serde(flatten)]
+    profile: ServiceProfile,
+}
+
+pub fn profiles_routes() -> Router<AppState> {
+    Router::new().route("/", get(list_profiles)).route(
+        "/{name}",
+        get(get_profile).put(put_profile).delete(delete_profile),
+    )
+}
+
+async fn list_profiles(
+    State(store): State<Arc<RwLock<ProfileStore>>>,
+) -> Json<Vec<ProfileResponse>> {
+    let store = store.read().await;
+    Json(
+        store
+            .list()
+            .into_iter()
+            .map(|(service_name, profile)| ProfileResponse {
+                service_name,
+                profile,
+            })
+            .collect(),
+    )
+}
+
+async fn get_profile(
+    State(store): State<Arc<RwLock<ProfileStore>>>,
+    Path(name): Path<ServiceName>,
+) -> Result<Json<ServiceProfile>, RegistryError> {
+    let store = store.read().await;
+
+    store
+        .get(&name)
+        .map(|profile| Json(profile.clone()))
+        .ok_or(RegistryError::NotFound)
+}
+
+async fn put_profile(
+    _admin: RequireAdmin,
+    State(store): State<Arc<RwLock<ProfileStore>>>,
+    Path(name): Path<ServiceName>,
+    Json(payload): Json<ServiceProfile>,
+) -> Result<Json<String>, RegistryError> {
+    if payload.ttl_seconds == Some(0) {
+        return Err(RegistryError::Validation(
+            "ttl_seconds must be greater than zero".to_string(),
+        ));
+    }
+    payload.gc.validate().map_err(RegistryError::Validation)?;
+    payload
+        .health
+        .validate()
+        .map_err(RegistryError::Validation)?;
+    if let Some(script) = &payload.resolution_script {
+        resolution_script::validate(script).map_err(RegistryError::Validation)?;
+    }
+
+    let mut store = store.write().await;
+    let message = format!("Successfully updated profile for service {}", name);
+    store.put(name, payload);
+
+    Ok(Json(message))
+}
+
+async fn delete_profile(
+    _admin: RequireAdmin,
+    State(store): State<Arc<RwLock<ProfileStore>>>,
+    Path(name): Path<ServiceName>,
+) -> Result<Json<String>, RegistryError> {
+    let mut store = store.write().await;
+    store.remove(&name)?;
+
+    Ok(Json(format!(
+        "Successfully removed profile for service {}",
+        name
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    fn create_test_app() -> Router {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        profiles_routes().with_state(AppState::new(registry, HealthPolicy::default(), None))
+    }
+
+    async fn send_request(
+        app: Router,
+        method: Method,
+        uri: &str,
+        body: Body,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap_or(json!({}));
+        (status, json)
+    }
+
+    #[tokio::test]
+    async fn test_profile_lifecycle() {
+        let app = create_test_app();
+
+        let payload = json!({ "ttl_seconds": 10, "required_tags": ["team"] });
+        let (status, _) = send_request(
+            app.clone(),
+            Method::PUT,
+            "/payments",
+            Body::from(payload.to_string()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, response) =
+            send_request(app.clone(), Method::GET, "/payments", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response["ttl_seconds"], 10);
+
+        let (_, response) = send_request(app.clone(), Method::GET, "/", Body::empty()).await;
+        assert_eq!(response[0]["service_name"], "payments");
+        assert_eq!(response[0]["required_tags"], json!(["team"]));
+
+        let (status, _) =
+            send_request(app.clone(), Method::DELETE, "/payments", Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send_request(app, Method::GET, "/payments", Body::empty()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_put_profile_rejects_zero_ttl() {
+        let app = create_test_app();
+
+        let (status, response) = send_request(
+            app,
+            Method::PUT,
+            "/payments",
+            Body::from(json!({ "ttl_seconds": 0 }).to_string()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(response["error_code"], "validation_failed");
+    }
+}
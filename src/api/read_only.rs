@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::{
+    Json, Router,
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::service_registry::RegistryError;
+
+const TOGGLE_PATH: &str = "/admin/read-only";
+const HANDOFF_PATH: &str = "/admin/handoff";
+
+/// Whether the node refuses every change made through the API, shared by the
+/// middleware and the admin toggle
+#[derive(Clone, Default)]
+pub struct ReadOnly(Arc<AtomicBool>);
+
+impl ReadOnly {
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReadOnlyStatus {
+    enabled: bool,
+}
+
+pub fn read_only_routes() -> Router<AppState> {
+    Router::new().route("/", get(get_read_only).put(put_read_only))
+}
+
+async fn get_read_only(State(read_only): State<ReadOnly>) -> Json<ReadOnlyStatus> {
+    Json(ReadOnlyStatus {
+        enabled: read_only.is_enabled(),
+    })
+}
+
+async fn put_read_only(
+    _admin: RequireAdmin,
+    State(read_only): State<ReadOnly>,
+    Json(payload): Json<ReadOnlyStatus>,
+) -> Json<ReadOnlyStatus> {
+    read_only.set(payload.enabled);
+    println!(
+        "Read-only mode {}",
+        if payload.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    Json(payload)
+}
+
+/// Whether a request changes the registry, batch resolution is a read sent as
+/// `POST` and agent sockets carry registrations and heartbeats over a `GET`
+fn is_write(method: &Method, path: &str) -> bool {
+    if path.starts_with(TOGGLE_PATH) || path.starts_with(HANDOFF_PATH) {
+        return false;
+    }
+    if path == "/agents/ws" {
+        return true;
+    }
+    if *method == Method::POST && path.trim_end_matches('/') == "/resolve" {
+        return false;
+    }
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Middleware refusing writes while the node is read-only, except the toggle itself
+/// and handoffs
+pub async fn refuse_writes(
+    State(read_only): State<ReadOnly>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if read_only.is_enabled() && is_write(request.method(), request.uri().path()) {
+        return RegistryError::ReadOnly(format!(
+            "This node is read-only, disable it with PUT {} or write to another node",
+            TOGGLE_PATH
+        ))
+        .into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_app;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use serde_json::{Value, json};
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_is_write() {
+        assert!(!is_write(&Method::GET, "/services"));
+        assert!(!is_write(&Method::POST, "/resolve"));
+        assert!(!is_write(&Method::PUT, "/admin/read-only"));
+        assert!(is_write(&Method::GET, "/agents/ws"));
+        assert!(is_write(&Method::PUT, "/services/heartbeat"));
+        assert!(is_write(&Method::DELETE, "/services/checkout"));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_refuses_writes() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None).with_read_only(true);
+        let app = create_app(state);
+        let send = |method: Method, uri: &str, body: Value| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let registration = json!({
+            "service_name": "checkout",
+            "environment": "prod",
+            "address": "http://10.0.0.1:8080"
+        });
+
+        let response = app
+            .clone()
+            .oneshot(send(Method::POST, "/services", registration.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let response = app
+            .clone()
+            .oneshot(send(Method::GET, "/services", json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                Method::PUT,
+                "/admin/read-only",
+                json!({"enabled": false}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .oneshot(send(Method::POST, "/services", registration))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    routing::get,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::RequireAdmin;
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::{RegistryError, RegistryReadHandle, ServiceRegistry};
+use crate::model::stale_report::{StaleService, find_stale, parse_age};
+use crate::registry::profile_store::ProfileStore;
+use crate::tombstone::tombstone_stale;
+
+const DEFAULT_STALE_AGE: &str = "7d";
+
+#[derive(Deserialize)]
+struct StaleQuery {
+    #[serde(rename = "older-than")]
+    older_than: Option<String>,
+}
+
+impl StaleQuery {
+    fn older_than(&self) -> Result<u64, RegistryError> {
+        parse_age(self.older_than.as_deref().unwrap_or(DEFAULT_STALE_AGE))
+            .map_err(RegistryError::Validation)
+    }
+}
+
+pub fn reports_routes() -> Router<AppState> {
+    Router::new().route("/stale", get(stale_report).delete(tombstone_stale_services))
+}
+
+async fn stale_report(
+    State(registry): State<RegistryReadHandle>,
+    State(clock): State<SharedClock>,
+    Query(query): Query<StaleQuery>,
+) -> Result<Json<Vec<StaleService>>, RegistryError> {
+    let older_than = query.older_than()?;
+    let registry = registry.read().await;
+    Ok(Json(find_stale(&registry.list(), older_than, clock.now())))
+}
+
+async fn tombstone_stale_services(
+    _admin: RequireAdmin,
+    State(registry): State<Arc<RwLock<dyn ServiceRegistry>>>,
+    State(profiles): State<Arc<RwLock<ProfileStore>>>,
+    State(clock): State<SharedClock>,
+    Query(query): Query<StaleQuery>,
+) -> Result<Json<Vec<StaleService>>, RegistryError> {
+    let older_than = query.older_than()?;
+    Ok(Json(
+        tombstone_stale(&registry, &profiles, older_than, clock.now()).await,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    async fn create_test_app() -> Router {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for (name, age) in [("dead", 8 * 86_400_000), ("alive", 0)] {
+            let mut entry = ServiceEntry::new(
+                name.parse().unwrap(),
+                "prod".parse().unwrap(),
+                "http://localhost:8080".to_string(),
+                HashMap::new(),
+                now(),
+            );
+            entry.last_heartbeat = now() - age;
+            registry.write().await.register(entry).unwrap();
+        }
+        reports_routes().with_state(AppState::new(registry, HealthPolicy::default(), None))
+    }
+
+    async fn send_request(app: Router, method: Method, uri: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(json!({})))
+    }
+
+    #[tokio::test]
+    async fn test_stale_report() {
+        let app = create_test_app().await;
+
+        let (status, response) = send_request(app.clone(), Method::GET, "/stale").await;
+        assert_eq!(status, StatusCode::OK);
+        let stale = response.as_array().unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0]["service_name"], "dead");
+
+        let (_, response) = send_request(app.clone(), Method::GET, "/stale?older-than=9d").await;
+        assert!(response.as_array().unwrap().is_empty());
+
+        let (status, _) = send_request(app.clone(), Method::GET, "/stale?older-than=soon").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) =
+            send_request(app, Method::GET, "/stale?older-than=18446744073709551615d").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_tombstone_stale_services() {
+        let app = create_test_app().await;
+
+        let (status, response) = send_request(app.clone(), Method::DELETE, "/stale").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.as_array().unwrap().len(), 1);
+
+        let (_, response) = send_request(app, Method::GET, "/stale?older-than=0s").await;
+        let remaining = response.as_array().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0]["service_name"], "alive");
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::post};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::cache_hints::{CacheHints, CacheTtl};
+use crate::api::services::{ServiceEntryResponse, resolvable, scripted};
+use crate::api::validation::{FieldError, ValidJson, Validate};
+use crate::api::view::ResolveView;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::RegistryReadHandle;
+use crate::registry::profile_store::ProfileStore;
+use crate::registry::resolve_cache::ResolveCache;
+use crate::registry::service_meta_store::ServiceMetaStore;
+
+/// Upper bound on the services resolved by a single request
+const MAX_RESOLVE_TARGETS: usize = 500;
+
+#[derive(Deserialize)]
+struct ResolveTarget {
+    service_name: ServiceName,
+    environment: Environment,
+}
+
+impl Validate for ResolveTarget {}
+
+#[derive(Deserialize)]
+struct ResolveRequest(Vec<ResolveTarget>);
+
+impl Validate for ResolveRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        if self.0.len() > MAX_RESOLVE_TARGETS {
+            return vec![FieldError::new(
+                "",
+                &format!(
+                    "at most {} services can be resolved at once",
+                    MAX_RESOLVE_TARGETS
+                ),
+            )];
+        }
+        self.0.validate()
+    }
+}
+
+#[derive(Serialize)]
+struct ResolveResult {
+    service_name: ServiceName,
+    environment: Environment,
+    instances: Vec<ServiceEntryResponse>,
+    cache: CacheHints,
+}
+
+pub fn resolve_routes() -> Router<AppState> {
+    Router::new().route("/", post(resolve_many))
+}
+
+/// Resolves every requested service and environment under a single registry read,
+/// in request order. Unknown services are looked up in DNS when the fallback is
+/// enabled and are otherwise returned without instances, like services the
+/// caller may not call.
+async fn resolve_many(
+    State(registry): State<RegistryReadHandle>,
+    State(meta_store): State<Arc<RwLock<ServiceMetaStore>>>,
+    State(profiles): State<Arc<RwLock<ProfileStore>>>,
+    State(cache_ttl): State<CacheTtl>,
+    State(resolve_cache): State<Arc<ResolveCache>>,
+    view: ResolveView,
+    ValidJson(ResolveRequest(targets)): ValidJson<ResolveRequest>,
+) -> (CacheHints, Json<Vec<ResolveResult>>) {
+    let registry = registry.read().await;
+    let meta_store = meta_store.read().await;
+    let profiles = profiles.read().await;
+
+    let mut unknown = Vec::new();
+    let mut results: Vec<ResolveResult> = targets
+        .into_iter()
+        .enumerate()
+        .map(|(index, target)| {
+            let registered = if view.may_call(&target.service_name) {
+                let registered = resolve_cache.resolve(
+                    &*registry,
+                    &target.service_name,
+                    &target.environment,
+                    view.at,
+                );
+                if registered.is_empty() {
+                    unknown.push(index);
+                }
+                registered
+            } else {
+                Vec::new()
+            };
+            let meta = meta_store
+                .get(&target.service_name, &target.environment)
+                .cloned();
+            let instances = scripted(
+                resolvable(registered, &view.policy, view.at),
+                profiles.get(&target.service_name),
+                &view,
+            )
+            .iter()
+            .map(|entry| view.response(entry).with_meta(meta.clone()))
+            .collect();
+
+            let cache =
+                CacheHints::for_service(&*registry, &profiles, cache_ttl, &target.service_name);
+
+            ResolveResult {
+                service_name: target.service_name,
+                environment: target.environment,
+                instances,
+                cache,
+            }
+        })
+        .collect();
+    drop(registry);
+    drop(meta_store);
+    drop(profiles);
+
+    if view.dns_fallback.enabled {
+        let lookups = unknown.iter().map(|&index| {
+            let result = &results[index];
+            view.dns_fallback
+                .lookup(&result.service_name, &result.environment, view.at)
+        });
+        for (index, entries) in unknown.iter().zip(join_all(lookups).await) {
+            results[*index].instances = entries.iter().map(|entry| view.response(entry)).collect();
+        }
+    }
+
+    // Valid for as long as every result is
+    let hints = results
+        .iter()
+        .map(|result| result.cache)
+        .reduce(CacheHints::combine)
+        .unwrap_or(CacheHints {
+            max_age: cache_ttl.0,
+            index: 0,
+        });
+    (hints, Json(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    async fn send_request(app: Router, payload: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/")
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_many() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for (name, environment) in [
+            ("payments", "prod"),
+            ("payments", "prod"),
+            ("search", "dev"),
+        ] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    environment.parse().unwrap(),
+                    "http://localhost:8080".to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+        let app =
+            resolve_routes().with_state(AppState::new(registry, HealthPolicy::default(), None));
+
+        let (status, response) = send_request(
+            app.clone(),
+            json!([
+                { "service_name": "search", "environment": "dev" },
+                { "service_name": "payments", "environment": "prod" },
+                { "service_name": "payments", "environment": "dev" }
+            ]),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response[0]["service_name"], "search");
+        assert_eq!(response[0]["instances"].as_array().unwrap().len(), 1);
+        assert_eq!(response[1]["instances"].as_array().unwrap().len(), 2);
+        assert_eq!(response[2]["environment"], "dev");
+        assert_eq!(response[2]["instances"], json!([]));
+        assert_eq!(response[0]["cache"], json!({ "max_age": 5, "index": 3 }));
+        assert_eq!(response[1]["cache"]["index"], 2);
+
+        let (status, response) = send_request(app, json!([{ "service_name": "search" }])).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response["fields"][0]["field"], "[0].environment");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_unknown_through_dns() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None).with_dns_fallback(true);
+
+        let (status, response) = send_request(
+            resolve_routes().with_state(state),
+            json!([{ "service_name": "localhost", "environment": "prod" }]),
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let instances = response[0]["instances"].as_array().unwrap();
+        assert!(!instances.is_empty());
+        assert_eq!(instances[0]["source"], "dns");
+        assert_eq!(instances[0]["health"], "Healthy");
+    }
+}
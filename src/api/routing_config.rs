@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::services::{resolvable, scripted};
+use crate::api::view::ResolveView;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{RegistryError, RegistryReadHandle};
+use crate::registry::intention_store::IntentionStore;
+use crate::registry::profile_store::ProfileStore;
+
+#[derive(Deserialize)]
+struct RoutingConfigQuery {
+    caller: ServiceName,
+    environment: Environment,
+}
+
+#[derive(Serialize)]
+struct Upstream {
+    service_name: ServiceName,
+    /// Addresses of the resolvable instances, as the caller can reach them
+    addresses: Vec<String>,
+}
+
+/// Everything a caller needs to reach its dependencies, fetched in one call
+#[derive(Serialize)]
+struct RoutingConfig {
+    caller: ServiceName,
+    environment: Environment,
+    /// Registry index the document reflects
+    index: u64,
+    upstreams: Vec<Upstream>,
+}
+
+pub fn routing_config_routes() -> Router<AppState> {
+    Router::new().route("/", get(get_routing_config))
+}
+
+/// Resolves every dependency declared in the caller's profile in one environment.
+/// Dependencies the caller may not call are listed without addresses
+async fn get_routing_config(
+    State(registry): State<RegistryReadHandle>,
+    State(profiles): State<Arc<RwLock<ProfileStore>>>,
+    State(intentions): State<Arc<RwLock<IntentionStore>>>,
+    view: ResolveView,
+    Query(query): Query<RoutingConfigQuery>,
+) -> Result<Json<RoutingConfig>, RegistryError> {
+    let registry = registry.read().await;
+    let profiles = profiles.read().await;
+    let intentions = intentions.read().await;
+    let dependencies = profiles
+        .get(&query.caller)
+        .map(|profile| profile.dependencies.clone())
+        .ok_or(RegistryError::NotFound)?;
+
+    let upstreams = dependencies
+        .into_iter()
+        .map(|service_name| {
+            let addresses = if intentions.is_allowed(query.caller.as_str(), service_name.as_str()) {
+                let registered = registry.resolve(&service_name, &query.environment);
+                scripted(
+                    resolvable(registered, &view.policy, view.at),
+                    profiles.get(&service_name),
+                    &view,
+                )
+                .iter()
+                .map(|entry| view.response(entry).address().to_string())
+                .collect()
+            } else {
+                Vec::new()
+            };
+            Upstream {
+                service_name,
+                addresses,
+            }
+        })
+        .collect();
+
+    Ok(Json(RoutingConfig {
+        caller: query.caller,
+        environment: query.environment,
+        index: registry.last_index(),
+        upstreams,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::intention::{Intention, IntentionAction};
+    use crate::model::service_profile::ServiceProfile;
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter, ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_routing_config_resolves_dependencies() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for (name, address) in [
+            ("db", "postgres://db:5432"),
+            ("cache", "redis://cache:6379"),
+        ] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    "prod".parse().unwrap(),
+                    address.to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+        let state = AppState::new(registry, HealthPolicy::default(), None);
+        state.profiles.write().await.put(
+            "web".parse().unwrap(),
+            ServiceProfile {
+                dependencies: ["db", "cache", "search"]
+                    .iter()
+                    .map(|name| name.parse().unwrap())
+                    .collect(),
+                ..ServiceProfile::default()
+            },
+        );
+        state.intentions.write().await.upsert(Intention {
+            source: "web".to_string(),
+            destination: "cache".to_string(),
+            action: IntentionAction::Deny,
+            created_at: 0,
+        });
+        let app = routing_config_routes().with_state(state);
+
+        let request = Request::builder()
+            .uri("/?caller=web&environment=prod")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let config: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(config["upstreams"][0]["service_name"], "db");
+        assert_eq!(config["upstreams"][0]["addresses"][0], "postgres://db:5432");
+        assert!(
+            config["upstreams"][1]["addresses"]
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            config["upstreams"][2]["addresses"]
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+
+        let request = Request::builder()
+            .uri("/?caller=unknown&environment=prod")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    routing::get,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::cache_hints::{CacheHints, CacheTtl};
+use crate::api::services::ServiceEntryResponse;
+use crate::api::view::ResolveView;
+use crate::model::search::{SearchField, SearchQuery};
+use crate::model::service_registry::{RegistryError, RegistryReadHandle};
+use crate::registry::profile_store::ProfileStore;
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    field: Option<SearchField>,
+    #[serde(default)]
+    regex: bool,
+}
+
+pub fn search_routes() -> Router<AppState> {
+    Router::new().route("/", get(search))
+}
+
+async fn search(
+    State(registry): State<RegistryReadHandle>,
+    State(profiles): State<Arc<RwLock<ProfileStore>>>,
+    State(cache_ttl): State<CacheTtl>,
+    view: ResolveView,
+    Query(params): Query<SearchParams>,
+) -> Result<(CacheHints, Json<Vec<ServiceEntryResponse>>), RegistryError> {
+    let query = if params.regex {
+        SearchQuery::regex(&params.q, params.field)
+            .map_err(|e| RegistryError::Validation(e.to_string()))?
+    } else {
+        SearchQuery::substring(&params.q, params.field)
+    };
+
+    let registry = registry.read().await;
+    let profiles = profiles.read().await;
+    let mut matches: Vec<_> = registry
+        .list()
+        .into_iter()
+        // Match against what the caller would see, so masked values cannot be probed
+        .map(|entry| view.redact(entry))
+        .filter(|entry| {
+            view.may_browse(entry, profiles.visibility(&entry.service_name)) && query.matches(entry)
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        (&a.service_name, &a.environment, &a.id).cmp(&(&b.service_name, &b.environment, &b.id))
+    });
+
+    Ok((
+        CacheHints::for_registry(&*registry, cache_ttl),
+        Json(matches.iter().map(|entry| view.listing(entry)).collect()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter, ServiceEntry, now};
+    use crate::model::tag_masking::{MASKED_VALUE, TagMasking};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{
+        body::Body,
+        http::{Method, Request, StatusCode},
+    };
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    async fn create_test_app() -> Router {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for (name, address) in [
+            ("payments", "http://10.0.3.12:8080"),
+            ("search", "http://10.0.4.7:8080"),
+        ] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    "prod".parse().unwrap(),
+                    address.to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+        search_routes().with_state(AppState::new(registry, HealthPolicy::default(), None))
+    }
+
+    async fn send_request(app: Router, uri: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_search_by_address() {
+        let app = create_test_app().await;
+
+        let (status, response) = send_request(app, "/?q=10.0.3.&field=address").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.as_array().unwrap().len(), 1);
+        assert_eq!(response[0]["service_name"], "payments");
+    }
+
+    #[tokio::test]
+    async fn test_search_regex() {
+        let app = create_test_app().await;
+
+        let (status, response) = send_request(app.clone(), "/?q=%5E(pay%7Csea)&regex=true").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.as_array().unwrap().len(), 2);
+
+        let (status, response) = send_request(app, "/?q=(&regex=true").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(response["error_code"], "validation_failed");
+    }
+
+    #[tokio::test]
+    async fn test_search_cannot_probe_masked_tags() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        registry
+            .write()
+            .await
+            .register(ServiceEntry::new(
+                "db".parse().unwrap(),
+                "prod".parse().unwrap(),
+                "http://10.0.1.5:5432".to_string(),
+                HashMap::from([("password".to_string(), "hunter2".to_string())]),
+                now(),
+            ))
+            .unwrap();
+        let state =
+            AppState::new(registry, HealthPolicy::default(), None).with_tag_masking(TagMasking {
+                tags: vec!["password".to_string()],
+                unmask: Vec::new(),
+            });
+        let app = search_routes().with_state(state);
+
+        let (_, response) = send_request(app.clone(), "/?q=hunter2&field=tags").await;
+        assert!(response.as_array().unwrap().is_empty());
+
+        let (_, response) = send_request(app, "/?q=db").await;
+        assert_eq!(response[0]["tags"]["password"], MASKED_VALUE);
+    }
+}
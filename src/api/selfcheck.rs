@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::model::selfcheck::SelfCheckReport;
+
+pub fn selfcheck_routes() -> Router<AppState> {
+    Router::new().route("/", get(get_selfcheck))
+}
+
+/// Answers 503 while the last canary run failed so external probes can alert on it
+async fn get_selfcheck(
+    State(report): State<Arc<RwLock<SelfCheckReport>>>,
+) -> (StatusCode, Json<SelfCheckReport>) {
+    let report = report.read().await.clone();
+    let status = if report.is_healthy() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{body::Body, http::Request};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn get_report(state: AppState) -> (StatusCode, Value) {
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = selfcheck_routes()
+            .with_state(state)
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_selfcheck_reflects_last_run() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None);
+
+        let (status, report) = get_report(state.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["enabled"], false);
+
+        state
+            .selfcheck
+            .write()
+            .await
+            .record_failure(1, "Xolotl responded with 500".to_string());
+        let (status, report) = get_report(state.clone()).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report["failures"], 1);
+
+        state.selfcheck.write().await.record_success(2, 3);
+        let (status, report) = get_report(state).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["last_latency_ms"], 3);
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+use crate::api::buffer_body;
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::{InstanceId, ServiceName};
+use crate::model::payload_signing::{
+    SigningHandle, SigningSecrets, XOLOTL_SIGNATURE, XOLOTL_TIMESTAMP,
+};
+use crate::model::service_registry::{RegistryError, RegistryReadHandle, RegistryReader};
+
+/// Whether the body of a request registers or refreshes instances, the writes
+/// an impostor would use to take over a service
+fn is_signed_write(method: &Method, path: &str) -> bool {
+    matches!(
+        (method, path),
+        (&Method::POST, "/services" | "/services/")
+            | (
+                &Method::PUT,
+                "/services/heartbeat" | "/services/heartbeat/batch"
+            )
+    )
+}
+
+/// Services a registration, heartbeat or batch of heartbeats writes to. Batch
+/// targets addressed by id are looked up in the registry
+fn written_services(body: &Value, registry: &dyn RegistryReader) -> BTreeSet<ServiceName> {
+    let targets = match body {
+        Value::Array(targets) => targets.iter().collect(),
+        target => vec![target],
+    };
+    targets
+        .into_iter()
+        .filter_map(|target| match (&target["service_name"], &target["id"]) {
+            (Value::String(service_name), _) => service_name.parse().ok(),
+            (_, Value::String(id)) => registry
+                .get(&id.parse::<InstanceId>().ok()?)
+                .map(|entry| entry.service_name),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Middleware refusing registrations and heartbeats of services with a signing
+/// secret unless their body is signed with it. A batch naming several such
+/// services has a single signature, so it only passes when they share a secret
+pub async fn verify_signatures(
+    State(secrets): State<SigningHandle>,
+    State(registry): State<RegistryReadHandle>,
+    State(clock): State<SharedClock>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(secrets) = secrets else {
+        return next.run(request).await;
+    };
+    if !is_signed_write(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = match buffer_body(body).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    // Malformed bodies are left for the handler to reject with a proper error
+    if let Ok(payload) = serde_json::from_slice::<Value>(&body) {
+        let services = written_services(&payload, &*registry.read().await);
+        if let Err(message) = verify_all(&secrets, &services, &body, &parts.headers, clock.now()) {
+            return RegistryError::Unauthorized(message).into_response();
+        }
+    }
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+fn verify_all(
+    secrets: &SigningSecrets,
+    services: &BTreeSet<ServiceName>,
+    body: &[u8],
+    headers: &HeaderMap,
+    at: u64,
+) -> Result<(), String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    services.iter().try_for_each(|service_name| {
+        secrets.verify(
+            service_name,
+            body,
+            header(XOLOTL_SIGNATURE),
+            header(XOLOTL_TIMESTAMP),
+            at,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AppState;
+    use crate::create_app;
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::payload_signing::sign;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::http::StatusCode;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_is_signed_write() {
+        assert!(is_signed_write(&Method::POST, "/services"));
+        assert!(is_signed_write(&Method::PUT, "/services/heartbeat/batch"));
+        assert!(!is_signed_write(&Method::GET, "/services"));
+        assert!(!is_signed_write(&Method::DELETE, "/services/sensors"));
+    }
+
+    /// Signature of `body` signed at the time of `clock`, with the timestamp it covers
+    fn signed(secret: &str, body: &Value, clock: &VirtualClock) -> (String, u64) {
+        let at = clock.now();
+        (sign(secret, at, body.to_string().as_bytes()), at)
+    }
+
+    async fn send(
+        app: &axum::Router,
+        uri: &str,
+        body: &Value,
+        signature: Option<(String, u64)>,
+    ) -> StatusCode {
+        let method = if uri == "/services" {
+            Method::POST
+        } else {
+            Method::PUT
+        };
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some((signature, at)) = signature {
+            request = request
+                .header(XOLOTL_SIGNATURE, signature)
+                .header(XOLOTL_TIMESTAMP, at.to_string());
+        }
+        let request = request.body(Body::from(body.to_string())).unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_signed_registrations_and_heartbeats() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let state = AppState::new(registry, HealthPolicy::default(), None)
+            .with_clock(Arc::new(clock.clone()))
+            .with_signing_secrets("sensors:s3cret".parse().unwrap());
+        let app = create_app(state);
+
+        let registration = json!({
+            "service_name": "sensors",
+            "environment": "edge",
+            "address": "http://10.0.0.7:8080",
+        });
+        let signature = signed("s3cret", &registration, &clock);
+        assert_eq!(
+            send(&app, "/services", &registration, None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(
+                &app,
+                "/services",
+                &registration,
+                Some(signed("guess", &registration, &clock))
+            )
+            .await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(&app, "/services", &registration, Some(signature)).await,
+            StatusCode::OK
+        );
+
+        // Services without a secret still register unsigned
+        let payments = json!({
+            "service_name": "payments",
+            "environment": "prod",
+            "address": "http://10.0.0.1:8080",
+        });
+        assert_eq!(
+            send(&app, "/services", &payments, None).await,
+            StatusCode::OK
+        );
+
+        let heartbeat = json!({ "service_name": "sensors", "environment": "edge" });
+        assert_eq!(
+            send(&app, "/services/heartbeat", &heartbeat, None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let signature = signed("s3cret", &heartbeat, &clock);
+        assert_eq!(
+            send(&app, "/services/heartbeat", &heartbeat, Some(signature)).await,
+            StatusCode::OK
+        );
+
+        // Batch targets addressed by id are traced back to their service
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/services/sensors/edge")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let instances: Value = serde_json::from_slice(&body).unwrap();
+        let batch = json!([{ "id": instances[0]["id"] }]);
+        assert_eq!(
+            send(&app, "/services/heartbeat/batch", &batch, None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let signature = signed("s3cret", &batch, &clock);
+        assert_eq!(
+            send(&app, "/services/heartbeat/batch", &batch, Some(signature)).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_refused() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None)
+            .with_signing_secrets("sensors:s3cret".parse().unwrap());
+        let app = create_app(state);
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/services")
+            .header("content-type", "application/json")
+            .body(Body::from(vec![b' '; crate::api::MAX_BODY_BYTES + 1]))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
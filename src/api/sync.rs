@@ -0,0 +1,308 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::services::ServiceEntryResponse;
+use crate::api::view::ResolveView;
+use crate::model::identifiers::InstanceId;
+use crate::model::service_registry::{RegistryReadHandle, ServiceEntry};
+use crate::registry::profile_store::ProfileStore;
+
+#[derive(Deserialize)]
+struct SyncQuery {
+    /// Only services whose name starts with this prefix, every service by default
+    #[serde(default)]
+    prefix: String,
+    /// Index returned by the previous sync, 0 for a full snapshot
+    #[serde(default)]
+    since: u64,
+    /// Epoch returned by the previous sync, a different one forces a full snapshot
+    epoch: Option<String>,
+    /// Time returned by the previous sync, instances heartbeating or changing
+    /// health after it are sent again
+    #[serde(default)]
+    synced_at: u64,
+}
+
+/// Identifies this run of the server. Indexes start over on a restart, so a
+/// replica synced against an earlier run starts over too even once the index
+/// has passed the one it holds
+#[derive(Clone)]
+pub struct SyncEpoch(pub Arc<str>);
+
+impl Default for SyncEpoch {
+    fn default() -> Self {
+        SyncEpoch(Arc::from(InstanceId::generate().as_str()))
+    }
+}
+
+/// Changes to bring a replica from `since` up to `index`
+#[derive(Serialize)]
+struct SyncResponse {
+    /// Pass as `since` on the next sync
+    index: u64,
+    /// Pass as `epoch` on the next sync
+    epoch: String,
+    /// Pass as `synced_at` on the next sync
+    synced_at: u64,
+    /// Whether the replica must be replaced by `upserts` instead of patched,
+    /// because `since` is 0, older than the events the registry keeps or from another epoch
+    full: bool,
+    /// Instances that are new, changed, heartbeated or changed health, in their current state
+    upserts: Vec<ServiceEntryResponse>,
+    /// Ids of instances that are gone
+    removals: Vec<InstanceId>,
+}
+
+pub fn sync_routes() -> Router<AppState> {
+    Router::new().route("/", get(sync))
+}
+
+/// Incremental replication of the registry, or of the services under a prefix,
+/// so agents fetch what changed since their last sync instead of full listings
+async fn sync(
+    State(registry): State<RegistryReadHandle>,
+    State(profiles): State<Arc<RwLock<ProfileStore>>>,
+    State(SyncEpoch(epoch)): State<SyncEpoch>,
+    view: ResolveView,
+    Query(query): Query<SyncQuery>,
+) -> Json<SyncResponse> {
+    let registry = registry.read().await;
+    let profiles = profiles.read().await;
+    let index = registry.last_index();
+    let subscribed = |entry: &ServiceEntry| {
+        entry.service_name.as_str().starts_with(&query.prefix)
+            && view.may_browse(entry, profiles.visibility(&entry.service_name))
+    };
+
+    let events = registry.events(query.since);
+    // A replica ahead of the registry saw a registry that has since restarted
+    let full = query.since == 0
+        || query.epoch.as_deref() != Some(&*epoch)
+        || query.since > index
+        || (query.since < index
+            && events
+                .first()
+                .is_none_or(|event| event.index > query.since + 1));
+    if full {
+        return Json(SyncResponse {
+            index,
+            epoch: epoch.to_string(),
+            synced_at: view.at,
+            full,
+            upserts: registry
+                .list()
+                .iter()
+                .filter(|entry| subscribed(entry))
+                .map(|entry| view.listing(entry))
+                .collect(),
+            removals: Vec::new(),
+        });
+    }
+
+    let current = registry.list();
+    // Heartbeats are not registry events, so instances that heartbeated or
+    // crossed a health threshold since the previous sync are sent as well
+    let refreshed = current.iter().filter(|entry| {
+        subscribed(entry)
+            && (entry.last_heartbeat > query.synced_at
+                || entry.health_status(&view.policy, query.synced_at)
+                    != entry.health_status(&view.policy, view.at))
+    });
+    let changed: BTreeSet<&InstanceId> = events
+        .iter()
+        .filter(|event| event.service_name.as_str().starts_with(&query.prefix))
+        .map(|event| &event.instance_id)
+        .chain(refreshed.map(|entry| &entry.id))
+        .collect();
+    let mut upserts = Vec::new();
+    let mut removals = Vec::new();
+    for id in changed {
+        match current.iter().find(|entry| &entry.id == id) {
+            Some(entry) if subscribed(entry) => upserts.push(view.listing(entry)),
+            _ => removals.push(id.clone()),
+        }
+    }
+
+    Json(SyncResponse {
+        index,
+        epoch: epoch.to_string(),
+        synced_at: view.at,
+        full,
+        upserts,
+        removals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::service_registry::{HealthPolicy, RegistryWriter};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{body::Body, http::Request};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    async fn get_sync(app: &Router, uri: &str) -> Value {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// The sync that follows `previous`
+    fn next(prefix: &str, previous: &Value) -> String {
+        format!(
+            "/?prefix={}&since={}&epoch={}&synced_at={}",
+            prefix,
+            previous["index"],
+            previous["epoch"].as_str().unwrap(),
+            previous["synced_at"]
+        )
+    }
+
+    fn entry(name: &str, clock: &VirtualClock) -> ServiceEntry {
+        ServiceEntry::new(
+            name.parse().unwrap(),
+            "prod".parse().unwrap(),
+            format!("http://{}:8080", name),
+            HashMap::new(),
+            clock.now(),
+        )
+    }
+
+    /// A registry and sync routes that share a virtual clock
+    fn sync_app() -> (Arc<RwLock<InMemoryRegistry>>, VirtualClock, Router) {
+        let clock = VirtualClock::new(1_000_000);
+        let registry = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let app = sync_routes().with_state(
+            AppState::new(registry.clone(), HealthPolicy::default(), None)
+                .with_clock(Arc::new(clock.clone())),
+        );
+        (registry, clock, app)
+    }
+
+    #[tokio::test]
+    async fn test_sync_deltas_by_prefix() {
+        let (registry, clock, app) = sync_app();
+        let payments = entry("payments", &clock);
+        registry.write().await.register(payments.clone()).unwrap();
+        registry
+            .write()
+            .await
+            .register(entry("search", &clock))
+            .unwrap();
+
+        let snapshot = get_sync(&app, "/?prefix=pay").await;
+        assert_eq!(snapshot["full"], true);
+        assert_eq!(snapshot["upserts"].as_array().unwrap().len(), 1);
+        let since = snapshot["index"].as_u64().unwrap();
+
+        clock.advance(Duration::from_secs(1));
+        let payments_2 = entry("payments-gateway", &clock);
+        registry.write().await.register(payments_2.clone()).unwrap();
+        registry
+            .write()
+            .await
+            .register(entry("search", &clock))
+            .unwrap();
+        registry
+            .write()
+            .await
+            .deregister_instance(&payments.id)
+            .unwrap();
+
+        clock.advance(Duration::from_secs(1));
+        let delta = get_sync(&app, &next("pay", &snapshot)).await;
+        assert_eq!(delta["full"], false);
+        assert_eq!(delta["index"], since + 3);
+        assert_eq!(delta["upserts"].as_array().unwrap().len(), 1);
+        assert_eq!(delta["upserts"][0]["id"], payments_2.id.as_str());
+        assert_eq!(delta["removals"], serde_json::json!([payments.id]));
+
+        clock.advance(Duration::from_secs(1));
+        let caught_up = get_sync(&app, &next("pay", &delta)).await;
+        assert_eq!(caught_up["full"], false);
+        assert!(caught_up["upserts"].as_array().unwrap().is_empty());
+        assert!(caught_up["removals"].as_array().unwrap().is_empty());
+
+        // A replica from before a restart starts over
+        let ahead = get_sync(&app, "/?since=1000").await;
+        assert_eq!(ahead["full"], true);
+        assert_eq!(ahead["upserts"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_sync_sends_heartbeats_and_health() {
+        let (registry, clock, app) = sync_app();
+        let payments = entry("payments", &clock);
+        let idle = entry("payments-batch", &clock);
+        registry.write().await.register(payments.clone()).unwrap();
+        registry.write().await.register(idle.clone()).unwrap();
+        let snapshot = get_sync(&app, "/?prefix=pay").await;
+
+        clock.advance(Duration::from_secs(20));
+        registry
+            .write()
+            .await
+            .heartbeat_instance(&payments.id)
+            .unwrap();
+        let heartbeat = get_sync(&app, &next("pay", &snapshot)).await;
+        assert_eq!(heartbeat["full"], false);
+        assert_eq!(heartbeat["upserts"].as_array().unwrap().len(), 1);
+        assert_eq!(heartbeat["upserts"][0]["id"], payments.id.as_str());
+        assert_eq!(heartbeat["upserts"][0]["health"], "Healthy");
+
+        // The idle instance goes stale without any change to the registry
+        clock.advance(Duration::from_secs(15));
+        let stale = get_sync(&app, &next("pay", &heartbeat)).await;
+        assert_eq!(stale["upserts"].as_array().unwrap().len(), 1);
+        assert_eq!(stale["upserts"][0]["id"], idle.id.as_str());
+        assert_eq!(stale["upserts"][0]["health"], "Stale");
+    }
+
+    #[tokio::test]
+    async fn test_sync_from_another_epoch_starts_over() {
+        let (registry, clock, app) = sync_app();
+        registry
+            .write()
+            .await
+            .register(entry("payments", &clock))
+            .unwrap();
+        registry
+            .write()
+            .await
+            .register(entry("search", &clock))
+            .unwrap();
+        let snapshot = get_sync(&app, "/").await;
+
+        // A restarted server that has since passed the index the replica holds
+        let (restarted, clock, restarted_app) = sync_app();
+        for name in ["payments", "search", "orders"] {
+            restarted
+                .write()
+                .await
+                .register(entry(name, &clock))
+                .unwrap();
+        }
+        let resync = get_sync(&restarted_app, &next("", &snapshot)).await;
+        assert_ne!(resync["epoch"], snapshot["epoch"]);
+        assert_eq!(resync["full"], true);
+        assert_eq!(resync["upserts"].as_array().unwrap().len(), 3);
+    }
+}
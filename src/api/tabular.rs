@@ -0,0 +1,152 @@
+use axum::{
+    Json,
+    extract::FromRequestParts,
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        request::Parts,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+pub const CSV: &str = "text/csv; charset=utf-8";
+pub const YAML: &str = "application/yaml";
+
+/// Representation of a listing picked from the `Accept` header, JSON unless
+/// the client prefers CSV or YAML
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tabular {
+    #[default]
+    Json,
+    Csv,
+    Yaml,
+}
+
+impl Tabular {
+    /// The first media type of an `Accept` value this server can produce, in
+    /// the order the client listed them
+    pub fn from_accept(accept: &str) -> Self {
+        accept
+            .split(',')
+            .filter_map(|media_type| {
+                match media_type
+                    .split(';')
+                    .next()?
+                    .trim()
+                    .to_ascii_lowercase()
+                    .as_str()
+                {
+                    "text/csv" => Some(Tabular::Csv),
+                    "application/yaml" | "application/x-yaml" | "text/yaml" => Some(Tabular::Yaml),
+                    "application/json" | "*/*" => Some(Tabular::Json),
+                    _ => None,
+                }
+            })
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Renders `rows`, with one CSV column per name in `columns`
+    pub fn render<T: Serialize>(self, rows: &[T], columns: &[&str]) -> Response {
+        match self {
+            Tabular::Json => Json(rows).into_response(),
+            Tabular::Csv => {
+                let rows: Vec<Value> = rows
+                    .iter()
+                    .map(|row| serde_json::to_value(row).unwrap_or_default())
+                    .collect();
+                ([(CONTENT_TYPE, CSV)], to_csv(&rows, columns)).into_response()
+            }
+            Tabular::Yaml => (
+                [(CONTENT_TYPE, YAML)],
+                serde_yaml::to_string(rows).unwrap_or_default(),
+            )
+                .into_response(),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Tabular
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .map(Tabular::from_accept)
+            .unwrap_or_default())
+    }
+}
+
+/// A header line naming `columns`, then one line per row. Nested objects are
+/// flattened to `key=value` pairs separated by `;`
+fn to_csv(rows: &[Value], columns: &[&str]) -> String {
+    let mut csv = csv_line(columns.iter().map(|column| column.to_string()));
+    for row in rows {
+        csv.push_str(&csv_line(columns.iter().map(|column| cell(&row[column]))));
+    }
+    csv
+}
+
+fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    let mut line = cells.map(|cell| quote(&cell)).collect::<Vec<_>>().join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, cell(value)))
+            .collect::<Vec<_>>()
+            .join(";"),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
+
+/// Quotes a cell holding a separator, quote or line break, as RFC 4180 asks
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(Tabular::from_accept("text/csv"), Tabular::Csv);
+        assert_eq!(
+            Tabular::from_accept("text/html, application/yaml;q=0.9"),
+            Tabular::Yaml
+        );
+        assert_eq!(Tabular::from_accept("*/*, text/csv"), Tabular::Json);
+        assert_eq!(Tabular::from_accept("text/html"), Tabular::Json);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let rows = vec![
+            json!({ "name": "payments", "tags": { "region": "eu", "team": "core" } }),
+            json!({ "name": "say \"hi\", world" }),
+        ];
+        assert_eq!(
+            to_csv(&rows, &["name", "tags"]),
+            "name,tags\r\npayments,region=eu;team=core\r\n\"say \"\"hi\"\", world\",\r\n"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::api::AppState;
+use crate::api::auth::{AdminToken, RequireAdmin};
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::RegistryError;
+use crate::registry::token_store::{IssuedSecret, IssuedToken, TokenEvent, TokenStore};
+
+/// How long a rotated token keeps working unless the rotation says otherwise
+const DEFAULT_GRACE_SECONDS: u64 = 24 * 60 * 60;
+
+#[derive(Deserialize)]
+struct IssueRequest {
+    label: String,
+    /// Lifetime of the token, it never expires when unset
+    ttl_seconds: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct RotateRequest {
+    /// How long the rotated token keeps working alongside its successor
+    grace_seconds: Option<u64>,
+    /// Lifetime of the successor, it never expires when unset
+    ttl_seconds: Option<u64>,
+}
+
+pub fn tokens_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_tokens).post(issue_token))
+        .route("/events", get(list_events))
+        .route("/{id}", delete(revoke_token))
+        .route("/{id}/rotate", post(rotate_token))
+}
+
+/// Millis in `seconds`, refusing durations too long to count from `now`
+fn millis(field: &str, seconds: u64, now: u64) -> Result<u64, RegistryError> {
+    seconds
+        .checked_mul(1000)
+        .filter(|millis| now.checked_add(*millis).is_some())
+        .ok_or_else(|| RegistryError::Validation(format!("{} is out of range", field)))
+}
+
+fn expires_at(now: u64, ttl_seconds: Option<u64>) -> Result<Option<u64>, RegistryError> {
+    ttl_seconds
+        .map(|ttl| millis("ttl_seconds", ttl, now).map(|ttl| now + ttl))
+        .transpose()
+}
+
+async fn list_tokens(
+    _admin: RequireAdmin,
+    State(tokens): State<Arc<RwLock<TokenStore>>>,
+    State(clock): State<SharedClock>,
+) -> Json<Vec<IssuedToken>> {
+    Json(tokens.write().await.list(clock.now()))
+}
+
+async fn list_events(
+    _admin: RequireAdmin,
+    State(tokens): State<Arc<RwLock<TokenStore>>>,
+) -> Json<Vec<TokenEvent>> {
+    Json(tokens.read().await.events())
+}
+
+/// Issues a token accepted wherever the admin token is, returning its secret this once
+async fn issue_token(
+    _admin: RequireAdmin,
+    State(AdminToken(admin_token)): State<AdminToken>,
+    State(tokens): State<Arc<RwLock<TokenStore>>>,
+    State(clock): State<SharedClock>,
+    Json(request): Json<IssueRequest>,
+) -> Result<(StatusCode, Json<IssuedSecret>), RegistryError> {
+    // Without an admin token administrative actions are open and tokens would never be checked
+    if admin_token.is_none() {
+        return Err(RegistryError::Conflict(
+            "Tokens can only be issued when the server runs with --admin-token".to_string(),
+        ));
+    }
+    if request.label.trim().is_empty() {
+        return Err(RegistryError::Validation(
+            "Token label must not be empty".to_string(),
+        ));
+    }
+    let now = clock.now();
+    let expires_at = expires_at(now, request.ttl_seconds)?;
+    let issued = tokens
+        .write()
+        .await
+        .issue(request.label.trim(), expires_at, now);
+    println!(
+        "Issued admin token {} ({})",
+        issued.token.id, issued.token.label
+    );
+    Ok((StatusCode::CREATED, Json(issued)))
+}
+
+/// Issues a successor to a token, which keeps working until the grace window ends
+async fn rotate_token(
+    _admin: RequireAdmin,
+    State(tokens): State<Arc<RwLock<TokenStore>>>,
+    State(clock): State<SharedClock>,
+    Path(id): Path<String>,
+    request: Option<Json<RotateRequest>>,
+) -> Result<(StatusCode, Json<IssuedSecret>), RegistryError> {
+    let Json(request) = request.unwrap_or_default();
+    let now = clock.now();
+    let grace = millis(
+        "grace_seconds",
+        request.grace_seconds.unwrap_or(DEFAULT_GRACE_SECONDS),
+        now,
+    )?;
+    let expires_at = expires_at(now, request.ttl_seconds)?;
+    let successor = tokens.write().await.rotate(&id, grace, expires_at, now)?;
+    println!(
+        "Rotated admin token {} to {}, the old token expires in {}s",
+        id,
+        successor.token.id,
+        grace / 1000
+    );
+    Ok((StatusCode::CREATED, Json(successor)))
+}
+
+async fn revoke_token(
+    _admin: RequireAdmin,
+    State(tokens): State<Arc<RwLock<TokenStore>>>,
+    State(clock): State<SharedClock>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, RegistryError> {
+    tokens.write().await.revoke(&id, clock.now())?;
+    println!("Revoked admin token {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::VirtualClock;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::body::Body;
+    use axum::http::{Method, Request, header::AUTHORIZATION};
+    use serde_json::{Value, json};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    async fn send(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        token: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .header("content-type", "application/json");
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        let response = app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_issue_rotate_and_revoke() {
+        let clock = VirtualClock::new(0);
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), Some("root".to_string()))
+            .with_clock(Arc::new(clock.clone()));
+        let app = Router::new()
+            .nest("/admin/tokens", tokens_routes())
+            .with_state(state);
+
+        let (status, issued) = send(
+            &app,
+            Method::POST,
+            "/admin/tokens",
+            "root",
+            Some(json!({ "label": "ci", "ttl_seconds": 172_800 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(issued["expires_at"], 172_800_000);
+        let old = issued["secret"].as_str().unwrap().to_string();
+
+        // Issued tokens are accepted for administrative actions, including rotating themselves
+        let uri = format!("/admin/tokens/{}/rotate", issued["id"].as_str().unwrap());
+        let (status, successor) = send(
+            &app,
+            Method::POST,
+            &uri,
+            &old,
+            Some(json!({ "grace_seconds": 60 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let new = successor["secret"].as_str().unwrap().to_string();
+
+        let (status, listed) = send(&app, Method::GET, "/admin/tokens", &new, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(listed[0]["expires_at"], 60_000);
+        assert_eq!(listed[0]["successor"], successor["id"]);
+        assert!(listed[0].get("secret").is_none());
+
+        // The rotated token keeps working for the grace window
+        let (status, _) = send(&app, Method::GET, "/admin/tokens", &old, None).await;
+        assert_eq!(status, StatusCode::OK);
+        clock.advance(Duration::from_secs(60));
+        let (status, _) = send(&app, Method::GET, "/admin/tokens", &old, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (_, events) = send(&app, Method::GET, "/admin/tokens/events", &new, None).await;
+        let kinds: Vec<&str> = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["kind"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, ["Issued", "Issued", "Rotated", "UsedWhileExpiring"]);
+
+        let uri = format!("/admin/tokens/{}", successor["id"].as_str().unwrap());
+        let (status, _) = send(&app, Method::DELETE, &uri, "root", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, Method::GET, "/admin/tokens", &new, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_issue_requires_admin_token() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), None);
+        let app = tokens_routes().with_state(state);
+
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/",
+            "anything",
+            Some(json!({ "label": "ci" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_lifetimes_are_rejected() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(registry, HealthPolicy::default(), Some("root".to_string()));
+        let app = tokens_routes().with_state(state);
+
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/",
+            "root",
+            Some(json!({ "label": "ci", "ttl_seconds": u64::MAX })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, issued) = send(
+            &app,
+            Method::POST,
+            "/",
+            "root",
+            Some(json!({ "label": "ci" })),
+        )
+        .await;
+        let uri = format!("/{}/rotate", issued["id"].as_str().unwrap());
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            &uri,
+            "root",
+            Some(json!({ "grace_seconds": u64::MAX })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,248 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// A problem with one field of a request body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn from_serde(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        // serde_json appends the position, which is noise next to the field path
+        let message = error.inner().to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+
+        // Missing fields are reported on their parent, name the field itself instead
+        let path = error.path().to_string();
+        let field = match message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.strip_suffix('`'))
+        {
+            Some(missing) if path == "." => missing.to_string(),
+            Some(missing) => format!("{}.{}", path, missing),
+            None => path,
+        };
+
+        FieldError {
+            field,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Checks on a request body beyond what deserialization enforces
+pub trait Validate {
+    fn validate(&self) -> Vec<FieldError> {
+        Vec::new()
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self) -> Vec<FieldError> {
+        self.iter()
+            .enumerate()
+            .flat_map(|(index, item)| {
+                item.validate().into_iter().map(move |error| FieldError {
+                    field: format!("[{}].{}", index, error.field),
+                    message: error.message,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InvalidRequestBody {
+    error_code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldError>,
+}
+
+/// Rejection of a request body, listing every field at fault
+#[derive(Debug)]
+pub struct InvalidRequest {
+    status: StatusCode,
+    message: String,
+    fields: Vec<FieldError>,
+}
+
+impl InvalidRequest {
+    fn fields(fields: Vec<FieldError>) -> Self {
+        InvalidRequest {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            message: format!(
+                "Invalid request body: {}",
+                fields
+                    .iter()
+                    .map(|error| format!("{}: {}", error.field, error.message))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ),
+            fields,
+        }
+    }
+}
+
+impl IntoResponse for InvalidRequest {
+    fn into_response(self) -> Response {
+        let body = InvalidRequestBody {
+            error_code: "validation_failed",
+            message: self.message,
+            fields: self.fields,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// JSON body extractor that reports deserialization and validation problems per
+/// field with a `422`, instead of a plain text rejection
+pub struct ValidJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = InvalidRequest;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        if !is_json {
+            return Err(InvalidRequest {
+                status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                message: "Expected a request body with Content-Type: application/json".to_string(),
+                fields: Vec::new(),
+            });
+        }
+
+        let bytes = Bytes::from_request(request, state)
+            .await
+            .map_err(|e| InvalidRequest {
+                status: StatusCode::BAD_REQUEST,
+                message: e.body_text(),
+                fields: Vec::new(),
+            })?;
+
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+        let value: T = match serde_path_to_error::deserialize(&mut deserializer) {
+            Ok(value) => value,
+            Err(e) if e.inner().is_syntax() || e.inner().is_eof() => {
+                return Err(InvalidRequest {
+                    status: StatusCode::BAD_REQUEST,
+                    message: format!("Malformed JSON: {}", e.inner()),
+                    fields: Vec::new(),
+                });
+            }
+            Err(e) => return Err(InvalidRequest::fields(vec![FieldError::from_serde(e)])),
+        };
+
+        let errors = value.validate();
+        if !errors.is_empty() {
+            return Err(InvalidRequest::fields(errors));
+        }
+        Ok(ValidJson(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    #[derive(Deserialize)]
+    struct Payload {
+        #[allow(dead_code)]
+        name: String,
+        count: u32,
+    }
+
+    impl Validate for Payload {
+        fn validate(&self) -> Vec<FieldError> {
+            if self.count == 0 {
+                vec![FieldError::new("count", "must be positive")]
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    async fn extract(body: &str) -> Result<Payload, (StatusCode, Value)> {
+        let request = Request::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        match ValidJson::<Payload>::from_request(request, &()).await {
+            Ok(ValidJson(payload)) => Ok(payload),
+            Err(rejection) => {
+                let response = rejection.into_response();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                Err((status, serde_json::from_slice(&body).unwrap()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_field_errors() {
+        let (status, body) = extract(r#"{"name": "a"}"#).await.err().unwrap();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "count");
+        assert_eq!(body["fields"][0]["message"], "missing field `count`");
+
+        let (_, body) = extract(r#"{"name": "a", "count": "x"}"#)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(body["fields"][0]["field"], "count");
+
+        let (_, body) = extract(r#"{"name": "a", "count": 0}"#).await.err().unwrap();
+        assert_eq!(body["error_code"], "validation_failed");
+        assert_eq!(
+            body["message"],
+            "Invalid request body: count: must be positive"
+        );
+
+        assert_eq!(
+            extract(r#"{"name": "a", "count": 2}"#).await.unwrap().count,
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json() {
+        let (status, body) = extract("invalid json").await.err().unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Malformed JSON")
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use tokio::sync::RwLock;
+
+use crate::api::auth::{AdminToken, Caller, CallerTokens, RequireAdmin};
+use crate::api::services::ServiceEntryResponse;
+use crate::dns_fallback::DnsFallback;
+use crate::model::address_rewrite::AddressRewrites;
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::ServiceName;
+use crate::model::ownership::OWNER_TAG;
+use crate::model::resolution_script::ResolutionContext;
+use crate::model::service_profile::Visibility;
+use crate::model::service_registry::{HealthPolicy, ServiceEntry};
+use crate::model::tag_masking::TagMasking;
+use crate::registry::intention_store::IntentionStore;
+use crate::registry::token_store::TokenStore;
+
+/// How resolution results look to the client making the request: the health
+/// policy and time they are judged by, the addresses the caller can reach,
+/// the services it is allowed to discover and browse, and the tags it may read
+pub struct ResolveView {
+    pub policy: HealthPolicy,
+    pub at: u64,
+    pub dns_fallback: DnsFallback,
+    pub caller: Option<IpAddr>,
+    rewrites: Arc<AddressRewrites>,
+    masking: Arc<TagMasking>,
+    /// The calling service, when it identified itself
+    identity: Option<String>,
+    /// The intentions the caller is held to
+    intentions: IntentionStore,
+    /// Set when the request carries the admin token
+    admin: bool,
+    /// Set when the caller may read masked tags
+    unmasked: bool,
+    /// Set when listing the registry as of an earlier time
+    historical: bool,
+}
+
+impl ResolveView {
+    /// The caller and time handed to resolution scripts
+    pub fn resolution_context(&self) -> ResolutionContext<'_> {
+        ResolutionContext {
+            name: self.identity.as_deref(),
+            ip: self.caller,
+            at: self.at,
+        }
+    }
+
+    /// Judges instances as of the earlier time `at`. History records changes but
+    /// not heartbeats, so only membership and state are known and health reads `Unknown`
+    pub fn as_of(&mut self, at: u64) {
+        self.at = at;
+        self.historical = true;
+    }
+
+    /// Whether the caller should see `service`: identified callers see those
+    /// their intentions allow them to call, anonymous ones those the intentions
+    /// from any source allow, and the admin every service
+    pub fn may_call(&self, service: &ServiceName) -> bool {
+        match &self.identity {
+            Some(caller) => self.intentions.is_allowed(caller, service.as_str()),
+            None => self.admin || self.intentions.is_allowed_anonymously(service.as_str()),
+        }
+    }
+
+    /// Whether the caller should see the instance in listings of every service
+    pub fn may_browse(&self, entry: &ServiceEntry, visibility: Visibility) -> bool {
+        let caller = self.identity.as_deref();
+        let visible = match visibility {
+            Visibility::Public => true,
+            Visibility::Internal => self.admin || caller.is_some(),
+            Visibility::Private => {
+                self.admin
+                    || caller.is_some_and(|caller| {
+                        caller == entry.service_name.as_str()
+                            || entry
+                                .tags
+                                .get(OWNER_TAG)
+                                .is_some_and(|owner| owner == caller)
+                    })
+            }
+        };
+        visible && self.may_call(&entry.service_name)
+    }
+
+    /// Redacts the masked tags of an instance unless the caller may read them
+    pub fn redact(&self, mut entry: ServiceEntry) -> ServiceEntry {
+        if !self.unmasked {
+            self.masking.mask(&mut entry.tags);
+        }
+        entry
+    }
+
+    /// Builds the response for an instance in a listing, with masked tags redacted for the caller
+    pub(crate) fn listing(&self, entry: &ServiceEntry) -> ServiceEntryResponse {
+        let mut response = ServiceEntryResponse::from_entry(entry, &self.policy, self.at);
+        if self.historical {
+            response = response.with_unknown_health();
+        }
+        if self.unmasked {
+            response
+        } else {
+            response.with_masked_tags(&self.masking)
+        }
+    }
+
+    /// Builds the response for a resolved instance, with its address also rewritten for the caller
+    pub(crate) fn response(&self, entry: &ServiceEntry) -> ServiceEntryResponse {
+        let address = self
+            .rewrites
+            .apply(entry.address_str(), &entry.environment, self.caller);
+        self.listing(entry).with_address(address)
+    }
+}
+
+impl<S> FromRequestParts<S> for ResolveView
+where
+    HealthPolicy: FromRef<S>,
+    SharedClock: FromRef<S>,
+    DnsFallback: FromRef<S>,
+    Arc<AddressRewrites>: FromRef<S>,
+    Arc<TagMasking>: FromRef<S>,
+    CallerTokens: FromRef<S>,
+    AdminToken: FromRef<S>,
+    Arc<RwLock<TokenStore>>: FromRef<S>,
+    Arc<RwLock<IntentionStore>>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Missing when the server is not started with connect info, e.g. in tests
+        let caller = ConnectInfo::<SocketAddr>::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|ConnectInfo(address)| address.ip());
+
+        let Ok(Caller(name)) = Caller::from_request_parts(parts, state).await;
+        let admin = AdminToken::from_ref(state).0.is_some()
+            && RequireAdmin::from_request_parts(parts, state).await.is_ok();
+        let masking = Arc::<TagMasking>::from_ref(state);
+        let unmasked = admin || name.as_deref().is_some_and(|name| masking.may_unmask(name));
+        let intentions = Arc::<RwLock<IntentionStore>>::from_ref(state);
+        let intentions = intentions.read().await.clone();
+
+        Ok(ResolveView {
+            policy: HealthPolicy::from_ref(state),
+            at: SharedClock::from_ref(state).now(),
+            dns_fallback: DnsFallback::from_ref(state),
+            caller,
+            rewrites: Arc::from_ref(state),
+            masking,
+            identity: name,
+            intentions,
+            admin,
+            unmasked,
+            historical: false,
+        })
+    }
+}
@@ -0,0 +1,167 @@
+//! Health of a service judged by the responses it serves, for reporting to Xolotl.
+//!
+//! ```ignore
+//! let health = AppHealth::default();
+//! let app = app.layer(HealthLayer::new(health.clone()));
+//! XolotlService::builder(url).name("payments").environment("prod").health(health).spawn_with(app).await?;
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use axum::http::{Request, Response};
+use tower::{Layer, Service};
+
+/// Responses needed in the window before the service can be judged unhealthy
+const MIN_SAMPLES: usize = 10;
+
+/// Outcomes of the most recent requests, shared between `HealthLayer` and the
+/// heartbeat loop. Server errors and failed requests count as errors
+#[derive(Clone)]
+pub struct AppHealth {
+    outcomes: Arc<Mutex<VecDeque<bool>>>,
+    window: usize,
+    max_error_rate: f64,
+}
+
+impl Default for AppHealth {
+    /// Unhealthy when more than half of the last 100 requests failed
+    fn default() -> Self {
+        AppHealth::new(100, 0.5)
+    }
+}
+
+impl AppHealth {
+    /// Judges the last `window` requests, unhealthy above `max_error_rate` (0 to 1)
+    pub fn new(window: usize, max_error_rate: f64) -> Self {
+        AppHealth {
+            outcomes: Arc::new(Mutex::new(VecDeque::with_capacity(window))),
+            window: window.max(1),
+            max_error_rate,
+        }
+    }
+
+    pub fn record(&self, failed: bool) {
+        let mut outcomes = self.outcomes.lock().unwrap();
+        if outcomes.len() == self.window {
+            outcomes.pop_front();
+        }
+        outcomes.push_back(failed);
+    }
+
+    /// Share of failed requests in the window, 0 before any request
+    pub fn error_rate(&self) -> f64 {
+        let outcomes = self.outcomes.lock().unwrap();
+        if outcomes.is_empty() {
+            return 0.0;
+        }
+        outcomes.iter().filter(|failed| **failed).count() as f64 / outcomes.len() as f64
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        let samples = self.outcomes.lock().unwrap().len();
+        samples < MIN_SAMPLES.min(self.window) || self.error_rate() <= self.max_error_rate
+    }
+}
+
+/// Tower layer recording the outcome of every request into an `AppHealth`
+#[derive(Clone)]
+pub struct HealthLayer {
+    health: AppHealth,
+}
+
+impl HealthLayer {
+    pub fn new(health: AppHealth) -> Self {
+        HealthLayer { health }
+    }
+}
+
+impl<S> Layer<S> for HealthLayer {
+    type Service = HealthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HealthService {
+            inner,
+            health: self.health.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HealthService<S> {
+    inner: S,
+    health: AppHealth,
+}
+
+impl<S, B, ResBody> Service<Request<B>> for HealthService<S>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let health = self.health.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            health.record(
+                response
+                    .as_ref()
+                    .map_or(true, |response| response.status().is_server_error()),
+            );
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::StatusCode, routing::get};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_error_rate_from_responses() {
+        let health = AppHealth::new(20, 0.5);
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/fail", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+            .layer(HealthLayer::new(health.clone()));
+
+        let call = |path: &'static str| {
+            let app = app.clone();
+            async move {
+                app.oneshot(Request::builder().uri(path).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+        for _ in 0..5 {
+            call("/fail").await;
+        }
+        // Too few requests to judge
+        assert!(health.is_healthy());
+        for _ in 0..5 {
+            call("/fail").await;
+        }
+        assert!(!health.is_healthy());
+
+        // Client errors are the caller's problem, not the service's
+        for _ in 0..20 {
+            call("/missing").await;
+        }
+        assert_eq!(health.error_rate(), 0.0);
+        assert!(health.is_healthy());
+    }
+}
@@ -0,0 +1,476 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+
+use crate::model::registry_event::{RegistryEvent, RegistryEventKind};
+use crate::model::service_registry::ServiceRegistry;
+
+/// How often the event log is checked for changes to export
+const EXPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Syslog facility 13, "log audit"
+const FACILITY: u8 = 13;
+
+/// Private enterprise number reserved for documentation by RFC 5612, naming
+/// the structured data element of exported events
+const SD_ID: &str = "xolotl@32473";
+
+/// Where registry events are streamed besides the local event log
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditSinks {
+    /// `host:port` receiving RFC 5424 syslog messages over UDP
+    pub syslog: Option<String>,
+    /// OTLP/HTTP endpoint receiving log records as JSON on `/v1/logs`
+    pub otlp: Option<String>,
+}
+
+impl AuditSinks {
+    pub fn is_empty(&self) -> bool {
+        self.syslog.is_none() && self.otlp.is_none()
+    }
+}
+
+/// Events that left the bounded event log before they were exported, from
+/// index `from` to `to` inclusive, noticed when the event at `timestamp` was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LostEvents {
+    pub from: u64,
+    pub to: u64,
+    pub timestamp: u64,
+}
+
+impl LostEvents {
+    /// The events missing between index `since` and the first of `events`
+    pub fn between(since: u64, events: &[RegistryEvent]) -> Option<Self> {
+        let first = events.first()?;
+        (first.index > since + 1).then_some(LostEvents {
+            from: since + 1,
+            to: first.index - 1,
+            timestamp: first.timestamp,
+        })
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} events lost, indexes {} to {} left the event log before they were exported",
+            self.to - self.from + 1,
+            self.from,
+            self.to
+        )
+    }
+}
+
+/// Removals and changes an admin made on behalf of a service are reported as
+/// notices, every other change as information
+fn is_notice(event: &RegistryEvent) -> bool {
+    event.impersonation.is_some()
+        || matches!(
+            event.kind,
+            RegistryEventKind::Deregistered | RegistryEventKind::Tombstoned
+        )
+}
+
+fn summary(event: &RegistryEvent) -> String {
+    let summary = format!(
+        "{:?} {} in {}, instance {}",
+        event.kind, event.service_name, event.environment, event.instance_id
+    );
+    let summary = match &event.impersonation {
+        Some(impersonation) => format!(
+            "{} by admin {} acting as {}",
+            summary, impersonation.admin, impersonation.acting_as
+        ),
+        None => summary,
+    };
+    match &event.detail {
+        Some(detail) => format!("{}: {}", summary, detail),
+        None => summary,
+    }
+}
+
+/// Formats a Unix time in millis as an RFC 3339 UTC timestamp
+fn rfc3339(millis: u64) -> String {
+    let seconds = millis / 1000;
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        millis % 1000
+    )
+}
+
+/// Escapes a structured data parameter value as RFC 5424 requires
+fn sd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+/// Renders `event` as an RFC 5424 syslog message sent from `hostname`
+pub fn syslog_message(event: &RegistryEvent, hostname: &str) -> String {
+    let severity = if is_notice(event) { 5 } else { 6 };
+    let impersonation = match &event.impersonation {
+        Some(impersonation) => format!(
+            " acting_as=\"{}\" admin=\"{}\"",
+            sd_escape(&impersonation.acting_as),
+            sd_escape(&impersonation.admin)
+        ),
+        None => String::new(),
+    };
+    format!(
+        "<{}>1 {} {} xolotl - {:?} [{} index=\"{}\" service=\"{}\" environment=\"{}\" instance=\"{}\"{}] {}",
+        FACILITY * 8 + severity,
+        rfc3339(event.timestamp),
+        hostname,
+        event.kind,
+        SD_ID,
+        event.index,
+        sd_escape(event.service_name.as_str()),
+        sd_escape(event.environment.as_str()),
+        sd_escape(event.instance_id.as_str()),
+        impersonation,
+        summary(event)
+    )
+}
+
+/// Renders `lost` as an RFC 5424 syslog warning sent from `hostname`
+pub fn syslog_lost_message(lost: &LostEvents, hostname: &str) -> String {
+    format!(
+        "<{}>1 {} {} xolotl - EventsLost [{} from=\"{}\" to=\"{}\"] {}",
+        FACILITY * 8 + 4,
+        rfc3339(lost.timestamp),
+        hostname,
+        SD_ID,
+        lost.from,
+        lost.to,
+        lost.summary()
+    )
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    let value = match value {
+        Value::Number(number) => json!({ "intValue": number.to_string() }),
+        other => json!({ "stringValue": other.as_str().unwrap_or_default() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+/// Builds an OTLP/HTTP JSON export request holding `events` as log records,
+/// preceded by a warning when events were `lost` before them
+pub fn otlp_logs(events: &[RegistryEvent], lost: Option<&LostEvents>) -> Value {
+    let lost = lost.map(|lost| {
+        json!({
+            "timeUnixNano": (u128::from(lost.timestamp) * 1_000_000).to_string(),
+            "severityNumber": 13,
+            "severityText": "WARN",
+            "body": { "stringValue": lost.summary() },
+            "attributes": [
+                attribute("xolotl.event.kind", json!("EventsLost")),
+                attribute("xolotl.lost.from", json!(lost.from)),
+                attribute("xolotl.lost.to", json!(lost.to)),
+            ],
+        })
+    });
+    let records: Vec<Value> = lost
+        .into_iter()
+        .chain(events.iter().map(|event| {
+            let (severity_number, severity_text) = if is_notice(event) {
+                (10, "NOTICE")
+            } else {
+                (9, "INFO")
+            };
+            let mut attributes = vec![
+                attribute("xolotl.event.index", json!(event.index)),
+                attribute("xolotl.event.kind", json!(format!("{:?}", event.kind))),
+                attribute("xolotl.service_name", json!(event.service_name)),
+                attribute("xolotl.environment", json!(event.environment)),
+                attribute("xolotl.instance_id", json!(event.instance_id)),
+            ];
+            if let Some(impersonation) = &event.impersonation {
+                attributes.push(attribute(
+                    "xolotl.acting_as",
+                    json!(impersonation.acting_as),
+                ));
+                attributes.push(attribute("xolotl.admin", json!(impersonation.admin)));
+            }
+            json!({
+                "timeUnixNano": (u128::from(event.timestamp) * 1_000_000).to_string(),
+                "severityNumber": severity_number,
+                "severityText": severity_text,
+                "body": { "stringValue": summary(event) },
+                "attributes": attributes,
+            })
+        }))
+        .collect();
+    json!({
+        "resourceLogs": [{
+            "resource": {
+                "attributes": [attribute("service.name", json!("xolotl"))],
+            },
+            "scopeLogs": [{
+                "scope": { "name": "xolotl.audit" },
+                "logRecords": records,
+            }],
+        }],
+    })
+}
+
+fn otlp_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/logs") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/logs", endpoint)
+    }
+}
+
+/// Sends the events recorded after index `since` to every sink, returning the
+/// index to continue from. Events a sink fails to take are reported and not retried,
+/// and events that left the event log before they could be sent are reported to
+/// every sink as lost
+pub async fn export(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    sinks: &AuditSinks,
+    since: u64,
+    socket: &UdpSocket,
+    http: &reqwest::Client,
+    hostname: &str,
+) -> u64 {
+    let events = registry.read().await.events(since);
+    let Some(last) = events.last().map(|event| event.index) else {
+        return since;
+    };
+    let lost = LostEvents::between(since, &events);
+    if let Some(lost) = &lost {
+        eprintln!("Audit export fell behind: {}", lost.summary());
+    }
+
+    if let Some(target) = &sinks.syslog {
+        if let Some(lost) = &lost {
+            let message = syslog_lost_message(lost, hostname);
+            if let Err(e) = socket.send_to(message.as_bytes(), target).await {
+                eprintln!("Failed to send lost events to syslog: {}", e);
+            }
+        }
+        for event in &events {
+            let message = syslog_message(event, hostname);
+            if let Err(e) = socket.send_to(message.as_bytes(), target).await {
+                eprintln!("Failed to send event {} to syslog: {}", event.index, e);
+            }
+        }
+    }
+    if let Some(endpoint) = &sinks.otlp {
+        let sent = http
+            .post(otlp_url(endpoint))
+            .json(&otlp_logs(&events, lost.as_ref()))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = sent {
+            eprintln!("Failed to export {} events to OTLP: {}", events.len(), e);
+        }
+    }
+    last
+}
+
+/// Streams every registry event recorded from now on to `sinks` for the lifetime of the process
+pub async fn spawn_audit_export(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    sinks: AuditSinks,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string());
+    let mut since = registry.read().await.last_index();
+    Ok(tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut ticker = tokio::time::interval(EXPORT_INTERVAL);
+        loop {
+            ticker.tick().await;
+            since = export(&registry, &sinks, since, &socket, &http, &hostname).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::impersonation::Impersonation;
+    use crate::model::service_registry::{ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{Json, Router, routing::post};
+    use std::collections::HashMap;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(951_782_400_123), "2000-02-29T00:00:00.123Z");
+        assert_eq!(rfc3339(1_718_454_645_000), "2024-06-15T12:30:45.000Z");
+    }
+
+    #[test]
+    fn test_impersonated_changes_are_notices() {
+        let entry = ServiceEntry::new(
+            "payments".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        let mut event = RegistryEvent::new(
+            3,
+            RegistryEventKind::StateChanged,
+            &entry,
+            entry.registered_at,
+        );
+        event.impersonation = Some(Impersonation {
+            acting_as: "payments".to_string(),
+            admin: "admin-token".to_string(),
+        });
+
+        let message = syslog_message(&event, "registry-1");
+        assert!(message.starts_with("<109>1 "));
+        assert!(message.contains(" acting_as=\"payments\" admin=\"admin-token\"]"));
+        assert!(message.ends_with("by admin admin-token acting as payments"));
+        let logs = otlp_logs(&[event], None);
+        let record = &logs["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["attributes"][5]["value"]["stringValue"], "payments");
+    }
+
+    #[tokio::test]
+    async fn test_export_to_syslog_and_otlp() {
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let entry = ServiceEntry::new(
+            "payments".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        registry.write().await.register(entry.clone()).unwrap();
+        registry
+            .write()
+            .await
+            .deregister(&entry.service_name, Some(&entry.environment))
+            .unwrap();
+
+        let syslog = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let collector = {
+            let received = received.clone();
+            Router::new().route(
+                "/v1/logs",
+                post(move |Json(body): Json<Value>| async move {
+                    received.lock().await.push(body);
+                }),
+            )
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let otlp = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, collector).await });
+
+        let sinks = AuditSinks {
+            syslog: Some(syslog.local_addr().unwrap().to_string()),
+            otlp: Some(otlp),
+        };
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let http = reqwest::Client::new();
+        let since = export(&registry, &sinks, 0, &socket, &http, "registry-1").await;
+        assert_eq!(since, 2);
+        assert_eq!(
+            export(&registry, &sinks, since, &socket, &http, "registry-1").await,
+            2
+        );
+
+        let mut datagram = [0u8; 1024];
+        let length = syslog.recv(&mut datagram).await.unwrap();
+        let message = String::from_utf8_lossy(&datagram[..length]).to_string();
+        assert!(message.starts_with("<110>1 "));
+        assert!(message.contains(" registry-1 xolotl - Registered [xolotl@32473 index=\"1\""));
+        let length = syslog.recv(&mut datagram).await.unwrap();
+        assert!(String::from_utf8_lossy(&datagram[..length]).starts_with("<109>1 "));
+
+        let received = received.lock().await;
+        assert_eq!(received.len(), 1);
+        let records = &received[0]["resourceLogs"][0]["scopeLogs"][0]["logRecords"];
+        assert_eq!(records.as_array().unwrap().len(), 2);
+        assert_eq!(records[1]["severityText"], "NOTICE");
+        assert_eq!(records[0]["attributes"][3]["value"]["stringValue"], "prod");
+    }
+
+    #[tokio::test]
+    async fn test_lost_events_are_reported() {
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        // Two more registrations than the event log keeps
+        for i in 0..1_002 {
+            let entry = ServiceEntry::new(
+                "payments".parse().unwrap(),
+                "prod".parse().unwrap(),
+                format!("http://10.0.0.1:{}", i),
+                HashMap::new(),
+                now(),
+            );
+            registry.write().await.register(entry).unwrap();
+        }
+        let events = registry.read().await.events(0);
+        let lost = LostEvents::between(0, &events).unwrap();
+        assert_eq!((lost.from, lost.to), (1, 2));
+        assert_eq!(LostEvents::between(2, &events), None);
+
+        let syslog = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let sinks = AuditSinks {
+            syslog: Some(syslog.local_addr().unwrap().to_string()),
+            otlp: None,
+        };
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let since = export(
+            &registry,
+            &sinks,
+            0,
+            &socket,
+            &reqwest::Client::new(),
+            "registry-1",
+        )
+        .await;
+        assert_eq!(since, 1_002);
+
+        let mut datagram = [0u8; 1024];
+        let length = syslog.recv(&mut datagram).await.unwrap();
+        let message = String::from_utf8_lossy(&datagram[..length]).to_string();
+        assert!(message.starts_with("<108>1 "));
+        assert!(message.contains(" EventsLost [xolotl@32473 from=\"1\" to=\"2\"] 2 events lost"));
+
+        let logs = otlp_logs(&events[..1], Some(&lost));
+        let records = &logs["resourceLogs"][0]["scopeLogs"][0]["logRecords"];
+        assert_eq!(records.as_array().unwrap().len(), 2);
+        assert_eq!(records[0]["severityText"], "WARN");
+        assert_eq!(records[0]["attributes"][1]["value"]["intValue"], "1");
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use object_store::{ObjectStore, PutPayload, aws::AmazonS3Builder, path::Path};
+use tokio::sync::RwLock;
+
+use crate::encryption::Keyring;
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::ServiceRegistry;
+
+const SNAPSHOT_PREFIX: &str = "xolotl-";
+const SNAPSHOT_EXTENSION: &str = ".json";
+
+pub struct BackupConfig {
+    pub prefix: String,
+    pub interval: Duration,
+    pub retention: usize,
+    /// Encrypts snapshots before they are uploaded when set
+    pub keyring: Option<Keyring>,
+}
+
+/// Builds an S3 client for `bucket`, reading credentials and endpoint from the usual AWS_* variables
+pub fn s3_store(bucket: &str) -> object_store::Result<Arc<dyn ObjectStore>> {
+    let store = AmazonS3Builder::from_env()
+        .with_bucket_name(bucket)
+        .build()?;
+    Ok(Arc::new(store))
+}
+
+/// Writes a snapshot of the registry taken at time `at` below the configured
+/// prefix and prunes old snapshots
+pub async fn backup_once(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    store: &dyn ObjectStore,
+    config: &BackupConfig,
+    at: u64,
+) -> object_store::Result<Path> {
+    let entries = registry.read().await.list();
+    let mut body = serde_json::to_vec(&entries).expect("Service entries are always serializable");
+    if let Some(keyring) = &config.keyring {
+        body = keyring.seal(&body);
+    }
+
+    // Zero-padded millis keep lexical and chronological order identical
+    let location = Path::from(format!(
+        "{}/{}{:013}{}",
+        config.prefix, SNAPSHOT_PREFIX, at, SNAPSHOT_EXTENSION
+    ));
+    store.put(&location, PutPayload::from(body)).await?;
+
+    let mut snapshots = list_snapshots(store, &config.prefix).await?;
+    if snapshots.len() > config.retention {
+        let expired = snapshots.len() - config.retention;
+        for snapshot in snapshots.drain(..expired) {
+            store.delete(&snapshot).await?;
+        }
+    }
+
+    Ok(location)
+}
+
+/// Runs `backup_once` every interval for the lifetime of the process
+pub fn spawn_backups(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    store: Arc<dyn ObjectStore>,
+    config: BackupConfig,
+    clock: SharedClock,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval);
+        loop {
+            ticker.tick().await;
+            match backup_once(&registry, store.as_ref(), &config, clock.now()).await {
+                Ok(location) => println!("Backed up registry to {}", location),
+                Err(e) => eprintln!("Failed to back up registry: {}", e),
+            }
+        }
+    })
+}
+
+/// Returns the snapshots below `prefix`, oldest first
+pub async fn list_snapshots(
+    store: &dyn ObjectStore,
+    prefix: &str,
+) -> object_store::Result<Vec<Path>> {
+    let prefix = Path::from(prefix);
+    let mut snapshots: Vec<Path> = store
+        .list(Some(&prefix))
+        .map_ok(|meta| meta.location)
+        .try_collect::<Vec<_>>()
+        .await?
+        .into_iter()
+        .filter(|location| {
+            location.filename().is_some_and(|name| {
+                name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_EXTENSION)
+            })
+        })
+        .collect();
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Reads a snapshot, resolving a prefix to its most recent snapshot
+pub async fn read_snapshot(
+    store: &dyn ObjectStore,
+    location: &str,
+) -> object_store::Result<Vec<u8>> {
+    let location = if location.ends_with(SNAPSHOT_EXTENSION) {
+        Path::from(location)
+    } else {
+        list_snapshots(store, location)
+            .await?
+            .pop()
+            .ok_or_else(|| object_store::Error::NotFound {
+                path: location.to_string(),
+                source: "No snapshots found below prefix".into(),
+            })?
+    };
+
+    Ok(store.get(&location).await?.bytes().await?.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{RegistryWriter, ServiceEntry};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use object_store::memory::InMemory;
+    use std::collections::HashMap;
+
+    fn create_registry(names: &[&str]) -> Arc<RwLock<dyn ServiceRegistry>> {
+        let mut registry = InMemoryRegistry::new();
+        for name in names {
+            registry
+                .register(ServiceEntry::new(
+                    name.parse().unwrap(),
+                    "prod".parse().unwrap(),
+                    format!("http://{}:8080", name),
+                    HashMap::new(),
+                    1_000,
+                ))
+                .unwrap();
+        }
+        Arc::new(RwLock::new(registry))
+    }
+
+    fn config(retention: usize) -> BackupConfig {
+        BackupConfig {
+            prefix: "backups".to_string(),
+            interval: Duration::from_secs(60),
+            retention,
+            keyring: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backup_writes_snapshot() {
+        let registry = create_registry(&["payments", "search"]);
+        let store = InMemory::new();
+
+        let location = backup_once(&registry, &store, &config(3), 1_000)
+            .await
+            .unwrap();
+
+        assert!(location.as_ref().starts_with("backups/xolotl-"));
+        let body = read_snapshot(&store, location.as_ref()).await.unwrap();
+        let entries: Vec<ServiceEntry> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_backup_encrypts_snapshot() {
+        let registry = create_registry(&["payments"]);
+        let store = InMemory::new();
+        let keyring: Keyring = "k1:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8="
+            .parse()
+            .unwrap();
+        let config = BackupConfig {
+            keyring: Some(keyring.clone()),
+            ..config(3)
+        };
+
+        let location = backup_once(&registry, &store, &config, 1_000)
+            .await
+            .unwrap();
+
+        let body = read_snapshot(&store, location.as_ref()).await.unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("payments"));
+        let body = crate::encryption::decrypt(body, Some(&keyring)).unwrap();
+        let entries: Vec<ServiceEntry> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_backup_prunes_beyond_retention() {
+        let registry = create_registry(&["payments"]);
+        let store = InMemory::new();
+
+        let mut locations = Vec::new();
+        for at in [1_000, 2_000, 3_000, 4_000] {
+            locations.push(
+                backup_once(&registry, &store, &config(2), at)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let snapshots = list_snapshots(&store, "backups").await.unwrap();
+        assert_eq!(snapshots, locations[2..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_read_latest_snapshot_from_prefix() {
+        let store = InMemory::new();
+
+        backup_once(&create_registry(&["old"]), &store, &config(5), 1_000)
+            .await
+            .unwrap();
+        backup_once(
+            &create_registry(&["new", "newer"]),
+            &store,
+            &config(5),
+            2_000,
+        )
+        .await
+        .unwrap();
+
+        let body = read_snapshot(&store, "backups").await.unwrap();
+        let entries: Vec<ServiceEntry> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_snapshot_missing() {
+        let store = InMemory::new();
+
+        assert!(matches!(
+            read_snapshot(&store, "backups").await,
+            Err(object_store::Error::NotFound { .. })
+        ));
+    }
+}
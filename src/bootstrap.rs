@@ -0,0 +1,120 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::RwLock;
+
+use crate::client::XolotlClient;
+use crate::model::service_registry::{ServiceEntry, ServiceRegistry};
+
+/// Copies every instance registered at the Xolotl behind `peer` into `registry`,
+/// so a fresh node does not start serving from an empty catalog. Returns how
+/// many instances were copied
+pub async fn bootstrap_from(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    peer: &XolotlClient,
+) -> Result<usize, String> {
+    let export = peer
+        .export()
+        .await
+        .map_err(|e| format!("Failed to export the registry of the peer: {}", e))?;
+    copy_entries(registry, export).await
+}
+
+/// Takes the place of the Xolotl behind `peer`, which stops taking writes,
+/// hands over its registry and shuts down. Returns how many instances were copied
+pub async fn take_over(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    peer: &XolotlClient,
+) -> Result<usize, String> {
+    let export = peer
+        .hand_off()
+        .await
+        .map_err(|e| format!("Failed to take over from the peer: {}", e))?;
+    copy_entries(registry, export).await
+}
+
+/// Binds a listener that shares its port with other sockets bound the same way,
+/// so a new process can listen before the one it replaces stops
+pub fn bind_reusable(address: SocketAddr) -> std::io::Result<TcpListener> {
+    let socket = match address {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    socket.set_reuseport(true)?;
+    socket.bind(address)?;
+    socket.listen(1024)
+}
+
+async fn copy_entries(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    export: Value,
+) -> Result<usize, String> {
+    let entries: Vec<ServiceEntry> = serde_json::from_value(export)
+        .map_err(|e| format!("Failed to parse the export of the peer: {}", e))?;
+
+    let mut registry = registry.write().await;
+    let count = entries.len();
+    for entry in entries {
+        let id = entry.id.clone();
+        registry
+            .register(entry)
+            .map_err(|e| format!("Failed to copy instance {}: {:?}", id, e))?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use crate::testing::TestServer;
+
+    #[tokio::test]
+    async fn test_bootstrap_from_peer() {
+        let peer = TestServer::start().await;
+        let entry = peer
+            .register("checkout", "prod", "http://10.0.0.1:8080", &[("zone", "a")])
+            .await;
+        peer.register("search", "dev", "http://10.0.0.2:8080", &[])
+            .await;
+
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let copied = bootstrap_from(&registry, &peer.client()).await.unwrap();
+
+        assert_eq!(copied, 2);
+        let registry = registry.read().await;
+        let copy = registry
+            .list()
+            .into_iter()
+            .find(|candidate| candidate.id == entry.id)
+            .unwrap();
+        assert_eq!(copy.tags, entry.tags);
+        assert_eq!(
+            copy.health_status(&HealthPolicy::default(), entry.last_heartbeat),
+            entry.health_status(&HealthPolicy::default(), entry.last_heartbeat)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_reusable() {
+        let first = bind_reusable("127.0.0.1:0".parse().unwrap()).unwrap();
+        let address = first.local_addr().unwrap();
+        let second = bind_reusable(address).unwrap();
+        assert_eq!(second.local_addr().unwrap(), address);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_from_unreachable_peer() {
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let peer = XolotlClient::new("http://127.0.0.1:1", None);
+        assert!(bootstrap_from(&registry, &peer).await.is_err());
+    }
+}
@@ -0,0 +1,1065 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::alerting::AlertingConfig;
+use crate::audit_export::AuditSinks;
+use crate::backup::{self, BackupConfig};
+use crate::client::{ClientError, XolotlClient};
+use crate::consul::ConsulClient;
+use crate::encryption::{self, Keyring};
+use crate::model::guardrail::GuardrailLimit;
+use crate::model::ownership::OwnerPolicy;
+use crate::model::payload_signing::SigningSecrets;
+use crate::model::server_config::ServerConfig;
+use crate::model::service_registry::HealthPolicy;
+use crate::model::stale_report::parse_age;
+use crate::model::tag_schema::TagSchema;
+use crate::node_agent::{NodeAgent, spawn_dns_stub};
+use crate::registry::resolve_cache::DEFAULT_RESOLVE_CACHE_MILLIS;
+use crate::ssdp;
+use output::{
+    EVENT_COLUMNS, HEARTBEAT_COLUMNS, INSTANCE_COLUMNS, OutputFormat, render, render_table,
+};
+
+pub mod output;
+pub mod top;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(flatten)]
+    pub server: ServerArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Args)]
+pub struct ServerArgs {
+    #[arg(short, long, env = "XOLOTL_ADDRESS", default_value = "0.0.0.0")]
+    pub address: String,
+
+    #[arg(short, long, env = "XOLOTL_PORT", default_value_t = 8000)]
+    pub port: u16,
+
+    /// Token required as `Authorization: Bearer <token>` for administrative actions
+    #[arg(long, env = "XOLOTL_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Seconds without a heartbeat before an instance is reported as stale
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(..=MAX_THRESHOLD_SECONDS))]
+    pub stale_after: u64,
+
+    /// Seconds without a heartbeat before an instance is reported as unhealthy,
+    /// at least `--stale-after`
+    #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u64).range(..=MAX_THRESHOLD_SECONDS))]
+    pub unhealthy_after: u64,
+
+    /// Seconds after starting with an empty registry during which heartbeats that carry
+    /// an address recreate unknown instances instead of failing
+    #[arg(long, env = "XOLOTL_RECOVERY_GRACE", value_name = "SECONDS")]
+    pub recovery_grace: Option<u64>,
+
+    /// Remove services whose instances have all been silent for this long, e.g. `7d`
+    #[arg(long, env = "XOLOTL_TOMBSTONE_AFTER", value_name = "AGE", value_parser = parse_age)]
+    pub tombstone_after: Option<u64>,
+
+    /// How long before a declared environment expires its notifiers are warned, e.g. `1h`
+    #[arg(
+        long,
+        env = "XOLOTL_ENVIRONMENT_EXPIRY_WARNING",
+        value_name = "AGE",
+        default_value = "1h",
+        value_parser = parse_age
+    )]
+    pub environment_expiry_warning: u64,
+
+    /// Refuse deregistrations of a service beyond this rate, e.g. `100/1m`, until an
+    /// admin confirms them with `POST /admin/guardrails/{name}/confirm`
+    #[arg(
+        long,
+        env = "XOLOTL_DEREGISTRATION_GUARDRAIL",
+        value_name = "COUNT/AGE"
+    )]
+    pub deregistration_guardrail: Option<GuardrailLimit>,
+
+    /// Only flag deregistration bursts at `GET /admin/guardrails` instead of refusing them
+    #[arg(
+        long,
+        env = "XOLOTL_GUARDRAIL_FLAG_ONLY",
+        requires = "deregistration_guardrail"
+    )]
+    pub guardrail_flag_only: bool,
+
+    /// URL of a running Xolotl whose instances are copied before this one starts
+    /// serving, authenticated with `--admin-token`
+    #[arg(long, env = "XOLOTL_BOOTSTRAP_FROM", value_name = "PEER_URL")]
+    pub bootstrap_from: Option<String>,
+
+    /// Bind the API port so another Xolotl can listen on it too, which lets a
+    /// new process take over from this one with `--take-over`
+    #[arg(long, env = "XOLOTL_REUSE_PORT")]
+    pub reuse_port: bool,
+
+    /// URL of the Xolotl, started with `--reuse-port`, that this one replaces: it
+    /// hands over its registry and shuts down once this one listens on the same port
+    #[arg(
+        long,
+        env = "XOLOTL_TAKE_OVER",
+        value_name = "PEER_URL",
+        conflicts_with = "bootstrap_from"
+    )]
+    pub take_over: Option<String>,
+
+    /// Refuse every change made through the API, for replicas, disaster recovery
+    /// copies and snapshots under inspection, until turned off at `PUT /admin/read-only`
+    #[arg(long, env = "XOLOTL_READ_ONLY")]
+    pub read_only: bool,
+
+    /// WebAssembly module deciding whether to admit, deny or change every
+    /// registration, reloaded whenever the file changes
+    #[cfg(feature = "wasm")]
+    #[arg(long, env = "XOLOTL_ADMISSION_POLICY", value_name = "PATH")]
+    pub admission_policy: Option<std::path::PathBuf>,
+
+    /// Serve the API over TLS with a certificate for these names, obtained and
+    /// renewed from `--acme-directory`
+    #[cfg(feature = "acme")]
+    #[arg(long, env = "XOLOTL_ACME_DOMAINS", value_delimiter = ',')]
+    pub acme_domain: Vec<String>,
+
+    /// Contact registered with the ACME account, e.g. `mailto:ops@example.com`
+    #[cfg(feature = "acme")]
+    #[arg(long, env = "XOLOTL_ACME_CONTACT")]
+    pub acme_contact: Vec<String>,
+
+    /// Directory URL of the ACME server, Let's Encrypt by default
+    #[cfg(feature = "acme")]
+    #[arg(
+        long,
+        env = "XOLOTL_ACME_DIRECTORY",
+        value_name = "URL",
+        default_value = rustls_acme::acme::LETS_ENCRYPT_PRODUCTION_DIRECTORY
+    )]
+    pub acme_directory: String,
+
+    /// Directory keeping state across restarts, such as the ACME account and certificates
+    #[cfg(feature = "acme")]
+    #[arg(
+        long,
+        env = "XOLOTL_DATA_DIR",
+        value_name = "PATH",
+        default_value = "data"
+    )]
+    pub data_dir: PathBuf,
+
+    /// Make `DELETE /services/{name}` answer a token that must be posted back to
+    /// `/services/{name}/confirm-deletion` within this long, e.g. `5m`
+    #[arg(long, env = "XOLOTL_CONFIRM_DELETIONS", value_name = "AGE", value_parser = parse_age)]
+    pub confirm_deletions: Option<u64>,
+
+    /// Reject registrations that do not declare an owner
+    #[arg(long, env = "XOLOTL_REQUIRE_OWNER")]
+    pub require_owner: bool,
+
+    /// Comma separated directory of teams allowed as owners
+    #[arg(long, env = "XOLOTL_OWNER_TEAMS", value_delimiter = ',')]
+    pub owner_teams: Vec<String>,
+
+    /// Regular expression every owner has to match
+    #[arg(long, env = "XOLOTL_OWNER_PATTERN", value_parser = Regex::new)]
+    pub owner_pattern: Option<Regex>,
+
+    /// YAML or JSON config file, e.g. with `static_services` registered at startup
+    #[arg(long, env = "XOLOTL_CONFIG", value_name = "PATH", value_parser = ServerConfig::from_file)]
+    pub config: Option<ServerConfig>,
+
+    /// YAML or JSON file describing the tags every registration has to conform to
+    #[arg(long, env = "XOLOTL_TAG_SCHEMA", value_name = "PATH", value_parser = TagSchema::from_file)]
+    pub tag_schema: Option<TagSchema>,
+
+    /// YAML or JSON file with alert rules and the notifiers they fire to
+    #[arg(long, env = "XOLOTL_ALERT_RULES", value_name = "PATH", value_parser = AlertingConfig::from_file)]
+    pub alert_rules: Option<AlertingConfig>,
+
+    /// Seconds between runs of a synthetic canary that registers, heartbeats and
+    /// resolves a service through the public API, reported on `/selfcheck`
+    #[arg(
+        long,
+        env = "XOLOTL_SELFCHECK_INTERVAL",
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub selfcheck_interval: Option<u64>,
+
+    /// Seconds a response is replayed for retries carrying the same `Idempotency-Key`
+    #[arg(
+        long,
+        env = "XOLOTL_IDEMPOTENCY_TTL",
+        value_name = "SECONDS",
+        default_value_t = 86_400
+    )]
+    pub idempotency_ttl: u64,
+
+    /// Seconds clients are told they may cache discovery results, unless the service's profile sets `cache_ttl_seconds`
+    #[arg(
+        long,
+        env = "XOLOTL_CACHE_TTL",
+        value_name = "SECONDS",
+        default_value_t = 5
+    )]
+    pub cache_ttl: u64,
+
+    /// Millis the server reuses the instances it resolved for a service until the
+    /// service changes, 0 disables the cache
+    #[arg(
+        long,
+        env = "XOLOTL_RESOLVE_CACHE_MILLIS",
+        value_name = "MILLIS",
+        default_value_t = DEFAULT_RESOLVE_CACHE_MILLIS
+    )]
+    pub resolve_cache_millis: u64,
+
+    /// Header in which the proxy terminating mTLS forwards the client certificate, such as
+    /// `x-forwarded-client-cert`. The SPIFFE ID it carries is recorded on registrations
+    #[arg(long, env = "XOLOTL_SPIFFE_HEADER", value_name = "HEADER")]
+    pub spiffe_header: Option<String>,
+
+    /// Resolve services the registry does not know through system DNS, returned with `"source": "dns"`
+    #[arg(long, env = "XOLOTL_DNS_FALLBACK")]
+    pub dns_fallback: bool,
+
+    /// Answer mDNS queries on the local network for registered services as `_<name>._tcp.local`
+    #[arg(long, env = "XOLOTL_MDNS")]
+    pub mdns: bool,
+
+    /// Accept signed heartbeat datagrams on this UDP port, next to the HTTP heartbeat endpoints
+    #[arg(
+        long,
+        env = "XOLOTL_UDP_HEARTBEAT_PORT",
+        value_name = "PORT",
+        requires = "udp_heartbeat_key"
+    )]
+    pub udp_heartbeat_port: Option<u16>,
+
+    /// Shared secret heartbeat datagrams are signed with using HMAC-SHA256
+    #[arg(long, env = "XOLOTL_UDP_HEARTBEAT_KEY", hide_env_values = true)]
+    pub udp_heartbeat_key: Option<String>,
+
+    /// Seconds an agent holding its instances alive with `liveness=connection` may stay
+    /// disconnected before they are marked down
+    #[arg(
+        long,
+        env = "XOLOTL_AGENT_GRACE_PERIOD",
+        value_name = "SECONDS",
+        default_value_t = 30
+    )]
+    pub agent_grace_period: u64,
+
+    /// Seconds between probes of the health check URLs of external endpoints
+    #[arg(
+        long,
+        env = "XOLOTL_HEALTH_CHECK_INTERVAL",
+        value_name = "SECONDS",
+        default_value_t = 10
+    )]
+    pub health_check_interval: u64,
+
+    /// `host:port` receiving every registry change as an RFC 5424 syslog message over UDP
+    #[arg(long, env = "XOLOTL_AUDIT_SYSLOG", value_name = "HOST:PORT")]
+    pub audit_syslog: Option<String>,
+
+    /// OTLP/HTTP collector receiving every registry change as a log record, e.g. `http://collector:4318`
+    #[arg(long, env = "XOLOTL_AUDIT_OTLP", value_name = "URL")]
+    pub audit_otlp: Option<String>,
+
+    /// Announce the API over SSDP so agents on the local network can find it with `xolotl discover`
+    #[arg(long, env = "XOLOTL_SSDP")]
+    pub ssdp: bool,
+
+    /// Run at most this many writes at once, queueing the rest with deregistrations,
+    /// state changes and heartbeats ahead of registrations
+    #[arg(long, env = "XOLOTL_MAX_CONCURRENT_WRITES", value_name = "N")]
+    pub max_concurrent_writes: Option<usize>,
+
+    /// Writes of each priority queued before new ones are rejected with `503`
+    #[arg(
+        long,
+        env = "XOLOTL_WRITE_QUEUE_DEPTH",
+        value_name = "N",
+        default_value_t = 1000
+    )]
+    pub write_queue_depth: usize,
+
+    /// Heartbeats per second the server is sized for. Heartbeat responses then suggest
+    /// an interval to clients, stretched while more heartbeats than this arrive
+    #[arg(long, env = "XOLOTL_HEARTBEAT_CAPACITY", value_name = "PER_SECOND")]
+    pub heartbeat_capacity: Option<u64>,
+
+    /// File of `<service>:<secret>` lines. Registrations and heartbeats of these
+    /// services must carry an HMAC-SHA256 of their body in `x-xolotl-signature`
+    #[arg(long, env = "XOLOTL_SIGNING_SECRETS", value_name = "PATH", value_parser = SigningSecrets::from_file)]
+    pub signing_secrets: Option<SigningSecrets>,
+
+    /// Enable fault injection for client testing, e.g. `seed=42`, configured through `/admin/chaos`
+    #[arg(long, env = "XOLOTL_CHAOS", value_name = "seed=SEED", value_parser = parse_chaos)]
+    pub chaos: Option<u64>,
+
+    /// S3 bucket receiving periodic registry snapshots, credentials come from AWS_* variables
+    #[arg(long, env = "XOLOTL_BACKUP_S3_BUCKET")]
+    pub backup_s3_bucket: Option<String>,
+
+    /// Key prefix for snapshots inside the backup bucket
+    #[arg(long, default_value = "xolotl")]
+    pub backup_prefix: String,
+
+    /// Seconds between registry snapshots
+    #[arg(long, default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    pub backup_interval: u64,
+
+    /// Number of snapshots kept in the backup bucket
+    #[arg(long, default_value_t = 24)]
+    pub backup_retention: usize,
+
+    /// File of `<id>:<base64 key>` lines encrypting snapshots with AES-256-GCM, the first key encrypts
+    #[arg(long, env = "XOLOTL_BACKUP_KEY_FILE", value_name = "PATH", value_parser = Keyring::from_file)]
+    pub backup_key_file: Option<Keyring>,
+
+    /// Snapshot keys in the same format as `--backup-key-file`, usually set from a secret
+    #[arg(
+        long,
+        env = "XOLOTL_BACKUP_KEY",
+        hide_env_values = true,
+        conflicts_with = "backup_key_file"
+    )]
+    pub backup_key: Option<Keyring>,
+}
+
+/// Longest health threshold, in seconds, that still fits in millis
+const MAX_THRESHOLD_SECONDS: u64 = u64::MAX / 1000;
+
+impl ServerArgs {
+    /// Rejects an unhealthy threshold shorter than the stale one, which would
+    /// skip instances straight from healthy to unhealthy
+    pub fn validate_thresholds(&self) -> Result<(), String> {
+        if self.unhealthy_after < self.stale_after {
+            return Err(format!(
+                "--unhealthy-after ({}) must be at least --stale-after ({})",
+                self.unhealthy_after, self.stale_after
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn health_policy(&self) -> HealthPolicy {
+        HealthPolicy {
+            stale_after: self.stale_after * 1000,
+            unhealthy_after: self.unhealthy_after * 1000,
+        }
+    }
+
+    pub fn owner_policy(&self) -> OwnerPolicy {
+        OwnerPolicy {
+            required: self.require_owner,
+            teams: self.owner_teams.clone(),
+            pattern: self.owner_pattern.clone(),
+        }
+    }
+
+    /// URL the server can reach itself on, using loopback when listening on every interface
+    pub fn self_url(&self) -> String {
+        let host = match self.address.as_str() {
+            "0.0.0.0" => "127.0.0.1",
+            "::" => "[::1]",
+            address => address,
+        };
+        format!("http://{}:{}", host, self.port)
+    }
+
+    pub fn audit_sinks(&self) -> AuditSinks {
+        AuditSinks {
+            syslog: self.audit_syslog.clone(),
+            otlp: self.audit_otlp.clone(),
+        }
+    }
+
+    pub fn backup_config(&self) -> BackupConfig {
+        BackupConfig {
+            prefix: self.backup_prefix.clone(),
+            interval: Duration::from_secs(self.backup_interval),
+            retention: self.backup_retention,
+            keyring: self.backup_key_file.clone().or(self.backup_key.clone()),
+        }
+    }
+
+    /// Certificate settings when `--acme-domain` is given, keeping ACME material under `acme` in the data dir
+    #[cfg(feature = "acme")]
+    pub fn acme_settings(&self) -> Option<crate::acme::AcmeSettings> {
+        if self.acme_domain.is_empty() {
+            return None;
+        }
+        Some(crate::acme::AcmeSettings {
+            domains: self.acme_domain.clone(),
+            contacts: self.acme_contact.clone(),
+            directory: self.acme_directory.clone(),
+            cache_dir: self.data_dir.join("acme"),
+        })
+    }
+}
+
+/// Parses the `seed=N` value of `--chaos`
+fn parse_chaos(value: &str) -> Result<u64, String> {
+    value
+        .strip_prefix("seed=")
+        .and_then(|seed| seed.parse().ok())
+        .ok_or_else(|| format!("Invalid chaos options '{}', expected seed=<number>", value))
+}
+
+/// Options locating the running server a subcommand talks to
+#[derive(Args)]
+pub struct ConnectionArgs {
+    /// Base URL of the Xolotl server
+    #[arg(long, env = "XOLOTL_URL", default_value = "http://localhost:8000")]
+    pub url: String,
+
+    /// Admin token sent as a bearer token with every request
+    #[arg(long, env = "XOLOTL_ADMIN_TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Shared secret signing the body of every request, see `--signing-secrets` on the server
+    #[arg(long, env = "XOLOTL_SIGNING_SECRET")]
+    pub signing_secret: Option<String>,
+
+    /// Service to act on behalf of with the admin token, recorded as such in the event log
+    #[arg(long, env = "XOLOTL_ACT_AS", requires = "admin_token")]
+    pub act_as: Option<String>,
+}
+
+impl ConnectionArgs {
+    fn client(&self) -> XolotlClient {
+        let client = XolotlClient::new(&self.url, self.admin_token.clone());
+        let client = match &self.signing_secret {
+            Some(secret) => client.with_signing_secret(secret),
+            None => client,
+        };
+        match &self.act_as {
+            Some(service_name) => client.with_act_as(service_name),
+            None => client,
+        }
+    }
+}
+
+/// Options shared by every subcommand that prints server responses
+#[derive(Args)]
+pub struct ClientArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+impl ClientArgs {
+    fn client(&self) -> XolotlClient {
+        self.connection.client()
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the registry server (the default when no subcommand is given)
+    Server(Box<ServerArgs>),
+
+    /// Register a service instance
+    Register {
+        service_name: String,
+        environment: String,
+        address: String,
+
+        /// Tag to attach to the instance, may be repeated
+        #[arg(short, long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Resolve the instances of a service in an environment
+    Resolve {
+        service_name: String,
+        environment: String,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// List every registered instance
+    List {
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Deregister a service, a service in one environment, or a single instance
+    Deregister {
+        #[arg(required_unless_present = "instance")]
+        service_name: Option<String>,
+        environment: Option<String>,
+
+        /// Id of a single instance to deregister
+        #[arg(long, conflicts_with_all = ["service_name", "environment"])]
+        instance: Option<String>,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Send a heartbeat for a service in an environment
+    Heartbeat {
+        service_name: String,
+        environment: String,
+
+        /// Keep sending heartbeats every given number of seconds
+        #[arg(long)]
+        interval: Option<u64>,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Stream registry events as they happen
+    Watch {
+        /// Only show events newer than this index, defaults to the latest event
+        #[arg(long)]
+        since: Option<u64>,
+
+        /// Seconds between polls for new events
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Restore a running server from a snapshot in S3
+    Restore {
+        /// Snapshot to restore, e.g. s3://bucket/xolotl/xolotl-1718000000000.json, a prefix picks the latest
+        #[arg(long, value_name = "S3_URL")]
+        from: String,
+
+        /// How the snapshot is combined with the current registry
+        #[arg(long, default_value = "replace", value_parser = ["merge", "replace"])]
+        mode: String,
+
+        /// Report what would change without applying it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Keys of an encrypted snapshot, as given to the server with `--backup-key-file`
+        #[arg(long, env = "XOLOTL_BACKUP_KEY_FILE", value_name = "PATH", value_parser = Keyring::from_file)]
+        key_file: Option<Keyring>,
+
+        /// Keys in the same format as `--key-file`, as given to the server with `--backup-key`
+        #[arg(
+            long,
+            env = "XOLOTL_BACKUP_KEY",
+            hide_env_values = true,
+            conflicts_with = "key_file"
+        )]
+        key: Option<Keyring>,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Register every service from a Consul catalog on a running server
+    Migrate {
+        /// Address of the Consul HTTP API, e.g. http://consul:8500
+        #[arg(long, value_name = "ADDR")]
+        from_consul: String,
+
+        /// ACL token sent to Consul
+        #[arg(long, env = "CONSUL_HTTP_TOKEN")]
+        consul_token: Option<String>,
+
+        /// Environment to register instances in, defaults to each instance's datacenter
+        #[arg(long)]
+        environment: Option<String>,
+
+        /// Skip instances with warning or critical checks
+        #[arg(long)]
+        passing_only: bool,
+
+        /// Print the entries that would be registered without registering them
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+
+    /// Find Xolotl servers on the local network that announce themselves over SSDP
+    Discover {
+        /// Seconds to wait for answers
+        #[arg(long, default_value_t = 3)]
+        timeout: u64,
+
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+    },
+
+    /// Serve a local DNS stub answering `<service>.<environment>.xolotl` from a cache of the registry
+    NodeAgent {
+        /// Address the DNS stub listens on
+        #[arg(long, env = "XOLOTL_DNS_LISTEN", default_value = "127.0.0.1:5354")]
+        dns_listen: SocketAddr,
+
+        /// Seconds answers are served from the cache before asking the registry again
+        #[arg(long, default_value_t = 5)]
+        cache_ttl: u64,
+
+        /// File keeping the last answers across restarts, served while the registry is unreachable
+        #[arg(long, env = "XOLOTL_CACHE_FILE", value_name = "PATH")]
+        cache_file: Option<PathBuf>,
+
+        #[command(flatten)]
+        connection: ConnectionArgs,
+    },
+
+    /// Interactive terminal monitor of instances, health and events
+    Top {
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+
+        #[command(flatten)]
+        connection: ConnectionArgs,
+    },
+}
+
+/// Splits an `s3://bucket/key` URL into its bucket and key
+fn parse_s3_url(url: &str) -> Result<(&str, &str), String> {
+    let rest = url
+        .strip_prefix("s3://")
+        .ok_or_else(|| format!("Invalid S3 URL {}, expected s3://bucket/key", url))?;
+    let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return Err(format!("Invalid S3 URL {}, missing bucket", url));
+    }
+    Ok((bucket, key.trim_end_matches('/')))
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    tag.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("Invalid tag {}, expected KEY=VALUE", tag))
+}
+
+/// Runs a subcommand that talks to a remote server
+pub async fn run_client_command(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Server(_) => unreachable!("server is not a client command"),
+        Command::Restore {
+            from,
+            mode,
+            dry_run,
+            key_file,
+            key,
+            client,
+        } => {
+            let keyring = key_file.or(key);
+            let (bucket, key) = parse_s3_url(&from)?;
+            let store = backup::s3_store(bucket)?;
+            let snapshot = backup::read_snapshot(store.as_ref(), key).await?;
+            let snapshot = encryption::decrypt(snapshot, keyring.as_ref())?;
+            let report = client.client().import(snapshot, &mode, dry_run).await?;
+            println!(
+                "{}",
+                render(
+                    client.output,
+                    &report,
+                    &["mode", "dry_run", "created", "updated", "removed"]
+                )
+            );
+        }
+        Command::Migrate {
+            from_consul,
+            consul_token,
+            environment,
+            passing_only,
+            dry_run,
+            client,
+        } => {
+            let consul_url = if from_consul.contains("://") {
+                from_consul
+            } else {
+                format!("http://{}", from_consul)
+            };
+            let consul = ConsulClient::new(&consul_url, consul_token);
+            let xolotl = client.client();
+
+            let mut migrated = Vec::new();
+            let mut failures = 0;
+            for name in consul.service_names().await? {
+                for instance in consul.service_health(&name).await? {
+                    if passing_only && instance.status() != "passing" {
+                        continue;
+                    }
+
+                    let entry = instance.to_entry(environment.as_deref());
+                    if !dry_run
+                        && let Err(e) = xolotl
+                            .register(
+                                &entry.service_name,
+                                &entry.environment,
+                                &entry.address,
+                                entry.tags.clone(),
+                            )
+                            .await
+                    {
+                        eprintln!(
+                            "Failed to register {} in {}: {}",
+                            entry.service_name, entry.environment, e
+                        );
+                        failures += 1;
+                        continue;
+                    }
+                    migrated.push(entry);
+                }
+            }
+
+            let migrated = serde_json::to_value(migrated)?;
+            println!(
+                "{}",
+                render(
+                    client.output,
+                    &migrated,
+                    &["service_name", "environment", "address", "tags"]
+                )
+            );
+            if failures > 0 {
+                return Err(format!("{} registrations failed", failures).into());
+            }
+        }
+        Command::Discover { timeout, output } => {
+            let urls = ssdp::discover(Duration::from_secs(timeout)).await?;
+            let servers: Vec<Value> = urls.into_iter().map(|url| json!({ "url": url })).collect();
+            println!("{}", render(output, &Value::Array(servers), &["url"]));
+        }
+        Command::NodeAgent {
+            dns_listen,
+            cache_ttl,
+            cache_file,
+            connection,
+        } => {
+            let mut client = connection.client();
+            if let Some(path) = cache_file {
+                client = client.with_offline_cache(path);
+            }
+            let agent = Arc::new(NodeAgent::new(client, Duration::from_secs(cache_ttl)));
+            let socket = tokio::net::UdpSocket::bind(dns_listen).await?;
+            println!("Answering DNS queries for .xolotl on {}", dns_listen);
+            spawn_dns_stub(agent, socket).await?;
+        }
+        Command::Top {
+            interval,
+            connection,
+        } => top::run(connection.client(), &connection.url, interval).await?,
+        Command::Register {
+            service_name,
+            environment,
+            address,
+            tags,
+            client,
+        } => {
+            let tags: HashMap<String, String> = tags.into_iter().collect();
+            let response = client
+                .client()
+                .register(&service_name, &environment, &address, tags)
+                .await?;
+            println!("{}", render(client.output, &response, INSTANCE_COLUMNS));
+        }
+        Command::Resolve {
+            service_name,
+            environment,
+            client,
+        } => {
+            let response = client.client().resolve(&service_name, &environment).await?;
+            println!("{}", render(client.output, &response, INSTANCE_COLUMNS));
+        }
+        Command::List { client } => {
+            let response = client.client().list().await?;
+            println!("{}", render(client.output, &response, INSTANCE_COLUMNS));
+        }
+        Command::Deregister {
+            service_name,
+            environment,
+            instance,
+            client,
+        } => {
+            let response = match (instance, service_name) {
+                (Some(id), _) => client.client().deregister_instance(&id).await?,
+                (None, Some(name)) => {
+                    client
+                        .client()
+                        .deregister(&name, environment.as_deref())
+                        .await?
+                }
+                (None, None) => unreachable!("clap requires a service name or an instance"),
+            };
+            println!("{}", render(client.output, &response, INSTANCE_COLUMNS));
+        }
+        Command::Heartbeat {
+            service_name,
+            environment,
+            interval,
+            client,
+        } => {
+            let xolotl = client.client();
+            let Some(interval) = interval else {
+                let response = xolotl.heartbeat(&service_name, &environment).await?;
+                println!("{}", render(client.output, &response, HEARTBEAT_COLUMNS));
+                return Ok(());
+            };
+
+            loop {
+                // A server pacing heartbeats overrides the interval while it suggests one
+                let mut wait = interval;
+                match xolotl.heartbeat(&service_name, &environment).await {
+                    Ok(response) => {
+                        println!("{}", render(client.output, &response, HEARTBEAT_COLUMNS));
+                        wait = response["heartbeat_interval_seconds"]
+                            .as_u64()
+                            .unwrap_or(interval);
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+            }
+        }
+        Command::Watch {
+            since,
+            interval,
+            client,
+        } => watch(client, since, interval).await?,
+    }
+
+    Ok(())
+}
+
+async fn watch(client: ClientArgs, since: Option<u64>, interval: u64) -> Result<(), ClientError> {
+    let xolotl = client.client();
+    let mut since = match since {
+        Some(since) => since,
+        None => last_index(&xolotl.events(0).await?).unwrap_or(0),
+    };
+
+    loop {
+        let events = xolotl.events(since).await?;
+        since = last_index(&events).unwrap_or(since);
+
+        for event in events.as_array().into_iter().flatten() {
+            let line = match client.output {
+                OutputFormat::Json => event.to_string(),
+                OutputFormat::Yaml => {
+                    format!("---\n{}", render(OutputFormat::Yaml, event, EVENT_COLUMNS))
+                }
+                OutputFormat::Table => {
+                    render_table(std::slice::from_ref(event), EVENT_COLUMNS, false)
+                }
+            };
+            println!("{}", line);
+        }
+
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+fn last_index(events: &Value) -> Option<u64> {
+    events.as_array()?.last()?["index"].as_u64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults_to_server() {
+        let cli = Cli::parse_from(["xolotl"]);
+
+        assert!(cli.command.is_none());
+        assert_eq!(cli.server.address, "0.0.0.0");
+        assert_eq!(cli.server.port, 8000);
+    }
+
+    #[test]
+    fn test_server_subcommand() {
+        let cli = Cli::parse_from(["xolotl", "server", "--port", "3000"]);
+
+        match cli.command {
+            Some(Command::Server(args)) => assert_eq!(args.port, 3000),
+            _ => panic!("Expected server subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_register_subcommand() {
+        let cli = Cli::parse_from([
+            "xolotl",
+            "register",
+            "payments",
+            "prod",
+            "http://payments:8080",
+            "--tag",
+            "team=core",
+            "--url",
+            "http://registry:8000",
+            "--output",
+            "json",
+        ]);
+
+        match cli.command {
+            Some(Command::Register {
+                service_name,
+                tags,
+                client,
+                ..
+            }) => {
+                assert_eq!(service_name, "payments");
+                assert_eq!(tags, vec![("team".to_string(), "core".to_string())]);
+                assert_eq!(client.connection.url, "http://registry:8000");
+                assert_eq!(client.output, OutputFormat::Json);
+            }
+            _ => panic!("Expected register subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_tag() {
+        let result = Cli::try_parse_from([
+            "xolotl",
+            "register",
+            "payments",
+            "prod",
+            "http://payments:8080",
+            "--tag",
+            "team",
+        ]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_deregister_requires_target() {
+        assert!(Cli::try_parse_from(["xolotl", "deregister"]).is_err());
+        assert!(Cli::try_parse_from(["xolotl", "deregister", "--instance", "abc"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["xolotl", "deregister", "payments", "--instance", "abc"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from([
+                "xolotl",
+                "deregister",
+                "payments",
+                "--admin-token",
+                "secret",
+                "--act-as",
+                "payments",
+            ])
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_parse_s3_url() {
+        assert_eq!(
+            parse_s3_url("s3://backups/xolotl/snapshot.json"),
+            Ok(("backups", "xolotl/snapshot.json"))
+        );
+        assert_eq!(
+            parse_s3_url("s3://backups/xolotl/"),
+            Ok(("backups", "xolotl"))
+        );
+        assert_eq!(parse_s3_url("s3://backups"), Ok(("backups", "")));
+        assert!(parse_s3_url("https://backups/xolotl").is_err());
+        assert!(parse_s3_url("s3:///xolotl").is_err());
+    }
+
+    #[test]
+    fn test_migrate_subcommand() {
+        let cli = Cli::parse_from([
+            "xolotl",
+            "migrate",
+            "--from-consul",
+            "consul:8500",
+            "--environment",
+            "prod",
+            "--passing-only",
+        ]);
+
+        match cli.command {
+            Some(Command::Migrate {
+                from_consul,
+                environment,
+                passing_only,
+                dry_run,
+                ..
+            }) => {
+                assert_eq!(from_consul, "consul:8500");
+                assert_eq!(environment.as_deref(), Some("prod"));
+                assert!(passing_only);
+                assert!(!dry_run);
+            }
+            _ => panic!("Expected migrate subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_restore_with_inline_key() {
+        let cli = Cli::parse_from([
+            "xolotl",
+            "restore",
+            "--from",
+            "s3://my-bucket/xolotl/",
+            "--key",
+            "k1:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=",
+        ]);
+
+        match cli.command {
+            Some(Command::Restore { key, key_file, .. }) => {
+                assert!(key.is_some());
+                assert!(key_file.is_none());
+            }
+            _ => panic!("Expected restore subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_backup_defaults() {
+        let cli = Cli::parse_from(["xolotl"]);
+        let config = cli.server.backup_config();
+
+        assert!(cli.server.backup_s3_bucket.is_none());
+        assert_eq!(config.prefix, "xolotl");
+        assert_eq!(config.interval, Duration::from_secs(3600));
+        assert_eq!(config.retention, 24);
+        assert!(config.keyring.is_none());
+    }
+
+    #[test]
+    fn test_last_index() {
+        assert_eq!(
+            last_index(&json!([{ "index": 1 }, { "index": 4 }])),
+            Some(4)
+        );
+        assert_eq!(last_index(&json!([])), None);
+    }
+}
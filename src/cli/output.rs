@@ -0,0 +1,163 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Table,
+    Yaml,
+}
+
+/// Columns shown when rendering service instances as a table
+pub const INSTANCE_COLUMNS: &[&str] = &[
+    "id",
+    "service_name",
+    "environment",
+    "address",
+    "health",
+    "tags",
+];
+
+/// Columns shown when rendering registry events as a table
+pub const EVENT_COLUMNS: &[&str] = &[
+    "index",
+    "kind",
+    "service_name",
+    "environment",
+    "instance_id",
+];
+
+/// Columns shown when rendering a heartbeat response as a table
+pub const HEARTBEAT_COLUMNS: &[&str] = &["message", "ttl_seconds", "directives"];
+
+/// Renders a server response in the requested format, using `columns` for tables
+pub fn render(format: OutputFormat, value: &Value, columns: &[&str]) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(value).unwrap_or_default(),
+        OutputFormat::Yaml => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+        OutputFormat::Table => match value {
+            Value::Array(rows) => render_table(rows, columns, true),
+            Value::Object(_) => render_table(std::slice::from_ref(value), columns, true),
+            other => format_cell(other),
+        },
+    }
+}
+
+/// Renders rows as aligned columns, optionally preceded by an upper-cased header
+pub fn render_table(rows: &[Value], columns: &[&str], header: bool) -> String {
+    let mut lines: Vec<Vec<String>> = Vec::new();
+    if header {
+        lines.push(columns.iter().map(|column| column.to_uppercase()).collect());
+    }
+    for row in rows {
+        lines.push(
+            columns
+                .iter()
+                .map(|column| format_cell(&row[column]))
+                .collect(),
+        );
+    }
+
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|i| lines.iter().map(|line| line[i].len()).max().unwrap_or(0))
+        .collect();
+
+    lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, format_cell(value)))
+            .collect::<Vec<_>>()
+            .join(","),
+        Value::Array(items) => items.iter().map(format_cell).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_table_aligns_columns() {
+        let rows = json!([
+            { "service_name": "payments", "environment": "prod", "tags": { "team": "core" } },
+            { "service_name": "db", "environment": "staging", "tags": {} },
+        ]);
+
+        let table = render(
+            OutputFormat::Table,
+            &rows,
+            &["service_name", "environment", "tags"],
+        );
+
+        assert_eq!(
+            table,
+            "SERVICE_NAME  ENVIRONMENT  TAGS\n\
+             payments      prod         team=core\n\
+             db            staging"
+        );
+    }
+
+    #[test]
+    fn test_render_table_message() {
+        let message = json!("Successfully registered service payments in prod");
+
+        assert_eq!(
+            render(OutputFormat::Table, &message, INSTANCE_COLUMNS),
+            "Successfully registered service payments in prod"
+        );
+    }
+
+    #[test]
+    fn test_render_table_single_object() {
+        let report = json!({ "mode": "merge", "created": 2 });
+
+        assert_eq!(
+            render(OutputFormat::Table, &report, &["mode", "created"]),
+            "MODE   CREATED\nmerge  2"
+        );
+    }
+
+    #[test]
+    fn test_render_json_and_yaml() {
+        let value = json!([{ "service_name": "payments", "index": 3 }]);
+
+        let rendered = render(OutputFormat::Json, &value, INSTANCE_COLUMNS);
+        assert_eq!(serde_json::from_str::<Value>(&rendered).unwrap(), value);
+
+        let rendered = render(OutputFormat::Yaml, &value, INSTANCE_COLUMNS);
+        assert_eq!(rendered, "- index: 3\n  service_name: payments");
+    }
+
+    #[test]
+    fn test_render_table_without_header() {
+        let rows = vec![json!({ "index": 7, "kind": "Registered" })];
+
+        assert_eq!(
+            render_table(&rows, &["index", "kind"], false),
+            "7  Registered"
+        );
+    }
+}
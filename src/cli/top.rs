@@ -0,0 +1,368 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row, Table},
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::client::{ClientError, XolotlClient};
+use crate::model::service_registry::now;
+
+/// Number of recent events kept in the events pane
+const MAX_EVENTS: usize = 50;
+
+#[derive(Debug, Clone, Deserialize)]
+struct InstanceView {
+    service_name: String,
+    environment: String,
+    address: String,
+    health: String,
+    last_heartbeat: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EventView {
+    index: u64,
+    timestamp: u64,
+    kind: String,
+    service_name: String,
+    environment: String,
+    instance_id: String,
+}
+
+#[derive(Default)]
+struct TopState {
+    instances: Vec<InstanceView>,
+    events: VecDeque<EventView>,
+    last_index: u64,
+    filter: String,
+    editing_filter: bool,
+    error: Option<String>,
+}
+
+impl TopState {
+    /// Instances matching the filter, either `service` or `service/environment` substrings
+    fn visible(&self) -> Vec<&InstanceView> {
+        let (service, environment) = self
+            .filter
+            .split_once('/')
+            .unwrap_or((self.filter.as_str(), ""));
+
+        let mut visible: Vec<&InstanceView> = self
+            .instances
+            .iter()
+            .filter(|instance| instance.service_name.contains(service))
+            .filter(|instance| instance.environment.contains(environment))
+            .collect();
+        visible.sort_by(|a, b| {
+            (&a.service_name, &a.environment, &a.address).cmp(&(
+                &b.service_name,
+                &b.environment,
+                &b.address,
+            ))
+        });
+        visible
+    }
+
+    fn apply_events(&mut self, events: Vec<EventView>) {
+        for event in events {
+            self.last_index = self.last_index.max(event.index);
+            if self.events.len() == MAX_EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(event);
+        }
+    }
+
+    /// Updates the state for a key press, returning true when the monitor should exit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if self.editing_filter {
+            match code {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            return false;
+        }
+
+        match code {
+            KeyCode::Char('q') => return true,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Esc => self.filter.clear(),
+            _ => {}
+        }
+        false
+    }
+
+    async fn refresh(&mut self, client: &XolotlClient) {
+        match self.fetch(client).await {
+            Ok(_) => self.error = None,
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    async fn fetch(&mut self, client: &XolotlClient) -> Result<(), ClientError> {
+        let events: Vec<EventView> = parse(client.events(self.last_index).await?);
+        self.apply_events(events);
+        self.instances = parse(client.list().await?);
+        Ok(())
+    }
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(value: Value) -> Vec<T> {
+    serde_json::from_value(value).unwrap_or_default()
+}
+
+fn format_age(millis: u64) -> String {
+    let seconds = millis / 1000;
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m{}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{}m", seconds / 3600, (seconds % 3600) / 60),
+    }
+}
+
+fn health_color(health: &str) -> Color {
+    match health {
+        "Healthy" => Color::Green,
+        "Stale" => Color::Yellow,
+        "Unhealthy" => Color::Red,
+        _ => Color::Gray,
+    }
+}
+
+/// Runs the monitor until the user quits, restoring the terminal afterwards
+pub async fn run(client: XolotlClient, url: &str, interval: u64) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &client, url, Duration::from_secs(interval)).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: &XolotlClient,
+    url: &str,
+    interval: Duration,
+) -> io::Result<()> {
+    let mut state = TopState::default();
+    let mut last_refresh: Option<Instant> = None;
+
+    loop {
+        if last_refresh.is_none_or(|refreshed| refreshed.elapsed() >= interval) {
+            state.refresh(client).await;
+            last_refresh = Some(Instant::now());
+        }
+
+        terminal.draw(|frame| draw(frame, &state, url))?;
+
+        if event::poll(Duration::from_millis(200))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && state.handle_key(key.code)
+        {
+            return Ok(());
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, state: &TopState, url: &str) {
+    let [header, instances, events, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(5),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let visible = state.visible();
+    let count = |health: &str| visible.iter().filter(|i| i.health == health).count();
+    frame.render_widget(
+        Paragraph::new(format!(
+            "Xolotl {} | {} instances: {} healthy, {} stale, {} unhealthy, {} unknown",
+            url,
+            visible.len(),
+            count("Healthy"),
+            count("Stale"),
+            count("Unhealthy"),
+            count("Unknown"),
+        )),
+        header,
+    );
+
+    let current = now();
+    let rows = visible.iter().map(|instance| {
+        Row::new(vec![
+            Cell::from(instance.service_name.as_str()),
+            Cell::from(instance.environment.as_str()),
+            Cell::from(instance.address.as_str()),
+            Cell::from(instance.health.as_str())
+                .style(Style::default().fg(health_color(&instance.health))),
+            Cell::from(format_age(current.saturating_sub(instance.last_heartbeat))),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(25),
+            Constraint::Percentage(15),
+            Constraint::Percentage(35),
+            Constraint::Percentage(12),
+            Constraint::Percentage(13),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "SERVICE",
+            "ENVIRONMENT",
+            "ADDRESS",
+            "HEALTH",
+            "HEARTBEAT",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::bordered().title("Instances"));
+    frame.render_widget(table, instances);
+
+    let lines: Vec<Line> = state
+        .events
+        .iter()
+        .rev()
+        .take(events.height.saturating_sub(2) as usize)
+        .map(|event| {
+            Line::from(format!(
+                "#{} {} ago {} {}/{} {}",
+                event.index,
+                format_age(current.saturating_sub(event.timestamp)),
+                event.kind,
+                event.service_name,
+                event.environment,
+                event.instance_id,
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Recent events")),
+        events,
+    );
+
+    let status = match (&state.error, state.editing_filter) {
+        (Some(error), _) => Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red)),
+        (None, true) => Paragraph::new(format!("filter (service/environment): {}_", state.filter)),
+        (None, false) => Paragraph::new(format!(
+            "q quit | / filter | esc clear filter | filter: {}",
+            state.filter
+        )),
+    };
+    frame.render_widget(status, footer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(service_name: &str, environment: &str) -> InstanceView {
+        InstanceView {
+            service_name: service_name.to_string(),
+            environment: environment.to_string(),
+            address: format!("http://{}.{}", service_name, environment),
+            health: "Healthy".to_string(),
+            last_heartbeat: 0,
+        }
+    }
+
+    fn event(index: u64) -> EventView {
+        EventView {
+            index,
+            timestamp: 0,
+            kind: "Registered".to_string(),
+            service_name: "payments".to_string(),
+            environment: "prod".to_string(),
+            instance_id: index.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_service_and_environment() {
+        let state = TopState {
+            instances: vec![
+                instance("payments", "prod"),
+                instance("payments", "dev"),
+                instance("search", "prod"),
+            ],
+            ..Default::default()
+        };
+
+        let filtered = |filter: &str| {
+            let state = TopState {
+                filter: filter.to_string(),
+                instances: state.instances.clone(),
+                ..Default::default()
+            };
+            state
+                .visible()
+                .iter()
+                .map(|i| format!("{}/{}", i.service_name, i.environment))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(filtered("").len(), 3);
+        assert_eq!(filtered("pay"), vec!["payments/dev", "payments/prod"]);
+        assert_eq!(filtered("/prod"), vec!["payments/prod", "search/prod"]);
+        assert_eq!(filtered("search/dev"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_apply_events_tracks_index_and_bounds() {
+        let mut state = TopState::default();
+
+        state.apply_events((1..=MAX_EVENTS as u64 + 5).map(event).collect());
+
+        assert_eq!(state.last_index, MAX_EVENTS as u64 + 5);
+        assert_eq!(state.events.len(), MAX_EVENTS);
+        assert_eq!(state.events.front().unwrap().index, 6);
+    }
+
+    #[test]
+    fn test_handle_keys() {
+        let mut state = TopState::default();
+
+        assert!(!state.handle_key(KeyCode::Char('/')));
+        assert!(state.editing_filter);
+        for c in "pay".chars() {
+            state.handle_key(KeyCode::Char(c));
+        }
+        // q is part of the filter while editing
+        assert!(!state.handle_key(KeyCode::Char('q')));
+        state.handle_key(KeyCode::Backspace);
+        state.handle_key(KeyCode::Enter);
+        assert_eq!(state.filter, "pay");
+        assert!(!state.editing_filter);
+
+        state.handle_key(KeyCode::Esc);
+        assert!(state.filter.is_empty());
+        assert!(state.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5_400), "5s");
+        assert_eq!(format_age(125_000), "2m5s");
+        assert_eq!(format_age(7_380_000), "2h3m");
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde_json::{Map, Value, json};
+use tokio::sync::Mutex;
+
+use crate::model::impersonation::ACT_AS_HEADER;
+use crate::model::payload_signing::{self, XOLOTL_SIGNATURE, XOLOTL_TIMESTAMP};
+use crate::model::service_registry::now;
+
+/// HTTP client for a remote Xolotl server
+pub struct XolotlClient {
+    http: reqwest::Client,
+    base_url: String,
+    admin_token: Option<String>,
+    signing_secret: Option<String>,
+    act_as: Option<String>,
+    offline_cache: Option<OfflineCache>,
+}
+
+/// Last successful resolution of every service and environment, kept on disk
+/// so they can be served while the registry is unreachable
+struct OfflineCache {
+    path: PathBuf,
+    /// Serializes read-modify-write cycles of the file within this process
+    lock: Mutex<()>,
+}
+
+impl OfflineCache {
+    fn key(service_name: &str, environment: &str) -> String {
+        format!("{}/{}", service_name, environment)
+    }
+
+    async fn read(&self) -> Map<String, Value> {
+        tokio::fs::read(&self.path)
+            .await
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    async fn store(&self, service_name: &str, environment: &str, instances: &Value) {
+        let _guard = self.lock.lock().await;
+        let mut cached = self.read().await;
+        cached.insert(Self::key(service_name, environment), instances.clone());
+
+        // Written aside and renamed so readers never see a partial file
+        let partial = self.path.with_extension("partial");
+        let contents = serde_json::to_vec(&cached).expect("JSON values are always serializable");
+        let written = match tokio::fs::write(&partial, contents).await {
+            Ok(()) => tokio::fs::rename(&partial, &self.path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            eprintln!(
+                "Failed to update offline cache {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    /// The cached instances, each flagged with `"stale": true`
+    async fn load(&self, service_name: &str, environment: &str) -> Option<Value> {
+        let _guard = self.lock.lock().await;
+        let mut instances = self
+            .read()
+            .await
+            .remove(&Self::key(service_name, environment))?;
+        for instance in instances.as_array_mut()? {
+            if let Some(instance) = instance.as_object_mut() {
+                instance.insert("stale".to_string(), Value::Bool(true));
+            }
+        }
+        Some(instances)
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    InvalidUrl(String),
+    Request(reqwest::Error),
+    Status(StatusCode),
+    Io(std::io::Error),
+    /// A required setting of a builder was not given
+    Incomplete(&'static str),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InvalidUrl(url) => write!(f, "Invalid server URL {}", url),
+            ClientError::Request(e) => write!(f, "Request to Xolotl failed: {}", e),
+            ClientError::Status(status) => write!(f, "Xolotl responded with {}", status),
+            ClientError::Io(e) => write!(f, "{}", e),
+            ClientError::Incomplete(setting) => write!(f, "Missing {}", setting),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Request(e)
+    }
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl ClientError {
+    /// Whether the registry could not answer at all, as opposed to answering with a client error
+    fn is_unavailable(&self) -> bool {
+        match self {
+            ClientError::InvalidUrl(_) | ClientError::Io(_) | ClientError::Incomplete(_) => false,
+            ClientError::Request(_) => true,
+            ClientError::Status(status) => status.is_server_error(),
+        }
+    }
+}
+
+impl XolotlClient {
+    pub fn new(base_url: &str, admin_token: Option<String>) -> Self {
+        XolotlClient {
+            http: reqwest::Client::new(),
+            base_url: base_url.to_string(),
+            admin_token,
+            signing_secret: None,
+            act_as: None,
+            offline_cache: None,
+        }
+    }
+
+    pub fn with_admin_token(mut self, token: &str) -> Self {
+        self.admin_token = Some(token.to_string());
+        self
+    }
+
+    /// Signs every request body with `secret`, for servers started with
+    /// `--signing-secrets` listing the services this client writes to
+    pub fn with_signing_secret(mut self, secret: &str) -> Self {
+        self.signing_secret = Some(secret.to_string());
+        self
+    }
+
+    /// Acts on behalf of `service_name` with the admin token, for break-glass changes
+    pub fn with_act_as(mut self, service_name: &str) -> Self {
+        self.act_as = Some(service_name.to_string());
+        self
+    }
+
+    /// Keeps the last successful result of `resolve` for every service in the
+    /// JSON file at `path`, and answers from it with instances flagged
+    /// `"stale": true` when the registry is unreachable or failing
+    pub fn with_offline_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.offline_cache = Some(OfflineCache {
+            path: path.into(),
+            lock: Mutex::new(()),
+        });
+        self
+    }
+
+    /// Connects to the first Xolotl server found on the local network over
+    /// SSDP within `timeout`, if any
+    pub async fn discover(
+        timeout: Duration,
+        admin_token: Option<String>,
+    ) -> std::io::Result<Option<Self>> {
+        let urls = crate::ssdp::discover(timeout).await?;
+        Ok(urls.first().map(|url| XolotlClient::new(url, admin_token)))
+    }
+
+    pub async fn register(
+        &self,
+        service_name: &str,
+        environment: &str,
+        address: &str,
+        tags: HashMap<String, String>,
+    ) -> Result<Value, ClientError> {
+        let payload = json!({
+            "service_name": service_name,
+            "environment": environment,
+            "address": address,
+            "tags": tags,
+        });
+        self.send(Method::POST, &["services"], Some(payload)).await
+    }
+
+    pub async fn resolve(
+        &self,
+        service_name: &str,
+        environment: &str,
+    ) -> Result<Value, ClientError> {
+        let result = self
+            .send(Method::GET, &["services", service_name, environment], None)
+            .await;
+        let Some(cache) = &self.offline_cache else {
+            return result;
+        };
+
+        match result {
+            Ok(instances) => {
+                cache.store(service_name, environment, &instances).await;
+                Ok(instances)
+            }
+            Err(e) if e.is_unavailable() => cache.load(service_name, environment).await.ok_or(e),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn list(&self) -> Result<Value, ClientError> {
+        self.send(Method::GET, &["services"], None).await
+    }
+
+    pub async fn deregister(
+        &self,
+        service_name: &str,
+        environment: Option<&str>,
+    ) -> Result<Value, ClientError> {
+        match environment {
+            Some(environment) => {
+                self.send(
+                    Method::DELETE,
+                    &["services", service_name, environment],
+                    None,
+                )
+                .await
+            }
+            None => {
+                self.send(Method::DELETE, &["services", service_name], None)
+                    .await
+            }
+        }
+    }
+
+    pub async fn deregister_instance(&self, id: &str) -> Result<Value, ClientError> {
+        self.send(Method::DELETE, &["services", "instances", id], None)
+            .await
+    }
+
+    pub async fn heartbeat(
+        &self,
+        service_name: &str,
+        environment: &str,
+    ) -> Result<Value, ClientError> {
+        let payload = json!({
+            "service_name": service_name,
+            "environment": environment,
+        });
+        self.send(Method::PUT, &["services", "heartbeat"], Some(payload))
+            .await
+    }
+
+    /// Refreshes a single instance, answering `404` when the registry does not know it
+    pub async fn heartbeat_instance(&self, id: &str) -> Result<Value, ClientError> {
+        let payload = json!([{ "id": id }]);
+        let response = self
+            .send(
+                Method::PUT,
+                &["services", "heartbeat", "batch"],
+                Some(payload),
+            )
+            .await?;
+        if response["refreshed"] == 0 {
+            return Err(ClientError::Status(StatusCode::NOT_FOUND));
+        }
+        Ok(response)
+    }
+
+    /// Moves an instance to a lifecycle state such as `Up` or `Down`
+    pub async fn set_state(&self, id: &str, state: &str) -> Result<Value, ClientError> {
+        let payload = json!({ "state": state });
+        self.send(
+            Method::PUT,
+            &["services", "instances", id, "state"],
+            Some(payload),
+        )
+        .await
+    }
+
+    /// Instances registered at `address`, with or without its protocol
+    pub async fn by_address(&self, address: &str) -> Result<Value, ClientError> {
+        let mut url = self.url(&["services", "by-address"])?;
+        url.query_pairs_mut().append_pair("address", address);
+        self.send_to(Method::GET, url, None).await
+    }
+
+    pub async fn events(&self, since: u64) -> Result<Value, ClientError> {
+        let mut url = self.url(&["events"])?;
+        url.query_pairs_mut()
+            .append_pair("since", &since.to_string());
+        self.send_to(Method::GET, url, None).await
+    }
+
+    /// Changes to the services under `prefix` since the index returned by the
+    /// previous sync, or a full snapshot when `since` is 0
+    pub async fn sync(&self, prefix: &str, since: u64) -> Result<Value, ClientError> {
+        let mut url = self.url(&["sync"])?;
+        url.query_pairs_mut()
+            .append_pair("prefix", prefix)
+            .append_pair("since", &since.to_string());
+        self.send_to(Method::GET, url, None).await
+    }
+
+    /// Every registered instance, through the admin export endpoint
+    pub async fn export(&self) -> Result<Value, ClientError> {
+        self.send(Method::GET, &["admin", "export"], None).await
+    }
+
+    /// Asks the server to hand its registry over and shut down, see `--take-over`
+    pub async fn hand_off(&self) -> Result<Value, ClientError> {
+        self.send(Method::POST, &["admin", "handoff"], None).await
+    }
+
+    /// Imports a registry export through the admin import endpoint
+    pub async fn import(
+        &self,
+        body: Vec<u8>,
+        mode: &str,
+        dry_run: bool,
+    ) -> Result<Value, ClientError> {
+        let mut url = self.url(&["admin", "import"])?;
+        url.query_pairs_mut()
+            .append_pair("mode", mode)
+            .append_pair("dry_run", &dry_run.to_string());
+
+        let request = self
+            .http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        self.execute(request).await
+    }
+
+    /// Builds a URL below the base URL, percent-encoding each path segment
+    fn url(&self, segments: &[&str]) -> Result<Url, ClientError> {
+        let mut url = Url::parse(&self.base_url)
+            .map_err(|_| ClientError::InvalidUrl(self.base_url.clone()))?;
+        url.path_segments_mut()
+            .map_err(|_| ClientError::InvalidUrl(self.base_url.clone()))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        segments: &[&str],
+        payload: Option<Value>,
+    ) -> Result<Value, ClientError> {
+        let url = self.url(segments)?;
+        self.send_to(method, url, payload).await
+    }
+
+    async fn send_to(
+        &self,
+        method: Method,
+        url: Url,
+        payload: Option<Value>,
+    ) -> Result<Value, ClientError> {
+        let mut request = self.http.request(method, url);
+        if let Some(payload) = payload {
+            // Signed over the exact bytes sent, so the body is serialized here
+            let body = serde_json::to_vec(&payload).unwrap_or_default();
+            if let Some(secret) = &self.signing_secret {
+                let at = now();
+                request = request
+                    .header(XOLOTL_SIGNATURE, payload_signing::sign(secret, at, &body))
+                    .header(XOLOTL_TIMESTAMP, at.to_string());
+            }
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+        self.execute(request).await
+    }
+
+    async fn execute(&self, mut request: RequestBuilder) -> Result<Value, ClientError> {
+        if let Some(token) = &self.admin_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(service_name) = &self.act_as {
+            request = request.header(ACT_AS_HEADER, service_name);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(ClientError::Status(response.status()));
+        }
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AppState;
+    use crate::model::service_registry::HealthPolicy;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use crate::testing::TestServer;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    async fn spawn_server(admin_token: Option<String>) -> TestServer {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        TestServer::with_state(AppState::new(
+            registry,
+            HealthPolicy::default(),
+            admin_token,
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_register_resolve_and_deregister() {
+        let server = spawn_server(None).await;
+        let client = XolotlClient::new(server.url(), None);
+
+        let mut tags = HashMap::new();
+        tags.insert("team".to_string(), "backend".to_string());
+        let message = client
+            .register("payments", "prod", "http://payments:8080", tags)
+            .await
+            .unwrap();
+        assert_eq!(message, "Successfully registered service payments in prod");
+
+        let instances = client.resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["address"], "http://payments:8080");
+        assert_eq!(instances[0]["tags"]["team"], "backend");
+
+        assert!(client.heartbeat("payments", "prod").await.is_ok());
+        assert_eq!(client.list().await.unwrap().as_array().unwrap().len(), 1);
+        let snapshot = client.sync("pay", 0).await.unwrap();
+        assert_eq!(snapshot["upserts"][0]["address"], "http://payments:8080");
+
+        client.deregister("payments", Some("prod")).await.unwrap();
+        assert!(matches!(
+            client.resolve("payments", "prod").await,
+            Err(ClientError::Status(StatusCode::NOT_FOUND))
+        ));
+
+        let events = client.events(1).await.unwrap();
+        assert_eq!(events.as_array().unwrap().len(), 1);
+        assert_eq!(events[0]["kind"], "Deregistered");
+    }
+
+    #[tokio::test]
+    async fn test_deregister_instance_with_admin_token() {
+        let server = spawn_server(Some("secret".to_string())).await;
+        let anonymous = XolotlClient::new(server.url(), None);
+        let admin = server.client();
+
+        anonymous
+            .register("cache", "dev", "redis://cache:6379", HashMap::new())
+            .await
+            .unwrap();
+        let instances = anonymous.resolve("cache", "dev").await.unwrap();
+        let id = instances[0]["id"].as_str().unwrap();
+
+        assert!(matches!(
+            anonymous.deregister_instance(id).await,
+            Err(ClientError::Status(StatusCode::UNAUTHORIZED))
+        ));
+        assert!(admin.deregister_instance(id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let server = spawn_server(None).await;
+        let client = XolotlClient::new(server.url(), None);
+        client
+            .register("old", "dev", "http://old:8080", HashMap::new())
+            .await
+            .unwrap();
+
+        let entry = crate::model::service_registry::ServiceEntry::new(
+            "new".parse().unwrap(),
+            "dev".parse().unwrap(),
+            "http://new:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        let body = serde_json::to_vec(&vec![entry]).unwrap();
+
+        let report = client.import(body, "replace", false).await.unwrap();
+        assert_eq!(report["created"], 1);
+        assert_eq!(report["removed"], 1);
+
+        let instances = client.list().await.unwrap();
+        assert_eq!(instances.as_array().unwrap().len(), 1);
+        assert_eq!(instances[0]["service_name"], "new");
+    }
+
+    #[tokio::test]
+    async fn test_offline_cache_serves_stale_results() {
+        let server = spawn_server(None).await;
+        let path = std::env::temp_dir().join(format!("xolotl-{}.json", uuid::Uuid::new_v4()));
+        let client = XolotlClient::new(server.url(), None).with_offline_cache(&path);
+        client
+            .register("payments", "prod", "http://payments:8080", HashMap::new())
+            .await
+            .unwrap();
+        let instances = client.resolve("payments", "prod").await.unwrap();
+        assert!(instances[0].get("stale").is_none());
+
+        // Nothing listens on port 1, as if the registry were down
+        let offline = XolotlClient::new("http://127.0.0.1:1", None).with_offline_cache(&path);
+        let instances = offline.resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["address"], "http://payments:8080");
+        assert_eq!(instances[0]["stale"], true);
+        assert!(matches!(
+            offline.resolve("search", "prod").await,
+            Err(ClientError::Request(_))
+        ));
+
+        // Not found is an answer, not an outage, so it is never covered up
+        client.deregister("payments", None).await.unwrap();
+        assert!(matches!(
+            client.resolve("payments", "prod").await,
+            Err(ClientError::Status(StatusCode::NOT_FOUND))
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_path_segments_are_encoded() {
+        let client = XolotlClient::new("http://localhost:8000/", None);
+
+        let url = client.url(&["services", "my service", "dev/1"]).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "http://localhost:8000/services/my%20service/dev%2F1"
+        );
+    }
+
+    #[test]
+    fn test_invalid_base_url() {
+        let client = XolotlClient::new("not a url", None);
+
+        assert!(matches!(
+            client.url(&["services"]),
+            Err(ClientError::InvalidUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::client::ClientError;
+
+/// Name Consul registers itself under, skipped during migrations
+const CONSUL_SERVICE: &str = "consul";
+
+/// Read-only client for the parts of the Consul HTTP API needed to migrate a catalog
+pub struct ConsulClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConsulServiceHealth {
+    pub node: ConsulNode,
+    pub service: ConsulService,
+    #[serde(default)]
+    pub checks: Vec<ConsulCheck>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConsulNode {
+    pub node: String,
+    pub address: String,
+    #[serde(default)]
+    pub datacenter: String,
+    #[serde(default)]
+    pub meta: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConsulService {
+    #[serde(rename = "ID")]
+    pub id: String,
+    pub service: String,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub address: String,
+    pub port: u16,
+    #[serde(default)]
+    pub meta: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConsulCheck {
+    pub status: String,
+}
+
+/// A Consul service instance translated into a Xolotl registration
+#[derive(Debug, Serialize, PartialEq)]
+pub struct MigratedEntry {
+    pub service_name: String,
+    pub environment: String,
+    pub address: String,
+    pub tags: HashMap<String, String>,
+}
+
+impl ConsulServiceHealth {
+    /// Aggregates the instance checks the way Consul does: critical beats warning beats passing
+    pub fn status(&self) -> &str {
+        ["critical", "warning"]
+            .into_iter()
+            .find(|status| self.checks.iter().any(|check| check.status == *status))
+            .unwrap_or("passing")
+    }
+
+    /// Maps the instance to a Xolotl entry, using the datacenter unless `environment` is given
+    pub fn to_entry(&self, environment: Option<&str>) -> MigratedEntry {
+        let host = if self.service.address.is_empty() {
+            &self.node.address
+        } else {
+            &self.service.address
+        };
+
+        let mut tags: HashMap<String, String> = self.service.meta.clone().unwrap_or_default();
+        for (key, value) in self.node.meta.iter().flatten() {
+            tags.insert(format!("node.{}", key), value.clone());
+        }
+        if let Some(consul_tags) = self.service.tags.as_ref().filter(|t| !t.is_empty()) {
+            tags.insert("consul.tags".to_string(), consul_tags.join(","));
+        }
+        tags.insert("consul.node".to_string(), self.node.node.clone());
+        tags.insert("consul.service_id".to_string(), self.service.id.clone());
+        tags.insert("consul.health".to_string(), self.status().to_string());
+
+        MigratedEntry {
+            service_name: self.service.service.clone(),
+            environment: environment.unwrap_or(&self.node.datacenter).to_string(),
+            address: format!("{}:{}", host, self.service.port),
+            tags,
+        }
+    }
+}
+
+impl ConsulClient {
+    pub fn new(base_url: &str, token: Option<String>) -> Self {
+        ConsulClient {
+            http: reqwest::Client::new(),
+            base_url: base_url.to_string(),
+            token,
+        }
+    }
+
+    /// Lists the names of every service in the catalog except Consul itself
+    pub async fn service_names(&self) -> Result<Vec<String>, ClientError> {
+        let services: HashMap<String, Vec<String>> =
+            self.get(&["v1", "catalog", "services"]).await?;
+        let mut names: Vec<String> = services
+            .into_keys()
+            .filter(|name| name != CONSUL_SERVICE)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Returns every instance of a service together with its health checks
+    pub async fn service_health(
+        &self,
+        name: &str,
+    ) -> Result<Vec<ConsulServiceHealth>, ClientError> {
+        self.get(&["v1", "health", "service", name]).await
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, segments: &[&str]) -> Result<T, ClientError> {
+        let mut url = Url::parse(&self.base_url)
+            .map_err(|_| ClientError::InvalidUrl(self.base_url.clone()))?;
+        url.path_segments_mut()
+            .map_err(|_| ClientError::InvalidUrl(self.base_url.clone()))?
+            .pop_if_empty()
+            .extend(segments);
+
+        let mut request = self.http.get(url);
+        if let Some(token) = &self.token {
+            request = request.header("X-Consul-Token", token);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(ClientError::Status(response.status()));
+        }
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, extract::Path, routing::get};
+    use serde_json::{Value, json};
+
+    fn health_response() -> Value {
+        json!([
+            {
+                "Node": {
+                    "Node": "node-1",
+                    "Address": "10.0.0.1",
+                    "Datacenter": "dc1",
+                    "Meta": { "rack": "a1" }
+                },
+                "Service": {
+                    "ID": "web-1",
+                    "Service": "web",
+                    "Tags": ["primary", "v2"],
+                    "Address": "",
+                    "Port": 8080,
+                    "Meta": { "version": "2.1.0" }
+                },
+                "Checks": [{ "Status": "passing" }, { "Status": "warning" }]
+            },
+            {
+                "Node": { "Node": "node-2", "Address": "10.0.0.2", "Datacenter": "dc1", "Meta": null },
+                "Service": {
+                    "ID": "web-2",
+                    "Service": "web",
+                    "Tags": null,
+                    "Address": "192.168.1.2",
+                    "Port": 9090,
+                    "Meta": null
+                },
+                "Checks": []
+            }
+        ])
+    }
+
+    #[test]
+    fn test_to_entry_maps_meta_and_tags() {
+        let instances: Vec<ConsulServiceHealth> =
+            serde_json::from_value(health_response()).unwrap();
+
+        let entry = instances[0].to_entry(None);
+        assert_eq!(entry.service_name, "web");
+        assert_eq!(entry.environment, "dc1");
+        assert_eq!(entry.address, "10.0.0.1:8080");
+        assert_eq!(entry.tags["version"], "2.1.0");
+        assert_eq!(entry.tags["node.rack"], "a1");
+        assert_eq!(entry.tags["consul.tags"], "primary,v2");
+        assert_eq!(entry.tags["consul.node"], "node-1");
+        assert_eq!(entry.tags["consul.service_id"], "web-1");
+        assert_eq!(entry.tags["consul.health"], "warning");
+
+        let entry = instances[1].to_entry(Some("prod"));
+        assert_eq!(entry.environment, "prod");
+        assert_eq!(entry.address, "192.168.1.2:9090");
+        assert_eq!(entry.tags["consul.health"], "passing");
+        assert!(!entry.tags.contains_key("consul.tags"));
+    }
+
+    #[test]
+    fn test_status_prefers_critical() {
+        let mut instances: Vec<ConsulServiceHealth> =
+            serde_json::from_value(health_response()).unwrap();
+        instances[0].checks.push(ConsulCheck {
+            status: "critical".to_string(),
+        });
+
+        assert_eq!(instances[0].status(), "critical");
+    }
+
+    #[tokio::test]
+    async fn test_reads_fake_consul() {
+        let app = Router::new()
+            .route(
+                "/v1/catalog/services",
+                get(|| async { Json(json!({ "consul": [], "web": ["primary"], "api": [] })) }),
+            )
+            .route(
+                "/v1/health/service/{name}",
+                get(|Path(name): Path<String>| async move {
+                    if name == "web" {
+                        Json(health_response())
+                    } else {
+                        Json(json!([]))
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let consul = ConsulClient::new(&format!("http://{}", address), None);
+
+        assert_eq!(consul.service_names().await.unwrap(), vec!["api", "web"]);
+        assert_eq!(consul.service_health("web").await.unwrap().len(), 2);
+        assert!(consul.service_health("api").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::model::entry_source::EntrySource;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::ServiceEntry;
+
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Tag carrying the DNS name a synthetic entry was resolved from
+pub const DNS_NAME_TAG: &str = "dns.name";
+
+/// Resolves services the registry does not know through the system resolver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DnsFallback {
+    pub enabled: bool,
+}
+
+impl DnsFallback {
+    /// Looks the service name up in DNS, returning one synthetic entry per
+    /// address found. Failed or slow lookups resolve to nothing.
+    pub async fn lookup(
+        &self,
+        service_name: &ServiceName,
+        environment: &Environment,
+        at: u64,
+    ) -> Vec<ServiceEntry> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        let host = (service_name.as_str(), 0);
+        let addresses =
+            match tokio::time::timeout(LOOKUP_TIMEOUT, tokio::net::lookup_host(host)).await {
+                Ok(Ok(addresses)) => addresses,
+                _ => return Vec::new(),
+            };
+
+        let mut entries: Vec<ServiceEntry> = Vec::new();
+        for address in addresses {
+            let ip = address.ip().to_string();
+            if entries.iter().any(|entry| entry.address_str() == ip) {
+                continue;
+            }
+            let tags = HashMap::from([(DNS_NAME_TAG.to_string(), service_name.to_string())]);
+            entries.push(ServiceEntry {
+                id: format!("dns-{}", ip)
+                    .parse()
+                    .expect("IP addresses are valid ids"),
+                source: EntrySource::Dns,
+                ..ServiceEntry::new(service_name.clone(), environment.clone(), ip, tags, at)
+            });
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lookup() {
+        let name: ServiceName = "localhost".parse().unwrap();
+        let environment: Environment = "prod".parse().unwrap();
+
+        let entries = DnsFallback { enabled: true }
+            .lookup(&name, &environment, 1_000)
+            .await;
+        assert!(!entries.is_empty());
+        assert!(entries.iter().all(|entry| entry.source == EntrySource::Dns));
+        assert_eq!(entries[0].tags[DNS_NAME_TAG], "localhost");
+
+        let disabled = DnsFallback::default()
+            .lookup(&name, &environment, 1_000)
+            .await;
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+const KEY_LEN: usize = 32;
+const ENVELOPE_VERSION: u32 = 1;
+
+/// AES-256-GCM keys by id, the first one encrypts and every one decrypts so
+/// keys can be rotated by adding a new key on top of the old ones
+#[derive(Clone)]
+pub struct Keyring {
+    keys: Vec<(String, [u8; KEY_LEN])>,
+}
+
+/// Lists key ids only, so the keys never end up in logs
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.keys.iter().map(|(id, _)| id))
+            .finish()
+    }
+}
+
+/// Encrypted payload as stored, itself JSON so it can be told apart from plain snapshots
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    xolotl_encrypted: u32,
+    key_id: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Keyring {
+    /// Reads a keyring with one `<id>:<base64 key>` line per key
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read key file {}: {}", path, e))?;
+        contents.parse()
+    }
+
+    fn key(&self, id: &str) -> Option<LessSafeKey> {
+        self.keys
+            .iter()
+            .find(|(key_id, _)| key_id == id)
+            .map(|(_, bytes)| {
+                LessSafeKey::new(UnboundKey::new(&AES_256_GCM, bytes).expect("Key length checked"))
+            })
+    }
+
+    /// Encrypts `plaintext` with the first key of the ring
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let (key_id, _) = &self.keys[0];
+        let key = self.key(key_id).expect("Keyring is never empty");
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .expect("System random source is available");
+        let mut ciphertext = plaintext.to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(key_id.as_bytes()),
+            &mut ciphertext,
+        )
+        .expect("Payload fits in a single AES-GCM message");
+
+        let envelope = Envelope {
+            xolotl_encrypted: ENVELOPE_VERSION,
+            key_id: key_id.clone(),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+        serde_json::to_vec(&envelope).expect("Envelopes are always serializable")
+    }
+
+    fn open(&self, envelope: Envelope) -> Result<Vec<u8>, String> {
+        let key = self.key(&envelope.key_id).ok_or_else(|| {
+            format!(
+                "Payload is encrypted with key {}, which is not in the keyring",
+                envelope.key_id
+            )
+        })?;
+        let nonce: [u8; NONCE_LEN] = STANDARD
+            .decode(&envelope.nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or("Encrypted payload has an invalid nonce")?;
+        let mut ciphertext = STANDARD
+            .decode(&envelope.ciphertext)
+            .map_err(|_| "Encrypted payload is not valid base64")?;
+
+        let plaintext = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(envelope.key_id.as_bytes()),
+                &mut ciphertext,
+            )
+            .map_err(|_| {
+                format!(
+                    "Failed to decrypt payload with key {}, it is either corrupt or the key is wrong",
+                    envelope.key_id
+                )
+            })?;
+        Ok(plaintext.to_vec())
+    }
+}
+
+impl FromStr for Keyring {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut keys: Vec<(String, [u8; KEY_LEN])> = Vec::new();
+        for line in value.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (id, key) = line
+                .split_once(':')
+                .ok_or("Invalid key, expected <id>:<base64 key>")?;
+            let id = id.trim();
+            let key: [u8; KEY_LEN] = STANDARD
+                .decode(key.trim())
+                .ok()
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| format!("Key {} must be {} bytes of base64", id, KEY_LEN))?;
+            if id.is_empty() || keys.iter().any(|(other, _)| other == id) {
+                return Err(format!("Key id '{}' is empty or repeated", id));
+            }
+            keys.push((id.to_string(), key));
+        }
+        if keys.is_empty() {
+            return Err("Keyring contains no keys".to_string());
+        }
+        Ok(Keyring { keys })
+    }
+}
+
+/// Returns the plaintext of a stored payload, which is passed through as is
+/// when it was written without encryption
+pub fn decrypt(payload: Vec<u8>, keyring: Option<&Keyring>) -> Result<Vec<u8>, String> {
+    let Ok(envelope) = serde_json::from_slice::<Envelope>(&payload) else {
+        return Ok(payload);
+    };
+    keyring
+        .ok_or_else(|| "Payload is encrypted, a key is required to read it".to_string())?
+        .open(envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "2024-01:AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+    const NEW: &str = "2024-06:ICEiIyQlJicoKSorLC0uLzAxMjM0NTY3ODk6Ozw9Pj8=";
+
+    #[test]
+    fn test_round_trip_and_rotation() {
+        let old: Keyring = OLD.parse().unwrap();
+        let rotated: Keyring = format!("# newest first\n{}\n{}\n", NEW, OLD)
+            .parse()
+            .unwrap();
+
+        let sealed = old.seal(br#"[{"id":"a"}]"#);
+        assert!(!String::from_utf8_lossy(&sealed).contains(r#""id""#));
+        assert_eq!(
+            decrypt(sealed.clone(), Some(&rotated)).unwrap(),
+            br#"[{"id":"a"}]"#
+        );
+
+        let resealed = rotated.seal(b"[]");
+        assert!(String::from_utf8_lossy(&resealed).contains("2024-06"));
+        assert!(
+            decrypt(resealed, Some(&old))
+                .unwrap_err()
+                .contains("2024-06")
+        );
+        assert!(decrypt(sealed, None).is_err());
+    }
+
+    #[test]
+    fn test_plaintext_passes_through() {
+        let keyring: Keyring = OLD.parse().unwrap();
+
+        assert_eq!(decrypt(b"[]".to_vec(), Some(&keyring)).unwrap(), b"[]");
+        assert_eq!(decrypt(b"[]".to_vec(), None).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn test_tampered_payload_is_rejected() {
+        let keyring: Keyring = OLD.parse().unwrap();
+        let sealed = String::from_utf8(keyring.seal(b"[]")).unwrap();
+        let forged = sealed.replace("2024-01", "2024-06");
+        let other: Keyring = OLD.replace("2024-01", "2024-06").parse().unwrap();
+
+        assert!(decrypt(forged.into_bytes(), Some(&other)).is_err());
+    }
+
+    #[test]
+    fn test_invalid_keyrings() {
+        assert!("".parse::<Keyring>().is_err());
+        assert!("2024-01".parse::<Keyring>().is_err());
+        assert!("2024-01:c2hvcnQ=".parse::<Keyring>().is_err());
+        assert!(format!("{}\n{}", OLD, OLD).parse::<Keyring>().is_err());
+
+        let keyring: Keyring = OLD.parse().unwrap();
+        assert_eq!(format!("{:?}", keyring), r#"["2024-01"]"#);
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use crate::alerting::notify;
+use crate::api::environments::services_in;
+use crate::model::clock::SharedClock;
+use crate::model::environment::{EnvironmentExpiry, EnvironmentExpiryKind};
+use crate::model::service_registry::ServiceRegistry;
+use crate::notifier::{Notification, Notifier};
+use crate::registry::environment_store::EnvironmentStore;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Warns once about every environment expiring within `warn_before` millis of time `at`
+pub async fn warn_expiring(
+    environments: &Arc<RwLock<EnvironmentStore>>,
+    at: u64,
+    warn_before: u64,
+) -> Vec<EnvironmentExpiry> {
+    environments
+        .write()
+        .await
+        .expiring(at, warn_before)
+        .into_iter()
+        .filter_map(|record| {
+            Some(EnvironmentExpiry {
+                kind: EnvironmentExpiryKind::Expiring,
+                expires_at: record.expires_at?,
+                environment: record.name,
+            })
+        })
+        .collect()
+}
+
+/// Archives every environment past its expiry at time `at` and tombstones
+/// its services, returning the environments that expired
+pub async fn expire_environments(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    environments: &Arc<RwLock<EnvironmentStore>>,
+    at: u64,
+) -> Vec<EnvironmentExpiry> {
+    let mut environments = environments.write().await;
+    let mut registry = registry.write().await;
+
+    let mut expired = Vec::new();
+    for environment in environments.expired(at) {
+        for service_name in services_in(&*registry, &environment) {
+            if let Err(e) = registry.tombstone(&service_name, &environment) {
+                eprintln!(
+                    "Failed to tombstone service {} in {}: {:?}",
+                    service_name, environment, e
+                );
+            }
+        }
+        if let Ok(record) = environments.archive(&environment, at) {
+            expired.push(EnvironmentExpiry {
+                kind: EnvironmentExpiryKind::Expired,
+                environment,
+                expires_at: record.expires_at.unwrap_or(at),
+            });
+        }
+    }
+    expired
+}
+
+/// Runs `warn_expiring` and `expire_environments` periodically for the lifetime
+/// of the process, sending what they report to every notifier
+pub fn spawn_environment_expiry(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    environments: Arc<RwLock<EnvironmentStore>>,
+    clock: SharedClock,
+    warn_before: u64,
+    notifiers: Vec<Notifier>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let at = clock.now();
+            let mut notices = warn_expiring(&environments, at, warn_before).await;
+            notices.extend(expire_environments(&registry, &environments, at).await);
+            for notice in notices {
+                notify(&http, &notifiers, Notification::Expiry(&notice)).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::environment::EnvironmentRecord;
+    use crate::model::identifiers::Environment;
+    use crate::model::service_registry::{ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_expired_environment_is_archived() {
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for environment in ["pr-42", "prod"] {
+            registry
+                .write()
+                .await
+                .register(ServiceEntry::new(
+                    "web".parse().unwrap(),
+                    environment.parse().unwrap(),
+                    "http://10.0.0.1:8080".to_string(),
+                    HashMap::new(),
+                    now(),
+                ))
+                .unwrap();
+        }
+        let environments = Arc::new(RwLock::new(EnvironmentStore::new()));
+        let name: Environment = "pr-42".parse().unwrap();
+        environments
+            .write()
+            .await
+            .create(EnvironmentRecord::new(name.clone(), Some(60), 0))
+            .unwrap();
+
+        let warnings = warn_expiring(&environments, 0, 60_000).await;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, EnvironmentExpiryKind::Expiring);
+        assert_eq!(warnings[0].expires_at, 60_000);
+        assert!(warn_expiring(&environments, 0, 60_000).await.is_empty());
+
+        assert!(
+            expire_environments(&registry, &environments, 59_000)
+                .await
+                .is_empty()
+        );
+        let expired = expire_environments(&registry, &environments, 60_000).await;
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].kind, EnvironmentExpiryKind::Expired);
+        assert_eq!(expired[0].environment, name);
+
+        let remaining = registry.read().await.list();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].environment.as_str(), "prod");
+        assert!(environments.read().await.get(&name).unwrap().is_archived());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::gc_policy::EvictionReason;
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::min_instances::check_min_instances;
+use crate::model::service_registry::{HealthPolicy, ServiceEntry, ServiceRegistry};
+use crate::registry::profile_store::ProfileStore;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// An instance removed by its service's garbage-collection policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Eviction {
+    pub instance_id: InstanceId,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub reason: EvictionReason,
+}
+
+/// Applies the garbage-collection policy of every service at time `at`,
+/// returning the instances that were removed. Evictions that would take a
+/// service below its minimum healthy instances are skipped
+pub async fn collect_garbage(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    profiles: &Arc<RwLock<ProfileStore>>,
+    policy: &HealthPolicy,
+    at: u64,
+) -> Vec<Eviction> {
+    let profiles = profiles.read().await;
+    let mut registry = registry.write().await;
+
+    let mut groups: BTreeMap<(ServiceName, Environment), Vec<ServiceEntry>> = BTreeMap::new();
+    for entry in registry.list() {
+        groups
+            .entry((entry.service_name.clone(), entry.environment.clone()))
+            .or_default()
+            .push(entry);
+    }
+
+    let mut evictions = Vec::new();
+    for ((service_name, environment), mut instances) in groups {
+        let minimums = profiles
+            .get(&service_name)
+            .map(|profile| &profile.min_instances);
+        let planned: Vec<(ServiceEntry, EvictionReason)> = profiles
+            .gc(&service_name)
+            .evictions(&instances, at)
+            .into_iter()
+            .map(|(entry, reason)| (entry.clone(), reason))
+            .collect();
+        for (entry, reason) in planned {
+            if let Some(minimums) = minimums
+                && let Err(e) = check_min_instances(
+                    minimums,
+                    &instances,
+                    std::slice::from_ref(&entry),
+                    policy,
+                    at,
+                )
+            {
+                eprintln!("Kept instance {}: {}", entry.id, e);
+                continue;
+            }
+            if let Err(e) = registry.deregister_instance(&entry.id) {
+                eprintln!("Failed to evict instance {}: {:?}", entry.id, e);
+                continue;
+            }
+            instances.retain(|instance| instance.id != entry.id);
+            evictions.push(Eviction {
+                instance_id: entry.id.clone(),
+                service_name: service_name.clone(),
+                environment: environment.clone(),
+                reason,
+            });
+        }
+    }
+    evictions
+}
+
+/// Runs `collect_garbage` periodically for the lifetime of the process
+pub fn spawn_gc(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    profiles: Arc<RwLock<ProfileStore>>,
+    clock: SharedClock,
+    policy: HealthPolicy,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for eviction in collect_garbage(&registry, &profiles, &policy, clock.now()).await {
+                println!(
+                    "Evicted instance {} of {} in {} ({:?})",
+                    eviction.instance_id,
+                    eviction.service_name,
+                    eviction.environment,
+                    eviction.reason
+                );
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::gc_policy::GcPolicy;
+    use crate::model::service_profile::ServiceProfile;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_collect_garbage_per_service() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry: Arc<RwLock<dyn ServiceRegistry>> = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        for name in ["jobs", "jobs", "jobs", "api"] {
+            let entry = ServiceEntry::new(
+                name.parse().unwrap(),
+                "prod".parse().unwrap(),
+                "http://10.0.0.1:8080".to_string(),
+                HashMap::new(),
+                clock.now(),
+            );
+            registry.write().await.register(entry).unwrap();
+            clock.advance(Duration::from_secs(1));
+        }
+        let profiles = Arc::new(RwLock::new(ProfileStore::new()));
+        profiles.write().await.put(
+            "jobs".parse().unwrap(),
+            ServiceProfile {
+                gc: GcPolicy {
+                    max_instances: Some(2),
+                    ..GcPolicy::default()
+                },
+                ..ServiceProfile::default()
+            },
+        );
+
+        let evictions =
+            collect_garbage(&registry, &profiles, &HealthPolicy::default(), clock.now()).await;
+
+        assert_eq!(evictions.len(), 1);
+        assert_eq!(evictions[0].service_name, "jobs");
+        assert_eq!(evictions[0].reason, EvictionReason::OverCapacity);
+        let remaining = registry.read().await.list();
+        assert_eq!(remaining.len(), 3);
+        assert!(
+            remaining
+                .iter()
+                .all(|entry| entry.id != evictions[0].instance_id)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{StreamExt, stream};
+use tokio::sync::RwLock;
+
+use crate::model::entry_source::EntrySource;
+use crate::model::identifiers::InstanceId;
+use crate::model::service_registry::ServiceRegistry;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most probes in flight at once, so slow endpoints cost one timeout per batch
+/// instead of one each
+const MAX_CONCURRENT_CHECKS: usize = 32;
+
+/// Probes the health check URL of every checked external entry, recording a
+/// heartbeat for each one answering with a success status. Returns the entries
+/// that failed their check
+pub async fn check_external(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    http: &reqwest::Client,
+) -> Vec<InstanceId> {
+    let targets: Vec<(InstanceId, String)> = registry
+        .read()
+        .await
+        .list()
+        .into_iter()
+        .filter(|entry| entry.source == EntrySource::External)
+        .filter_map(|entry| entry.health_check.map(|url| (entry.id, url)))
+        .collect();
+
+    let results: Vec<(InstanceId, bool)> = stream::iter(targets)
+        .map(|(id, url)| async move {
+            let healthy = http
+                .get(&url)
+                .timeout(CHECK_TIMEOUT)
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            (id, healthy)
+        })
+        .buffer_unordered(MAX_CONCURRENT_CHECKS)
+        .collect()
+        .await;
+
+    let mut failed = Vec::new();
+    let mut registry = registry.write().await;
+    for (id, healthy) in results {
+        if !healthy {
+            failed.push(id);
+            continue;
+        }
+        // The entry may have been deregistered while it was being checked
+        let _ = registry.heartbeat_instance(&id);
+    }
+    failed
+}
+
+/// Runs `check_external` every `interval` for the lifetime of the process
+pub fn spawn_health_checks(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let http = reqwest::Client::new();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for id in check_external(&registry, &http).await {
+                eprintln!("Health check of external entry {} failed", id);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::ServiceEntry;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use axum::{Router, http::StatusCode, routing::get};
+    use std::collections::HashMap;
+
+    fn external_entry(health_check: String) -> ServiceEntry {
+        ServiceEntry {
+            source: EntrySource::External,
+            health_check: Some(health_check),
+            ..ServiceEntry::new(
+                "payments-api".parse().unwrap(),
+                "prod".parse().unwrap(),
+                "https://api.payments.example.com".to_string(),
+                HashMap::new(),
+                0,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_external() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/up", get(|| async { StatusCode::OK }))
+            .route("/down", get(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let up = external_entry(format!("{}/up", base));
+        let down = external_entry(format!("{}/down", base));
+        registry.write().await.register(up.clone()).unwrap();
+        registry.write().await.register(down.clone()).unwrap();
+
+        let failed = check_external(&registry, &reqwest::Client::new()).await;
+        assert_eq!(failed, vec![down.id.clone()]);
+        let registry = registry.read().await;
+        let entries = registry.list();
+        let last_heartbeat = |id: &InstanceId| {
+            entries
+                .iter()
+                .find(|entry| &entry.id == id)
+                .unwrap()
+                .last_heartbeat
+        };
+        assert!(last_heartbeat(&up.id) > 0);
+        assert_eq!(last_heartbeat(&down.id), 0);
+    }
+
+    #[tokio::test]
+    async fn test_checks_run_concurrently() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                StatusCode::OK
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        for _ in 0..10 {
+            registry
+                .write()
+                .await
+                .register(external_entry(format!("{}/slow", base)))
+                .unwrap();
+        }
+
+        let started = std::time::Instant::now();
+        let failed = check_external(&registry, &reqwest::Client::new()).await;
+        assert!(failed.is_empty());
+        assert!(started.elapsed() < Duration::from_millis(1500));
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::ServiceRegistry;
+use crate::registry::history_store::HistoryStore;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Records the instance counts at time `at` and the churn since the previous sample
+pub async fn sample_history(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    history: &Arc<RwLock<HistoryStore>>,
+    at: u64,
+) {
+    let mut history = history.write().await;
+    let registry = registry.read().await;
+    let events = registry.events(history.last_event_index());
+    history.record(at, &registry.list(), &events);
+}
+
+/// Runs `sample_history` periodically for the lifetime of the process
+pub fn spawn_history_sampling(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    history: Arc<RwLock<HistoryStore>>,
+    clock: SharedClock,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            sample_history(&registry, &history, clock.now()).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{ServiceEntry, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_sample_history_counts_churn_once() {
+        let registry: Arc<RwLock<dyn ServiceRegistry>> =
+            Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let history = Arc::new(RwLock::new(HistoryStore::new()));
+        registry
+            .write()
+            .await
+            .register(ServiceEntry::new(
+                "payments".parse().unwrap(),
+                "prod".parse().unwrap(),
+                "http://localhost:8080".to_string(),
+                HashMap::new(),
+                now(),
+            ))
+            .unwrap();
+
+        sample_history(&registry, &history, 1_000).await;
+        sample_history(&registry, &history, 61_000).await;
+
+        let samples =
+            history
+                .read()
+                .await
+                .samples(&"payments".parse().unwrap(), &"prod".parse().unwrap(), 0);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].registrations, 1);
+        assert_eq!(samples[1].registrations, 0);
+        assert_eq!(samples[1].instances, 1);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::InstanceId;
+use crate::model::service_registry::{HealthPolicy, HealthStatus, ServiceRegistry};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Percentage of the silence allowed before an instance is unhealthy at which it
+/// is recorded as expiring, when `--alert-rules` does not set another
+pub const DEFAULT_WARNING_PERCENT: u8 = 80;
+
+/// Records an `Expiring` event for every instance that has been silent for at
+/// least `percent` of the time it may miss heartbeats at time `at` without being
+/// unhealthy yet, once per silence. `expiring` holds the instances already
+/// recorded, which are forgotten once they heartbeat again or become unhealthy
+pub async fn mark_expiring(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    policy: &HealthPolicy,
+    percent: u8,
+    expiring: &mut HashSet<InstanceId>,
+    at: u64,
+) -> Vec<InstanceId> {
+    let mut registry = registry.write().await;
+    let silent: Vec<(InstanceId, u64)> = registry
+        .list()
+        .into_iter()
+        .filter(|entry| entry.health_status(policy, at) != HealthStatus::Unhealthy)
+        .map(|entry| (entry.id.clone(), entry.silence_percent(policy, at)))
+        .filter(|(_, silence)| *silence >= u64::from(percent))
+        .collect();
+
+    let mut marked = Vec::new();
+    for (id, silence) in &silent {
+        if expiring.contains(id) {
+            continue;
+        }
+        match registry.mark_expiring(id, *silence) {
+            Ok(()) => marked.push(id.clone()),
+            Err(e) => eprintln!("Failed to mark instance {} expiring: {:?}", id, e),
+        }
+    }
+    *expiring = silent.into_iter().map(|(id, _)| id).collect();
+    marked
+}
+
+/// Runs `mark_expiring` periodically for the lifetime of the process
+pub fn spawn_expiry_warnings(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    clock: SharedClock,
+    policy: HealthPolicy,
+    percent: u8,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut expiring = HashSet::new();
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            mark_expiring(&registry, &policy, percent, &mut expiring, clock.now()).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::service_registry::ServiceEntry;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_expiring_recorded_once_per_silence() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry: Arc<RwLock<dyn ServiceRegistry>> = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let entry = ServiceEntry::new(
+            "checkout".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            clock.now(),
+        );
+        let id = entry.id.clone();
+        registry.write().await.register(entry).unwrap();
+        let policy = HealthPolicy::default();
+        let mut expiring = HashSet::new();
+
+        clock.advance(Duration::from_millis(policy.unhealthy_after * 8 / 10 - 1));
+        assert!(
+            mark_expiring(&registry, &policy, 80, &mut expiring, clock.now())
+                .await
+                .is_empty()
+        );
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(
+            mark_expiring(&registry, &policy, 80, &mut expiring, clock.now()).await,
+            std::slice::from_ref(&id)
+        );
+        assert!(
+            mark_expiring(&registry, &policy, 80, &mut expiring, clock.now())
+                .await
+                .is_empty()
+        );
+
+        // A heartbeat ends the silence, so the next one is recorded again
+        registry.write().await.heartbeat_instance(&id).unwrap();
+        mark_expiring(&registry, &policy, 80, &mut expiring, clock.now()).await;
+        clock.advance(Duration::from_millis(policy.unhealthy_after * 8 / 10));
+        assert_eq!(
+            mark_expiring(&registry, &policy, 80, &mut expiring, clock.now()).await,
+            [id]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Lightweight, environment-aware service discovery and endpoint registry.
+//!
+//! The `xolotl` binary is a thin wrapper around this library, which also
+//! exposes the `testing` module behind the `testing` feature.
+
+use api::AppState;
+use api::admin::admin_routes;
+use api::admission::admit_writes;
+use api::agents::agents_routes;
+use api::chaos::inject_faults;
+use api::environments::environments_routes;
+use api::events::events_routes;
+use api::expectations::expectations_routes;
+use api::idempotency::remember_idempotent;
+use api::impersonation::act_as;
+use api::intentions::intentions_routes;
+use api::owners::owners_routes;
+use api::profiles::profiles_routes;
+use api::read_only::refuse_writes;
+use api::reports::reports_routes;
+use api::resolve::resolve_routes;
+use api::routing_config::routing_config_routes;
+use api::search::search_routes;
+use api::selfcheck::selfcheck_routes;
+use api::services::services_routes;
+use api::signing::verify_signatures;
+use api::sync::sync_routes;
+use api::ui::ui_routes;
+use axum::{Router, middleware};
+
+#[cfg(feature = "acme")]
+pub mod acme;
+pub mod agent_liveness;
+pub mod alerting;
+pub mod api;
+pub mod app_health;
+pub mod audit_export;
+pub mod backup;
+pub mod bootstrap;
+pub mod cli;
+pub mod client;
+pub mod consul;
+pub mod dns_fallback;
+pub mod encryption;
+pub mod environment_expiry;
+pub mod gc;
+pub mod health_checker;
+pub mod history;
+pub mod instance_expiry;
+pub mod mdns;
+pub mod model;
+pub mod node_agent;
+pub mod notifier;
+pub mod registry;
+pub mod selfcheck;
+pub mod service;
+pub mod ssdp;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tombstone;
+pub mod udp_heartbeat;
+#[cfg(feature = "wasm")]
+pub mod wasm_policy;
+
+pub fn create_app(state: AppState) -> Router {
+    let chaos = state.chaos.clone();
+    let admission = state.admission.clone();
+    let app = Router::new()
+        .nest("/services", services_routes())
+        .nest("/intentions", intentions_routes())
+        .nest("/environments", environments_routes())
+        .nest("/expectations", expectations_routes())
+        .nest("/owners", owners_routes())
+        .nest("/profiles", profiles_routes())
+        .nest("/reports", reports_routes())
+        .nest("/resolve", resolve_routes())
+        .nest("/routing-config", routing_config_routes())
+        .nest("/search", search_routes())
+        .nest("/selfcheck", selfcheck_routes())
+        .nest("/events", events_routes())
+        .nest("/sync", sync_routes())
+        .nest("/ui", ui_routes())
+        .nest("/admin", admin_routes())
+        .nest("/agents", agents_routes())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            remember_idempotent,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.read_only.clone(),
+            refuse_writes,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            verify_signatures,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), act_as))
+        .with_state(state);
+
+    let app = match admission {
+        Some(admission) => app.layer(middleware::from_fn_with_state(admission, admit_writes)),
+        None => app,
+    };
+    match chaos {
+        Some(chaos) => app.layer(middleware::from_fn_with_state(chaos, inject_faults)),
+        None => app,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::service_registry::HealthPolicy;
+    use registry::in_memory_registry::InMemoryRegistry;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[test]
+    fn test_create_app() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let app = create_app(AppState::new(registry, HealthPolicy::default(), None));
+
+        // Just verify the app can be created without panicking
+        // This tests the initialization and dependency injection
+        assert!(std::any::type_name_of_val(&app).contains("Router"));
+    }
+}
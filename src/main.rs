@@ -1,82 +1,467 @@
-use api::services::services_routes;
-use axum::Router;
 use clap::Parser;
-use registry::in_memory_registry::InMemoryRegistry;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use xolotl::api::AppState;
+use xolotl::cli::{self, Cli, Command, ServerArgs};
+use xolotl::client::XolotlClient;
+use xolotl::model::clock::system_clock;
+use xolotl::model::service_registry::{RecoveryWindow, RegistryReadHandle, ServiceRegistry};
+use xolotl::registry::in_memory_registry::InMemoryRegistry;
+use xolotl::{
+    agent_liveness, alerting, audit_export, backup, bootstrap, create_app, environment_expiry, gc,
+    health_checker, history, instance_expiry, mdns, selfcheck, ssdp, tombstone, udp_heartbeat,
+};
 
-mod api;
-mod model;
-mod registry;
-
-#[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long, default_value = "0.0.0.0")]
-    address: String,
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
 
-    #[arg(short, long, default_value_t = 8000)]
-    port: u16,
+    match cli.command {
+        None => serve(cli.server).await,
+        Some(Command::Server(args)) => serve(*args).await,
+        Some(command) => {
+            if let Err(e) = cli::run_client_command(command).await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 }
 
-#[tokio::main]
-async fn main() {
-    let args = Args::parse();
-    let app = create_app();
+async fn serve(args: ServerArgs) {
+    if let Err(e) = args.validate_thresholds() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    let clock = system_clock();
+    let config = args.config.clone().unwrap_or_default();
+    let backend = InMemoryRegistry::new()
+        .with_clock(clock.clone())
+        .with_environment_tags(config.environment_tags.clone());
+    #[cfg(feature = "wasm")]
+    let registry = with_admission_policy(backend, &args);
+    #[cfg(not(feature = "wasm"))]
+    let registry: Arc<RwLock<dyn ServiceRegistry>> = Arc::new(RwLock::new(backend));
+
+    if let Some(bucket) = &args.backup_s3_bucket {
+        match backup::s3_store(bucket) {
+            Ok(store) => {
+                backup::spawn_backups(registry.clone(), store, args.backup_config(), clock.clone());
+            }
+            Err(e) => {
+                eprintln!("Failed to configure backups to bucket {}: {}", bucket, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(peer_url) = &args.take_over {
+        let peer = XolotlClient::new(peer_url, args.admin_token.clone());
+        match bootstrap::take_over(&registry, &peer).await {
+            Ok(count) => println!("Took over {} instances from {}", count, peer_url),
+            Err(e) => {
+                eprintln!("Failed to take over from {}: {}", peer_url, e);
+                std::process::exit(1);
+            }
+        }
+    }
     let bind_address = format!("{}:{}", args.address, args.port);
+    // Listening while the old process finishes its handoff keeps the port open throughout,
+    // only after it though, so the handoff request cannot reach this process
+    let shared_listener = if args.reuse_port || args.take_over.is_some() {
+        match bind_address
+            .parse()
+            .map_err(|e| format!("{}", e))
+            .and_then(|address| bootstrap::bind_reusable(address).map_err(|e| e.to_string()))
+        {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                eprintln!("Failed to bind to address {}: {}", bind_address, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(peer_url) = &args.bootstrap_from {
+        let peer = XolotlClient::new(peer_url, args.admin_token.clone());
+        match bootstrap::bootstrap_from(&registry, &peer).await {
+            Ok(count) => println!("Copied {} instances from {}", count, peer_url),
+            Err(e) => {
+                eprintln!("Failed to bootstrap from {}: {}", peer_url, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let listener = match tokio::net::TcpListener::bind(&bind_address).await {
-        Ok(listener) => listener,
-        Err(e) => {
-            eprintln!("Failed to bind to address {}: {}", bind_address, e);
+    // Unless bootstrapped from a peer the in-memory registry starts empty, so a restart opens the window
+    let recovery = match args.recovery_grace {
+        Some(grace) if registry.read().await.list().is_empty() => {
+            RecoveryWindow::open_for(grace.saturating_mul(1000), clock.now())
+        }
+        _ => RecoveryWindow::default(),
+    };
+
+    for service in &config.static_services {
+        let entry = service.to_entry(clock.now());
+        if let Err(e) = registry.write().await.register(entry) {
+            eprintln!(
+                "Failed to register static service {} in {}: {:?}",
+                service.service_name, service.environment, e
+            );
             std::process::exit(1);
         }
+    }
+
+    if let Some(config) = &args.alert_rules {
+        alerting::spawn_alerting(
+            registry.clone(),
+            clock.clone(),
+            args.health_policy(),
+            config.clone(),
+        );
+    }
+
+    if args.mdns {
+        let handle = RegistryReadHandle::new(registry.clone());
+        if let Err(e) = mdns::spawn_mdns(
+            handle,
+            clock.clone(),
+            args.health_policy(),
+            config.tag_masking.clone(),
+        ) {
+            eprintln!(
+                "Failed to start mDNS responder on port {}: {}",
+                mdns::MDNS_PORT,
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+
+    if let (Some(port), Some(key)) = (args.udp_heartbeat_port, &args.udp_heartbeat_key) {
+        let address = match format!("{}:{}", args.address, port).parse::<SocketAddr>() {
+            Ok(address) => address,
+            Err(e) => {
+                eprintln!("Invalid UDP heartbeat address: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = udp_heartbeat::spawn_udp_heartbeats(
+            registry.clone(),
+            clock.clone(),
+            address,
+            key.clone(),
+        )
+        .await
+        {
+            eprintln!("Failed to listen for UDP heartbeats on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    }
+
+    if args.ssdp
+        && let Err(e) = ssdp::spawn_ssdp(args.address.clone(), args.port)
+    {
+        eprintln!(
+            "Failed to start SSDP announcements on port {}: {}",
+            ssdp::SSDP_PORT,
+            e
+        );
+        std::process::exit(1);
+    }
+
+    let state = AppState::new(
+        registry.clone(),
+        args.health_policy(),
+        args.admin_token.clone(),
+    )
+    .with_owner_policy(args.owner_policy())
+    .with_tag_schema(args.tag_schema.clone().unwrap_or_default())
+    .with_recovery(recovery)
+    .with_idempotency_ttl(args.idempotency_ttl.saturating_mul(1000))
+    .with_cache_ttl(args.cache_ttl)
+    .with_resolve_cache(args.resolve_cache_millis)
+    .with_read_only(args.read_only)
+    .with_dns_fallback(args.dns_fallback)
+    .with_address_rewrites(config.address_rewrites.clone())
+    .with_callers(&config.callers)
+    .with_tag_masking(config.tag_masking.clone())
+    .with_clock(clock.clone());
+    let state = match args.chaos {
+        Some(seed) => {
+            println!("Chaos mode enabled with seed {}", seed);
+            state.with_chaos(seed)
+        }
+        None => state,
     };
-    println!("Starting Xolotl on {}", bind_address);
-    axum::serve(listener, app.into_make_service())
+    let state = match args.confirm_deletions {
+        Some(timeout) => state.with_deletion_confirmation(timeout),
+        None => state,
+    };
+    let state = match args.deregistration_guardrail {
+        Some(limit) => state.with_guardrail(limit, !args.guardrail_flag_only),
+        None => state,
+    };
+    let state = match args.max_concurrent_writes {
+        Some(max_concurrent) => state.with_admission(max_concurrent, args.write_queue_depth),
+        None => state,
+    };
+    let state = match args.heartbeat_capacity {
+        Some(capacity) => state.with_heartbeat_capacity(capacity),
+        None => state,
+    };
+    let state = match &args.spiffe_header {
+        Some(header) => state.with_spiffe_header(header),
+        None => state,
+    };
+    let state = match args.signing_secrets.clone() {
+        Some(secrets) => state.with_signing_secrets(secrets),
+        None => state,
+    };
+    gc::spawn_gc(
+        registry.clone(),
+        state.profiles.clone(),
+        clock.clone(),
+        args.health_policy(),
+    );
+    environment_expiry::spawn_environment_expiry(
+        registry.clone(),
+        state.environments.clone(),
+        clock.clone(),
+        args.environment_expiry_warning,
+        args.alert_rules
+            .as_ref()
+            .map(|config| config.notifiers.clone())
+            .unwrap_or_default(),
+    );
+    alerting::spawn_min_instances(
+        registry.clone(),
+        state.profiles.clone(),
+        clock.clone(),
+        args.health_policy(),
+        args.alert_rules
+            .as_ref()
+            .map(|config| config.notifiers.clone())
+            .unwrap_or_default(),
+    );
+    alerting::spawn_expectations(
+        registry.clone(),
+        state.expectations.clone(),
+        clock.clone(),
+        args.health_policy(),
+        args.alert_rules
+            .as_ref()
+            .map(|config| config.notifiers.clone())
+            .unwrap_or_default(),
+    );
+    agent_liveness::spawn_agent_liveness(
+        registry.clone(),
+        state.agents.clone(),
+        clock.clone(),
+        args.agent_grace_period.saturating_mul(1000),
+    );
+    instance_expiry::spawn_expiry_warnings(
+        registry.clone(),
+        clock.clone(),
+        args.health_policy(),
+        args.alert_rules
+            .as_ref()
+            .and_then(|config| config.heartbeat_warning_percent)
+            .unwrap_or(instance_expiry::DEFAULT_WARNING_PERCENT),
+    );
+    health_checker::spawn_health_checks(
+        registry.clone(),
+        Duration::from_secs(args.health_check_interval.max(1)),
+    );
+    if let Some(older_than) = args.tombstone_after {
+        tombstone::spawn_tombstoning(
+            registry.clone(),
+            state.profiles.clone(),
+            clock.clone(),
+            older_than,
+        );
+    }
+    let sinks = args.audit_sinks();
+    if !sinks.is_empty()
+        && let Err(e) = audit_export::spawn_audit_export(registry.clone(), sinks).await
+    {
+        eprintln!("Failed to start audit export: {}", e);
+        std::process::exit(1);
+    }
+    history::spawn_history_sampling(registry, state.history.clone(), clock.clone());
+
+    if let Some(interval) = args.selfcheck_interval {
+        selfcheck::spawn_selfcheck(
+            args.self_url(),
+            args.admin_token.clone(),
+            Duration::from_secs(interval),
+            state.selfcheck.clone(),
+            clock,
+        );
+    }
+
+    let shutdown = state.shutdown.clone();
+    let app = create_app(state);
+
+    let listener = match shared_listener {
+        Some(listener) => listener,
+        None => match tokio::net::TcpListener::bind(&bind_address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Failed to bind to address {}: {}", bind_address, e);
+                std::process::exit(1);
+            }
+        },
+    };
+    #[cfg(feature = "acme")]
+    if let Some(settings) = args.acme_settings() {
+        use axum::serve::ListenerExt;
+        use xolotl::acme::AcmeListener;
+
+        let domains = settings.domains.join(", ");
+        let listener = match AcmeListener::new(listener, settings) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Failed to serve TLS on {}: {}", bind_address, e);
+                std::process::exit(1);
+            }
+        };
+        println!(
+            "Starting Xolotl on {} with TLS for {}",
+            bind_address, domains
+        );
+        // Tapping the listener lets connect info read the peer address of TLS connections
+        axum::serve(
+            listener.tap_io(|_| {}),
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { shutdown.triggered().await })
         .await
         .unwrap();
+        return;
+    }
+    println!("Starting Xolotl on {}", bind_address);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { shutdown.triggered().await })
+    .await
+    .unwrap();
 }
 
-pub fn create_app() -> Router {
-    let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
-    Router::new()
-        .nest("/services", services_routes())
-        .with_state(registry)
+/// Runs every registration through the configured admission policy, if any
+#[cfg(feature = "wasm")]
+fn with_admission_policy(
+    backend: InMemoryRegistry,
+    args: &ServerArgs,
+) -> Arc<RwLock<dyn ServiceRegistry>> {
+    use xolotl::registry::hooked_registry::HookedRegistry;
+    use xolotl::wasm_policy::{WasmPolicy, spawn_policy_reload};
+
+    let Some(path) = &args.admission_policy else {
+        return Arc::new(RwLock::new(backend));
+    };
+    let policy = match WasmPolicy::load(path) {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    spawn_policy_reload(policy.clone());
+    Arc::new(RwLock::new(HookedRegistry::new(backend).with_hook(policy)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_create_app() {
-        let app = create_app();
-
-        // Just verify the app can be created without panicking
-        // This tests the initialization and dependency injection
-        assert!(std::any::type_name_of_val(&app).contains("Router"));
-    }
-
     #[test]
     fn test_args_defaults() {
-        let args = Args {
-            address: "0.0.0.0".to_string(),
-            port: 8000,
-        };
+        let args = Cli::parse_from(["xolotl"]).server;
 
         assert_eq!(args.address, "0.0.0.0");
         assert_eq!(args.port, 8000);
+        assert!(args.admin_token.is_none());
+        assert_eq!(args.health_policy().stale_after, 30_000);
+        assert_eq!(args.health_policy().unhealthy_after, 90_000);
+        assert_eq!(args.self_url(), "http://127.0.0.1:8000");
     }
 
     #[test]
     fn test_args_custom_values() {
-        let args = Args {
-            address: "127.0.0.1".to_string(),
-            port: 3000,
-        };
+        let args = Cli::parse_from([
+            "xolotl",
+            "--address",
+            "127.0.0.1",
+            "--port",
+            "3000",
+            "--admin-token",
+            "secret",
+            "--stale-after",
+            "10",
+            "--require-owner",
+            "--owner-teams",
+            "team-a,team-b",
+        ])
+        .server;
 
         assert_eq!(args.address, "127.0.0.1");
         assert_eq!(args.port, 3000);
+        assert_eq!(args.self_url(), "http://127.0.0.1:3000");
+        assert_eq!(args.admin_token.as_deref(), Some("secret"));
+        assert_eq!(args.health_policy().stale_after, 10_000);
+        assert!(args.owner_policy().required);
+        assert_eq!(args.owner_teams, vec!["team-a", "team-b"]);
+    }
+
+    #[test]
+    fn test_args_reject_invalid_thresholds() {
+        assert!(Cli::try_parse_from(["xolotl", "--stale-after", &u64::MAX.to_string()]).is_err());
+
+        let inverted =
+            Cli::parse_from(["xolotl", "--stale-after", "60", "--unhealthy-after", "30"]).server;
+        assert!(inverted.validate_thresholds().is_err());
+        assert!(
+            Cli::parse_from(["xolotl"])
+                .server
+                .validate_thresholds()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_args_reject_zero_backup_interval() {
+        assert!(Cli::try_parse_from(["xolotl", "--backup-interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_args_reject_zero_selfcheck_interval() {
+        assert!(Cli::try_parse_from(["xolotl", "--selfcheck-interval", "0"]).is_err());
+    }
+
+    #[cfg(feature = "acme")]
+    #[test]
+    fn test_args_acme() {
+        assert!(Cli::parse_from(["xolotl"]).server.acme_settings().is_none());
+
+        let args = Cli::parse_from([
+            "xolotl",
+            "--acme-domain",
+            "registry.example.com,xolotl.example.com",
+            "--data-dir",
+            "/var/lib/xolotl",
+        ])
+        .server;
+        let settings = args.acme_settings().unwrap();
+        assert_eq!(settings.domains.len(), 2);
+        assert_eq!(
+            settings.cache_dir,
+            std::path::PathBuf::from("/var/lib/xolotl/acme")
+        );
     }
 }
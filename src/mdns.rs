@@ -0,0 +1,456 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::{HealthPolicy, RegistryReadHandle, ServiceEntry};
+use crate::model::tag_masking::TagMasking;
+
+pub const MDNS_PORT: u16 = 5353;
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const SERVICES_META: &str = "_services._dns-sd._udp.local";
+const RECORD_TTL: u32 = 120;
+const MAX_LABEL_LENGTH: usize = 63;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Set on the class of records owned by a single responder
+const CACHE_FLUSH: u16 = 0x8000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    Ptr(String),
+    Srv { port: u16, target: String },
+    Txt(Vec<String>),
+    A(Ipv4Addr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub data: RecordData,
+}
+
+impl Record {
+    fn record_type(&self) -> u16 {
+        match self.data {
+            RecordData::Ptr(_) => TYPE_PTR,
+            RecordData::Srv { .. } => TYPE_SRV,
+            RecordData::Txt(_) => TYPE_TXT,
+            RecordData::A(_) => TYPE_A,
+        }
+    }
+
+    /// Whether the record answers a question for `name` and `qtype`
+    fn answers(&self, name: &str, qtype: u16) -> bool {
+        self.name.eq_ignore_ascii_case(name) && (qtype == TYPE_ANY || qtype == self.record_type())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+}
+
+/// Turns a service name or environment into a single DNS label
+fn label(value: &str) -> String {
+    let mut label = value.replace('.', "-");
+    if label.len() > MAX_LABEL_LENGTH {
+        let mut end = MAX_LABEL_LENGTH;
+        while !label.is_char_boundary(end) {
+            end -= 1;
+        }
+        label.truncate(end);
+    }
+    label
+}
+
+/// Builds the DNS-SD records advertising every available instance that has a
+/// port in its address, as `_<name>._tcp.local` services
+pub fn records_for(entries: &[ServiceEntry], policy: &HealthPolicy, at: u64) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut service_types: Vec<String> = Vec::new();
+
+    for entry in entries {
+        if !entry.state.is_routable() || !entry.is_available(policy, at) {
+            continue;
+        }
+        let (Some(host), Some(port)) = (entry.address.host(), entry.address.extract_port()) else {
+            continue;
+        };
+
+        let service_type = format!("_{}._tcp.local", label(entry.service_name.as_str()));
+        let instance_label = label(&format!(
+            "{}-{}-{}",
+            entry.service_name,
+            entry.environment,
+            &entry.id.as_str()[..8.min(entry.id.as_str().len())]
+        ));
+        let instance = format!("{}.{}", instance_label, service_type);
+
+        let target = match host.parse::<Ipv4Addr>() {
+            Ok(ip) => {
+                let target = format!("{}.local", instance_label);
+                records.push(Record {
+                    name: target.clone(),
+                    data: RecordData::A(ip),
+                });
+                target
+            }
+            Err(_) => host.to_string(),
+        };
+
+        let mut txt = vec![format!("environment={}", entry.environment)];
+        let mut tags: Vec<_> = entry.tags.iter().collect();
+        tags.sort();
+        txt.extend(
+            tags.into_iter()
+                .map(|(key, value)| format!("{}={}", key, value)),
+        );
+
+        records.push(Record {
+            name: service_type.clone(),
+            data: RecordData::Ptr(instance.clone()),
+        });
+        records.push(Record {
+            name: instance.clone(),
+            data: RecordData::Srv { port, target },
+        });
+        records.push(Record {
+            name: instance,
+            data: RecordData::Txt(txt),
+        });
+
+        if !service_types.contains(&service_type) {
+            service_types.push(service_type);
+        }
+    }
+
+    records.extend(service_types.into_iter().map(|service_type| Record {
+        name: SERVICES_META.to_string(),
+        data: RecordData::Ptr(service_type),
+    }));
+    records
+}
+
+/// Picks the records answering `questions`, plus the records the answers
+/// point to as additionals so a browser can resolve an instance in one round trip
+pub fn answer<'a>(
+    records: &'a [Record],
+    questions: &[Question],
+) -> (Vec<&'a Record>, Vec<&'a Record>) {
+    let answers: Vec<&Record> = records
+        .iter()
+        .filter(|record| questions.iter().any(|q| record.answers(&q.name, q.qtype)))
+        .collect();
+
+    let mut additionals: Vec<&Record> = Vec::new();
+    let mut pending: Vec<&str> = answers.iter().filter_map(|r| points_to(r)).collect();
+    while let Some(name) = pending.pop() {
+        for record in records.iter().filter(|r| r.name.eq_ignore_ascii_case(name)) {
+            if answers.contains(&record) || additionals.contains(&record) {
+                continue;
+            }
+            additionals.push(record);
+            pending.extend(points_to(record));
+        }
+    }
+
+    (answers, additionals)
+}
+
+fn points_to(record: &Record) -> Option<&str> {
+    match &record.data {
+        RecordData::Ptr(target) | RecordData::Srv { target, .. } => Some(target),
+        _ => None,
+    }
+}
+
+/// Reads a possibly compressed name starting at `offset`, returning it and
+/// the offset right after it
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let length = *packet.get(offset)? as usize;
+        if length & 0xC0 == 0xC0 {
+            let pointer = ((length & 0x3F) << 8) | *packet.get(offset + 1)? as usize;
+            end.get_or_insert(offset + 2);
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            offset = pointer;
+        } else if length == 0 {
+            let name = labels.join(".");
+            return Some((name, end.unwrap_or(offset + 1)));
+        } else {
+            let bytes = packet.get(offset + 1..offset + 1 + length)?;
+            labels.push(String::from_utf8_lossy(bytes).into_owned());
+            offset += 1 + length;
+        }
+    }
+}
+
+/// Parses the questions of an mDNS query, ignoring responses from other hosts
+pub fn parse_query(packet: &[u8]) -> Option<Vec<Question>> {
+    let header = packet.get(..12)?;
+    if header[2] & 0x80 != 0 {
+        return None;
+    }
+    let count = u16::from_be_bytes([header[4], header[5]]);
+
+    let mut offset = 12;
+    let mut questions = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let (name, next) = read_name(packet, offset)?;
+        let qtype = u16::from_be_bytes([*packet.get(next)?, *packet.get(next + 1)?]);
+        offset = next + 4;
+        questions.push(Question { name, qtype });
+    }
+    Some(questions)
+}
+
+pub(crate) fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for part in name.split('.').filter(|part| !part.is_empty()) {
+        let bytes = &part.as_bytes()[..part.len().min(MAX_LABEL_LENGTH)];
+        packet.push(bytes.len() as u8);
+        packet.extend_from_slice(bytes);
+    }
+    packet.push(0);
+}
+
+fn write_record(packet: &mut Vec<u8>, record: &Record) {
+    write_name(packet, &record.name);
+    let class = match record.data {
+        RecordData::Ptr(_) => CLASS_IN,
+        _ => CLASS_IN | CACHE_FLUSH,
+    };
+    packet.extend_from_slice(&record.record_type().to_be_bytes());
+    packet.extend_from_slice(&class.to_be_bytes());
+    packet.extend_from_slice(&RECORD_TTL.to_be_bytes());
+
+    let mut data = Vec::new();
+    match &record.data {
+        RecordData::Ptr(target) => write_name(&mut data, target),
+        RecordData::Srv { port, target } => {
+            data.extend_from_slice(&[0, 0, 0, 0]);
+            data.extend_from_slice(&port.to_be_bytes());
+            write_name(&mut data, target);
+        }
+        RecordData::Txt(strings) => {
+            for string in strings {
+                let bytes = &string.as_bytes()[..string.len().min(255)];
+                data.push(bytes.len() as u8);
+                data.extend_from_slice(bytes);
+            }
+        }
+        RecordData::A(ip) => data.extend_from_slice(&ip.octets()),
+    }
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&data);
+}
+
+/// Encodes an authoritative response without name compression
+pub fn encode_response(id: u16, answers: &[&Record], additionals: &[&Record]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(512);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&0x8400u16.to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(&(additionals.len() as u16).to_be_bytes());
+
+    for record in answers.iter().chain(additionals) {
+        write_record(&mut packet, record);
+    }
+    packet
+}
+
+/// Joins the mDNS multicast group, sharing the port with other responders on the host
+fn bind_multicast() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Answers mDNS queries for registered services for the lifetime of the process,
+/// with masked tags always redacted since queriers cannot identify themselves
+pub fn spawn_mdns(
+    registry: RegistryReadHandle,
+    clock: SharedClock,
+    policy: HealthPolicy,
+    masking: TagMasking,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    let socket = bind_multicast()?;
+
+    Ok(tokio::spawn(async move {
+        let group = SocketAddr::V4(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT));
+        let mut buffer = [0u8; 9000];
+        loop {
+            let (length, source) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("Failed to receive mDNS query: {}", e);
+                    continue;
+                }
+            };
+            let packet = &buffer[..length];
+            let Some(questions) = parse_query(packet) else {
+                continue;
+            };
+
+            let mut entries = registry.read().await.list();
+            for entry in &mut entries {
+                masking.mask(&mut entry.tags);
+            }
+            let records = records_for(&entries, &policy, clock.now());
+            let (answers, additionals) = answer(&records, &questions);
+            if answers.is_empty() {
+                continue;
+            }
+
+            // Queries from ports other than 5353 are one-shot resolvers that
+            // expect a unicast reply echoing their id
+            let (id, destination) = if source.port() == MDNS_PORT {
+                (0, group)
+            } else {
+                (u16::from_be_bytes([packet[0], packet[1]]), source)
+            };
+            let response = encode_response(id, &answers, &additionals);
+            if let Err(e) = socket.send_to(&response, destination).await {
+                eprintln!("Failed to send mDNS response: {}", e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::instance_state::InstanceState;
+    use std::collections::HashMap;
+
+    fn entry(name: &str, address: &str) -> ServiceEntry {
+        ServiceEntry::new(
+            name.parse().unwrap(),
+            "prod".parse().unwrap(),
+            address.to_string(),
+            HashMap::from([("version".to_string(), "1.2".to_string())]),
+            1_000,
+        )
+    }
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = vec![0, 7, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        write_name(&mut packet, name);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_records_for_available_instances() {
+        let mut down = entry("search", "http://10.0.0.2:9000");
+        down.state = InstanceState::Down;
+        let entries = vec![
+            entry("payments", "http://10.0.0.1:8080"),
+            entry("payments", "payments.internal:8080"),
+            entry("no-port", "http://10.0.0.3"),
+            down,
+        ];
+
+        let records = records_for(&entries, &HealthPolicy::default(), 2_000);
+
+        let ptrs: Vec<_> = records
+            .iter()
+            .filter(|r| r.name == "_payments._tcp.local")
+            .collect();
+        assert_eq!(ptrs.len(), 2);
+        assert!(records.contains(&Record {
+            name: SERVICES_META.to_string(),
+            data: RecordData::Ptr("_payments._tcp.local".to_string()),
+        }));
+        assert!(
+            records
+                .iter()
+                .any(|r| r.data == RecordData::A("10.0.0.1".parse().unwrap()))
+        );
+        assert!(records.iter().any(|r| matches!(
+            &r.data,
+            RecordData::Srv { port: 8080, target } if target == "payments.internal"
+        )));
+        assert!(records.iter().any(|r| r.data
+            == RecordData::Txt(vec![
+                "environment=prod".to_string(),
+                "version=1.2".to_string()
+            ])));
+        assert!(!records.iter().any(|r| r.name.contains("search")));
+        assert!(!records.iter().any(|r| r.name.contains("no-port")));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let questions = parse_query(&query("_payments._tcp.local", TYPE_PTR)).unwrap();
+        assert_eq!(
+            questions,
+            vec![Question {
+                name: "_payments._tcp.local".to_string(),
+                qtype: TYPE_PTR
+            }]
+        );
+
+        let mut compressed = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+        write_name(&mut compressed, "_payments._tcp.local");
+        compressed.extend_from_slice(&[0, 12, 0, 1]);
+        compressed.extend_from_slice(&[0xC0, 12, 0, 255, 0, 1]);
+        let questions = parse_query(&compressed).unwrap();
+        assert_eq!(questions[1].name, "_payments._tcp.local");
+        assert_eq!(questions[1].qtype, TYPE_ANY);
+
+        let mut response = query("_payments._tcp.local", TYPE_PTR);
+        response[2] = 0x84;
+        assert!(parse_query(&response).is_none());
+        assert!(parse_query(&[0, 0]).is_none());
+    }
+
+    #[test]
+    fn test_answer_includes_additionals() {
+        let records = records_for(
+            &[entry("payments", "http://10.0.0.1:8080")],
+            &HealthPolicy::default(),
+            2_000,
+        );
+        let questions = parse_query(&query("_PAYMENTS._tcp.local", TYPE_PTR)).unwrap();
+
+        let (answers, additionals) = answer(&records, &questions);
+
+        assert_eq!(answers.len(), 1);
+        let types: Vec<_> = additionals.iter().map(|r| r.record_type()).collect();
+        assert_eq!(types.len(), 3);
+        assert!(types.contains(&TYPE_SRV));
+        assert!(types.contains(&TYPE_TXT));
+        assert!(types.contains(&TYPE_A));
+
+        let packet = encode_response(0, &answers, &additionals);
+        assert_eq!(&packet[2..4], &[0x84, 0x00]);
+        assert_eq!(&packet[6..8], &[0, 1]);
+        assert_eq!(&packet[10..12], &[0, 3]);
+        let (name, _) = read_name(&packet, 12).unwrap();
+        assert_eq!(name, "_payments._tcp.local");
+
+        let unknown = parse_query(&query("_search._tcp.local", TYPE_PTR)).unwrap();
+        assert!(answer(&records, &unknown).0.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::model::identifiers::Environment;
+
+/// A block of IP addresses such as `10.8.0.0/16`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid network '{}', expected e.g. 10.8.0.0/16", value);
+        let (network, prefix) = value.split_once('/').ok_or_else(invalid)?;
+        let network: IpAddr = network.parse().map_err(|_| invalid())?;
+        let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Cidr { network, prefix })
+    }
+}
+
+impl TryFrom<String> for Cidr {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Translates registered addresses starting with `from` to start with `to`
+/// instead, optionally only in one environment or for callers in one network
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AddressRewrite {
+    #[serde(default)]
+    pub environment: Option<Environment>,
+    #[serde(default)]
+    pub caller_network: Option<Cidr>,
+    pub from: String,
+    pub to: String,
+}
+
+impl AddressRewrite {
+    fn applies(&self, environment: &Environment, caller: Option<IpAddr>) -> bool {
+        self.environment
+            .as_ref()
+            .is_none_or(|only| only == environment)
+            && self
+                .caller_network
+                .is_none_or(|network| caller.is_some_and(|ip| network.contains(ip)))
+    }
+
+    /// Rewrites the address, matching `from` against it with or without its protocol
+    fn rewrite(&self, address: &str) -> Option<String> {
+        if let Some(rest) = address.strip_prefix(self.from.as_str()) {
+            return Some(format!("{}{}", self.to, rest));
+        }
+        let (scheme, rest) = address.split_once("://")?;
+        let rest = rest.strip_prefix(self.from.as_str())?;
+        Some(format!("{}://{}{}", scheme, self.to, rest))
+    }
+}
+
+/// Ordered rewrite rules, the first one that matches an address wins
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct AddressRewrites(pub Vec<AddressRewrite>);
+
+impl AddressRewrites {
+    /// Returns the address as a caller at `caller` should see it
+    pub fn apply(
+        &self,
+        address: &str,
+        environment: &Environment,
+        caller: Option<IpAddr>,
+    ) -> String {
+        self.0
+            .iter()
+            .filter(|rule| rule.applies(environment, caller))
+            .find_map(|rule| rule.rewrite(address))
+            .unwrap_or_else(|| address.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cidr() {
+        let network: Cidr = "10.8.0.0/16".parse().unwrap();
+        assert!(network.contains("10.8.3.4".parse().unwrap()));
+        assert!(!network.contains("10.9.0.1".parse().unwrap()));
+        assert!(!network.contains("::1".parse().unwrap()));
+
+        let everything: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(everything.contains("192.0.2.1".parse().unwrap()));
+
+        assert!("10.8.0.0".parse::<Cidr>().is_err());
+        assert!("10.8.0.0/33".parse::<Cidr>().is_err());
+        assert_eq!(network.to_string(), "10.8.0.0/16");
+    }
+
+    #[test]
+    fn test_apply() {
+        let rewrites: AddressRewrites = serde_yaml::from_str(
+            r#"
+- environment: prod
+  caller_network: 192.168.0.0/16
+  from: "10.0.1.5:8080"
+  to: "gw.example.com:31080"
+- from: "10.0."
+  to: "172.16."
+"#,
+        )
+        .unwrap();
+        let prod: Environment = "prod".parse().unwrap();
+        let office = Some("192.168.1.10".parse().unwrap());
+
+        assert_eq!(
+            rewrites.apply("http://10.0.1.5:8080/api", &prod, office),
+            "http://gw.example.com:31080/api"
+        );
+        assert_eq!(
+            rewrites.apply("http://10.0.1.5:8080", &prod, None),
+            "http://172.16.1.5:8080"
+        );
+        assert_eq!(
+            rewrites.apply("payments.internal:8080", &prod, office),
+            "payments.internal:8080"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+/// How urgently a write should reach the registry when it is saturated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePriority {
+    /// Deregistrations, state changes and heartbeats, which keep resolution
+    /// accurate and are queued ahead of normal writes
+    High,
+    /// Registrations and every other write
+    Normal,
+}
+
+/// Counters describing the admission queue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AdmissionStats {
+    pub max_concurrent: usize,
+    pub max_queued: usize,
+    pub in_flight: usize,
+    pub queued_high: usize,
+    pub queued_normal: usize,
+    pub admitted: u64,
+    pub rejected: u64,
+}
+
+struct Queue {
+    in_flight: usize,
+    high: VecDeque<oneshot::Sender<()>>,
+    normal: VecDeque<oneshot::Sender<()>>,
+    admitted: u64,
+    rejected: u64,
+}
+
+impl Queue {
+    fn prune(&mut self) {
+        self.high.retain(|waiter| !waiter.is_closed());
+        self.normal.retain(|waiter| !waiter.is_closed());
+    }
+}
+
+/// Bounds the writes running at once, queueing the rest with high priority
+/// writes ahead of normal ones
+pub struct AdmissionController {
+    max_concurrent: usize,
+    max_queued: usize,
+    queue: Mutex<Queue>,
+}
+
+/// Slot held by an admitted write, handed to the next queued write when dropped
+pub struct WritePermit {
+    controller: Arc<AdmissionController>,
+}
+
+/// Queued write, which passes on a slot handed to it after its request was cancelled
+struct Pending {
+    receiver: Option<oneshot::Receiver<()>>,
+    controller: Arc<AdmissionController>,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        if let Some(mut receiver) = self.receiver.take() {
+            receiver.close();
+            if receiver.try_recv().is_ok() {
+                self.controller.release();
+            }
+        }
+    }
+}
+
+impl AdmissionController {
+    /// Runs up to `max_concurrent` writes at once and queues up to `max_queued`
+    /// writes of each priority
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        AdmissionController {
+            max_concurrent: max_concurrent.max(1),
+            max_queued,
+            queue: Mutex::new(Queue {
+                in_flight: 0,
+                high: VecDeque::new(),
+                normal: VecDeque::new(),
+                admitted: 0,
+                rejected: 0,
+            }),
+        }
+    }
+
+    /// Waits for a slot, or fails right away when the queue of its priority is full
+    pub async fn admit(
+        self: &Arc<Self>,
+        priority: WritePriority,
+    ) -> Result<WritePermit, AdmissionStats> {
+        let receiver = {
+            let mut queue = self.queue.lock().unwrap();
+            queue.prune();
+            let ahead = match priority {
+                WritePriority::High => queue.high.len(),
+                WritePriority::Normal => queue.high.len() + queue.normal.len(),
+            };
+            if queue.in_flight < self.max_concurrent && ahead == 0 {
+                queue.in_flight += 1;
+                queue.admitted += 1;
+                return Ok(self.permit());
+            }
+
+            let waiting = match priority {
+                WritePriority::High => &mut queue.high,
+                WritePriority::Normal => &mut queue.normal,
+            };
+            if waiting.len() >= self.max_queued {
+                queue.rejected += 1;
+                drop(queue);
+                return Err(self.stats());
+            }
+            let (sender, receiver) = oneshot::channel();
+            waiting.push_back(sender);
+            receiver
+        };
+
+        let mut pending = Pending {
+            receiver: Some(receiver),
+            controller: self.clone(),
+        };
+        // The releasing permit has already counted this write as in flight
+        if let Some(receiver) = pending.receiver.as_mut() {
+            receiver
+                .await
+                .expect("Queued writes are only dropped once admitted");
+        }
+        pending.receiver = None;
+        Ok(self.permit())
+    }
+
+    pub fn stats(&self) -> AdmissionStats {
+        let mut queue = self.queue.lock().unwrap();
+        queue.prune();
+        AdmissionStats {
+            max_concurrent: self.max_concurrent,
+            max_queued: self.max_queued,
+            in_flight: queue.in_flight,
+            queued_high: queue.high.len(),
+            queued_normal: queue.normal.len(),
+            admitted: queue.admitted,
+            rejected: queue.rejected,
+        }
+    }
+
+    fn permit(self: &Arc<Self>) -> WritePermit {
+        WritePermit {
+            controller: self.clone(),
+        }
+    }
+
+    fn release(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        while let Some(waiter) = queue.high.pop_front().or_else(|| queue.normal.pop_front()) {
+            // Waiters whose request was cancelled are skipped
+            if waiter.send(()).is_ok() {
+                queue.admitted += 1;
+                return;
+            }
+        }
+        queue.in_flight -= 1;
+    }
+}
+
+impl Drop for WritePermit {
+    fn drop(&mut self) {
+        self.controller.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_high_priority_writes_go_first() {
+        let controller = Arc::new(AdmissionController::new(1, 1));
+        let running = controller.admit(WritePriority::Normal).await.unwrap();
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, priority) in [
+            ("register", WritePriority::Normal),
+            ("deregister", WritePriority::High),
+        ] {
+            let controller = controller.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = controller.admit(priority).await.unwrap();
+                order.lock().unwrap().push(name);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let stats = controller.stats();
+        assert_eq!((stats.queued_high, stats.queued_normal), (1, 1));
+        assert!(controller.admit(WritePriority::Normal).await.is_err());
+        assert!(controller.admit(WritePriority::High).await.is_err());
+
+        drop(running);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["deregister", "register"]);
+
+        let stats = controller.stats();
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!((stats.admitted, stats.rejected), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_writes_release_their_slot() {
+        let controller = Arc::new(AdmissionController::new(1, 4));
+        let running = controller.admit(WritePriority::High).await.unwrap();
+
+        let queued = tokio::spawn({
+            let controller = controller.clone();
+            async move { controller.admit(WritePriority::High).await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        queued.abort();
+        let _ = queued.await;
+
+        drop(running);
+        assert_eq!(controller.stats().in_flight, 0);
+        assert!(controller.admit(WritePriority::Normal).await.is_ok());
+    }
+}
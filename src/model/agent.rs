@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::identifiers::InstanceId;
+
+/// Instruction pushed to a connected agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "directive", rename_all = "snake_case")]
+pub enum Directive {
+    /// Stop taking new work on the instance and shut it down
+    Drain { instance_id: InstanceId },
+    /// The registry does not know the instance, register it again
+    Reregister { instance_id: InstanceId },
+    /// Settings the agent should apply, opaque to the registry
+    Config { settings: Value },
+}
+
+/// Message an agent sends over its channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentMessage {
+    /// Refreshes the heartbeat of every listed instance
+    Heartbeat { instance_ids: Vec<InstanceId> },
+}
+
+/// Message the server sends over an agent channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Number of instances whose heartbeat was recorded
+    Ack {
+        accepted: usize,
+    },
+    Directive(Directive),
+    /// The agent's message could not be used
+    Error {
+        message: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wire_format() {
+        let message: AgentMessage =
+            serde_json::from_value(json!({ "type": "heartbeat", "instance_ids": ["a1"] })).unwrap();
+        assert_eq!(
+            message,
+            AgentMessage::Heartbeat {
+                instance_ids: vec!["a1".parse().unwrap()]
+            }
+        );
+
+        let directive = ServerMessage::Directive(Directive::Drain {
+            instance_id: "a1".parse().unwrap(),
+        });
+        assert_eq!(
+            serde_json::to_value(directive).unwrap(),
+            json!({ "type": "directive", "directive": "drain", "instance_id": "a1" })
+        );
+    }
+}
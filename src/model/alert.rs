@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{HealthPolicy, HealthStatus, ServiceEntry};
+use crate::model::stale_report::parse_age;
+
+/// What a rule measures about a service in an environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Fewer than this many instances report as healthy
+    HealthyBelow(usize),
+    /// Fewer than this many instances are registered at all
+    InstancesBelow(usize),
+}
+
+impl AlertCondition {
+    pub fn threshold(self) -> usize {
+        match self {
+            AlertCondition::HealthyBelow(threshold) | AlertCondition::InstancesBelow(threshold) => {
+                threshold
+            }
+        }
+    }
+}
+
+/// A condition on a service that fires once it has held for `for` millis
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+    #[serde(rename = "for", default, deserialize_with = "deserialize_age")]
+    pub for_millis: u64,
+}
+
+fn deserialize_age<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_age(&value).map_err(serde::de::Error::custom)
+}
+
+impl AlertRule {
+    /// Returns the measured value when the condition is breached at time `at`
+    pub fn breach(
+        &self,
+        entries: &[ServiceEntry],
+        policy: &HealthPolicy,
+        at: u64,
+    ) -> Option<usize> {
+        let instances = entries
+            .iter()
+            .filter(|entry| {
+                entry.service_name == self.service_name && entry.environment == self.environment
+            })
+            .filter(|entry| match self.condition {
+                AlertCondition::HealthyBelow(_) => {
+                    entry.health_status(policy, at) == HealthStatus::Healthy
+                }
+                AlertCondition::InstancesBelow(_) => true,
+            })
+            .count();
+
+        (instances < self.condition.threshold()).then_some(instances)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// A change in the state of a rule, sent to every notifier
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub status: AlertStatus,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub condition: AlertCondition,
+    pub value: usize,
+    pub timestamp: u64,
+}
+
+impl Alert {
+    /// One line description used by chat and paging notifiers
+    pub fn summary(&self) -> String {
+        let measured = match self.condition {
+            AlertCondition::HealthyBelow(_) => "healthy instances",
+            AlertCondition::InstancesBelow(_) => "instances",
+        };
+        match self.status {
+            AlertStatus::Firing => format!(
+                "{}: {} {} of {} in {}, expected at least {}",
+                self.rule,
+                self.value,
+                measured,
+                self.service_name,
+                self.environment,
+                self.condition.threshold()
+            ),
+            AlertStatus::Resolved => format!(
+                "{} resolved: {} {} of {} in {}",
+                self.rule, self.value, measured, self.service_name, self.environment
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RuleState {
+    breaching_since: Option<u64>,
+    firing: bool,
+}
+
+/// Tracks how long every rule has been breached and reports when rules start and stop firing
+pub struct AlertEvaluator {
+    rules: Vec<AlertRule>,
+    states: HashMap<String, RuleState>,
+}
+
+impl AlertEvaluator {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        AlertEvaluator {
+            rules,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Evaluates every rule against the registry as of `at` and returns the alerts to send
+    pub fn evaluate(
+        &mut self,
+        entries: &[ServiceEntry],
+        policy: &HealthPolicy,
+        at: u64,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+
+        for rule in &self.rules {
+            let state = self.states.entry(rule.name.clone()).or_default();
+            let alert = |status, value| Alert {
+                rule: rule.name.clone(),
+                status,
+                service_name: rule.service_name.clone(),
+                environment: rule.environment.clone(),
+                condition: rule.condition,
+                value,
+                timestamp: at,
+            };
+
+            match rule.breach(entries, policy, at) {
+                Some(value) => {
+                    let since = *state.breaching_since.get_or_insert(at);
+                    if !state.firing && at.saturating_sub(since) >= rule.for_millis {
+                        state.firing = true;
+                        alerts.push(alert(AlertStatus::Firing, value));
+                    }
+                }
+                None => {
+                    state.breaching_since = None;
+                    if state.firing {
+                        state.firing = false;
+                        let value = entries
+                            .iter()
+                            .filter(|entry| {
+                                entry.service_name == rule.service_name
+                                    && entry.environment == rule.environment
+                            })
+                            .count();
+                        alerts.push(alert(AlertStatus::Resolved, value));
+                    }
+                }
+            }
+        }
+
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+
+    fn create_rule() -> AlertRule {
+        serde_yaml::from_str(
+            r#"
+name: checkout-capacity
+service_name: checkout
+environment: prod
+healthy_below: 2
+for: 5m
+"#,
+        )
+        .unwrap()
+    }
+
+    fn create_healthy_entry() -> ServiceEntry {
+        let mut entry = ServiceEntry::new(
+            "checkout".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://localhost:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        entry.last_heartbeat = now() + 1;
+        entry
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = create_rule();
+
+        assert_eq!(rule.condition, AlertCondition::HealthyBelow(2));
+        assert_eq!(rule.for_millis, 300_000);
+    }
+
+    #[test]
+    fn test_breach_counts_healthy_instances() {
+        let rule = create_rule();
+        let policy = HealthPolicy::default();
+        let healthy = create_healthy_entry();
+        let mut unhealthy = create_healthy_entry();
+        unhealthy.last_heartbeat = 0;
+
+        let at = healthy.last_heartbeat;
+
+        assert_eq!(
+            rule.breach(&[healthy.clone(), unhealthy], &policy, at),
+            Some(1)
+        );
+        assert_eq!(rule.breach(&[healthy.clone(), healthy], &policy, at), None);
+    }
+
+    #[test]
+    fn test_fires_after_duration_and_resolves() {
+        let policy = HealthPolicy::default();
+        let mut evaluator = AlertEvaluator::new(vec![create_rule()]);
+
+        assert!(evaluator.evaluate(&[], &policy, 0).is_empty());
+        assert!(evaluator.evaluate(&[], &policy, 299_999).is_empty());
+
+        let alerts = evaluator.evaluate(&[], &policy, 300_000);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Firing);
+        assert_eq!(
+            alerts[0].summary(),
+            "checkout-capacity: 0 healthy instances of checkout in prod, expected at least 2"
+        );
+
+        // A firing rule only notifies once
+        assert!(evaluator.evaluate(&[], &policy, 400_000).is_empty());
+
+        let healthy = [create_healthy_entry(), create_healthy_entry()];
+        let alerts = evaluator.evaluate(&healthy, &policy, 500_000);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+        assert_eq!(alerts[0].value, 2);
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Annotations kept per instance, the oldest are dropped past this
+pub const MAX_ANNOTATIONS: usize = 20;
+pub const MAX_NOTE_LENGTH: usize = 500;
+
+/// Free-form operational note attached to an instance, such as why it was cordoned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub note: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub created_at: u64,
+}
+
+/// Appends `annotation` to `annotations`, dropping the oldest past `MAX_ANNOTATIONS`
+pub fn append(annotations: &mut Vec<Annotation>, annotation: Annotation) {
+    annotations.push(annotation);
+    let excess = annotations.len().saturating_sub(MAX_ANNOTATIONS);
+    annotations.drain(..excess);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_drops_oldest() {
+        let mut annotations = Vec::new();
+        for created_at in 0..MAX_ANNOTATIONS as u64 + 2 {
+            append(
+                &mut annotations,
+                Annotation {
+                    note: format!("note {}", created_at),
+                    author: None,
+                    created_at,
+                },
+            );
+        }
+        assert_eq!(annotations.len(), MAX_ANNOTATIONS);
+        assert_eq!(annotations[0].created_at, 2);
+    }
+}
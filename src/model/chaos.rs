@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Faults injected into the responses of every route below a path prefix
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChaosRule {
+    /// Delay added before every response
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Share of requests, between 0 and 1, answered with a 500
+    #[serde(default)]
+    pub error_rate: f64,
+    /// Share of reads, between 0 and 1, answered with an earlier response for the same URL
+    #[serde(default)]
+    pub stale_rate: f64,
+}
+
+impl ChaosRule {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("error_rate", self.error_rate),
+            ("stale_rate", self.stale_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} must be between 0 and 1", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What to do to a single request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosDecision {
+    pub latency: Duration,
+    pub fail: bool,
+    pub stale: bool,
+}
+
+/// Seeded source of faults, so a client test run can be replayed with the same seed
+pub struct ChaosController {
+    seed: u64,
+    state: u64,
+    rules: BTreeMap<String, ChaosRule>,
+    /// Last successful body returned for every URL, replayed as stale reads
+    responses: HashMap<String, Vec<u8>>,
+}
+
+impl ChaosController {
+    pub fn new(seed: u64) -> Self {
+        ChaosController {
+            seed,
+            state: seed,
+            rules: BTreeMap::new(),
+            responses: HashMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rules(&self) -> &BTreeMap<String, ChaosRule> {
+        &self.rules
+    }
+
+    pub fn set_rule(&mut self, route: String, rule: ChaosRule) {
+        self.rules.insert(route, rule);
+    }
+
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.responses.clear();
+    }
+
+    /// Keeps a successful response so it can later be served as a stale read.
+    /// Only the first body per URL is kept, so stale reads stay stale.
+    pub fn remember(&mut self, uri: &str, body: &[u8]) {
+        self.responses
+            .entry(uri.to_string())
+            .or_insert_with(|| body.to_vec());
+    }
+
+    pub fn recall(&self, uri: &str) -> Option<&[u8]> {
+        self.responses.get(uri).map(Vec::as_slice)
+    }
+
+    /// SplitMix64, mapped to `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Decides the faults for a request to `path` using the rule with the longest matching prefix
+    pub fn decide(&mut self, path: &str) -> Option<ChaosDecision> {
+        let rule = *self
+            .rules
+            .iter()
+            .filter(|(route, _)| path.starts_with(route.as_str()))
+            .max_by_key(|(route, _)| route.len())?
+            .1;
+
+        Some(ChaosDecision {
+            latency: Duration::from_millis(rule.latency_ms),
+            fail: self.next_f64() < rule.error_rate,
+            stale: self.next_f64() < rule.stale_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decide_uses_longest_prefix() {
+        let mut controller = ChaosController::new(42);
+        controller.set_rule(
+            "/services".to_string(),
+            ChaosRule {
+                latency_ms: 10,
+                ..ChaosRule::default()
+            },
+        );
+        controller.set_rule(
+            "/services/heartbeat".to_string(),
+            ChaosRule {
+                error_rate: 1.0,
+                ..ChaosRule::default()
+            },
+        );
+
+        let decision = controller.decide("/services/payments/prod").unwrap();
+        assert_eq!(decision.latency, Duration::from_millis(10));
+        assert!(!decision.fail);
+
+        assert!(controller.decide("/services/heartbeat").unwrap().fail);
+        assert!(controller.decide("/events").is_none());
+    }
+
+    #[test]
+    fn test_same_seed_same_faults() {
+        let rule = ChaosRule {
+            error_rate: 0.5,
+            ..ChaosRule::default()
+        };
+        let run = |seed| {
+            let mut controller = ChaosController::new(seed);
+            controller.set_rule("/".to_string(), rule);
+            (0..32)
+                .map(|_| controller.decide("/services").unwrap().fail)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(7));
+        assert!(run(42).contains(&true) && run(42).contains(&false));
+    }
+
+    #[test]
+    fn test_validate_rates() {
+        assert!(ChaosRule::default().validate().is_ok());
+        assert!(
+            ChaosRule {
+                error_rate: 1.5,
+                ..ChaosRule::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::model::service_registry::now;
+
+/// Source of the current time in millis since the Unix epoch. Time based
+/// features read it instead of the system time so they can be simulated.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> u64;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall clock time, used outside of simulations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        now()
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// Clock that only moves when told to. Clones share the same time, so a test
+/// can keep one and advance the time seen by the registry and the server.
+#[derive(Debug, Clone)]
+pub struct VirtualClock(Arc<AtomicU64>);
+
+impl VirtualClock {
+    pub fn new(start: u64) -> Self {
+        VirtualClock(Arc::new(AtomicU64::new(start)))
+    }
+
+    /// Starts at the current wall clock time, so timestamps still look realistic
+    pub fn starting_now() -> Self {
+        Self::new(now())
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.0.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, millis: u64) {
+        self.0.store(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock_advances_only_when_told() {
+        let clock = VirtualClock::new(1_000);
+        let shared: SharedClock = Arc::new(clock.clone());
+        assert_eq!(shared.now(), 1_000);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(shared.now(), 91_000);
+
+        clock.set(5);
+        assert_eq!(shared.now(), 5);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let before = now();
+        assert!(SystemClock.now() >= before);
+    }
+}
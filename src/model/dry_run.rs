@@ -0,0 +1,60 @@
+use serde::Serialize;
+
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::service_registry::ServiceEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedChangeKind {
+    Register,
+    Deregister,
+}
+
+/// A change to one instance that a request would make
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedChange {
+    pub kind: PlannedChangeKind,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub instance_id: InstanceId,
+    pub address: String,
+}
+
+impl PlannedChange {
+    pub fn new(kind: PlannedChangeKind, entry: &ServiceEntry) -> Self {
+        PlannedChange {
+            kind,
+            service_name: entry.service_name.clone(),
+            environment: entry.environment.clone(),
+            instance_id: entry.id.clone(),
+            address: entry.address.as_str().to_string(),
+        }
+    }
+}
+
+/// Answer to a request sent with `?dry_run=true`: what it would have changed,
+/// after passing the same validation as a real request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DryRunReport {
+    pub dry_run: bool,
+    pub changes: Vec<PlannedChange>,
+}
+
+impl DryRunReport {
+    pub fn new(changes: Vec<PlannedChange>) -> Self {
+        DryRunReport {
+            dry_run: true,
+            changes,
+        }
+    }
+
+    /// Report for removing every one of `entries`
+    pub fn deregistering(entries: &[ServiceEntry]) -> Self {
+        DryRunReport::new(
+            entries
+                .iter()
+                .map(|entry| PlannedChange::new(PlannedChangeKind::Deregister, entry))
+                .collect(),
+        )
+    }
+}
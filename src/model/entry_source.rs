@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Where an entry in the registry came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntrySource {
+    /// Registered through the API and kept alive by heartbeats
+    #[default]
+    Registered,
+    /// Seeded from the server config at startup, never expected to heartbeat
+    Static,
+    /// Synthesized from a DNS lookup for a service the registry does not know
+    Dns,
+    /// Registered through the API for an endpoint Xolotl does not run, such as a
+    /// SaaS API or a managed database, never expected to heartbeat
+    External,
+}
+
+impl EntrySource {
+    /// Whether the entry is held to the heartbeat policy and may be reaped for missing it
+    pub fn expects_heartbeats(self) -> bool {
+        self == EntrySource::Registered
+    }
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::identifiers::Environment;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentStatus {
+    /// Open to registrations
+    #[default]
+    Active,
+    /// Torn down, new instances are refused
+    Archived,
+}
+
+/// An environment created explicitly, as opposed to one that only exists
+/// because instances were registered in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvironmentRecord {
+    pub name: Environment,
+    pub status: EnvironmentStatus,
+    pub created_at: u64,
+    /// Time at which the environment is archived and its instances removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<u64>,
+    /// Whether notifiers were warned of the upcoming expiry
+    #[serde(skip)]
+    pub expiry_notified: bool,
+}
+
+/// A freeze on an environment, refusing registrations and deregistrations
+/// during an incident or a change freeze while reads go on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvironmentFreeze {
+    pub frozen_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvironmentExpiryKind {
+    /// The environment expires soon
+    Expiring,
+    /// The environment expired, its services were tombstoned and it was archived
+    Expired,
+}
+
+/// Notice sent to notifiers about a declared environment reaching its expiry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvironmentExpiry {
+    pub kind: EnvironmentExpiryKind,
+    pub environment: Environment,
+    pub expires_at: u64,
+}
+
+impl EnvironmentExpiry {
+    pub fn summary(&self) -> String {
+        match self.kind {
+            EnvironmentExpiryKind::Expiring => format!(
+                "Environment {} expires at {}",
+                self.environment, self.expires_at
+            ),
+            EnvironmentExpiryKind::Expired => format!(
+                "Environment {} expired, its services were removed and it was archived",
+                self.environment
+            ),
+        }
+    }
+}
+
+impl EnvironmentRecord {
+    /// An environment created at `at`, expiring `ttl_seconds` later when given
+    pub fn new(name: Environment, ttl_seconds: Option<u64>, at: u64) -> Self {
+        EnvironmentRecord {
+            name,
+            status: EnvironmentStatus::Active,
+            created_at: at,
+            expires_at: ttl_seconds.map(|seconds| at.saturating_add(seconds.saturating_mul(1000))),
+            archived_at: None,
+            expiry_notified: false,
+        }
+    }
+
+    /// Expires the environment at `expires_at` instead
+    pub fn with_expiry(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.status == EnvironmentStatus::Archived
+    }
+
+    /// Whether the environment is active but past its expiry at time `at`
+    pub fn is_expired(&self, at: u64) -> bool {
+        !self.is_archived() && self.expires_at.is_some_and(|expires_at| at >= expires_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let record = EnvironmentRecord::new("pr-42".parse().unwrap(), Some(60), 1_000);
+        assert_eq!(record.expires_at, Some(61_000));
+        assert!(!record.is_expired(60_999));
+        assert!(record.is_expired(61_000));
+
+        let forever = EnvironmentRecord::new("prod".parse().unwrap(), None, 1_000);
+        assert!(!forever.is_expired(u64::MAX));
+
+        let pinned = forever.with_expiry(5_000);
+        assert!(pinned.is_expired(5_000));
+    }
+}
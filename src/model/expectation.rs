@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{HealthPolicy, HealthStatus, ServiceEntry};
+
+/// A service that should always have a healthy instance, in one environment or anywhere
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expectation {
+    pub service_name: ServiceName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    /// Millis without a healthy instance before the expectation is violated
+    pub window: u64,
+    pub expected_since: u64,
+    /// Last time a healthy instance was seen, by the periodic check or a listing
+    pub last_healthy_at: Option<u64>,
+    /// Whether the violation was notified and not yet restored
+    #[serde(skip)]
+    pub notified: bool,
+}
+
+/// An expectation as reported by `GET /expectations`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpectationStatus {
+    #[serde(flatten)]
+    pub expectation: Expectation,
+    pub violated: bool,
+}
+
+impl Expectation {
+    pub fn new(
+        service_name: ServiceName,
+        environment: Option<Environment>,
+        window: u64,
+        at: u64,
+    ) -> Self {
+        Expectation {
+            service_name,
+            environment,
+            window,
+            expected_since: at,
+            last_healthy_at: None,
+            notified: false,
+        }
+    }
+
+    /// Whether `entries` include a healthy instance meeting the expectation at time `at`
+    pub fn is_met(&self, entries: &[ServiceEntry], policy: &HealthPolicy, at: u64) -> bool {
+        entries.iter().any(|entry| {
+            entry.service_name == self.service_name
+                && self
+                    .environment
+                    .as_ref()
+                    .is_none_or(|environment| &entry.environment == environment)
+                && entry.health_status(policy, at) == HealthStatus::Healthy
+        })
+    }
+
+    /// Whether no healthy instance has been seen for the window at time `at`
+    pub fn is_violated(&self, at: u64) -> bool {
+        let since = self.last_healthy_at.unwrap_or(self.expected_since);
+        at.saturating_sub(since) >= self.window
+    }
+}
+
+fn scope(service_name: &ServiceName, environment: Option<&Environment>) -> String {
+    match environment {
+        Some(environment) => format!("{} in {}", service_name, environment),
+        None => format!("{} in any environment", service_name),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpectationChangeKind {
+    /// No healthy instance was seen for the window
+    Violated,
+    /// A healthy instance showed up again
+    Restored,
+}
+
+/// Notice sent to notifiers when an expected service goes missing or comes back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpectationChange {
+    pub kind: ExpectationChangeKind,
+    pub service_name: ServiceName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    pub last_healthy_at: Option<u64>,
+    pub timestamp: u64,
+}
+
+impl ExpectationChange {
+    pub fn new(kind: ExpectationChangeKind, expectation: &Expectation, at: u64) -> Self {
+        ExpectationChange {
+            kind,
+            service_name: expectation.service_name.clone(),
+            environment: expectation.environment.clone(),
+            last_healthy_at: expectation.last_healthy_at,
+            timestamp: at,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let scope = scope(&self.service_name, self.environment.as_ref());
+        match self.kind {
+            ExpectationChangeKind::Violated => {
+                format!("Expected service {} has no healthy instance", scope)
+            }
+            ExpectationChangeKind::Restored => {
+                format!("Expected service {} has a healthy instance again", scope)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_met_in_any_environment() {
+        let policy = HealthPolicy::default();
+        let mut entry = ServiceEntry::new(
+            "billing".parse().unwrap(),
+            "staging".parse().unwrap(),
+            "http://billing:8080".to_string(),
+            HashMap::new(),
+            0,
+        );
+        entry.last_heartbeat = 1_000;
+
+        let anywhere = Expectation::new("billing".parse().unwrap(), None, 60_000, 0);
+        let in_prod = Expectation::new(
+            "billing".parse().unwrap(),
+            Some("prod".parse().unwrap()),
+            60_000,
+            0,
+        );
+        assert!(anywhere.is_met(std::slice::from_ref(&entry), &policy, 2_000));
+        assert!(!in_prod.is_met(std::slice::from_ref(&entry), &policy, 2_000));
+        assert!(!in_prod.is_violated(59_999));
+        assert!(in_prod.is_violated(60_000));
+    }
+}
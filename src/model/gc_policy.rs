@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::service_registry::ServiceEntry;
+
+/// When instances of a service are removed without being deregistered.
+/// Entries that never heartbeat, such as static services, are always kept
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GcPolicy {
+    /// Seconds without a heartbeat after which an instance is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reap_after_seconds: Option<u64>,
+    /// Most instances kept per environment, the oldest registrations are evicted first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_instances: Option<usize>,
+    /// Keeps every instance, including through the server wide tombstoning
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub never_reap: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionReason {
+    /// Silent for longer than `reap_after_seconds`
+    Expired,
+    /// Beyond `max_instances`
+    OverCapacity,
+}
+
+impl GcPolicy {
+    /// Rejects policies that both keep and remove instances, or whose limits are out of range
+    pub fn validate(&self) -> Result<(), String> {
+        if self.never_reap && (self.reap_after_seconds.is_some() || self.max_instances.is_some()) {
+            return Err("never_reap cannot be combined with other gc settings".to_string());
+        }
+        if self.reap_after_seconds == Some(0) || self.max_instances == Some(0) {
+            return Err("gc limits must be greater than zero".to_string());
+        }
+        if self
+            .reap_after_seconds
+            .is_some_and(|seconds| seconds > u64::MAX / 1000)
+        {
+            return Err(format!(
+                "reap_after_seconds must be at most {}",
+                u64::MAX / 1000
+            ));
+        }
+        Ok(())
+    }
+
+    /// Picks the instances of one service in one environment to remove at time `at`
+    pub fn evictions<'a>(
+        &self,
+        instances: &'a [ServiceEntry],
+        at: u64,
+    ) -> Vec<(&'a ServiceEntry, EvictionReason)> {
+        if self.never_reap {
+            return Vec::new();
+        }
+
+        let mut kept: Vec<&ServiceEntry> = Vec::new();
+        let mut evicted = Vec::new();
+        for entry in instances {
+            let expired = entry.source.expects_heartbeats()
+                && self.reap_after_seconds.is_some_and(|seconds| {
+                    at.saturating_sub(entry.last_heartbeat) >= seconds * 1000
+                });
+            if expired {
+                evicted.push((entry, EvictionReason::Expired));
+            } else {
+                kept.push(entry);
+            }
+        }
+
+        if let Some(max) = self.max_instances {
+            let mut candidates: Vec<&ServiceEntry> = kept
+                .into_iter()
+                .filter(|entry| entry.source.expects_heartbeats())
+                .collect();
+            candidates.sort_by_key(|entry| entry.registered_at);
+            // Entries that never heartbeat count towards the limit but are never evicted
+            let excess = (instances.len() - evicted.len()).saturating_sub(max);
+            evicted.extend(
+                candidates
+                    .into_iter()
+                    .take(excess)
+                    .map(|entry| (entry, EvictionReason::OverCapacity)),
+            );
+        }
+
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::entry_source::EntrySource;
+    use std::collections::HashMap;
+
+    fn entry(registered_at: u64, last_heartbeat: u64) -> ServiceEntry {
+        let mut entry = ServiceEntry::new(
+            "worker".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            registered_at,
+        );
+        entry.last_heartbeat = last_heartbeat;
+        entry
+    }
+
+    #[test]
+    fn test_reap_after() {
+        let policy = GcPolicy {
+            reap_after_seconds: Some(60),
+            ..GcPolicy::default()
+        };
+        let instances = [entry(0, 0), entry(0, 50_000)];
+
+        let evicted = policy.evictions(&instances, 60_000);
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].0.id, instances[0].id);
+        assert_eq!(evicted[0].1, EvictionReason::Expired);
+    }
+
+    #[test]
+    fn test_max_instances_evicts_oldest() {
+        let policy = GcPolicy {
+            max_instances: Some(2),
+            ..GcPolicy::default()
+        };
+        let instances = [
+            entry(3_000, 3_000),
+            entry(1_000, 9_000),
+            entry(2_000, 9_000),
+        ];
+
+        let evicted = policy.evictions(&instances, 10_000);
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].0.id, instances[1].id);
+        assert_eq!(evicted[0].1, EvictionReason::OverCapacity);
+    }
+
+    #[test]
+    fn test_static_and_never_reap_are_kept() {
+        let mut pinned = entry(0, 0);
+        pinned.source = EntrySource::Static;
+        let instances = [pinned, entry(1_000, 1_000)];
+        let policy = GcPolicy {
+            reap_after_seconds: Some(1),
+            max_instances: Some(1),
+            never_reap: false,
+        };
+
+        let evicted = policy.evictions(&instances, 100_000);
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].0.id, instances[1].id);
+
+        let never = GcPolicy {
+            never_reap: true,
+            ..GcPolicy::default()
+        };
+        assert!(never.evictions(&instances, 100_000).is_empty());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(GcPolicy::default().validate().is_ok());
+        assert!(
+            GcPolicy {
+                never_reap: true,
+                max_instances: Some(3),
+                ..GcPolicy::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            GcPolicy {
+                reap_after_seconds: Some(0),
+                ..GcPolicy::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            GcPolicy {
+                reap_after_seconds: Some(u64::MAX / 1000 + 1),
+                ..GcPolicy::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
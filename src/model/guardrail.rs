@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::model::identifiers::ServiceName;
+use crate::model::service_registry::RegistryError;
+use crate::model::stale_report::parse_age;
+
+/// Most deregistrations of one service allowed within a window, e.g. `100/1m`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuardrailLimit {
+    pub max: usize,
+    /// Length of the window in millis
+    pub window: u64,
+}
+
+impl FromStr for GuardrailLimit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid guardrail '{}', expected <count>/<age> such as 100/1m",
+                value
+            )
+        };
+        let (max, window) = value.split_once('/').ok_or_else(invalid)?;
+        let max: usize = max.parse().map_err(|_| invalid())?;
+        let window = parse_age(window)?;
+        if max == 0 || window == 0 {
+            return Err(invalid());
+        }
+        Ok(GuardrailLimit { max, window })
+    }
+}
+
+/// A service whose deregistrations went over the limit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GuardrailTrip {
+    pub service_name: ServiceName,
+    pub tripped_at: u64,
+    /// Deregistrations within the window when the guardrail tripped, including the refused ones
+    pub deregistrations: usize,
+    /// Whether the deregistrations were refused or only flagged
+    pub blocked: bool,
+}
+
+/// Watches the rate at which instances of each service are deregistered and
+/// flags, or blocks until an admin confirms, bursts over the limit
+#[derive(Default)]
+pub struct Guardrail {
+    limit: Option<GuardrailLimit>,
+    block: bool,
+    recent: HashMap<ServiceName, VecDeque<u64>>,
+    trips: BTreeMap<ServiceName, GuardrailTrip>,
+    /// Services an admin confirmed, allowed through until the given time
+    confirmed: HashMap<ServiceName, u64>,
+}
+
+impl Guardrail {
+    /// Guards deregistrations with `limit`, refusing bursts when `block` is set and only flagging them otherwise
+    pub fn new(limit: GuardrailLimit, block: bool) -> Self {
+        Guardrail {
+            limit: Some(limit),
+            block,
+            ..Guardrail::default()
+        }
+    }
+
+    /// Deregistrations of `service_name` within the window at time `at` if `count`
+    /// more would go over the limit and the service was not confirmed
+    fn over_limit(&self, service_name: &ServiceName, count: usize, at: u64) -> Option<usize> {
+        let limit = self.limit?;
+        if self
+            .confirmed
+            .get(service_name)
+            .is_some_and(|until| *until > at)
+        {
+            return None;
+        }
+        let recent = self.recent.get(service_name).map_or(0, |recent| {
+            recent
+                .iter()
+                .filter(|when| at.saturating_sub(**when) < limit.window)
+                .count()
+        });
+        let total = recent + count;
+        (total > limit.max).then_some(total)
+    }
+
+    fn refusal(&self, service_name: &ServiceName, total: usize) -> RegistryError {
+        RegistryError::Conflict(format!(
+            "{} deregistrations of {} within the guardrail window exceed the limit of {}, \
+             confirm with POST /admin/guardrails/{}/confirm",
+            total,
+            service_name,
+            self.limit.map_or(0, |limit| limit.max),
+            service_name
+        ))
+    }
+
+    /// Fails like `check` would, without recording anything
+    pub fn preview(
+        &self,
+        service_name: &ServiceName,
+        count: usize,
+        at: u64,
+    ) -> Result<(), RegistryError> {
+        match self.over_limit(service_name, count, at) {
+            Some(total) if self.block => Err(self.refusal(service_name, total)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that `count` instances of `service_name` are about to be deregistered at
+    /// time `at`, failing when that trips a blocking guardrail
+    pub fn check(
+        &mut self,
+        service_name: &ServiceName,
+        count: usize,
+        at: u64,
+    ) -> Result<(), RegistryError> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        self.confirmed.retain(|_, until| *until > at);
+        if let Some(total) = self.over_limit(service_name, count, at) {
+            self.trips.insert(
+                service_name.clone(),
+                GuardrailTrip {
+                    service_name: service_name.clone(),
+                    tripped_at: at,
+                    deregistrations: total,
+                    blocked: self.block,
+                },
+            );
+            if self.block {
+                return Err(self.refusal(service_name, total));
+            }
+            eprintln!(
+                "Guardrail flagged {} deregistrations of {}",
+                total, service_name
+            );
+        }
+        let recent = self.recent.entry(service_name.clone()).or_default();
+        while recent
+            .front()
+            .is_some_and(|when| at.saturating_sub(*when) >= limit.window)
+        {
+            recent.pop_front();
+        }
+        recent.extend(std::iter::repeat_n(at, count));
+        Ok(())
+    }
+
+    /// Services that went over the limit and were not confirmed since
+    pub fn trips(&self) -> Vec<GuardrailTrip> {
+        self.trips.values().cloned().collect()
+    }
+
+    /// Clears the trip of `service_name` at time `at` and lets its deregistrations
+    /// through for the rest of the window
+    pub fn confirm(&mut self, service_name: &ServiceName, at: u64) -> Result<(), RegistryError> {
+        let limit = self.limit.ok_or(RegistryError::NotFound)?;
+        self.trips
+            .remove(service_name)
+            .ok_or(RegistryError::NotFound)?;
+        self.confirmed
+            .insert(service_name.clone(), at + limit.window);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(
+            "100/1m".parse(),
+            Ok(GuardrailLimit {
+                max: 100,
+                window: 60_000
+            })
+        );
+        assert!("100".parse::<GuardrailLimit>().is_err());
+        assert!("0/1m".parse::<GuardrailLimit>().is_err());
+    }
+
+    #[test]
+    fn test_block_until_confirmed() {
+        let mut guardrail = Guardrail::new("3/1m".parse().unwrap(), true);
+        let name: ServiceName = "checkout".parse().unwrap();
+
+        guardrail.check(&name, 2, 0).unwrap();
+        assert!(guardrail.preview(&name, 2, 1_000).is_err());
+        assert!(guardrail.trips().is_empty());
+        assert!(guardrail.check(&name, 2, 1_000).is_err());
+        assert_eq!(guardrail.trips()[0].deregistrations, 4);
+        guardrail.check(&name, 1, 1_000).unwrap();
+
+        guardrail.confirm(&name, 2_000).unwrap();
+        assert!(guardrail.trips().is_empty());
+        guardrail.check(&name, 10, 2_000).unwrap();
+        assert!(guardrail.confirm(&name, 2_000).is_err());
+
+        // Once the confirmation and the window have passed the limit applies again
+        guardrail.check(&name, 3, 200_000).unwrap();
+        assert!(guardrail.check(&name, 1, 200_000).is_err());
+    }
+
+    #[test]
+    fn test_flag_only() {
+        let mut guardrail = Guardrail::new("1/1m".parse().unwrap(), false);
+        let name: ServiceName = "checkout".parse().unwrap();
+
+        guardrail.check(&name, 5, 0).unwrap();
+        assert!(!guardrail.trips()[0].blocked);
+        assert!(Guardrail::default().check(&name, 1_000, 0).is_ok());
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex};
+
+/// Shared heartbeat pacer, only present when the server runs with `--heartbeat-capacity`
+pub type PacerHandle = Option<Arc<HeartbeatPacer>>;
+
+/// Heartbeats counted in the current and previous second
+#[derive(Default)]
+struct RateWindow {
+    second: u64,
+    current: u64,
+    previous: u64,
+}
+
+impl RateWindow {
+    fn roll(&mut self, at: u64) {
+        let second = at / 1000;
+        if second != self.second {
+            self.previous = if second == self.second + 1 {
+                self.current
+            } else {
+                0
+            };
+            self.second = second;
+            self.current = 0;
+        }
+    }
+}
+
+/// Suggests heartbeat intervals to clients, stretching them while the server
+/// receives more heartbeats per second than its capacity
+pub struct HeartbeatPacer {
+    capacity: u64,
+    window: Mutex<RateWindow>,
+}
+
+impl HeartbeatPacer {
+    /// Paces clients so the server receives about `capacity` heartbeats per second
+    pub fn new(capacity: u64) -> Self {
+        HeartbeatPacer {
+            capacity: capacity.max(1),
+            window: Mutex::new(RateWindow::default()),
+        }
+    }
+
+    /// Counts `count` heartbeats received at time `at`
+    pub fn record(&self, count: u64, at: u64) {
+        let mut window = self.window.lock().unwrap();
+        window.roll(at);
+        window.current = window.current.saturating_add(count);
+    }
+
+    /// Heartbeats per second, the busier of the current and previous second
+    pub fn rate(&self, at: u64) -> u64 {
+        let mut window = self.window.lock().unwrap();
+        window.roll(at);
+        window.previous.max(window.current)
+    }
+
+    /// Seconds between heartbeats for an instance that turns stale after
+    /// `ttl_seconds`: a third of it under capacity, stretched in proportion to
+    /// the load above it, but never past two thirds so the instance stays fresh
+    pub fn suggest(&self, ttl_seconds: u64, at: u64) -> u64 {
+        let base = (ttl_seconds / 3).max(1);
+        // Two thirds of any u64 fits in one, computed wider so it cannot overflow
+        let ceiling = ((ttl_seconds as u128 * 2 / 3) as u64).max(base);
+        let rate = self.rate(at);
+        if rate <= self.capacity {
+            return base;
+        }
+        let stretched = base as u128 * rate as u128 / self.capacity as u128;
+        u64::try_from(stretched).unwrap_or(u64::MAX).min(ceiling)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_stretches_under_load() {
+        let pacer = HeartbeatPacer::new(100);
+        assert_eq!(pacer.suggest(30, 0), 10);
+
+        pacer.record(150, 1_000);
+        assert_eq!(pacer.rate(1_500), 150);
+        assert_eq!(pacer.suggest(30, 1_500), 15);
+        pacer.record(1_000, 1_600);
+        assert_eq!(pacer.suggest(30, 1_700), 20);
+
+        // The previous second still counts, then the load is forgotten
+        assert_eq!(pacer.rate(2_100), 1_150);
+        assert_eq!(pacer.suggest(30, 4_000), 10);
+    }
+
+    #[test]
+    fn test_suggest_does_not_overflow() {
+        let pacer = HeartbeatPacer::new(1);
+        pacer.record(u64::MAX, 1_000);
+        pacer.record(1, 1_000);
+        assert_eq!(pacer.suggest(u64::MAX, 1_500), u64::MAX / 3 * 2);
+    }
+}
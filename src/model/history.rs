@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+/// Instance count of a service in an environment at one point in time, with
+/// the registrations and deregistrations seen since the previous sample
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct HistorySample {
+    pub timestamp: u64,
+    pub instances: usize,
+    pub registrations: usize,
+    pub deregistrations: usize,
+}
@@ -0,0 +1,181 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Longest identifier accepted, matching the limit of a DNS name
+pub const MAX_IDENTIFIER_LENGTH: usize = 253;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidIdentifier {
+    pub kind: &'static str,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for InvalidIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {}: {}", self.kind, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidIdentifier {}
+
+/// Checks the rules shared by every identifier: non-empty, bounded, and usable as a URL path segment
+fn validate(kind: &'static str, value: &str) -> Result<(), InvalidIdentifier> {
+    let reason = if value.is_empty() {
+        "must not be empty"
+    } else if value.chars().count() > MAX_IDENTIFIER_LENGTH {
+        "must be at most 253 characters"
+    } else if value.contains('/') {
+        "must not contain '/'"
+    } else if value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        "must not contain whitespace or control characters"
+    } else {
+        return Ok(());
+    };
+
+    Err(InvalidIdentifier { kind, reason })
+}
+
+/// Defines a validated string newtype that can only be built through parsing
+macro_rules! identifier {
+    ($(#[$meta:meta])* $name:ident, $kind:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = InvalidIdentifier;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                validate($kind, &value)?;
+                Ok($name(value))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = InvalidIdentifier;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                $name::try_from(value.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+identifier!(
+    /// Name a service registers under
+    ServiceName,
+    "service name"
+);
+
+identifier!(
+    /// Environment an instance runs in, such as `prod` or `staging`
+    Environment,
+    "environment"
+);
+
+identifier!(
+    /// Unique id of a registered instance
+    InstanceId,
+    "instance id"
+);
+
+impl InstanceId {
+    /// Generates a fresh random id
+    pub fn generate() -> Self {
+        InstanceId(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_identifiers() {
+        let name: ServiceName = "service-with-dashes_and_underscores".parse().unwrap();
+        assert_eq!(name, "service-with-dashes_and_underscores");
+        assert_eq!(name.to_string(), "service-with-dashes_and_underscores");
+
+        let environment: Environment = "测试环境".parse().unwrap();
+        assert_eq!(environment.as_str(), "测试环境");
+    }
+
+    #[test]
+    fn test_parse_invalid_identifiers() {
+        assert_eq!(
+            "".parse::<ServiceName>().unwrap_err().to_string(),
+            "Invalid service name: must not be empty"
+        );
+        assert!("a/b".parse::<Environment>().is_err());
+        assert!("has space".parse::<ServiceName>().is_err());
+        assert!("x".repeat(254).parse::<InstanceId>().is_err());
+        assert!("x".repeat(253).parse::<InstanceId>().is_ok());
+    }
+
+    #[test]
+    fn test_serde_validates() {
+        let name: ServiceName = serde_json::from_str("\"payments\"").unwrap();
+        assert_eq!(serde_json::to_string(&name).unwrap(), "\"payments\"");
+        assert!(serde_json::from_str::<ServiceName>("\"\"").is_err());
+    }
+
+    #[test]
+    fn test_generated_ids_are_unique() {
+        assert_ne!(InstanceId::generate(), InstanceId::generate());
+    }
+}
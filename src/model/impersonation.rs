@@ -0,0 +1,51 @@
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+/// Header an admin sets to act on behalf of a service identity
+pub const ACT_AS_HEADER: &str = "x-act-as";
+
+/// An admin acting on behalf of a service identity, attached to every
+/// registry event recorded while serving the request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Impersonation {
+    /// Service identity the request acts as
+    pub acting_as: String,
+    /// Admin credential behind the request, `admin-token` or the id of an issued token
+    pub admin: String,
+}
+
+tokio::task_local! {
+    static IMPERSONATION: Impersonation;
+}
+
+impl Impersonation {
+    /// Runs `future`, attributing the registry events it records to this impersonation
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        IMPERSONATION.scope(self, future).await
+    }
+
+    /// The impersonation the current task runs under, if any
+    pub fn current() -> Option<Impersonation> {
+        IMPERSONATION.try_with(Clone::clone).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope() {
+        let impersonation = Impersonation {
+            acting_as: "payments".to_string(),
+            admin: "admin-token".to_string(),
+        };
+        assert_eq!(Impersonation::current(), None);
+        let current = impersonation
+            .clone()
+            .scope(async { Impersonation::current() })
+            .await;
+        assert_eq!(current, Some(impersonation));
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle of an instance, set explicitly by the instance or an operator
+/// and independent of the health derived from heartbeats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InstanceState {
+    /// Registered but not ready to receive traffic yet
+    Starting,
+    #[default]
+    Up,
+    /// Finishing in-flight work before shutting down, no new traffic
+    Draining,
+    /// Taken out of rotation by an operator
+    Maintenance,
+    Down,
+}
+
+impl InstanceState {
+    /// Whether an instance may move from this state to `next`. Staying in the
+    /// same state is always allowed so repeated updates are harmless.
+    pub fn can_transition_to(self, next: InstanceState) -> bool {
+        use InstanceState::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Starting, Up | Maintenance | Down)
+                    | (Up, Draining | Maintenance | Down)
+                    | (Draining, Up | Down)
+                    | (Maintenance, Up | Down)
+                    | (Down, Starting | Up)
+            )
+    }
+
+    /// Whether resolution should hand the instance out to callers
+    pub fn is_routable(self) -> bool {
+        self == InstanceState::Up
+    }
+}
+
+impl fmt::Display for InstanceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        use InstanceState::*;
+
+        assert!(Starting.can_transition_to(Up));
+        assert!(Up.can_transition_to(Draining));
+        assert!(Draining.can_transition_to(Down));
+        assert!(Maintenance.can_transition_to(Up));
+        assert!(Down.can_transition_to(Starting));
+        assert!(Up.can_transition_to(Up));
+
+        assert!(!Starting.can_transition_to(Draining));
+        assert!(!Draining.can_transition_to(Maintenance));
+        assert!(!Down.can_transition_to(Draining));
+    }
+
+    #[test]
+    fn test_only_up_is_routable() {
+        assert!(InstanceState::Up.is_routable());
+        assert!(!InstanceState::Starting.is_routable());
+        assert!(!InstanceState::Draining.is_routable());
+        assert!(!InstanceState::Maintenance.is_routable());
+        assert!(!InstanceState::Down.is_routable());
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// Matches any service when used as an intention source or destination
+pub const WILDCARD: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntentionAction {
+    Allow,
+    Deny,
+}
+
+/// A rule describing whether `source` is allowed to call `destination`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Intention {
+    pub source: String,
+    pub destination: String,
+    pub action: IntentionAction,
+    pub created_at: u64,
+}
+
+impl Intention {
+    /// Returns how specific this intention is, higher values win when several match
+    pub fn precedence(&self) -> u8 {
+        match (self.source == WILDCARD, self.destination == WILDCARD) {
+            (false, false) => 3,
+            (true, false) => 2,
+            (false, true) => 1,
+            (true, true) => 0,
+        }
+    }
+
+    /// Checks if this intention applies to a call from `source` to `destination`
+    pub fn matches(&self, source: &str, destination: &str) -> bool {
+        (self.source == WILDCARD || self.source == source)
+            && (self.destination == WILDCARD || self.destination == destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intention(source: &str, destination: &str) -> Intention {
+        Intention {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            action: IntentionAction::Allow,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(intention("web", "db").matches("web", "db"));
+        assert!(!intention("web", "db").matches("api", "db"));
+        assert!(intention("*", "db").matches("api", "db"));
+        assert!(intention("web", "*").matches("web", "cache"));
+        assert!(intention("*", "*").matches("anything", "else"));
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert!(intention("web", "db").precedence() > intention("*", "db").precedence());
+        assert!(intention("*", "db").precedence() > intention("web", "*").precedence());
+        assert!(intention("web", "*").precedence() > intention("*", "*").precedence());
+    }
+
+    #[test]
+    fn test_action_serialization() {
+        assert_eq!(
+            serde_json::to_string(&IntentionAction::Deny).unwrap(),
+            r#""deny""#
+        );
+        let action: IntentionAction = serde_json::from_str(r#""allow""#).unwrap();
+        assert_eq!(action, IntentionAction::Allow);
+    }
+}
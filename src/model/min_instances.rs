@@ -0,0 +1,210 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_profile::ServiceProfile;
+use crate::model::service_registry::{HealthPolicy, HealthStatus, RegistryError, ServiceEntry};
+
+fn healthy_instances<'a>(
+    entries: impl IntoIterator<Item = &'a ServiceEntry>,
+    service_name: &ServiceName,
+    environment: &Environment,
+    policy: &HealthPolicy,
+    at: u64,
+) -> usize {
+    entries
+        .into_iter()
+        .filter(|entry| {
+            &entry.service_name == service_name
+                && &entry.environment == environment
+                && entry.health_status(policy, at) == HealthStatus::Healthy
+        })
+        .count()
+}
+
+/// Refuses removing `removed` from `entries` when that takes healthy instances
+/// out of an environment whose `minimums` it would then no longer meet
+pub fn check_min_instances(
+    minimums: &BTreeMap<Environment, usize>,
+    entries: &[ServiceEntry],
+    removed: &[ServiceEntry],
+    policy: &HealthPolicy,
+    at: u64,
+) -> Result<(), RegistryError> {
+    for (environment, minimum) in minimums {
+        let Some(service_name) = removed
+            .iter()
+            .find(|entry| &entry.environment == environment)
+            .map(|entry| &entry.service_name)
+        else {
+            continue;
+        };
+        if healthy_instances(removed, service_name, environment, policy, at) == 0 {
+            continue;
+        }
+        let remaining = healthy_instances(
+            entries
+                .iter()
+                .filter(|entry| removed.iter().all(|gone| gone.id != entry.id)),
+            service_name,
+            environment,
+            policy,
+            at,
+        );
+        if remaining < *minimum {
+            return Err(RegistryError::Conflict(format!(
+                "Removing {} instances would leave {} healthy instances of {} in {}, \
+                 below the minimum of {}, retry with force=true to override",
+                removed.len(),
+                remaining,
+                service_name,
+                environment,
+                minimum
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MinimumChangeKind {
+    /// Fewer healthy instances than the minimum are left
+    Breached,
+    /// The minimum is met again
+    Restored,
+}
+
+/// Notice sent to notifiers when a service falls below its minimum or recovers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MinimumChange {
+    pub kind: MinimumChangeKind,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub healthy: usize,
+    pub minimum: usize,
+    pub timestamp: u64,
+}
+
+impl MinimumChange {
+    pub fn summary(&self) -> String {
+        match self.kind {
+            MinimumChangeKind::Breached => format!(
+                "Service {} in {} is down to {} healthy instances, below its minimum of {}",
+                self.service_name, self.environment, self.healthy, self.minimum
+            ),
+            MinimumChangeKind::Restored => format!(
+                "Service {} in {} is back to {} healthy instances, meeting its minimum of {}",
+                self.service_name, self.environment, self.healthy, self.minimum
+            ),
+        }
+    }
+}
+
+/// Reports services that fall below the minimum of their profile, only once
+/// they have met it, so a restart does not report every service as breached
+#[derive(Default)]
+pub struct MinimumWatcher {
+    met: HashSet<(ServiceName, Environment)>,
+    breached: HashSet<(ServiceName, Environment)>,
+}
+
+impl MinimumWatcher {
+    pub fn observe(
+        &mut self,
+        entries: &[ServiceEntry],
+        profiles: &[(ServiceName, ServiceProfile)],
+        policy: &HealthPolicy,
+        at: u64,
+    ) -> Vec<MinimumChange> {
+        let mut changes = Vec::new();
+        for (service_name, profile) in profiles {
+            for (environment, minimum) in &profile.min_instances {
+                let healthy = healthy_instances(entries, service_name, environment, policy, at);
+                let key = (service_name.clone(), environment.clone());
+                let kind = if healthy >= *minimum {
+                    self.met.insert(key.clone());
+                    if !self.breached.remove(&key) {
+                        continue;
+                    }
+                    MinimumChangeKind::Restored
+                } else {
+                    if !self.met.remove(&key) {
+                        continue;
+                    }
+                    self.breached.insert(key);
+                    MinimumChangeKind::Breached
+                };
+                changes.push(MinimumChange {
+                    kind,
+                    service_name: service_name.clone(),
+                    environment: environment.clone(),
+                    healthy,
+                    minimum: *minimum,
+                    timestamp: at,
+                });
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn create_entry(environment: &str, last_heartbeat: u64) -> ServiceEntry {
+        let mut entry = ServiceEntry::new(
+            "checkout".parse().unwrap(),
+            environment.parse().unwrap(),
+            "http://checkout:8080".to_string(),
+            HashMap::new(),
+            0,
+        );
+        entry.last_heartbeat = last_heartbeat;
+        entry
+    }
+
+    #[test]
+    fn test_min_instances() {
+        let policy = HealthPolicy::default();
+        let minimums = BTreeMap::from([("prod".parse().unwrap(), 2)]);
+        let entries = vec![
+            create_entry("prod", 1_000),
+            create_entry("prod", 1_000),
+            create_entry("staging", 1_000),
+        ];
+
+        let last_prod = &entries[..1];
+        assert!(check_min_instances(&minimums, &entries, last_prod, &policy, 2_000).is_err());
+        let staging = &entries[2..];
+        assert!(check_min_instances(&minimums, &entries, staging, &policy, 2_000).is_ok());
+
+        let mut watcher = MinimumWatcher::default();
+        let profiles = vec![(
+            "checkout".parse().unwrap(),
+            ServiceProfile {
+                min_instances: minimums,
+                ..ServiceProfile::default()
+            },
+        )];
+        assert!(
+            watcher
+                .observe(&entries[..1], &profiles, &policy, 2_000)
+                .is_empty()
+        );
+        assert!(
+            watcher
+                .observe(&entries, &profiles, &policy, 2_000)
+                .is_empty()
+        );
+        let changes = watcher.observe(&entries[1..], &profiles, &policy, 2_000);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, MinimumChangeKind::Breached);
+        assert_eq!(changes[0].healthy, 1);
+        let changes = watcher.observe(&entries, &profiles, &policy, 2_000);
+        assert_eq!(changes[0].kind, MinimumChangeKind::Restored);
+    }
+}
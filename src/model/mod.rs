@@ -1,2 +1,38 @@
+pub mod address_rewrite;
+pub mod admission;
+pub mod agent;
+pub mod alert;
+pub mod annotation;
+pub mod chaos;
+pub mod clock;
+pub mod dry_run;
+pub mod entry_source;
+pub mod environment;
+pub mod expectation;
+pub mod gc_policy;
+pub mod guardrail;
+pub mod heartbeat_pacing;
+pub mod history;
+pub mod identifiers;
+pub mod impersonation;
+pub mod instance_state;
+pub mod intention;
+pub mod min_instances;
+pub mod ownership;
+pub mod payload_signing;
+pub mod registry_event;
+pub mod registry_hook;
+pub mod resolution_script;
+pub mod search;
+pub mod selfcheck;
+pub mod server_config;
 pub mod service_address;
+pub mod service_change;
+pub mod service_meta;
+pub mod service_profile;
 pub mod service_registry;
+pub mod spiffe;
+pub mod stale_report;
+pub mod tag_masking;
+pub mod tag_schema;
+pub mod tag_selector;
@@ -0,0 +1,97 @@
+use regex::Regex;
+
+use crate::model::service_registry::RegistryError;
+
+/// Tag holding the team that owns an instance
+pub const OWNER_TAG: &str = "owner";
+
+/// Rules for the owner a service has to declare when registering
+#[derive(Debug, Clone, Default)]
+pub struct OwnerPolicy {
+    pub required: bool,
+    pub teams: Vec<String>,
+    pub pattern: Option<Regex>,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum OwnerError {
+    #[error("An owner is required")]
+    Missing,
+    #[error("Owner {0} is not a known team")]
+    UnknownTeam(String),
+    #[error("Owner {0} does not match the required pattern")]
+    InvalidFormat(String),
+}
+
+impl From<OwnerError> for RegistryError {
+    fn from(error: OwnerError) -> Self {
+        RegistryError::Validation(error.to_string())
+    }
+}
+
+impl OwnerPolicy {
+    /// Checks an optional owner against the team directory and pattern
+    pub fn validate(&self, owner: Option<&str>) -> Result<(), OwnerError> {
+        let owner = match owner {
+            Some(owner) if !owner.is_empty() => owner,
+            _ if self.required => return Err(OwnerError::Missing),
+            _ => return Ok(()),
+        };
+
+        if !self.teams.is_empty() && !self.teams.iter().any(|team| team == owner) {
+            return Err(OwnerError::UnknownTeam(owner.to_string()));
+        }
+
+        if let Some(pattern) = &self.pattern
+            && !pattern.is_match(owner)
+        {
+            return Err(OwnerError::InvalidFormat(owner.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_accepts_anything() {
+        let policy = OwnerPolicy::default();
+
+        assert_eq!(policy.validate(None), Ok(()));
+        assert_eq!(policy.validate(Some("anyone")), Ok(()));
+    }
+
+    #[test]
+    fn test_required_owner() {
+        let policy = OwnerPolicy {
+            required: true,
+            ..OwnerPolicy::default()
+        };
+
+        assert_eq!(policy.validate(None), Err(OwnerError::Missing));
+        assert_eq!(policy.validate(Some("")), Err(OwnerError::Missing));
+        assert_eq!(policy.validate(Some("team-a")), Ok(()));
+    }
+
+    #[test]
+    fn test_team_directory_and_pattern() {
+        let policy = OwnerPolicy {
+            required: false,
+            teams: vec!["team-a".to_string(), "ops".to_string()],
+            pattern: Some(Regex::new("^team-").unwrap()),
+        };
+
+        assert_eq!(policy.validate(Some("team-a")), Ok(()));
+        assert_eq!(
+            policy.validate(Some("team-b")),
+            Err(OwnerError::UnknownTeam("team-b".to_string()))
+        );
+        assert_eq!(
+            policy.validate(Some("ops")),
+            Err(OwnerError::InvalidFormat("ops".to_string()))
+        );
+    }
+}
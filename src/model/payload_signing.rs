@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ring::hmac;
+
+use crate::model::identifiers::ServiceName;
+
+/// Request header carrying the HMAC of the timestamp and body, as `sha256=<hex digest>`
+pub const XOLOTL_SIGNATURE: &str = "x-xolotl-signature";
+
+/// Request header carrying the Unix time in millis the request was signed at
+pub const XOLOTL_TIMESTAMP: &str = "x-xolotl-timestamp";
+
+const SCHEME: &str = "sha256=";
+
+/// How far, in millis, a signed request's timestamp may be from the server's
+/// time, which bounds how long a captured request can be replayed
+const MAX_SKEW: u64 = 30_000;
+
+/// The message a signature covers, `<timestamp>.<body>`
+fn signed_message(timestamp: &str, body: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(timestamp.len() + 1 + body.len());
+    message.extend_from_slice(timestamp.as_bytes());
+    message.push(b'.');
+    message.extend_from_slice(body);
+    message
+}
+
+/// Shared signing secrets, only present when the server runs with `--signing-secrets`
+pub type SigningHandle = Option<Arc<SigningSecrets>>;
+
+/// Shared secrets by service. Registrations and heartbeats of these services
+/// must be signed with theirs, other services may still write unsigned
+#[derive(Clone)]
+pub struct SigningSecrets {
+    secrets: HashMap<ServiceName, hmac::Key>,
+}
+
+/// Lists service names only, so the secrets never end up in logs
+impl fmt::Debug for SigningSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.secrets.keys()).finish()
+    }
+}
+
+impl SigningSecrets {
+    /// Reads secrets with one `<service>:<secret>` line per service
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read signing secrets {}: {}", path, e))?;
+        contents.parse()
+    }
+
+    pub fn requires_signature(&self, service_name: &ServiceName) -> bool {
+        self.secrets.contains_key(service_name)
+    }
+
+    /// Checks a `sha256=<hex>` signature of `timestamp` and `body` against the
+    /// secret of `service_name`, in constant time, and that the request was
+    /// signed close to time `at`. Services without a secret always pass
+    pub fn verify(
+        &self,
+        service_name: &ServiceName,
+        body: &[u8],
+        signature: Option<&str>,
+        timestamp: Option<&str>,
+        at: u64,
+    ) -> Result<(), String> {
+        let Some(key) = self.secrets.get(service_name) else {
+            return Ok(());
+        };
+        let signature = signature.ok_or_else(|| {
+            format!(
+                "Writes to {} must be signed in the {} header",
+                service_name, XOLOTL_SIGNATURE
+            )
+        })?;
+        let digest = signature
+            .strip_prefix(SCHEME)
+            .and_then(decode_hex)
+            .ok_or_else(|| format!("Invalid signature, expected {}<hex digest>", SCHEME))?;
+        let timestamp = timestamp.ok_or_else(|| {
+            format!(
+                "Signed writes must carry the time they were signed in the {} header",
+                XOLOTL_TIMESTAMP
+            )
+        })?;
+        let signed_at: u64 = timestamp
+            .parse()
+            .map_err(|_| format!("Invalid {} header", XOLOTL_TIMESTAMP))?;
+        hmac::verify(key, &signed_message(timestamp, body), &digest)
+            .map_err(|_| format!("Signature does not match the body for {}", service_name))?;
+        if signed_at.abs_diff(at) > MAX_SKEW {
+            return Err(format!(
+                "Request signed at {} is too far from {}",
+                signed_at, at
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SigningSecrets {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut secrets = HashMap::new();
+        for line in value.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (service_name, secret) = line
+                .split_once(':')
+                .ok_or("Invalid signing secret, expected <service>:<secret>")?;
+            let service_name: ServiceName = service_name
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid signing secret: {}", e))?;
+            let secret = secret.trim();
+            if secret.is_empty() {
+                return Err(format!("Signing secret of {} is empty", service_name));
+            }
+            let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+            if secrets.insert(service_name.clone(), key).is_some() {
+                return Err(format!("Signing secret of {} is repeated", service_name));
+            }
+        }
+        Ok(SigningSecrets { secrets })
+    }
+}
+
+/// Signs `body` sent at time `at` with `secret`, as the value of the
+/// `x-xolotl-signature` header. `at` goes in the `x-xolotl-timestamp` header
+pub fn sign(secret: &str, at: u64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let digest: String = hmac::sign(&key, &signed_message(&at.to_string(), body))
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}{}", SCHEME, digest)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let secrets: SigningSecrets = "# edge agents\nsensors: s3cret\n".parse().unwrap();
+        let sensors = "sensors".parse().unwrap();
+        let body = br#"{"service_name":"sensors"}"#;
+        let at = 1_700_000_000_000;
+        let verify = |body: &[u8], signature: &str, timestamp: Option<&str>, now: u64| {
+            secrets.verify(&sensors, body, Some(signature), timestamp, now)
+        };
+        let timestamp = at.to_string();
+        let signature = sign("s3cret", at, body);
+
+        assert!(secrets.requires_signature(&sensors));
+        assert!(verify(body, &signature, Some(&timestamp), at + MAX_SKEW).is_ok());
+        assert!(
+            secrets
+                .verify(&sensors, body, None, Some(&timestamp), at)
+                .is_err()
+        );
+        assert!(verify(b"{}", &signature, Some(&timestamp), at).is_err());
+        assert!(verify(body, &sign("guess", at, body), Some(&timestamp), at).is_err());
+        assert!(verify(body, "sha256=zz", Some(&timestamp), at).is_err());
+        // The timestamp is signed, and a captured request only replays within the skew
+        assert!(verify(body, &signature, None, at).is_err());
+        assert!(verify(body, &signature, Some("1700000000001"), at).is_err());
+        assert!(verify(body, &signature, Some(&timestamp), at + MAX_SKEW + 1).is_err());
+        assert!(
+            secrets
+                .verify(&"payments".parse().unwrap(), body, None, None, at)
+                .is_ok()
+        );
+
+        assert!("sensors".parse::<SigningSecrets>().is_err());
+        assert!("sensors:a\nsensors:b".parse::<SigningSecrets>().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::impersonation::Impersonation;
+use crate::model::service_registry::ServiceEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegistryEventKind {
+    Registered,
+    Deregistered,
+    /// Removed automatically after its instances stopped heartbeating
+    Tombstoned,
+    Promoted,
+    StateChanged,
+    Annotated,
+    /// Silent for most of the time allowed before it becomes unhealthy
+    Expiring,
+}
+
+/// A change applied to the registry, numbered by a monotonically increasing index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryEvent {
+    pub index: u64,
+    pub timestamp: u64,
+    pub kind: RegistryEventKind,
+    pub instance_id: InstanceId,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Admin that made the change on behalf of a service with `X-Act-As`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonation: Option<Impersonation>,
+}
+
+impl RegistryEvent {
+    /// Creates a new RegistryEvent describing a change to `entry` made at time
+    /// `timestamp`, attributed to the impersonation the current request runs under
+    pub fn new(index: u64, kind: RegistryEventKind, entry: &ServiceEntry, timestamp: u64) -> Self {
+        RegistryEvent {
+            index,
+            timestamp,
+            kind,
+            instance_id: entry.id.clone(),
+            service_name: entry.service_name.clone(),
+            environment: entry.environment.clone(),
+            detail: None,
+            impersonation: Impersonation::current(),
+        }
+    }
+
+    /// Attaches a human readable explanation of the change
+    pub fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
@@ -0,0 +1,22 @@
+use crate::model::registry_event::RegistryEvent;
+use crate::model::service_registry::{RegistryError, ServiceEntry};
+
+/// Custom policy run on the write path of a registry wrapped in a `HookedRegistry`.
+/// Every method does nothing by default, so a hook only implements the ones it needs
+pub trait RegistryHook: Sync + Send + 'static {
+    /// Runs before an instance is registered, and may enrich the entry or refuse it
+    fn pre_register(&self, _entry: &mut ServiceEntry) -> Result<(), RegistryError> {
+        Ok(())
+    }
+
+    /// Runs once an instance was registered
+    fn post_register(&self, _entry: &ServiceEntry) {}
+
+    /// Runs before instances are deregistered or tombstoned, and may refuse it
+    fn pre_deregister(&self, _entries: &[ServiceEntry]) -> Result<(), RegistryError> {
+        Ok(())
+    }
+
+    /// Runs for every event recorded by a write, in order
+    fn post_change(&self, _event: &RegistryEvent) {}
+}
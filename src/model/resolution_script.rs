@@ -0,0 +1,171 @@
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+use rhai::{Dynamic, Engine, Scope};
+use serde_json::{Value, json};
+
+use crate::model::service_registry::ServiceEntry;
+
+/// Operations a script may run per resolution before it is stopped
+const MAX_OPERATIONS: u64 = 100_000;
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(16);
+    engine
+});
+
+/// Who is resolving a service and when, handed to resolution scripts as `caller`
+pub struct ResolutionContext<'a> {
+    /// The calling service, when it identified itself
+    pub name: Option<&'a str>,
+    pub ip: Option<IpAddr>,
+    pub at: u64,
+}
+
+impl ResolutionContext<'_> {
+    fn to_value(&self) -> Value {
+        let day = self.at / DAY_MILLIS;
+        json!({
+            "name": self.name,
+            "ip": self.ip.map(|ip| ip.to_string()),
+            "time": self.at,
+            "hour_utc": self.at % DAY_MILLIS / (60 * 60 * 1000),
+            // The epoch fell on a Thursday, 0 is Monday
+            "weekday_utc": (day + 3) % 7,
+        })
+    }
+}
+
+/// Checks that a resolution script compiles
+pub fn validate(source: &str) -> Result<(), String> {
+    ENGINE
+        .compile(source)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid resolution script: {}", e))
+}
+
+/// Runs a Rhai resolution script over the `candidates` a resolution would return.
+///
+/// The script sees them as `instances`, an array of maps with `id`, `address`,
+/// `environment`, `tags`, `priority` and `state`, and the caller as `caller`.
+/// It evaluates to the instances to return, in order, as the maps it was given
+/// or their ids; ids that are not candidates are ignored.
+pub fn apply(
+    source: &str,
+    candidates: Vec<ServiceEntry>,
+    context: &ResolutionContext,
+) -> Result<Vec<ServiceEntry>, String> {
+    let instances: Vec<Value> = candidates
+        .iter()
+        .map(|entry| {
+            json!({
+                "id": entry.id,
+                "address": entry.address_str(),
+                "environment": entry.environment,
+                "tags": entry.tags,
+                "priority": entry.priority,
+                "state": entry.state,
+            })
+        })
+        .collect();
+
+    let mut scope = Scope::new();
+    scope.push_dynamic(
+        "instances",
+        rhai::serde::to_dynamic(instances).map_err(|e| e.to_string())?,
+    );
+    scope.push_dynamic(
+        "caller",
+        rhai::serde::to_dynamic(context.to_value()).map_err(|e| e.to_string())?,
+    );
+    let result: Dynamic = ENGINE
+        .eval_with_scope(&mut scope, source)
+        .map_err(|e| e.to_string())?;
+    let chosen: Vec<Value> = rhai::serde::from_dynamic(&result)
+        .map_err(|_| "the script must evaluate to an array of instances".to_string())?;
+
+    Ok(chosen
+        .iter()
+        .filter_map(|choice| match choice {
+            Value::String(id) => Some(id.as_str()),
+            Value::Object(instance) => instance.get("id").and_then(Value::as_str),
+            _ => None,
+        })
+        .filter_map(|id| {
+            candidates
+                .iter()
+                .find(|entry| entry.id.as_str() == id)
+                .cloned()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+    use std::collections::HashMap;
+
+    fn candidates() -> Vec<ServiceEntry> {
+        ["acme", "globex", "acme"]
+            .iter()
+            .map(|tenant| {
+                ServiceEntry::new(
+                    "checkout".parse().unwrap(),
+                    "prod".parse().unwrap(),
+                    "http://10.0.0.1:8080".to_string(),
+                    HashMap::from([("tenant".to_string(), tenant.to_string())]),
+                    now(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tenant_pinning() {
+        let candidates = candidates();
+        let context = ResolutionContext {
+            name: Some("acme-frontend"),
+            ip: None,
+            // Monday 1970-01-05 at 10:00 UTC
+            at: 4 * DAY_MILLIS + 10 * 60 * 60 * 1000,
+        };
+        let script = r#"
+            let tenant = caller.name.split("-")[0];
+            if caller.weekday_utc < 5 && caller.hour_utc >= 9 {
+                instances.filter(|instance| instance.tags.tenant == tenant)
+            } else {
+                instances
+            }
+        "#;
+        validate(script).unwrap();
+
+        let chosen = apply(script, candidates.clone(), &context).unwrap();
+        assert_eq!(chosen.len(), 2);
+        assert!(chosen.iter().all(|entry| entry.tags["tenant"] == "acme"));
+
+        let reversed = apply(
+            "let ids = instances.map(|instance| instance.id); ids.reverse(); ids",
+            candidates.clone(),
+            &context,
+        )
+        .unwrap();
+        assert_eq!(reversed[0].id, candidates[2].id);
+    }
+
+    #[test]
+    fn test_invalid_scripts() {
+        let context = ResolutionContext {
+            name: None,
+            ip: None,
+            at: 0,
+        };
+        assert!(validate("let x = ").is_err());
+        assert!(apply("42", candidates(), &context).is_err());
+        assert!(apply("loop {}", candidates(), &context).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::model::service_registry::ServiceEntry;
+
+/// Part of an instance a search looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    ServiceName,
+    Tags,
+    Address,
+}
+
+enum Matcher {
+    Substring(String),
+    Pattern(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            Matcher::Substring(needle) => value.contains(needle.as_str()),
+            Matcher::Pattern(pattern) => pattern.is_match(value),
+        }
+    }
+}
+
+/// A substring or regular expression search over one field, or every field when none is given
+pub struct SearchQuery {
+    matcher: Matcher,
+    field: Option<SearchField>,
+}
+
+impl SearchQuery {
+    pub fn substring(query: &str, field: Option<SearchField>) -> Self {
+        SearchQuery {
+            matcher: Matcher::Substring(query.to_string()),
+            field,
+        }
+    }
+
+    pub fn regex(query: &str, field: Option<SearchField>) -> Result<Self, regex::Error> {
+        Ok(SearchQuery {
+            matcher: Matcher::Pattern(Regex::new(query)?),
+            field,
+        })
+    }
+
+    /// Tags, including computed ones, match on their key, their value, or `key=value`
+    pub fn matches(&self, entry: &ServiceEntry) -> bool {
+        let searches = |field| self.field.is_none_or(|selected| selected == field);
+
+        (searches(SearchField::ServiceName) && self.matcher.is_match(&entry.service_name))
+            || (searches(SearchField::Address) && self.matcher.is_match(entry.address_str()))
+            || (searches(SearchField::Tags)
+                && entry
+                    .tags
+                    .iter()
+                    .chain(&entry.computed_tags())
+                    .any(|(key, value)| {
+                        self.matcher.is_match(key)
+                            || self.matcher.is_match(value)
+                            || self.matcher.is_match(&format!("{}={}", key, value))
+                    }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+    use std::collections::HashMap;
+
+    fn create_entry() -> ServiceEntry {
+        ServiceEntry::new(
+            "payments".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.3.12:8080".to_string(),
+            HashMap::from([("team".to_string(), "billing".to_string())]),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_substring_any_field() {
+        let entry = create_entry();
+
+        assert!(SearchQuery::substring("pay", None).matches(&entry));
+        assert!(SearchQuery::substring("10.0.3.", None).matches(&entry));
+        assert!(SearchQuery::substring("team=billing", None).matches(&entry));
+        assert!(!SearchQuery::substring("search", None).matches(&entry));
+    }
+
+    #[test]
+    fn test_substring_single_field() {
+        let entry = create_entry();
+
+        assert!(!SearchQuery::substring("pay", Some(SearchField::Address)).matches(&entry));
+        assert!(SearchQuery::substring("bill", Some(SearchField::Tags)).matches(&entry));
+    }
+
+    #[test]
+    fn test_computed_tags() {
+        let entry = create_entry();
+
+        assert!(SearchQuery::substring("port=8080", Some(SearchField::Tags)).matches(&entry));
+        assert!(SearchQuery::substring("secure=false", None).matches(&entry));
+        assert!(!SearchQuery::substring("secure=true", None).matches(&entry));
+    }
+
+    #[test]
+    fn test_regex() {
+        let entry = create_entry();
+
+        assert!(
+            SearchQuery::regex(r"10\.0\.3\.\d+", Some(SearchField::Address))
+                .unwrap()
+                .matches(&entry)
+        );
+        assert!(
+            !SearchQuery::regex("^pay$", Some(SearchField::ServiceName))
+                .unwrap()
+                .matches(&entry)
+        );
+        assert!(SearchQuery::regex("(", None).is_err());
+    }
+}
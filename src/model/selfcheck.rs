@@ -0,0 +1,33 @@
+use serde::Serialize;
+
+/// Outcome of the synthetic canary that exercises the public API end to end
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SelfCheckReport {
+    pub enabled: bool,
+    pub last_run: Option<u64>,
+    pub last_latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub successes: u64,
+    pub failures: u64,
+}
+
+impl SelfCheckReport {
+    pub fn record_success(&mut self, at: u64, latency_ms: u64) {
+        self.last_run = Some(at);
+        self.last_latency_ms = Some(latency_ms);
+        self.last_error = None;
+        self.successes += 1;
+    }
+
+    pub fn record_failure(&mut self, at: u64, error: String) {
+        self.last_run = Some(at);
+        self.last_latency_ms = None;
+        self.last_error = Some(error);
+        self.failures += 1;
+    }
+
+    /// Healthy until a run fails, including before the first run
+    pub fn is_healthy(&self) -> bool {
+        self.last_error.is_none()
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::model::address_rewrite::AddressRewrites;
+use crate::model::entry_source::EntrySource;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::ServiceEntry;
+use crate::model::tag_masking::TagMasking;
+
+/// Settings read from the `--config` file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Entries registered at startup for dependencies that never heartbeat
+    #[serde(default)]
+    pub static_services: Vec<StaticService>,
+    /// Tags merged into every instance of an environment that does not set them itself
+    #[serde(default)]
+    pub environment_tags: HashMap<Environment, HashMap<String, String>>,
+    /// Rules translating registered addresses in resolution responses
+    #[serde(default)]
+    pub address_rewrites: AddressRewrites,
+    /// Tokens calling services present to be identified, keyed by the name intentions use
+    #[serde(default)]
+    pub callers: HashMap<String, String>,
+    /// Tags redacted in discovery responses unless the caller may unmask them
+    #[serde(default)]
+    pub tag_masking: TagMasking,
+}
+
+/// An external dependency, such as a managed database, listed in discovery
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StaticService {
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub address: String,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+impl ServerConfig {
+    /// Loads the config from a YAML or JSON file
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path, e))?;
+        serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse config {}: {}", path, e))
+    }
+}
+
+impl StaticService {
+    /// Builds the registry entry for this service, registered at time `at`
+    pub fn to_entry(&self, at: u64) -> ServiceEntry {
+        ServiceEntry {
+            source: EntrySource::Static,
+            ..ServiceEntry::new(
+                self.service_name.clone(),
+                self.environment.clone(),
+                self.address.clone(),
+                self.tags.clone(),
+                at,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{HealthPolicy, HealthStatus};
+
+    #[test]
+    fn test_parse_static_services() {
+        let config: ServerConfig = serde_yaml::from_str(
+            r#"
+static_services:
+  - service_name: orders-db
+    environment: prod
+    address: postgres://orders.abc123.rds.amazonaws.com:5432
+    tags:
+      engine: postgres
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.static_services.len(), 1);
+        let entry = config.static_services[0].to_entry(1_000);
+        assert_eq!(entry.service_name, "orders-db");
+        assert_eq!(entry.source, EntrySource::Static);
+        assert_eq!(entry.tags["engine"], "postgres");
+        assert_eq!(
+            entry.health_status(&HealthPolicy::default(), 1_000_000_000),
+            HealthStatus::Healthy
+        );
+
+        assert!(serde_yaml::from_str::<ServerConfig>("static_service: []").is_err());
+    }
+}
@@ -21,7 +21,6 @@ impl ServiceAddress {
     }
 
     /// Attempts to extract the port from the address
-    #[allow(dead_code)]
     pub fn extract_port(&self) -> Option<u16> {
         match self {
             ServiceAddress::String(addr) => {
@@ -54,8 +53,42 @@ impl ServiceAddress {
         }
     }
 
+    /// Returns the address without its protocol, e.g. `10.1.2.3:8080` for `http://10.1.2.3:8080`
+    pub fn without_scheme(&self) -> &str {
+        let addr = self.as_str();
+        addr.split_once("://").map_or(addr, |(_, rest)| rest)
+    }
+
+    /// Returns the host part of the address, e.g. `10.1.2.3` for `http://10.1.2.3:8080/api`
+    pub fn host(&self) -> Option<&str> {
+        let authority = self.without_scheme().split('/').next()?;
+        let host = match authority.strip_prefix('[') {
+            Some(bracketed) => bracketed.split(']').next()?,
+            None => authority.split(':').next()?,
+        };
+        (!host.is_empty()).then_some(host)
+    }
+
+    /// Checks the address against `query`, with or without its protocol,
+    /// either exactly or as a prefix
+    pub fn matches(&self, query: &str, prefix: bool) -> bool {
+        [self.as_str(), self.without_scheme()]
+            .into_iter()
+            .any(|addr| {
+                if prefix {
+                    addr.starts_with(query)
+                } else {
+                    addr == query
+                }
+            })
+    }
+
+    /// Returns the protocol of the address, e.g. `http` for `http://10.1.2.3:8080`
+    pub fn scheme(&self) -> Option<&str> {
+        self.as_str().split_once("://").map(|(scheme, _)| scheme)
+    }
+
     /// Checks if the address uses a secure protocol (https, wss, etc.)
-    #[allow(dead_code)]
     pub fn is_secure(&self) -> bool {
         match self {
             ServiceAddress::String(addr) => {
@@ -117,6 +150,26 @@ mod tests {
         assert_eq!(address.extract_port(), None);
     }
 
+    #[test]
+    fn test_host() {
+        let host = |addr: &str| {
+            ServiceAddress::from_string(addr.to_string())
+                .host()
+                .map(String::from)
+        };
+
+        assert_eq!(
+            host("http://10.1.2.3:8080/api").as_deref(),
+            Some("10.1.2.3")
+        );
+        assert_eq!(
+            host("payments.internal").as_deref(),
+            Some("payments.internal")
+        );
+        assert_eq!(host("https://[::1]:443").as_deref(), Some("::1"));
+        assert_eq!(host("http://"), None);
+    }
+
     #[test]
     fn test_is_secure() {
         let secure_addresses = vec![
@@ -154,6 +207,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_matches() {
+        let address = ServiceAddress::String("http://10.1.2.3:8080".to_string());
+        assert_eq!(address.without_scheme(), "10.1.2.3:8080");
+
+        assert!(address.matches("10.1.2.3:8080", false));
+        assert!(address.matches("http://10.1.2.3:8080", false));
+        assert!(!address.matches("10.1.2.3", false));
+        assert!(address.matches("10.1.2.", true));
+        assert!(!address.matches("10.1.3.", true));
+
+        let address = ServiceAddress::String("localhost:8080".to_string());
+        assert_eq!(address.without_scheme(), "localhost:8080");
+    }
+
     #[test]
     fn test_serialize_deserialize() {
         let address = ServiceAddress::String("https://api.example.com:443".to_string());
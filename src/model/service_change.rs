@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::registry_event::{RegistryEvent, RegistryEventKind};
+use crate::model::service_registry::{HealthPolicy, HealthStatus, ServiceEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceChangeKind {
+    /// Silent for most of the time allowed before it becomes unhealthy
+    Expiring,
+    Unhealthy,
+    Deregistered,
+    Tombstoned,
+}
+
+/// Something that happened to an instance that is worth telling a team about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceChange {
+    pub kind: ServiceChangeKind,
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub instance_id: InstanceId,
+}
+
+impl ServiceChange {
+    pub fn summary(&self) -> String {
+        let what = match self.kind {
+            ServiceChangeKind::Expiring => "is about to become unhealthy for missing heartbeats",
+            ServiceChangeKind::Unhealthy => "became unhealthy",
+            ServiceChangeKind::Deregistered => "was deregistered",
+            ServiceChangeKind::Tombstoned => "was removed after it stopped heartbeating",
+        };
+        format!(
+            "Instance {} of {} in {} {}",
+            self.instance_id, self.service_name, self.environment, what
+        )
+    }
+}
+
+/// Turns registry events and health transitions into service changes, reporting each only once
+#[derive(Default)]
+pub struct ServiceChangeWatcher {
+    last_event_index: u64,
+    unhealthy: HashSet<InstanceId>,
+}
+
+impl ServiceChangeWatcher {
+    /// Index of the last event already reported
+    pub fn last_event_index(&self) -> u64 {
+        self.last_event_index
+    }
+
+    pub fn observe(
+        &mut self,
+        entries: &[ServiceEntry],
+        events: &[RegistryEvent],
+        policy: &HealthPolicy,
+        at: u64,
+    ) -> Vec<ServiceChange> {
+        let mut changes = Vec::new();
+
+        let unhealthy: HashSet<InstanceId> = entries
+            .iter()
+            .filter(|entry| entry.health_status(policy, at) == HealthStatus::Unhealthy)
+            .map(|entry| entry.id.clone())
+            .collect();
+        for entry in entries {
+            if unhealthy.contains(&entry.id) && !self.unhealthy.contains(&entry.id) {
+                changes.push(ServiceChange {
+                    kind: ServiceChangeKind::Unhealthy,
+                    service_name: entry.service_name.clone(),
+                    environment: entry.environment.clone(),
+                    instance_id: entry.id.clone(),
+                });
+            }
+        }
+        self.unhealthy = unhealthy;
+
+        for event in events {
+            let kind = match event.kind {
+                RegistryEventKind::Expiring => ServiceChangeKind::Expiring,
+                RegistryEventKind::Deregistered => ServiceChangeKind::Deregistered,
+                RegistryEventKind::Tombstoned => ServiceChangeKind::Tombstoned,
+                _ => continue,
+            };
+            changes.push(ServiceChange {
+                kind,
+                service_name: event.service_name.clone(),
+                environment: event.environment.clone(),
+                instance_id: event.instance_id.clone(),
+            });
+        }
+        if let Some(last) = events.last() {
+            self.last_event_index = self.last_event_index.max(last.index);
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+    use std::collections::HashMap;
+
+    fn create_entry() -> ServiceEntry {
+        ServiceEntry::new(
+            "checkout".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://localhost:8080".to_string(),
+            HashMap::new(),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_unhealthy_reported_once() {
+        let policy = HealthPolicy::default();
+        let mut watcher = ServiceChangeWatcher::default();
+        let mut entry = create_entry();
+        entry.last_heartbeat = 0;
+
+        let at = entry.registered_at;
+
+        let changes = watcher.observe(std::slice::from_ref(&entry), &[], &policy, at);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ServiceChangeKind::Unhealthy);
+
+        assert!(watcher.observe(&[entry], &[], &policy, at).is_empty());
+    }
+
+    #[test]
+    fn test_expiring_reported_before_unhealthy() {
+        let policy = HealthPolicy::default();
+        let mut watcher = ServiceChangeWatcher::default();
+        let entry = create_entry();
+        let at = entry.last_heartbeat;
+        let events = vec![RegistryEvent::new(
+            1,
+            RegistryEventKind::Expiring,
+            &entry,
+            entry.registered_at,
+        )];
+
+        let changes = watcher.observe(std::slice::from_ref(&entry), &events, &policy, at);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ServiceChangeKind::Expiring);
+
+        let changes = watcher.observe(&[entry], &[], &policy, at + policy.unhealthy_after);
+        assert_eq!(changes[0].kind, ServiceChangeKind::Unhealthy);
+    }
+
+    #[test]
+    fn test_removals_reported() {
+        let policy = HealthPolicy::default();
+        let mut watcher = ServiceChangeWatcher::default();
+        let entry = create_entry();
+        let events = vec![
+            RegistryEvent::new(
+                4,
+                RegistryEventKind::Registered,
+                &entry,
+                entry.registered_at,
+            ),
+            RegistryEvent::new(
+                5,
+                RegistryEventKind::Tombstoned,
+                &entry,
+                entry.registered_at,
+            ),
+        ];
+
+        let changes = watcher.observe(&[], &events, &policy, 0);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].summary(),
+            format!(
+                "Instance {} of checkout in prod was removed after it stopped heartbeating",
+                entry.id
+            )
+        );
+        assert_eq!(watcher.last_event_index(), 5);
+    }
+}
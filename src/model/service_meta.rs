@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Service level facts shared by every instance of a service in one environment
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceMeta {
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub repo_url: Option<String>,
+    #[serde(default)]
+    pub on_call: Option<String>,
+    #[serde(default)]
+    pub slo: Option<String>,
+    #[serde(default)]
+    pub updated_at: u64,
+}
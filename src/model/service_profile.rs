@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::gc_policy::GcPolicy;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{HealthThresholds, RegistryError};
+use crate::model::tag_schema::TagSchema;
+
+/// How a registration is handled when the service already has instances in the environment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstancePolicy {
+    /// Every registration adds an instance
+    #[default]
+    Multiple,
+    /// Registrations are rejected while an instance exists
+    Singleton,
+    /// A registration displaces the existing instances
+    Replace,
+}
+
+/// Who sees a service in listings, resolution is governed by intentions alone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Listed for every caller
+    #[default]
+    Public,
+    /// Listed for callers that identify themselves and for operators
+    Internal,
+    /// Listed only for the service itself, its owner and operators
+    Private,
+}
+
+/// Defaults applied to every instance registered under a service name, so
+/// platform teams can set standards once instead of in every client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceProfile {
+    /// Seconds an instance may go without a heartbeat before it is reported as stale
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+    /// Warmup used when the registration does not ask for one
+    #[serde(default)]
+    pub warmup_seconds: Option<u64>,
+    /// Tag keys every registration must carry, after defaults are applied
+    #[serde(default)]
+    pub required_tags: Vec<String>,
+    /// Tags added to registrations that do not set them
+    #[serde(default)]
+    pub default_tags: HashMap<String, String>,
+    /// Checked after the server wide schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_schema: Option<TagSchema>,
+    #[serde(default)]
+    pub instances: InstancePolicy,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// When instances are removed without deregistering
+    #[serde(default)]
+    pub gc: GcPolicy,
+    /// Hysteresis on the health of the service's instances
+    #[serde(default, skip_serializing_if = "HealthThresholds::is_default")]
+    pub health: HealthThresholds,
+    /// Healthy instances to keep in each environment, deregistrations and garbage
+    /// collection that would leave fewer are refused
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub min_instances: BTreeMap<Environment, usize>,
+    /// Seconds clients may cache resolution results, instead of `--cache-ttl`
+    #[serde(default)]
+    pub cache_ttl_seconds: Option<u64>,
+    /// Services this one calls, resolved together by `GET /routing-config`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<ServiceName>,
+    /// Rhai script choosing and ordering the instances resolutions return
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution_script: Option<String>,
+}
+
+impl ServiceProfile {
+    /// Fills in the default tags and checks that every required tag is present
+    pub fn apply_tags(&self, tags: &mut HashMap<String, String>) -> Result<(), RegistryError> {
+        for (key, value) in &self.default_tags {
+            tags.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        let missing: Vec<&str> = self
+            .required_tags
+            .iter()
+            .filter(|key| !tags.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(RegistryError::Validation(format!(
+                "Missing required tags: {}",
+                missing.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_profile() -> ServiceProfile {
+        ServiceProfile {
+            required_tags: vec!["team".to_string(), "tier".to_string()],
+            default_tags: HashMap::from([("tier".to_string(), "backend".to_string())]),
+            ..ServiceProfile::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_tags_fills_defaults() {
+        let mut tags = HashMap::from([("team".to_string(), "payments".to_string())]);
+
+        create_profile().apply_tags(&mut tags).unwrap();
+
+        assert_eq!(tags["tier"], "backend");
+    }
+
+    #[test]
+    fn test_apply_tags_keeps_explicit_values() {
+        let mut tags = HashMap::from([
+            ("team".to_string(), "payments".to_string()),
+            ("tier".to_string(), "edge".to_string()),
+        ]);
+
+        create_profile().apply_tags(&mut tags).unwrap();
+
+        assert_eq!(tags["tier"], "edge");
+    }
+
+    #[test]
+    fn test_apply_tags_rejects_missing_required() {
+        let mut tags = HashMap::new();
+
+        let error = create_profile().apply_tags(&mut tags).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Validation failed: Missing required tags: team"
+        );
+    }
+}
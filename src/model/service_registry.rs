@@ -1,18 +1,60 @@
+use crate::model::annotation::Annotation;
+use crate::model::entry_source::EntrySource;
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::instance_state::InstanceState;
+use crate::model::registry_event::RegistryEvent;
 use crate::model::service_address::ServiceAddress;
+use crate::model::spiffe::SpiffeId;
+use crate::model::tag_selector::TagSelectors;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use uuid::Uuid;
+use tokio::sync::{RwLock, RwLockReadGuard};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceEntry {
-    pub id: String,
-    pub service_name: String,
-    pub environment: String,
+    pub id: InstanceId,
+    pub service_name: ServiceName,
+    pub environment: Environment,
     pub address: ServiceAddress,
     pub tags: HashMap<String, String>,
     pub registered_at: u64,
     pub last_heartbeat: u64,
+    /// Set when the entry was recreated from a heartbeat instead of a registration
+    #[serde(default)]
+    pub recovered: bool,
+    #[serde(default)]
+    pub state: InstanceState,
+    /// While `Starting`, the time in millis after which the instance counts as `Up`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup_until: Option<u64>,
+    /// Heartbeat interval this instance is held to instead of the server wide policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+    /// Failover tier, resolution only returns the lowest tier with a healthy instance
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub source: EntrySource,
+    /// Hysteresis this instance is held to on top of the heartbeat age thresholds
+    #[serde(default, skip_serializing_if = "HealthThresholds::is_default")]
+    pub health_thresholds: HealthThresholds,
+    /// Times of the latest heartbeats, kept as far back as the success threshold looks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_heartbeats: Vec<u64>,
+    /// Operational notes left by operators, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// URL the active checker probes for an external entry, each success counting as a heartbeat
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<String>,
+    /// Identity of the workload, from the SVID it registered with over mTLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spiffe_id: Option<SpiffeId>,
+    /// Credential the instance was registered with, the only one whose agent may hold it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registered_by: Option<String>,
 }
 
 pub fn now() -> u64 {
@@ -23,15 +65,15 @@ pub fn now() -> u64 {
 }
 
 impl ServiceEntry {
-    /// Creates a new ServiceEntry with auto-generated UUID and timestamp
+    /// Creates a new ServiceEntry with auto-generated UUID, registered at time `registered_at`
     pub fn new(
-        service_name: String,
-        environment: String,
+        service_name: ServiceName,
+        environment: Environment,
         address: String,
         tags: HashMap<String, String>,
+        registered_at: u64,
     ) -> Self {
-        let id = Uuid::new_v4().to_string();
-        let registered_at = now();
+        let id = InstanceId::generate();
 
         ServiceEntry {
             id,
@@ -41,28 +83,243 @@ impl ServiceEntry {
             tags,
             registered_at,
             last_heartbeat: registered_at, // This is a new entry so let's set heartbeat to the creation time
+            recovered: false,
+            state: InstanceState::Up,
+            warmup_until: None,
+            ttl_seconds: None,
+            priority: 0,
+            source: EntrySource::Registered,
+            health_thresholds: HealthThresholds::default(),
+            recent_heartbeats: Vec::new(),
+            annotations: Vec::new(),
+            health_check: None,
+            spiffe_id: None,
+            registered_by: None,
         }
     }
 
+    /// Keeps the instance in `Starting` for `seconds` before it becomes visible to resolution
+    pub fn with_warmup(mut self, seconds: u64) -> Self {
+        self.state = InstanceState::Starting;
+        self.warmup_until = Some(
+            self.registered_at
+                .saturating_add(seconds.saturating_mul(1000)),
+        );
+        self
+    }
+
+    /// Sets the registration and heartbeat time, for entries created on a simulated clock
+    pub fn at(mut self, timestamp: u64) -> Self {
+        self.registered_at = timestamp;
+        self.last_heartbeat = timestamp;
+        self
+    }
+
+    /// Records a heartbeat received at time `at`
+    pub fn record_heartbeat(&mut self, at: u64) {
+        self.last_heartbeat = at;
+        let kept = self.health_thresholds.success_threshold.unwrap_or(1) as usize;
+        if kept > 1 {
+            self.recent_heartbeats.push(at);
+            let excess = self.recent_heartbeats.len().saturating_sub(kept);
+            self.recent_heartbeats.drain(..excess);
+        }
+    }
+
+    /// Whether the last `count` heartbeats, counting the registration as the
+    /// first, each came within `interval` of the one before
+    fn is_steady(&self, count: usize, interval: u64) -> bool {
+        let beats: Vec<u64> = std::iter::once(self.registered_at)
+            .chain(self.recent_heartbeats.iter().copied())
+            .collect();
+        let recent = &beats[beats.len().saturating_sub(count)..];
+        recent.len() == count && recent.windows(2).all(|pair| pair[1] - pair[0] < interval)
+    }
+
+    /// Tags derived from the entry's fields rather than set by the instance:
+    /// `scheme` and `port` when the address has them, and `secure`
+    pub fn computed_tags(&self) -> HashMap<String, String> {
+        let mut computed =
+            HashMap::from([("secure".to_string(), self.address.is_secure().to_string())]);
+        if let Some(scheme) = self.address.scheme() {
+            computed.insert("scheme".to_string(), scheme.to_string());
+        }
+        if let Some(port) = self.address.extract_port() {
+            computed.insert("port".to_string(), port.to_string());
+        }
+        computed
+    }
+
+    /// Adds the `defaults` the instance does not set itself
+    pub fn inherit_tags(&mut self, defaults: &HashMap<String, String>) {
+        for (key, value) in defaults {
+            self.tags
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Returns the lifecycle state at time `at`, treating an instance whose warmup has elapsed as `Up`
+    pub fn current_state(&self, at: u64) -> InstanceState {
+        match (self.state, self.warmup_until) {
+            (InstanceState::Starting, Some(until)) if at >= until => InstanceState::Up,
+            (state, _) => state,
+        }
+    }
+
+    /// Whether the instance can take traffic for failover purposes at time `at`
+    pub fn is_available(&self, policy: &HealthPolicy, at: u64) -> bool {
+        matches!(
+            self.health_status(policy, at),
+            HealthStatus::Healthy | HealthStatus::Unknown
+        )
+    }
+
     /// Returns the address as a string reference
     pub fn address_str(&self) -> &str {
         self.address.as_str()
     }
 
-    #[allow(dead_code)]
-    pub fn health_status(&self) -> HealthStatus {
-        // TODO: Think about if this should be dynamic and how it can use env variables to determine health
-        HealthStatus::Unknown
+    /// Whether the health of the entry follows its heartbeats, either sent by the
+    /// instance or recorded by the active checker for a checked external entry
+    pub fn tracks_health(&self) -> bool {
+        self.source.expects_heartbeats() || self.health_check.is_some()
     }
 
-    /// Returns the time elapsed since the last heartbeat in millis
-    #[allow(dead_code)]
-    pub fn time_since_last_heartbeat(&self) -> u64 {
-        now() - self.last_heartbeat
+    /// Derives the health of the instance at time `at` from the age of its last heartbeat,
+    /// entries that never heartbeat are always healthy
+    pub fn health_status(&self, policy: &HealthPolicy, at: u64) -> HealthStatus {
+        if !self.tracks_health() {
+            return HealthStatus::Healthy;
+        }
+        let policy = self.effective_policy(policy);
+        let elapsed = self.time_since_last_heartbeat(at);
+
+        if elapsed >= policy.unhealthy_after {
+            HealthStatus::Unhealthy
+        } else if elapsed >= policy.stale_after {
+            HealthStatus::Stale
+        } else if self.last_heartbeat == self.registered_at {
+            HealthStatus::Unknown
+        } else if let Some(required) = self.health_thresholds.success_threshold
+            && !self.is_steady(required as usize, policy.stale_after)
+        {
+            // Coming back, but not for long enough to be trusted with traffic
+            HealthStatus::Stale
+        } else {
+            HealthStatus::Healthy
+        }
+    }
+
+    /// The server wide policy adjusted by the TTL and thresholds of the instance
+    fn effective_policy(&self, policy: &HealthPolicy) -> HealthPolicy {
+        policy
+            .with_ttl(self.ttl_seconds)
+            .with_failure_threshold(self.health_thresholds.failure_threshold)
+    }
+
+    /// Percentage of the silence allowed before the instance is `Unhealthy` that
+    /// has passed at time `at`, 0 for entries that never heartbeat
+    pub fn silence_percent(&self, policy: &HealthPolicy, at: u64) -> u64 {
+        if !self.tracks_health() {
+            return 0;
+        }
+        let unhealthy_after = self.effective_policy(policy).unhealthy_after.max(1);
+        self.time_since_last_heartbeat(at) * 100 / unhealthy_after
+    }
+
+    /// Returns the time elapsed between the last heartbeat and `at` in millis
+    pub fn time_since_last_heartbeat(&self, at: u64) -> u64 {
+        at.saturating_sub(self.last_heartbeat)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Heartbeat age thresholds, in millis, used to derive an instance's health
+#[derive(Debug, Clone, Copy)]
+pub struct HealthPolicy {
+    pub stale_after: u64,
+    pub unhealthy_after: u64,
+}
+
+impl HealthPolicy {
+    /// Returns the policy for an instance with its own TTL, keeping the same
+    /// ratio between the stale and unhealthy thresholds
+    pub fn with_ttl(self, ttl_seconds: Option<u64>) -> Self {
+        match ttl_seconds {
+            Some(ttl) if self.stale_after > 0 => {
+                let stale_after = ttl.saturating_mul(1000);
+                let unhealthy_after =
+                    stale_after as u128 * self.unhealthy_after as u128 / self.stale_after as u128;
+                HealthPolicy {
+                    stale_after,
+                    unhealthy_after: u64::try_from(unhealthy_after).unwrap_or(u64::MAX),
+                }
+            }
+            _ => self,
+        }
+    }
+
+    /// Returns the policy for an instance that turns unhealthy after missing
+    /// `intervals` heartbeat intervals instead of the server wide ratio
+    pub fn with_failure_threshold(self, intervals: Option<u32>) -> Self {
+        match intervals {
+            Some(intervals) if intervals > 0 => HealthPolicy {
+                unhealthy_after: self.stale_after.saturating_mul(intervals as u64),
+                ..self
+            },
+            _ => self,
+        }
+    }
+}
+
+/// Per-service hysteresis on the health derived from heartbeats, so flapping
+/// instances are not handed out and sensitive services fail fast
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthThresholds {
+    /// Consecutive heartbeats, each within the heartbeat interval and counting
+    /// the registration, needed before an instance is `Healthy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success_threshold: Option<u32>,
+    /// Heartbeat intervals an instance may miss before it is `Unhealthy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_threshold: Option<u32>,
+}
+
+impl HealthThresholds {
+    /// Most heartbeats a success threshold may ask for, bounding what every instance keeps
+    pub const MAX_SUCCESS_THRESHOLD: u32 = 10;
+
+    pub fn is_default(&self) -> bool {
+        *self == HealthThresholds::default()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .success_threshold
+            .is_some_and(|n| !(1..=Self::MAX_SUCCESS_THRESHOLD).contains(&n))
+        {
+            return Err(format!(
+                "success_threshold must be between 1 and {}",
+                Self::MAX_SUCCESS_THRESHOLD
+            ));
+        }
+        if self.failure_threshold == Some(0) {
+            return Err("failure_threshold must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        HealthPolicy {
+            stale_after: 30_000,
+            unhealthy_after: 90_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HealthStatus {
     Healthy,
     Unknown,   // Maybe just registered without heartbeat
@@ -70,26 +327,189 @@ pub enum HealthStatus {
     Unhealthy, // No heartbeat and will be cleaned up
 }
 
-pub trait ServiceRegistry: Sync + Send + 'static {
+/// Period after startup during which heartbeats for unknown instances recreate them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecoveryWindow {
+    pub until: Option<u64>,
+}
+
+impl RecoveryWindow {
+    /// Opens a window lasting `grace` millis from `at`
+    pub fn open_for(grace: u64, at: u64) -> Self {
+        RecoveryWindow {
+            until: Some(at.saturating_add(grace)),
+        }
+    }
+
+    pub fn is_open(&self, at: u64) -> bool {
+        self.until.is_some_and(|until| at < until)
+    }
+}
+
+/// Keeps the instances of the lowest priority tier that has an available
+/// instance at time `at`, or of the lowest tier when none is available
+pub fn failover_tier(
+    entries: Vec<ServiceEntry>,
+    policy: &HealthPolicy,
+    at: u64,
+) -> Vec<ServiceEntry> {
+    let tier = entries
+        .iter()
+        .filter(|entry| entry.is_available(policy, at))
+        .map(|entry| entry.priority)
+        .min()
+        .or_else(|| entries.iter().map(|entry| entry.priority).min());
+
+    entries
+        .into_iter()
+        .filter(|entry| Some(entry.priority) == tier)
+        .collect()
+}
+
+/// Read side of a registry backend, enough to serve lookups
+pub trait RegistryReader: Sync + Send + 'static {
     fn list(&self) -> Vec<ServiceEntry>;
+    /// The instance with id `id`. Backends keyed by id should override this full scan
+    fn get(&self, id: &InstanceId) -> Option<ServiceEntry> {
+        self.list().into_iter().find(|entry| &entry.id == id)
+    }
+    fn resolve(&self, service_name: &ServiceName, environment: &Environment) -> Vec<ServiceEntry>;
+    /// Returns the retained events with an index greater than `since`, oldest first
+    fn events(&self, since: u64) -> Vec<RegistryEvent>;
+    /// Instances as they were right after the change numbered `index`, or nothing
+    /// when that state is older than the history kept
+    fn list_at(&self, index: u64) -> Option<Vec<ServiceEntry>>;
+    /// Index of the most recent event, 0 before the first change
+    fn last_index(&self) -> u64;
+    /// Index of the most recent event that changed `service_name`, 0 if it never changed
+    fn modify_index(&self, service_name: &ServiceName) -> u64;
+    /// Instances carrying every selected tag. Backends with a tag index should
+    /// override this full scan
+    fn find_by_tags(&self, selectors: &TagSelectors) -> Vec<ServiceEntry> {
+        self.list()
+            .into_iter()
+            .filter(|entry| selectors.matches(&entry.tags))
+            .collect()
+    }
+}
+
+/// Write side of a registry backend
+pub trait RegistryWriter: Sync + Send + 'static {
     fn register(&mut self, entry: ServiceEntry) -> Result<(), RegistryError>;
-    fn resolve(&self, service_name: &str, environment: &str) -> Vec<ServiceEntry>;
     fn deregister(
         &mut self,
-        service_name: &str,
-        environment: Option<&str>,
+        service_name: &ServiceName,
+        environment: Option<&Environment>,
+    ) -> Result<(), RegistryError>;
+    fn deregister_instance(&mut self, id: &InstanceId) -> Result<(), RegistryError>;
+    /// Removes a service in an environment that stopped heartbeating, recorded apart from deregistrations
+    fn tombstone(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Result<(), RegistryError>;
+    fn heartbeat(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
     ) -> Result<(), RegistryError>;
-    fn heartbeat(&mut self, service_name: &str, environment: &str) -> Result<(), RegistryError>;
+    fn heartbeat_instance(&mut self, id: &InstanceId) -> Result<(), RegistryError>;
+    /// Moves an instance to a new lifecycle state, rejecting invalid transitions with a conflict
+    fn set_state(
+        &mut self,
+        id: &InstanceId,
+        state: InstanceState,
+    ) -> Result<ServiceEntry, RegistryError>;
+    /// Attaches an operational note to an instance
+    fn annotate(
+        &mut self,
+        id: &InstanceId,
+        annotation: Annotation,
+    ) -> Result<ServiceEntry, RegistryError>;
+    /// Records that an instance has been silent for `percent` of the time it may
+    /// miss heartbeats before it becomes unhealthy
+    fn mark_expiring(&mut self, id: &InstanceId, percent: u64) -> Result<(), RegistryError>;
+    /// Replaces the instances of a service in `to` with copies of those in `from`,
+    /// removing the originals when `remove_source` is set
+    fn promote(
+        &mut self,
+        service_name: &ServiceName,
+        from: &Environment,
+        to: &Environment,
+        remove_source: bool,
+    ) -> Result<Vec<ServiceEntry>, RegistryError>;
+}
+
+/// A fully mutable registry backend, implemented by every backend that is both a reader and a writer
+pub trait ServiceRegistry: RegistryReader + RegistryWriter {}
+
+impl<T: RegistryReader + RegistryWriter> ServiceRegistry for T {}
+
+/// Handle that only exposes the read side of a shared registry
+#[derive(Clone)]
+pub struct RegistryReadHandle(Arc<RwLock<dyn ServiceRegistry>>);
+
+impl RegistryReadHandle {
+    pub fn new(registry: Arc<RwLock<dyn ServiceRegistry>>) -> Self {
+        RegistryReadHandle(registry)
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, dyn RegistryReader> {
+        RwLockReadGuard::map(self.0.read().await, |registry| {
+            registry as &dyn RegistryReader
+        })
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
 pub enum RegistryError {
+    #[error("Instance already exists")]
     AlreadyExists,
+    #[error("Not found")]
     NotFound,
+    #[error("Validation failed: {0}")]
+    Validation(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[allow(dead_code)]
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[allow(dead_code)]
+    #[error("Storage unavailable: {0}")]
+    StorageUnavailable(String),
+    #[allow(dead_code)]
+    #[error("Operation timed out")]
+    Timeout,
+    #[error("Overloaded: {0}")]
+    Overloaded(String),
+    #[error("Read-only: {0}")]
+    ReadOnly(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
     #[allow(dead_code)]
+    #[error("Internal error: {0}")]
     InternalError(String),
 }
 
+impl RegistryError {
+    /// Machine readable code identifying the kind of error in API responses
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            RegistryError::AlreadyExists => "already_exists",
+            RegistryError::NotFound => "not_found",
+            RegistryError::Validation(_) => "validation_failed",
+            RegistryError::Conflict(_) => "conflict",
+            RegistryError::QuotaExceeded(_) => "quota_exceeded",
+            RegistryError::StorageUnavailable(_) => "storage_unavailable",
+            RegistryError::Timeout => "timeout",
+            RegistryError::Overloaded(_) => "overloaded",
+            RegistryError::ReadOnly(_) => "read_only",
+            RegistryError::Unauthorized(_) => "unauthorized",
+            RegistryError::InternalError(_) => "internal_error",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,10 +522,11 @@ mod tests {
         tags.insert("version".to_string(), "v1".to_string());
 
         let entry = ServiceEntry::new(
-            "my-service".to_string(),
-            "production".to_string(),
+            "my-service".parse().unwrap(),
+            "production".parse().unwrap(),
             "https://api.example.com:443".to_string(),
             tags.clone(),
+            now(),
         );
 
         // Check fields are properly set
@@ -115,7 +536,10 @@ mod tests {
         assert_eq!(entry.address_str(), "https://api.example.com:443");
         assert_eq!(entry.tags, tags);
         assert!(entry.registered_at > 0); // Timestamp should be set
-        assert!(matches!(entry.health_status(), HealthStatus::Unknown));
+        assert_eq!(
+            entry.health_status(&HealthPolicy::default(), entry.registered_at),
+            HealthStatus::Unknown
+        );
         assert_eq!(entry.last_heartbeat, entry.registered_at); // Last heartbeat should be equal to the creation time
 
         // Check that we're using millisecond precision (timestamp should be much larger than a seconds-based one)
@@ -131,16 +555,225 @@ mod tests {
         tags.insert("type".to_string(), "api".to_string());
 
         let entry = ServiceEntry::new(
-            "my-service".to_string(),
-            "production".to_string(),
+            "my-service".parse().unwrap(),
+            "production".parse().unwrap(),
             "https://api.example.com:443".to_string(),
             tags,
+            now(),
         );
 
         assert_eq!(entry.address_str(), "https://api.example.com:443");
         assert_eq!(entry.address_str(), entry.address.as_str());
     }
 
+    #[test]
+    fn test_health_status_thresholds() {
+        let policy = HealthPolicy::default();
+        let mut entry = ServiceEntry::new(
+            "my-service".parse().unwrap(),
+            "production".parse().unwrap(),
+            "https://api.example.com:443".to_string(),
+            HashMap::new(),
+            now(),
+        );
+
+        let at = entry.registered_at + 1;
+        entry.last_heartbeat = at;
+        assert_eq!(entry.health_status(&policy, at), HealthStatus::Healthy);
+        assert_eq!(
+            entry.health_status(&policy, at + policy.stale_after),
+            HealthStatus::Stale
+        );
+        assert_eq!(
+            entry.health_status(&policy, at + policy.unhealthy_after),
+            HealthStatus::Unhealthy
+        );
+    }
+
+    #[test]
+    fn test_health_policy_with_ttl() {
+        let policy = HealthPolicy::default().with_ttl(Some(10));
+        assert_eq!(policy.stale_after, 10_000);
+        assert_eq!(policy.unhealthy_after, 30_000);
+
+        let policy = HealthPolicy::default().with_ttl(None);
+        assert_eq!(policy.stale_after, 30_000);
+
+        // TTLs too long to count in millis saturate instead of overflowing
+        let policy = HealthPolicy::default().with_ttl(Some(u64::MAX));
+        assert_eq!(policy.stale_after, u64::MAX);
+        assert_eq!(policy.unhealthy_after, u64::MAX);
+    }
+
+    #[test]
+    fn test_warmup_elapses() {
+        let entry = ServiceEntry::new(
+            "my-service".parse().unwrap(),
+            "production".parse().unwrap(),
+            "https://api.example.com:443".to_string(),
+            HashMap::new(),
+            now(),
+        );
+
+        let warming = entry.with_warmup(60);
+        assert_eq!(warming.state, InstanceState::Starting);
+        assert_eq!(
+            warming.current_state(warming.registered_at + 59_999),
+            InstanceState::Starting
+        );
+        assert_eq!(
+            warming.current_state(warming.registered_at + 60_000),
+            InstanceState::Up
+        );
+    }
+
+    #[test]
+    fn test_computed_tags() {
+        let entry = |address: &str| {
+            ServiceEntry::new(
+                "my-service".parse().unwrap(),
+                "production".parse().unwrap(),
+                address.to_string(),
+                HashMap::new(),
+                now(),
+            )
+        };
+
+        let computed = entry("https://api.example.com:8443/v1").computed_tags();
+        assert_eq!(computed["scheme"], "https");
+        assert_eq!(computed["port"], "8443");
+        assert_eq!(computed["secure"], "true");
+
+        let computed = entry("db.internal").computed_tags();
+        assert_eq!(computed.len(), 1);
+        assert_eq!(computed["secure"], "false");
+    }
+
+    #[test]
+    fn test_health_thresholds() {
+        let policy = HealthPolicy::default();
+        let mut entry = ServiceEntry::new(
+            "my-service".parse().unwrap(),
+            "production".parse().unwrap(),
+            "https://api.example.com:443".to_string(),
+            HashMap::new(),
+            0,
+        );
+        entry.health_thresholds = HealthThresholds {
+            success_threshold: Some(3),
+            failure_threshold: Some(2),
+        };
+
+        entry.record_heartbeat(10_000);
+        assert_eq!(entry.health_status(&policy, 10_000), HealthStatus::Stale);
+        entry.record_heartbeat(20_000);
+        assert_eq!(entry.health_status(&policy, 20_000), HealthStatus::Healthy);
+        assert_eq!(
+            entry.health_status(&policy, 80_000),
+            HealthStatus::Unhealthy
+        );
+
+        // Back after an outage, it takes three steady heartbeats to be trusted again
+        entry.record_heartbeat(100_000);
+        entry.record_heartbeat(110_000);
+        assert_eq!(entry.health_status(&policy, 110_000), HealthStatus::Stale);
+        entry.record_heartbeat(120_000);
+        assert_eq!(entry.health_status(&policy, 120_000), HealthStatus::Healthy);
+        assert_eq!(entry.recent_heartbeats, vec![100_000, 110_000, 120_000]);
+
+        assert!(
+            HealthThresholds {
+                success_threshold: Some(11),
+                failure_threshold: None
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_failover_tier() {
+        let policy = HealthPolicy::default();
+        let entry = |priority: u32, last_heartbeat: u64| ServiceEntry {
+            priority,
+            registered_at: 0,
+            last_heartbeat,
+            ..ServiceEntry::new(
+                "my-service".parse().unwrap(),
+                "production".parse().unwrap(),
+                "https://api.example.com:443".to_string(),
+                HashMap::new(),
+                now(),
+            )
+        };
+        let at = 100_000;
+
+        let tier = failover_tier(vec![entry(1, at), entry(0, at), entry(0, 0)], &policy, at);
+        assert_eq!(tier.len(), 2);
+        assert!(tier.iter().all(|entry| entry.priority == 0));
+
+        let tier = failover_tier(vec![entry(1, at), entry(0, 0)], &policy, at);
+        assert_eq!(tier.len(), 1);
+        assert_eq!(tier[0].priority, 1);
+
+        let tier = failover_tier(vec![entry(2, 0), entry(1, 0)], &policy, at);
+        assert_eq!(tier[0].priority, 1);
+    }
+
+    #[test]
+    fn test_recovery_window() {
+        assert!(!RecoveryWindow::default().is_open(0));
+        let window = RecoveryWindow::open_for(60_000, 1_000);
+        assert!(window.is_open(60_999));
+        assert!(!window.is_open(61_000));
+        assert!(RecoveryWindow::open_for(u64::MAX, 1_000).is_open(u64::MAX - 1));
+    }
+
+    #[tokio::test]
+    async fn test_registry_read_handle() {
+        let registry = Arc::new(RwLock::new(
+            crate::registry::in_memory_registry::InMemoryRegistry::new(),
+        ));
+        registry
+            .write()
+            .await
+            .register(ServiceEntry::new(
+                "my-service".parse().unwrap(),
+                "production".parse().unwrap(),
+                "https://api.example.com:443".to_string(),
+                HashMap::new(),
+                now(),
+            ))
+            .unwrap();
+
+        let handle = RegistryReadHandle::new(registry);
+        assert_eq!(
+            handle
+                .read()
+                .await
+                .resolve(
+                    &"my-service".parse().unwrap(),
+                    &"production".parse().unwrap()
+                )
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_registry_error_codes() {
+        assert_eq!(RegistryError::NotFound.error_code(), "not_found");
+        assert_eq!(
+            RegistryError::QuotaExceeded("too many instances".to_string()).to_string(),
+            "Quota exceeded: too many instances"
+        );
+        assert_eq!(RegistryError::Timeout.error_code(), "timeout");
+        assert_eq!(
+            RegistryError::Overloaded("busy".to_string()).error_code(),
+            "overloaded"
+        );
+    }
+
     #[test]
     fn test_registry_error_internal_error() {
         let error = RegistryError::InternalError("Database connection failed".to_string());
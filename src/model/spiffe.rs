@@ -0,0 +1,155 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const SCHEME: &str = "spiffe://";
+
+/// SPIFFE identity of a workload, `spiffe://<trust domain>/<path>`, as found
+/// in the URI SAN of its X.509 SVID
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SpiffeId {
+    id: String,
+    /// Length of the trust domain, which follows the scheme
+    trust_domain_len: usize,
+}
+
+impl SpiffeId {
+    pub fn trust_domain(&self) -> &str {
+        &self.id[SCHEME.len()..SCHEME.len() + self.trust_domain_len]
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// The identity a terminating proxy forwards for the client certificate,
+    /// either a bare SPIFFE ID or an Envoy style `x-forwarded-client-cert`
+    /// value, whose last element describes the certificate of the direct client
+    pub fn from_forwarded(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.starts_with(SCHEME) {
+            return value.parse();
+        }
+        let elements = split_unquoted(value, ',');
+        let client = elements.last().copied().unwrap_or_default();
+        split_unquoted(client, ';')
+            .into_iter()
+            .filter_map(|field| field.trim().split_once('='))
+            .find(|(key, _)| key.eq_ignore_ascii_case("uri"))
+            .ok_or_else(|| "The client certificate carries no SPIFFE ID".to_string())?
+            .1
+            .trim_matches('"')
+            .parse()
+    }
+}
+
+/// Splits on `separator` outside of double quotes, as in the quoted subject of a certificate
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+impl FromStr for SpiffeId {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("Invalid SPIFFE ID '{}': {}", value, reason);
+        let rest = value
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| invalid("must start with spiffe://"))?;
+        let trust_domain = rest.split('/').next().unwrap_or_default();
+        if trust_domain.is_empty()
+            || !trust_domain
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._".contains(c))
+        {
+            return Err(invalid(
+                "the trust domain must be lowercase letters, digits, '-', '.' or '_'",
+            ));
+        }
+        if rest.ends_with('/') || rest.contains("//") || rest.contains(['?', '#']) {
+            return Err(invalid(
+                "the path must not have empty segments, a query or a fragment",
+            ));
+        }
+        Ok(SpiffeId {
+            id: value.to_string(),
+            trust_domain_len: trust_domain.len(),
+        })
+    }
+}
+
+impl fmt::Display for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+impl Serialize for SpiffeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.id)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpiffeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let id: SpiffeId = "spiffe://prod.example.org/ns/payments/sa/api"
+            .parse()
+            .unwrap();
+        assert_eq!(id.trust_domain(), "prod.example.org");
+        assert_eq!(
+            "spiffe://example.org"
+                .parse::<SpiffeId>()
+                .unwrap()
+                .trust_domain(),
+            "example.org"
+        );
+        assert!("https://example.org/api".parse::<SpiffeId>().is_err());
+        assert!("spiffe://Example.org/api".parse::<SpiffeId>().is_err());
+        assert!("spiffe:///api".parse::<SpiffeId>().is_err());
+        assert!("spiffe://example.org/api/".parse::<SpiffeId>().is_err());
+    }
+
+    #[test]
+    fn test_from_forwarded() {
+        let forwarded = "By=spiffe://example.org/envoy;Hash=ab12,\
+            By=spiffe://example.org/envoy;Hash=cd34;Subject=\"CN=sensor,O=Edge\";URI=spiffe://edge.example.org/sensor";
+        assert_eq!(
+            SpiffeId::from_forwarded(forwarded).unwrap().as_str(),
+            "spiffe://edge.example.org/sensor"
+        );
+        assert_eq!(
+            SpiffeId::from_forwarded(" spiffe://example.org/api ")
+                .unwrap()
+                .trust_domain(),
+            "example.org"
+        );
+        assert!(SpiffeId::from_forwarded("Hash=ab12").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::ServiceEntry;
+
+/// A service whose instances in an environment have all been silent past a threshold
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleService {
+    pub service_name: ServiceName,
+    pub environment: Environment,
+    pub instances: usize,
+    pub last_heartbeat: u64,
+}
+
+/// Groups entries by service and environment and keeps the groups whose most
+/// recent heartbeat is at least `older_than` millis old at time `at`
+pub fn find_stale(entries: &[ServiceEntry], older_than: u64, at: u64) -> Vec<StaleService> {
+    let mut groups: BTreeMap<(&ServiceName, &Environment), (usize, u64)> = BTreeMap::new();
+    for entry in entries {
+        let group = groups
+            .entry((&entry.service_name, &entry.environment))
+            .or_default();
+        group.0 += 1;
+        // Entries that never heartbeat keep their whole service alive
+        let last_heartbeat = if entry.source.expects_heartbeats() {
+            entry.last_heartbeat
+        } else {
+            at
+        };
+        group.1 = group.1.max(last_heartbeat);
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, (_, last_heartbeat))| at.saturating_sub(*last_heartbeat) >= older_than)
+        .map(
+            |((service_name, environment), (instances, last_heartbeat))| StaleService {
+                service_name: service_name.clone(),
+                environment: environment.clone(),
+                instances,
+                last_heartbeat,
+            },
+        )
+        .collect()
+}
+
+/// Parses an age such as `90s`, `15m`, `12h` or `7d` into millis
+pub fn parse_age(value: &str) -> Result<u64, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid age '{}'", value))?;
+
+    let unit_millis = match unit {
+        "s" | "" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => {
+            return Err(format!(
+                "Invalid age unit in '{}', expected s, m, h or d",
+                value
+            ));
+        }
+    };
+
+    amount
+        .checked_mul(unit_millis)
+        .ok_or_else(|| format!("Age '{}' is too long", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::entry_source::EntrySource;
+    use crate::model::service_registry::now;
+    use std::collections::HashMap;
+
+    fn create_entry(name: &str, env: &str, age: u64) -> ServiceEntry {
+        let mut entry = ServiceEntry::new(
+            name.parse().unwrap(),
+            env.parse().unwrap(),
+            "http://localhost:8080".to_string(),
+            HashMap::new(),
+            now(),
+        );
+        entry.last_heartbeat = 100_000 - age;
+        entry
+    }
+
+    #[test]
+    fn test_find_stale_requires_every_instance() {
+        let entries = vec![
+            create_entry("dead", "prod", 10_000),
+            create_entry("dead", "prod", 20_000),
+            create_entry("mixed", "prod", 10_000),
+            create_entry("mixed", "prod", 0),
+        ];
+
+        let stale = find_stale(&entries, 5_000, 100_000);
+
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].service_name, "dead");
+        assert_eq!(stale[0].instances, 2);
+        assert_eq!(stale[0].last_heartbeat, entries[0].last_heartbeat);
+    }
+
+    #[test]
+    fn test_find_stale_skips_static_entries() {
+        let mut seeded = create_entry("database", "prod", 50_000);
+        seeded.source = EntrySource::Static;
+        let entries = vec![seeded, create_entry("database", "prod", 50_000)];
+
+        assert!(find_stale(&entries, 5_000, 100_000).is_empty());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90s"), Ok(90_000));
+        assert_eq!(parse_age("15m"), Ok(900_000));
+        assert_eq!(parse_age("12h"), Ok(43_200_000));
+        assert_eq!(parse_age("7d"), Ok(604_800_000));
+        assert_eq!(parse_age("30"), Ok(30_000));
+        assert!(parse_age("7w").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("18446744073709551615d").is_err());
+    }
+}
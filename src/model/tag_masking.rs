@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// Value shown in place of a masked tag
+pub const MASKED_VALUE: &str = "********";
+
+/// Tags, such as connection strings, that are stored as registered but
+/// redacted in discovery responses for callers without the `unmask` permission
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TagMasking {
+    /// Tag keys whose values are redacted
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Callers, by the names in `callers`, allowed to see masked values
+    #[serde(default)]
+    pub unmask: Vec<String>,
+}
+
+impl TagMasking {
+    /// Whether `caller` holds the `unmask` permission
+    pub fn may_unmask(&self, caller: &str) -> bool {
+        self.unmask.iter().any(|allowed| allowed == caller)
+    }
+
+    /// Replaces the values of masked tags in `tags`
+    pub fn mask(&self, tags: &mut HashMap<String, String>) {
+        for key in &self.tags {
+            if let Some(value) = tags.get_mut(key) {
+                *value = MASKED_VALUE.to_string();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask() {
+        let masking: TagMasking = serde_yaml::from_str(
+            r#"
+tags: [connection_string, api_key]
+unmask: [billing]
+"#,
+        )
+        .unwrap();
+        let mut tags = HashMap::from([
+            (
+                "connection_string".to_string(),
+                "postgres://app:secret@db".to_string(),
+            ),
+            ("region".to_string(), "us-east-1".to_string()),
+        ]);
+
+        masking.mask(&mut tags);
+
+        assert_eq!(tags["connection_string"], MASKED_VALUE);
+        assert_eq!(tags["region"], "us-east-1");
+        assert!(!tags.contains_key("api_key"));
+        assert!(masking.may_unmask("billing"));
+        assert!(!masking.may_unmask("search"));
+    }
+}
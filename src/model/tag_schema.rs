@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::model::service_registry::RegistryError;
+
+/// Regular expression a tag value has to match, kept as its source text when serialized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TagPattern(Regex);
+
+impl TryFrom<String> for TagPattern {
+    type Error = regex::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value).map(TagPattern)
+    }
+}
+
+impl From<TagPattern> for String {
+    fn from(pattern: TagPattern) -> Self {
+        pattern.0.as_str().to_string()
+    }
+}
+
+impl PartialEq for TagPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl fmt::Display for TagPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.as_str())
+    }
+}
+
+/// Constraints on a single tag key
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagRule {
+    #[serde(default)]
+    pub required: bool,
+    /// When not empty, the only values the tag may take
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_values: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<TagPattern>,
+}
+
+/// Expected shape of instance tags, keyed by tag name. Tags without a rule are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagSchema {
+    #[serde(default)]
+    pub tags: BTreeMap<String, TagRule>,
+}
+
+impl TagSchema {
+    /// Loads a schema from a YAML or JSON file
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read tag schema {}: {}", path, e))?;
+        serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse tag schema {}: {}", path, e))
+    }
+
+    /// Returns every way the tags break the schema, in tag name order
+    pub fn violations(&self, tags: &HashMap<String, String>) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        for (key, rule) in &self.tags {
+            let Some(value) = tags.get(key) else {
+                if rule.required {
+                    violations.push(format!("Tag {} is required", key));
+                }
+                continue;
+            };
+
+            if !rule.allowed_values.is_empty() && !rule.allowed_values.contains(value) {
+                violations.push(format!(
+                    "Tag {} has value {}, expected one of {}",
+                    key,
+                    value,
+                    rule.allowed_values.join(", ")
+                ));
+            }
+            if let Some(pattern) = &rule.pattern
+                && !pattern.0.is_match(value)
+            {
+                violations.push(format!(
+                    "Tag {} has value {}, which does not match {}",
+                    key, value, pattern
+                ));
+            }
+        }
+
+        violations
+    }
+
+    /// Rejects tags that break the schema with every violation in the error message
+    pub fn validate(&self, tags: &HashMap<String, String>) -> Result<(), RegistryError> {
+        let violations = self.violations(tags);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(RegistryError::Validation(violations.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_schema() -> TagSchema {
+        serde_yaml::from_str(
+            r#"
+tags:
+  team:
+    required: true
+  tier:
+    allowed_values: [backend, frontend]
+  version:
+    pattern: '^v\d+$'
+"#,
+        )
+        .unwrap()
+    }
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_conforming_tags() {
+        let schema = create_schema();
+
+        assert!(
+            schema
+                .validate(&tags(&[
+                    ("team", "payments"),
+                    ("tier", "backend"),
+                    ("version", "v2")
+                ]))
+                .is_ok()
+        );
+        assert!(schema.validate(&tags(&[("team", "payments")])).is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let schema = create_schema();
+
+        let violations = schema.violations(&tags(&[("tier", "edge"), ("version", "2.0")]));
+
+        assert_eq!(
+            violations,
+            vec![
+                "Tag team is required",
+                "Tag tier has value edge, expected one of backend, frontend",
+                "Tag version has value 2.0, which does not match ^v\\d+$",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        let schema = serde_yaml::from_str::<TagSchema>("tags: { version: { pattern: '(' } }");
+
+        assert!(schema.is_err());
+    }
+}
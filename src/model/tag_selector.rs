@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+/// Exact `key=value` tag matches an instance must all have to be selected
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagSelectors(Vec<(String, String)>);
+
+impl TagSelectors {
+    /// Parses comma separated `key=value` selectors, such as `team=billing,region=eu`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        value
+            .split(',')
+            .map(|selector| match selector.trim().split_once('=') {
+                Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+                _ => Err(format!(
+                    "Invalid tag selector '{}', expected key=value",
+                    selector
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(TagSelectors)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, String)> {
+        self.0.iter()
+    }
+
+    pub fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        self.0
+            .iter()
+            .all(|(key, value)| tags.get(key) == Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_selectors() {
+        let selectors = TagSelectors::parse("team=billing, region=eu").unwrap();
+        let tags = HashMap::from([
+            ("team".to_string(), "billing".to_string()),
+            ("region".to_string(), "eu".to_string()),
+        ]);
+        assert!(selectors.matches(&tags));
+        assert!(!TagSelectors::parse("team=search").unwrap().matches(&tags));
+        assert!(TagSelectors::parse("team").is_err());
+        assert!(TagSelectors::parse("=billing").is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+
+use crate::client::{ClientError, XolotlClient};
+use crate::mdns::{parse_query, write_name};
+use crate::model::service_address::ServiceAddress;
+
+/// Domain the stub answers for, as `<service>.<environment>.xolotl`
+pub const DOMAIN: &str = "xolotl";
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+
+const RCODE_SERVFAIL: u16 = 2;
+const RCODE_NXDOMAIN: u16 = 3;
+const RCODE_REFUSED: u16 = 5;
+
+/// Answers by service and environment, with when they were fetched
+type Cache = HashMap<(String, String), (Instant, Vec<IpAddr>)>;
+
+/// Per-host agent answering DNS queries for registered services from a cache
+/// of the central registry, kept serving stale answers while it is unreachable
+pub struct NodeAgent {
+    client: XolotlClient,
+    cache_ttl: Duration,
+    cache: Mutex<Cache>,
+}
+
+impl NodeAgent {
+    /// Resolves through `client`, asking the registry again once answers are older than `cache_ttl`
+    pub fn new(client: XolotlClient, cache_ttl: Duration) -> Self {
+        NodeAgent {
+            client,
+            cache_ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// IPs of the resolvable instances of a service, or the response code to answer with
+    async fn lookup(&self, service_name: &str, environment: &str) -> Result<Vec<IpAddr>, u16> {
+        let key = (service_name.to_string(), environment.to_string());
+        if let Some((fetched_at, ips)) = self.cache.lock().await.get(&key)
+            && fetched_at.elapsed() < self.cache_ttl
+        {
+            return Ok(ips.clone());
+        }
+
+        match self.client.resolve(service_name, environment).await {
+            Ok(instances) => {
+                let ips: Vec<IpAddr> = instances
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|instance| instance["address"].as_str())
+                    .filter_map(|address| {
+                        ServiceAddress::from_string(address.to_string())
+                            .host()?
+                            .parse()
+                            .ok()
+                    })
+                    .collect();
+                self.cache
+                    .lock()
+                    .await
+                    .insert(key, (Instant::now(), ips.clone()));
+                Ok(ips)
+            }
+            Err(ClientError::Status(StatusCode::NOT_FOUND)) => {
+                self.cache.lock().await.remove(&key);
+                Err(RCODE_NXDOMAIN)
+            }
+            Err(e) => {
+                eprintln!(
+                    "Failed to resolve {} in {}, answering from cache: {}",
+                    service_name, environment, e
+                );
+                self.cache
+                    .lock()
+                    .await
+                    .get(&key)
+                    .map(|(_, ips)| ips.clone())
+                    .ok_or(RCODE_SERVFAIL)
+            }
+        }
+    }
+
+    /// Answers the first question of a DNS query
+    pub async fn answer(&self, packet: &[u8]) -> Option<Vec<u8>> {
+        let question = parse_query(packet)?.into_iter().next()?;
+        let id = u16::from_be_bytes([packet[0], packet[1]]);
+        let name = question.name.trim_end_matches('.').to_ascii_lowercase();
+
+        let result = match name
+            .strip_suffix(DOMAIN)
+            .and_then(|rest| rest.strip_suffix('.'))
+            .and_then(|rest| rest.rsplit_once('.'))
+        {
+            Some((service_name, environment)) => self.lookup(service_name, environment).await,
+            None => Err(RCODE_REFUSED),
+        };
+        let (rcode, ips) = match result {
+            Ok(ips) => (0, ips),
+            Err(rcode) => (rcode, Vec::new()),
+        };
+        let answers: Vec<IpAddr> = ips
+            .into_iter()
+            .filter(|ip| match question.qtype {
+                TYPE_ANY => true,
+                TYPE_A => ip.is_ipv4(),
+                TYPE_AAAA => ip.is_ipv6(),
+                _ => false,
+            })
+            .collect();
+
+        Some(encode_response(
+            id,
+            &question.name,
+            question.qtype,
+            rcode,
+            &answers,
+            self.cache_ttl.as_secs() as u32,
+        ))
+    }
+}
+
+/// Encodes a recursive-style response echoing the question, with answers
+/// pointing back at the question name
+fn encode_response(
+    id: u16,
+    name: &str,
+    qtype: u16,
+    rcode: u16,
+    answers: &[IpAddr],
+    ttl: u32,
+) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(512);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&(0x8180 | rcode).to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 0]);
+    write_name(&mut packet, name);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    for ip in answers {
+        // Compressed pointer to the question name at offset 12
+        packet.extend_from_slice(&0xC00Cu16.to_be_bytes());
+        let data = match ip {
+            IpAddr::V4(ip) => {
+                packet.extend_from_slice(&TYPE_A.to_be_bytes());
+                ip.octets().to_vec()
+            }
+            IpAddr::V6(ip) => {
+                packet.extend_from_slice(&TYPE_AAAA.to_be_bytes());
+                ip.octets().to_vec()
+            }
+        };
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&ttl.to_be_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&data);
+    }
+    packet
+}
+
+/// Answers DNS queries on `socket` for the lifetime of the process
+pub fn spawn_dns_stub(agent: Arc<NodeAgent>, socket: UdpSocket) -> tokio::task::JoinHandle<()> {
+    let socket = Arc::new(socket);
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 1500];
+        loop {
+            let (length, source) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("Failed to receive DNS query: {}", e);
+                    continue;
+                }
+            };
+            let packet = buffer[..length].to_vec();
+            let (agent, socket) = (agent.clone(), socket.clone());
+            // A slow registry must not hold up queries answered from the cache
+            tokio::spawn(async move {
+                let Some(response) = agent.answer(&packet).await else {
+                    return;
+                };
+                if let Err(e) = socket.send_to(&response, source).await {
+                    eprintln!("Failed to send DNS response: {}", e);
+                }
+            });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+    use std::net::Ipv4Addr;
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        write_name(&mut packet, name);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    fn rcode(response: &[u8]) -> u16 {
+        u16::from_be_bytes([response[2], response[3]]) & 0x000F
+    }
+
+    #[tokio::test]
+    async fn test_answers_registered_services() {
+        let registry = TestServer::start().await;
+        registry
+            .register("payments", "prod", "http://10.0.0.5:8080", &[])
+            .await;
+        let agent = Arc::new(NodeAgent::new(registry.client(), Duration::from_secs(0)));
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        spawn_dns_stub(agent.clone(), socket);
+
+        let resolver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        resolver
+            .send_to(&query("payments.prod.xolotl", TYPE_A), address)
+            .await
+            .unwrap();
+        let mut buffer = [0u8; 512];
+        let length = resolver.recv(&mut buffer).await.unwrap();
+        let response = &buffer[..length];
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        assert_eq!(rcode(response), 0);
+        assert_eq!(u16::from_be_bytes([response[6], response[7]]), 1);
+        assert_eq!(&response[length - 4..], &[10, 0, 0, 5]);
+
+        let missing = agent
+            .answer(&query("search.prod.xolotl", TYPE_A))
+            .await
+            .unwrap();
+        assert_eq!(rcode(&missing), RCODE_NXDOMAIN);
+        let elsewhere = agent.answer(&query("example.com", TYPE_A)).await.unwrap();
+        assert_eq!(rcode(&elsewhere), RCODE_REFUSED);
+    }
+
+    #[tokio::test]
+    async fn test_serves_stale_answers_while_unreachable() {
+        // Nothing listens on port 1, as if the registry were down
+        let agent = NodeAgent::new(
+            XolotlClient::new("http://127.0.0.1:1", None),
+            Duration::from_secs(5),
+        );
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5));
+        agent.cache.lock().await.insert(
+            ("payments".to_string(), "prod".to_string()),
+            (Instant::now() - Duration::from_secs(60), vec![ip]),
+        );
+
+        let stale = agent
+            .answer(&query("Payments.prod.xolotl", TYPE_ANY))
+            .await
+            .unwrap();
+        assert_eq!(rcode(&stale), 0);
+        assert_eq!(&stale[stale.len() - 4..], &[10, 0, 0, 5]);
+        let unknown = agent
+            .answer(&query("search.prod.xolotl", TYPE_A))
+            .await
+            .unwrap();
+        assert_eq!(rcode(&unknown), RCODE_SERVFAIL);
+    }
+}
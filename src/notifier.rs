@@ -0,0 +1,339 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::model::alert::{Alert, AlertStatus};
+use crate::model::environment::EnvironmentExpiry;
+use crate::model::expectation::{ExpectationChange, ExpectationChangeKind};
+use crate::model::min_instances::{MinimumChange, MinimumChangeKind};
+use crate::model::service_change::ServiceChange;
+
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+fn default_pagerduty_url() -> String {
+    PAGERDUTY_EVENTS_URL.to_string()
+}
+
+/// Something sent to notifiers: an alert rule changing state, a change to an
+/// instance, a declared environment reaching its expiry or an expected service
+/// going missing
+#[derive(Debug, Clone, Copy)]
+pub enum Notification<'a> {
+    Alert(&'a Alert),
+    Change(&'a ServiceChange),
+    Expiry(&'a EnvironmentExpiry),
+    Expectation(&'a ExpectationChange),
+    Minimum(&'a MinimumChange),
+}
+
+impl Notification<'_> {
+    pub fn summary(&self) -> String {
+        match self {
+            Notification::Alert(alert) => alert.summary(),
+            Notification::Change(change) => change.summary(),
+            Notification::Expiry(expiry) => expiry.summary(),
+            Notification::Expectation(change) => change.summary(),
+            Notification::Minimum(change) => change.summary(),
+        }
+    }
+}
+
+/// Destination for notifications
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Notifier {
+    /// Receives every alert and change as JSON
+    Webhook { url: String },
+    /// Slack or Mattermost incoming webhook, receives every alert and change as a message
+    Slack { url: String },
+    /// Any endpoint accepting the PagerDuty Events API v2, only pages for alerts
+    PagerDuty {
+        routing_key: String,
+        #[serde(default = "default_pagerduty_url")]
+        url: String,
+    },
+}
+
+impl Notifier {
+    fn url(&self) -> &str {
+        match self {
+            Notifier::Webhook { url }
+            | Notifier::Slack { url }
+            | Notifier::PagerDuty { url, .. } => url,
+        }
+    }
+
+    /// Builds the request body this notifier expects, or nothing when it does not take the notification
+    pub fn payload(&self, notification: Notification) -> Option<Value> {
+        match (self, notification) {
+            (Notifier::Webhook { .. }, Notification::Alert(alert)) => Some(json!(alert)),
+            (Notifier::Webhook { .. }, Notification::Change(change)) => Some(json!(change)),
+            (Notifier::Webhook { .. }, Notification::Expiry(expiry)) => Some(json!(expiry)),
+            (Notifier::Webhook { .. }, Notification::Expectation(change)) => Some(json!(change)),
+            (Notifier::Webhook { .. }, Notification::Minimum(change)) => Some(json!(change)),
+            (Notifier::Slack { .. }, notification) => {
+                let icon = match notification {
+                    Notification::Alert(alert) if alert.status == AlertStatus::Resolved => {
+                        ":white_check_mark:"
+                    }
+                    Notification::Alert(_) => ":rotating_light:",
+                    Notification::Change(_) => ":warning:",
+                    Notification::Expiry(_) => ":hourglass:",
+                    Notification::Expectation(change)
+                        if change.kind == ExpectationChangeKind::Restored =>
+                    {
+                        ":white_check_mark:"
+                    }
+                    Notification::Expectation(_) => ":ghost:",
+                    Notification::Minimum(change) if change.kind == MinimumChangeKind::Restored => {
+                        ":white_check_mark:"
+                    }
+                    Notification::Minimum(_) => ":arrow_down:",
+                };
+                Some(json!({ "text": format!("{} {}", icon, notification.summary()) }))
+            }
+            (Notifier::PagerDuty { routing_key, .. }, Notification::Alert(alert)) => Some(json!({
+                "routing_key": routing_key,
+                "event_action": match alert.status {
+                    AlertStatus::Firing => "trigger",
+                    AlertStatus::Resolved => "resolve",
+                },
+                // Resolving uses the same key so PagerDuty closes the incident it opened
+                "dedup_key": format!("xolotl-{}", alert.rule),
+                "payload": {
+                    "summary": alert.summary(),
+                    "source": "xolotl",
+                    "severity": "critical",
+                    "custom_details": alert,
+                },
+            })),
+            (Notifier::PagerDuty { routing_key, .. }, Notification::Expectation(change)) => {
+                Some(json!({
+                    "routing_key": routing_key,
+                    "event_action": match change.kind {
+                        ExpectationChangeKind::Violated => "trigger",
+                        ExpectationChangeKind::Restored => "resolve",
+                    },
+                    "dedup_key": format!("xolotl-expected-{}", change.service_name),
+                    "payload": {
+                        "summary": change.summary(),
+                        "source": "xolotl",
+                        "severity": "critical",
+                        "custom_details": change,
+                    },
+                }))
+            }
+            (Notifier::PagerDuty { routing_key, .. }, Notification::Minimum(change)) => {
+                Some(json!({
+                    "routing_key": routing_key,
+                    "event_action": match change.kind {
+                        MinimumChangeKind::Breached => "trigger",
+                        MinimumChangeKind::Restored => "resolve",
+                    },
+                    "dedup_key": format!(
+                        "xolotl-minimum-{}-{}",
+                        change.service_name, change.environment
+                    ),
+                    "payload": {
+                        "summary": change.summary(),
+                        "source": "xolotl",
+                        "severity": "critical",
+                        "custom_details": change,
+                    },
+                }))
+            }
+            (Notifier::PagerDuty { .. }, Notification::Change(_) | Notification::Expiry(_)) => None,
+        }
+    }
+
+    pub async fn send(
+        &self,
+        http: &reqwest::Client,
+        notification: Notification<'_>,
+    ) -> reqwest::Result<()> {
+        let Some(payload) = self.payload(notification) else {
+            return Ok(());
+        };
+        http.post(self.url())
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::alert::AlertCondition;
+    use crate::model::environment::EnvironmentExpiryKind;
+    use crate::model::service_change::ServiceChangeKind;
+    use axum::{Json, Router, routing::post};
+    use std::sync::{Arc, Mutex};
+
+    fn create_alert() -> Alert {
+        Alert {
+            rule: "checkout-capacity".to_string(),
+            status: AlertStatus::Firing,
+            service_name: "checkout".parse().unwrap(),
+            environment: "prod".parse().unwrap(),
+            condition: AlertCondition::HealthyBelow(2),
+            value: 0,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_notifiers() {
+        let notifiers: Vec<Notifier> = serde_yaml::from_str(
+            r#"
+- type: webhook
+  url: http://localhost/hook
+- type: pagerduty
+  routing_key: abc
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            notifiers[1],
+            Notifier::PagerDuty {
+                routing_key: "abc".to_string(),
+                url: PAGERDUTY_EVENTS_URL.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_pagerduty_payload() {
+        let notifier = Notifier::PagerDuty {
+            routing_key: "abc".to_string(),
+            url: PAGERDUTY_EVENTS_URL.to_string(),
+        };
+
+        let payload = notifier
+            .payload(Notification::Alert(&create_alert()))
+            .unwrap();
+
+        assert_eq!(payload["event_action"], "trigger");
+        assert_eq!(payload["dedup_key"], "xolotl-checkout-capacity");
+        assert_eq!(payload["payload"]["severity"], "critical");
+    }
+
+    #[test]
+    fn test_slack_payload() {
+        let notifier = Notifier::Slack {
+            url: "http://localhost/hook".to_string(),
+        };
+        let change = ServiceChange {
+            kind: ServiceChangeKind::Deregistered,
+            service_name: "checkout".parse().unwrap(),
+            environment: "prod".parse().unwrap(),
+            instance_id: "instance-1".parse().unwrap(),
+        };
+
+        assert_eq!(
+            notifier.payload(Notification::Change(&change)).unwrap(),
+            json!({ "text": ":warning: Instance instance-1 of checkout in prod was deregistered" })
+        );
+        assert_eq!(
+            notifier
+                .payload(Notification::Alert(&create_alert()))
+                .unwrap()["text"],
+            ":rotating_light: checkout-capacity: 0 healthy instances of checkout in prod, expected at least 2"
+        );
+    }
+
+    #[test]
+    fn test_pagerduty_ignores_changes() {
+        let notifier = Notifier::PagerDuty {
+            routing_key: "abc".to_string(),
+            url: PAGERDUTY_EVENTS_URL.to_string(),
+        };
+        let change = ServiceChange {
+            kind: ServiceChangeKind::Unhealthy,
+            service_name: "checkout".parse().unwrap(),
+            environment: "prod".parse().unwrap(),
+            instance_id: "instance-1".parse().unwrap(),
+        };
+
+        assert!(notifier.payload(Notification::Change(&change)).is_none());
+    }
+
+    #[test]
+    fn test_expiry_payload() {
+        let expiry = EnvironmentExpiry {
+            kind: EnvironmentExpiryKind::Expiring,
+            environment: "pr-42".parse().unwrap(),
+            expires_at: 1_700_000_000_000,
+        };
+        let webhook = Notifier::Webhook {
+            url: "http://localhost/hook".to_string(),
+        };
+
+        assert_eq!(
+            webhook.payload(Notification::Expiry(&expiry)),
+            Some(json!({
+                "kind": "expiring",
+                "environment": "pr-42",
+                "expires_at": 1_700_000_000_000u64
+            }))
+        );
+    }
+
+    #[test]
+    fn test_expectation_pages() {
+        let notifier = Notifier::PagerDuty {
+            routing_key: "abc".to_string(),
+            url: PAGERDUTY_EVENTS_URL.to_string(),
+        };
+        let change = ExpectationChange {
+            kind: ExpectationChangeKind::Violated,
+            service_name: "billing".parse().unwrap(),
+            environment: None,
+            last_healthy_at: None,
+            timestamp: 0,
+        };
+
+        let payload = notifier
+            .payload(Notification::Expectation(&change))
+            .unwrap();
+        assert_eq!(payload["event_action"], "trigger");
+        assert_eq!(payload["dedup_key"], "xolotl-expected-billing");
+        assert_eq!(
+            payload["payload"]["summary"],
+            "Expected service billing in any environment has no healthy instance"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_alert() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let app = Router::new().route(
+            "/hook",
+            post(move |Json(body): Json<Value>| async move {
+                sink.lock().unwrap().push(body);
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let notifier = Notifier::Webhook {
+            url: format!("http://{}/hook", address),
+        };
+        notifier
+            .send(
+                &reqwest::Client::new(),
+                Notification::Alert(&create_alert()),
+            )
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["status"], "firing");
+        assert_eq!(received[0]["condition"]["healthy_below"], 2);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::model::agent::Directive;
+use crate::model::identifiers::InstanceId;
+use crate::model::service_registry::RegistryError;
+use std::collections::{BTreeMap, BTreeSet};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+struct Connection {
+    id: u64,
+    /// Credential the node connected with, see [`Principal::label`](crate::api::auth::Principal::label)
+    identity: Option<String>,
+    directives: UnboundedSender<Directive>,
+}
+
+/// Instances whose liveness follows the channel of their node
+#[derive(Default)]
+struct Held {
+    /// Credential of the connection that took hold of the instances
+    identity: Option<String>,
+    instances: BTreeSet<InstanceId>,
+    /// When the channel dropped, `None` while it is open
+    disconnected_at: Option<u64>,
+}
+
+/// Outcome of [`AgentHub::sweep`]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Liveness {
+    /// Held by a connected node, alive without sending heartbeats
+    pub alive: Vec<InstanceId>,
+    /// Held by a node that did not reconnect within the grace period
+    pub lost: Vec<InstanceId>,
+}
+
+/// Agents holding a channel to the server, by node name
+pub struct AgentHub {
+    connections: BTreeMap<String, Connection>,
+    held: BTreeMap<String, Held>,
+    last_id: u64,
+}
+
+impl AgentHub {
+    pub fn new() -> Self {
+        AgentHub {
+            connections: BTreeMap::new(),
+            held: BTreeMap::new(),
+            last_id: 0,
+        }
+    }
+
+    /// Registers the channel of `node` opened with the credential `identity`,
+    /// replacing an older one, and returns its connection id with the receiving
+    /// end of its directives. Instances the node held before stay held if it
+    /// reconnects within the grace period. Only the credential the node is
+    /// connected or holding instances with may take its place
+    pub fn connect(
+        &mut self,
+        node: &str,
+        identity: Option<String>,
+    ) -> Result<(u64, UnboundedReceiver<Directive>), RegistryError> {
+        let current = self
+            .connections
+            .get(node)
+            .map(|connection| &connection.identity)
+            .or(self.held.get(node).map(|held| &held.identity));
+        if current.is_some_and(|current| *current != identity) {
+            return Err(RegistryError::Conflict(format!(
+                "Node {} is connected with other credentials",
+                node
+            )));
+        }
+        if let Some(held) = self.held.get_mut(node) {
+            held.disconnected_at = None;
+        }
+        let (sender, receiver) = unbounded_channel();
+        self.last_id += 1;
+        self.connections.insert(
+            node.to_string(),
+            Connection {
+                id: self.last_id,
+                identity,
+                directives: sender,
+            },
+        );
+        Ok((self.last_id, receiver))
+    }
+
+    /// Forgets the channel of `node` at time `at` unless it has already been replaced by a newer connection
+    pub fn disconnect(&mut self, node: &str, id: u64, at: u64) {
+        if self
+            .connections
+            .get(node)
+            .is_some_and(|connection| connection.id == id)
+        {
+            self.connections.remove(node);
+            if let Some(held) = self.held.get_mut(node) {
+                held.disconnected_at = Some(at);
+            }
+        }
+    }
+
+    /// Ties the liveness of `instances` to the channel of `node`, which must be connected
+    pub fn hold(&mut self, node: &str, instances: impl IntoIterator<Item = InstanceId>) {
+        let Some(connection) = self.connections.get(node) else {
+            return;
+        };
+        let identity = connection.identity.clone();
+        self.held
+            .entry(node.to_string())
+            .or_insert_with(|| Held {
+                identity,
+                ..Held::default()
+            })
+            .instances
+            .extend(instances);
+    }
+
+    /// Stops tying the liveness of `instance` to any channel
+    pub fn release(&mut self, instance: &InstanceId) {
+        for held in self.held.values_mut() {
+            held.instances.remove(instance);
+        }
+        self.held.retain(|_, held| !held.instances.is_empty());
+    }
+
+    /// Splits held instances at time `at` into those of connected nodes and those
+    /// of nodes gone for longer than `grace` millis, which are released
+    pub fn sweep(&mut self, at: u64, grace: u64) -> Liveness {
+        let mut liveness = Liveness::default();
+        self.held.retain(|_, held| match held.disconnected_at {
+            None => {
+                liveness.alive.extend(held.instances.iter().cloned());
+                true
+            }
+            Some(since) if at.saturating_sub(since) >= grace => {
+                liveness.lost.extend(std::mem::take(&mut held.instances));
+                false
+            }
+            Some(_) => true,
+        });
+        liveness
+    }
+
+    /// Connected node names in order
+    pub fn nodes(&self) -> Vec<String> {
+        self.connections.keys().cloned().collect()
+    }
+
+    /// Queues a directive for `node`, failing when it is not connected
+    pub fn send(&self, node: &str, directive: Directive) -> Result<(), RegistryError> {
+        self.connections
+            .get(node)
+            .ok_or(RegistryError::NotFound)?
+            .directives
+            .send(directive)
+            .map_err(|_| RegistryError::NotFound)
+    }
+}
+
+impl Default for AgentHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reconnect_replaces_channel() {
+        let mut hub = AgentHub::new();
+        let (first, _) = hub.connect("node-1", None).unwrap();
+        let (second, mut directives) = hub.connect("node-1", None).unwrap();
+
+        hub.disconnect("node-1", first, 0);
+        assert_eq!(hub.nodes(), ["node-1"]);
+
+        let directive = Directive::Config {
+            settings: json!({ "log_level": "debug" }),
+        };
+        hub.send("node-1", directive.clone()).unwrap();
+        assert_eq!(directives.try_recv().unwrap(), directive);
+
+        hub.disconnect("node-1", second, 0);
+        assert!(hub.nodes().is_empty());
+        assert!(matches!(
+            hub.send("node-1", directive),
+            Err(RegistryError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_sweep_grace_period() {
+        let mut hub = AgentHub::new();
+        let a1: InstanceId = "a1".parse().unwrap();
+        let b2: InstanceId = "b2".parse().unwrap();
+        let (first, _) = hub.connect("node-1", None).unwrap();
+        let (second, _) = hub.connect("node-2", None).unwrap();
+        hub.hold("node-1", [a1.clone()]);
+        hub.hold("node-2", [b2.clone()]);
+        assert_eq!(
+            hub.sweep(1_000, 30_000),
+            Liveness {
+                alive: vec![a1.clone(), b2.clone()],
+                lost: vec![]
+            }
+        );
+
+        hub.disconnect("node-1", first, 1_000);
+        hub.disconnect("node-2", second, 1_000);
+        assert_eq!(
+            hub.sweep(20_000, 30_000),
+            Liveness {
+                alive: vec![],
+                lost: vec![]
+            }
+        );
+
+        hub.connect("node-1", None).unwrap();
+        assert_eq!(
+            hub.sweep(31_000, 30_000),
+            Liveness {
+                alive: vec![a1],
+                lost: vec![b2]
+            }
+        );
+        assert!(hub.sweep(90_000, 30_000).lost.is_empty());
+    }
+
+    #[test]
+    fn test_only_same_credentials_take_over() {
+        let mut hub = AgentHub::new();
+        let a1: InstanceId = "a1".parse().unwrap();
+        let agent = Some("caller:worker".to_string());
+        let (connection, _) = hub.connect("node-1", agent.clone()).unwrap();
+        hub.hold("node-1", [a1.clone()]);
+
+        assert!(matches!(
+            hub.connect("node-1", None),
+            Err(RegistryError::Conflict(_))
+        ));
+        assert!(hub.connect("node-1", Some("admin".to_string())).is_err());
+
+        // Held instances keep the node reserved while it is gone
+        hub.disconnect("node-1", connection, 1_000);
+        assert!(hub.connect("node-1", None).is_err());
+        hub.connect("node-1", agent).unwrap();
+        assert_eq!(hub.sweep(60_000, 30_000).alive, [a1]);
+    }
+}
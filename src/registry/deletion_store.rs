@@ -0,0 +1,116 @@
+use crate::model::identifiers::ServiceName;
+use crate::model::service_registry::RegistryError;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Token handed out by `DELETE /services/{name}` when deletions must be confirmed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingDeletion {
+    pub service_name: ServiceName,
+    pub token: String,
+    /// Instances the deletion removes as of the request
+    pub instances: usize,
+    pub expires_at: u64,
+}
+
+/// Deletions of whole services waiting for their confirmation token to be posted back
+#[derive(Default)]
+pub struct DeletionStore {
+    /// How long a token stays valid in millis, deletions run at once when unset
+    timeout: Option<u64>,
+    pending: HashMap<ServiceName, PendingDeletion>,
+}
+
+impl DeletionStore {
+    pub fn new(timeout: u64) -> Self {
+        DeletionStore {
+            timeout: Some(timeout),
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn requires_confirmation(&self) -> bool {
+        self.timeout.is_some()
+    }
+
+    /// Issues a token for deleting `instances` instances of `service_name` at time `at`,
+    /// replacing any earlier one
+    pub fn request(
+        &mut self,
+        service_name: &ServiceName,
+        instances: usize,
+        at: u64,
+    ) -> PendingDeletion {
+        self.pending.retain(|_, pending| pending.expires_at > at);
+        let pending = PendingDeletion {
+            service_name: service_name.clone(),
+            token: uuid::Uuid::new_v4().to_string(),
+            instances,
+            expires_at: at + self.timeout.unwrap_or_default(),
+        };
+        self.pending.insert(service_name.clone(), pending.clone());
+        pending
+    }
+
+    /// Checks the token for deleting `service_name` at time `at` without consuming
+    /// it, failing when it does not match or expired
+    pub fn verify(
+        &self,
+        service_name: &ServiceName,
+        token: &str,
+        at: u64,
+    ) -> Result<(), RegistryError> {
+        match self.pending.get(service_name) {
+            Some(pending) if pending.token == token && pending.expires_at > at => Ok(()),
+            Some(pending) if pending.token == token => Err(RegistryError::Conflict(format!(
+                "Confirmation token for deleting {} expired, request the deletion again",
+                service_name
+            ))),
+            _ => Err(RegistryError::Validation(format!(
+                "No pending deletion of {} with this confirmation token",
+                service_name
+            ))),
+        }
+    }
+
+    /// Consumes the token for deleting `service_name` at time `at`, failing when it
+    /// does not match or expired. Expired tokens are consumed too
+    pub fn confirm(
+        &mut self,
+        service_name: &ServiceName,
+        token: &str,
+        at: u64,
+    ) -> Result<(), RegistryError> {
+        let verified = self.verify(service_name, token, at);
+        if !matches!(verified, Err(RegistryError::Validation(_))) {
+            self.pending.remove(service_name);
+        }
+        verified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirm_within_timeout() {
+        let mut store = DeletionStore::new(30_000);
+        let name: ServiceName = "checkout".parse().unwrap();
+        assert!(store.requires_confirmation());
+        assert!(!DeletionStore::default().requires_confirmation());
+
+        let first = store.request(&name, 3, 0);
+        let second = store.request(&name, 3, 1_000);
+        assert_eq!(second.expires_at, 31_000);
+        assert!(store.confirm(&name, &first.token, 2_000).is_err());
+        store.confirm(&name, &second.token, 2_000).unwrap();
+        assert!(store.confirm(&name, &second.token, 2_000).is_err());
+
+        let late = store.request(&name, 3, 0);
+        assert!(matches!(
+            store.confirm(&name, &late.token, 30_000),
+            Err(RegistryError::Conflict(_))
+        ));
+    }
+}
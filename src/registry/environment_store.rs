@@ -0,0 +1,196 @@
+use crate::model::environment::{EnvironmentFreeze, EnvironmentRecord, EnvironmentStatus};
+use crate::model::identifiers::Environment;
+use crate::model::service_registry::RegistryError;
+use std::collections::BTreeMap;
+
+/// Environments created through the API, by name, and the frozen ones
+#[derive(Default)]
+pub struct EnvironmentStore {
+    environments: BTreeMap<Environment, EnvironmentRecord>,
+    /// Declared or not, so `prod` can be frozen without being created first
+    freezes: BTreeMap<Environment, EnvironmentFreeze>,
+}
+
+impl EnvironmentStore {
+    pub fn new() -> Self {
+        EnvironmentStore::default()
+    }
+
+    pub fn list(&self) -> Vec<EnvironmentRecord> {
+        self.environments.values().cloned().collect()
+    }
+
+    pub fn get(&self, name: &Environment) -> Option<&EnvironmentRecord> {
+        self.environments.get(name)
+    }
+
+    /// Adds an environment, failing when one with the same name exists
+    pub fn create(&mut self, record: EnvironmentRecord) -> Result<(), RegistryError> {
+        if self.environments.contains_key(&record.name) {
+            return Err(RegistryError::Conflict(format!(
+                "Environment {} already exists",
+                record.name
+            )));
+        }
+        self.environments.insert(record.name.clone(), record);
+        Ok(())
+    }
+
+    /// Closes an environment to registrations at time `at`
+    pub fn archive(
+        &mut self,
+        name: &Environment,
+        at: u64,
+    ) -> Result<EnvironmentRecord, RegistryError> {
+        let record = self
+            .environments
+            .get_mut(name)
+            .ok_or(RegistryError::NotFound)?;
+        if !record.is_archived() {
+            record.status = EnvironmentStatus::Archived;
+            record.archived_at = Some(at);
+        }
+        Ok(record.clone())
+    }
+
+    pub fn remove(&mut self, name: &Environment) -> Result<EnvironmentRecord, RegistryError> {
+        self.environments
+            .remove(name)
+            .ok_or(RegistryError::NotFound)
+    }
+
+    /// Active environments past their expiry at time `at`
+    pub fn expired(&self, at: u64) -> Vec<Environment> {
+        self.environments
+            .values()
+            .filter(|record| record.is_expired(at))
+            .map(|record| record.name.clone())
+            .collect()
+    }
+
+    /// Active environments expiring within `warn_before` millis of time `at`
+    /// that were not reported yet, each is only returned once
+    pub fn expiring(&mut self, at: u64, warn_before: u64) -> Vec<EnvironmentRecord> {
+        self.environments
+            .values_mut()
+            .filter(|record| {
+                !record.is_archived()
+                    && !record.expiry_notified
+                    && record
+                        .expires_at
+                        .is_some_and(|expires_at| at + warn_before >= expires_at)
+            })
+            .map(|record| {
+                record.expiry_notified = true;
+                record.clone()
+            })
+            .collect()
+    }
+
+    pub fn freeze_of(&self, name: &Environment) -> Option<&EnvironmentFreeze> {
+        self.freezes.get(name)
+    }
+
+    /// Environments that are frozen, declared or not
+    pub fn frozen(&self) -> impl Iterator<Item = &Environment> {
+        self.freezes.keys()
+    }
+
+    /// Freezes an environment, replacing the reason of an existing freeze
+    pub fn freeze(&mut self, name: Environment, freeze: EnvironmentFreeze) {
+        self.freezes.insert(name, freeze);
+    }
+
+    pub fn unfreeze(&mut self, name: &Environment) -> Result<(), RegistryError> {
+        self.freezes
+            .remove(name)
+            .map(|_| ())
+            .ok_or(RegistryError::NotFound)
+    }
+
+    /// Rejects registrations and deregistrations in frozen environments
+    pub fn check_unfrozen(&self, name: &Environment) -> Result<(), RegistryError> {
+        match self.freezes.get(name) {
+            Some(freeze) => Err(RegistryError::Conflict(format!(
+                "Environment {} is frozen{}, registrations and deregistrations are refused \
+                 until DELETE /environments/{}/freeze",
+                name,
+                freeze
+                    .reason
+                    .as_ref()
+                    .map(|reason| format!(" ({})", reason))
+                    .unwrap_or_default(),
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects registrations in archived or frozen environments, undeclared ones are accepted
+    pub fn check_open(&self, name: &Environment) -> Result<(), RegistryError> {
+        match self.environments.get(name) {
+            Some(record) if record.is_archived() => Err(RegistryError::Conflict(format!(
+                "Environment {} is archived",
+                name
+            ))),
+            _ => self.check_unfrozen(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle() {
+        let mut store = EnvironmentStore::new();
+        let name: Environment = "pr-42".parse().unwrap();
+        store
+            .create(EnvironmentRecord::new(name.clone(), Some(60), 0))
+            .unwrap();
+        assert!(matches!(
+            store.create(EnvironmentRecord::new(name.clone(), None, 0)),
+            Err(RegistryError::Conflict(_))
+        ));
+        assert!(store.check_open(&name).is_ok());
+        assert!(store.check_open(&"undeclared".parse().unwrap()).is_ok());
+        assert!(store.expiring(29_999, 30_000).is_empty());
+        assert_eq!(store.expiring(30_000, 30_000).len(), 1);
+        assert!(store.expiring(30_000, 30_000).is_empty());
+        assert_eq!(store.expired(60_000), std::slice::from_ref(&name));
+
+        let archived = store.archive(&name, 60_000).unwrap();
+        assert_eq!(archived.archived_at, Some(60_000));
+        assert!(store.check_open(&name).is_err());
+        assert!(store.expired(60_000).is_empty());
+
+        store.remove(&name).unwrap();
+        assert!(matches!(store.remove(&name), Err(RegistryError::NotFound)));
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut store = EnvironmentStore::new();
+        let prod: Environment = "prod".parse().unwrap();
+        store.freeze(
+            prod.clone(),
+            EnvironmentFreeze {
+                frozen_at: 1_000,
+                reason: Some("incident 42".to_string()),
+            },
+        );
+        match store.check_open(&prod) {
+            Err(RegistryError::Conflict(message)) => assert!(message.contains("incident 42")),
+            other => panic!("Expected a conflict, got {:?}", other),
+        }
+        assert!(store.check_open(&"staging".parse().unwrap()).is_ok());
+
+        store.unfreeze(&prod).unwrap();
+        assert!(store.check_unfrozen(&prod).is_ok());
+        assert!(matches!(
+            store.unfreeze(&prod),
+            Err(RegistryError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use crate::model::expectation::{
+    Expectation, ExpectationChange, ExpectationChangeKind, ExpectationStatus,
+};
+use crate::model::identifiers::ServiceName;
+use crate::model::service_registry::{HealthPolicy, RegistryError, ServiceEntry};
+
+/// Services that should always be running, watched for going missing
+#[derive(Default)]
+pub struct ExpectationStore {
+    expectations: BTreeMap<ServiceName, Expectation>,
+}
+
+impl ExpectationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the expectation of a service, keeping when it was last seen healthy
+    pub fn put(&mut self, mut expectation: Expectation) {
+        if let Some(previous) = self.expectations.get(&expectation.service_name) {
+            expectation.last_healthy_at = previous.last_healthy_at;
+        }
+        self.expectations
+            .insert(expectation.service_name.clone(), expectation);
+    }
+
+    pub fn remove(&mut self, service_name: &ServiceName) -> Result<Expectation, RegistryError> {
+        self.expectations
+            .remove(service_name)
+            .ok_or(RegistryError::NotFound)
+    }
+
+    /// Every expectation as of time `at`, counting the healthy instances in `entries` as seen
+    pub fn statuses(
+        &self,
+        entries: &[ServiceEntry],
+        policy: &HealthPolicy,
+        at: u64,
+    ) -> Vec<ExpectationStatus> {
+        self.expectations
+            .values()
+            .map(|expectation| {
+                let mut expectation = expectation.clone();
+                if expectation.is_met(entries, policy, at) {
+                    expectation.last_healthy_at = Some(at);
+                }
+                ExpectationStatus {
+                    violated: expectation.is_violated(at),
+                    expectation,
+                }
+            })
+            .collect()
+    }
+
+    /// Records which expectations `entries` meet at time `at`, returning the ones
+    /// that became violated or were restored since the last observation
+    pub fn observe(
+        &mut self,
+        entries: &[ServiceEntry],
+        policy: &HealthPolicy,
+        at: u64,
+    ) -> Vec<ExpectationChange> {
+        let mut changes = Vec::new();
+        for expectation in self.expectations.values_mut() {
+            if expectation.is_met(entries, policy, at) {
+                expectation.last_healthy_at = Some(at);
+            }
+            let violated = expectation.is_violated(at);
+            if violated != expectation.notified {
+                expectation.notified = violated;
+                let kind = if violated {
+                    ExpectationChangeKind::Violated
+                } else {
+                    ExpectationChangeKind::Restored
+                };
+                changes.push(ExpectationChange::new(kind, expectation, at));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_violated_then_restored() {
+        let policy = HealthPolicy::default();
+        let mut store = ExpectationStore::new();
+        store.put(Expectation::new(
+            "billing".parse().unwrap(),
+            Some("prod".parse().unwrap()),
+            60_000,
+            0,
+        ));
+
+        assert!(store.observe(&[], &policy, 30_000).is_empty());
+        let changes = store.observe(&[], &policy, 60_000);
+        assert_eq!(changes[0].kind, ExpectationChangeKind::Violated);
+        assert!(store.observe(&[], &policy, 90_000).is_empty());
+        assert!(store.statuses(&[], &policy, 90_000)[0].violated);
+
+        let mut entry = ServiceEntry::new(
+            "billing".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://billing:8080".to_string(),
+            HashMap::new(),
+            100_000,
+        );
+        entry.last_heartbeat = 101_000;
+        let changes = store.observe(std::slice::from_ref(&entry), &policy, 102_000);
+        assert_eq!(changes[0].kind, ExpectationChangeKind::Restored);
+        assert_eq!(changes[0].last_healthy_at, Some(102_000));
+
+        assert!(store.remove(&"billing".parse().unwrap()).is_ok());
+        assert!(store.remove(&"billing".parse().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::model::history::HistorySample;
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::registry_event::{RegistryEvent, RegistryEventKind};
+use crate::model::service_registry::ServiceEntry;
+use std::collections::{HashMap, VecDeque};
+
+/// Samples kept per service and environment, a day at one sample a minute
+const MAX_SAMPLES: usize = 1440;
+
+pub struct HistoryStore {
+    series: HashMap<(ServiceName, Environment), VecDeque<HistorySample>>,
+    last_event_index: u64,
+}
+
+impl HistoryStore {
+    pub fn new() -> Self {
+        HistoryStore {
+            series: HashMap::new(),
+            last_event_index: 0,
+        }
+    }
+
+    /// Index of the last event already counted as churn
+    pub fn last_event_index(&self) -> u64 {
+        self.last_event_index
+    }
+
+    /// Appends a sample taken at `timestamp` for every service and environment
+    /// that has instances, had churn, or was tracked before
+    pub fn record(&mut self, timestamp: u64, entries: &[ServiceEntry], events: &[RegistryEvent]) {
+        let mut samples: HashMap<(ServiceName, Environment), HistorySample> = self
+            .series
+            .keys()
+            .map(|key| (key.clone(), HistorySample::default()))
+            .collect();
+
+        for entry in entries {
+            samples
+                .entry((entry.service_name.clone(), entry.environment.clone()))
+                .or_default()
+                .instances += 1;
+        }
+        for event in events {
+            let sample = samples
+                .entry((event.service_name.clone(), event.environment.clone()))
+                .or_default();
+            match event.kind {
+                RegistryEventKind::Registered | RegistryEventKind::Promoted => {
+                    sample.registrations += 1
+                }
+                RegistryEventKind::Deregistered | RegistryEventKind::Tombstoned => {
+                    sample.deregistrations += 1
+                }
+                RegistryEventKind::StateChanged
+                | RegistryEventKind::Annotated
+                | RegistryEventKind::Expiring => {}
+            }
+            self.last_event_index = self.last_event_index.max(event.index);
+        }
+
+        for (key, sample) in samples {
+            let series = self.series.entry(key).or_default();
+            if series.len() == MAX_SAMPLES {
+                series.pop_front();
+            }
+            series.push_back(HistorySample {
+                timestamp,
+                ..sample
+            });
+        }
+    }
+
+    /// Returns the samples taken at or after `since`, oldest first
+    pub fn samples(
+        &self,
+        service_name: &ServiceName,
+        environment: &Environment,
+        since: u64,
+    ) -> Vec<HistorySample> {
+        self.series
+            .get(&(service_name.clone(), environment.clone()))
+            .map(|series| {
+                series
+                    .iter()
+                    .filter(|sample| sample.timestamp >= since)
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Default for HistoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+
+    fn create_entry(name: &str) -> ServiceEntry {
+        ServiceEntry::new(
+            name.parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://localhost:8080".to_string(),
+            HashMap::new(),
+            now(),
+        )
+    }
+
+    fn key(name: &str) -> (ServiceName, Environment) {
+        (name.parse().unwrap(), "prod".parse().unwrap())
+    }
+
+    #[test]
+    fn test_record_counts_instances_and_churn() {
+        let mut store = HistoryStore::new();
+        let first = create_entry("payments");
+        let second = create_entry("payments");
+        let events = vec![
+            RegistryEvent::new(
+                1,
+                RegistryEventKind::Registered,
+                &first,
+                first.registered_at,
+            ),
+            RegistryEvent::new(
+                2,
+                RegistryEventKind::Registered,
+                &second,
+                second.registered_at,
+            ),
+            RegistryEvent::new(
+                3,
+                RegistryEventKind::Deregistered,
+                &second,
+                second.registered_at,
+            ),
+        ];
+
+        store.record(1_000, &[first], &events);
+
+        let (name, environment) = key("payments");
+        assert_eq!(
+            store.samples(&name, &environment, 0),
+            vec![HistorySample {
+                timestamp: 1_000,
+                instances: 1,
+                registrations: 2,
+                deregistrations: 1,
+            }]
+        );
+        assert_eq!(store.last_event_index(), 3);
+    }
+
+    #[test]
+    fn test_tracked_services_keep_being_sampled() {
+        let mut store = HistoryStore::new();
+        store.record(1_000, &[create_entry("payments")], &[]);
+        store.record(2_000, &[], &[]);
+
+        let (name, environment) = key("payments");
+        let samples = store.samples(&name, &environment, 0);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].instances, 0);
+        assert_eq!(store.samples(&name, &environment, 1_500).len(), 1);
+    }
+
+    #[test]
+    fn test_samples_bounded() {
+        let mut store = HistoryStore::new();
+        let entry = create_entry("payments");
+        for timestamp in 0..(MAX_SAMPLES as u64 + 5) {
+            store.record(timestamp, std::slice::from_ref(&entry), &[]);
+        }
+
+        let (name, environment) = key("payments");
+        let samples = store.samples(&name, &environment, 0);
+        assert_eq!(samples.len(), MAX_SAMPLES);
+        assert_eq!(samples[0].timestamp, 5);
+    }
+}
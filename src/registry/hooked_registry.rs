@@ -0,0 +1,345 @@
+use crate::model::annotation::Annotation;
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::instance_state::InstanceState;
+use crate::model::registry_event::RegistryEvent;
+use crate::model::registry_hook::RegistryHook;
+use crate::model::service_registry::{
+    RegistryError, RegistryReader, RegistryWriter, ServiceEntry, ServiceRegistry,
+};
+use crate::model::tag_selector::TagSelectors;
+
+/// Wraps a registry backend to run hooks around its writes, in the order they were added.
+/// The first hook refusing a write stops it before it reaches the backend
+pub struct HookedRegistry<R> {
+    inner: R,
+    hooks: Vec<Box<dyn RegistryHook>>,
+}
+
+impl<R: ServiceRegistry> HookedRegistry<R> {
+    pub fn new(inner: R) -> Self {
+        HookedRegistry {
+            inner,
+            hooks: Vec::new(),
+        }
+    }
+
+    pub fn with_hook(mut self, hook: impl RegistryHook) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    fn pre_deregister(&self, filter: impl Fn(&ServiceEntry) -> bool) -> Result<(), RegistryError> {
+        let entries: Vec<ServiceEntry> = self
+            .inner
+            .list()
+            .into_iter()
+            .filter(|entry| filter(entry))
+            .collect();
+        if entries.is_empty() {
+            return Ok(());
+        }
+        self.hooks
+            .iter()
+            .try_for_each(|hook| hook.pre_deregister(&entries))
+    }
+
+    /// Runs `write` on the backend, then hands every event it recorded to the hooks
+    fn changing<T>(
+        &mut self,
+        write: impl FnOnce(&mut R) -> Result<T, RegistryError>,
+    ) -> Result<T, RegistryError> {
+        let since = self.inner.last_index();
+        let result = write(&mut self.inner)?;
+        for event in self.inner.events(since) {
+            for hook in &self.hooks {
+                hook.post_change(&event);
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl<R: ServiceRegistry> RegistryReader for HookedRegistry<R> {
+    fn list(&self) -> Vec<ServiceEntry> {
+        self.inner.list()
+    }
+
+    fn get(&self, id: &InstanceId) -> Option<ServiceEntry> {
+        self.inner.get(id)
+    }
+
+    fn resolve(&self, service_name: &ServiceName, environment: &Environment) -> Vec<ServiceEntry> {
+        self.inner.resolve(service_name, environment)
+    }
+
+    fn events(&self, since: u64) -> Vec<RegistryEvent> {
+        self.inner.events(since)
+    }
+
+    fn list_at(&self, index: u64) -> Option<Vec<ServiceEntry>> {
+        self.inner.list_at(index)
+    }
+
+    fn last_index(&self) -> u64 {
+        self.inner.last_index()
+    }
+
+    fn modify_index(&self, service_name: &ServiceName) -> u64 {
+        self.inner.modify_index(service_name)
+    }
+
+    fn find_by_tags(&self, selectors: &TagSelectors) -> Vec<ServiceEntry> {
+        self.inner.find_by_tags(selectors)
+    }
+}
+
+impl<R: ServiceRegistry> RegistryWriter for HookedRegistry<R> {
+    fn register(&mut self, mut entry: ServiceEntry) -> Result<(), RegistryError> {
+        for hook in &self.hooks {
+            hook.pre_register(&mut entry)?;
+        }
+        let registered = entry.clone();
+        self.changing(|inner| inner.register(entry))?;
+        for hook in &self.hooks {
+            hook.post_register(&registered);
+        }
+        Ok(())
+    }
+
+    fn deregister(
+        &mut self,
+        service_name: &ServiceName,
+        environment: Option<&Environment>,
+    ) -> Result<(), RegistryError> {
+        self.pre_deregister(|entry| {
+            &entry.service_name == service_name
+                && environment.is_none_or(|environment| &entry.environment == environment)
+        })?;
+        self.changing(|inner| inner.deregister(service_name, environment))
+    }
+
+    fn deregister_instance(&mut self, id: &InstanceId) -> Result<(), RegistryError> {
+        self.pre_deregister(|entry| &entry.id == id)?;
+        self.changing(|inner| inner.deregister_instance(id))
+    }
+
+    fn tombstone(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Result<(), RegistryError> {
+        self.pre_deregister(|entry| {
+            &entry.service_name == service_name && &entry.environment == environment
+        })?;
+        self.changing(|inner| inner.tombstone(service_name, environment))
+    }
+
+    fn heartbeat(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Result<(), RegistryError> {
+        self.inner.heartbeat(service_name, environment)
+    }
+
+    fn heartbeat_instance(&mut self, id: &InstanceId) -> Result<(), RegistryError> {
+        self.inner.heartbeat_instance(id)
+    }
+
+    fn set_state(
+        &mut self,
+        id: &InstanceId,
+        state: InstanceState,
+    ) -> Result<ServiceEntry, RegistryError> {
+        self.changing(|inner| inner.set_state(id, state))
+    }
+
+    fn annotate(
+        &mut self,
+        id: &InstanceId,
+        annotation: Annotation,
+    ) -> Result<ServiceEntry, RegistryError> {
+        self.changing(|inner| inner.annotate(id, annotation))
+    }
+
+    fn mark_expiring(&mut self, id: &InstanceId, percent: u64) -> Result<(), RegistryError> {
+        self.changing(|inner| inner.mark_expiring(id, percent))
+    }
+
+    fn promote(
+        &mut self,
+        service_name: &ServiceName,
+        from: &Environment,
+        to: &Environment,
+        remove_source: bool,
+    ) -> Result<Vec<ServiceEntry>, RegistryError> {
+        // Promotion registers copies of the source instances and removes those it
+        // replaces, so it is admitted like the registrations and deregistrations it amounts to
+        for source in self.inner.resolve(service_name, from) {
+            let mut copy = ServiceEntry {
+                environment: to.clone(),
+                ..source
+            };
+            for hook in &self.hooks {
+                hook.pre_register(&mut copy)?;
+            }
+        }
+        self.pre_deregister(|entry| {
+            &entry.service_name == service_name
+                && (&entry.environment == to || (remove_source && &entry.environment == from))
+        })?;
+
+        let promoted =
+            self.changing(|inner| inner.promote(service_name, from, to, remove_source))?;
+        for entry in &promoted {
+            for hook in &self.hooks {
+                hook.post_register(entry);
+            }
+        }
+        Ok(promoted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::registry_event::RegistryEventKind;
+    use crate::model::service_registry::now;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Requires a team tag, tags instances with the region and keeps prod services
+    struct TeamPolicy {
+        changes: Arc<Mutex<Vec<RegistryEventKind>>>,
+    }
+
+    impl RegistryHook for TeamPolicy {
+        fn pre_register(&self, entry: &mut ServiceEntry) -> Result<(), RegistryError> {
+            if !entry.tags.contains_key("team") {
+                return Err(RegistryError::Validation(
+                    "A team tag is required".to_string(),
+                ));
+            }
+            entry
+                .tags
+                .insert("region".to_string(), "eu-west-1".to_string());
+            Ok(())
+        }
+
+        fn pre_deregister(&self, entries: &[ServiceEntry]) -> Result<(), RegistryError> {
+            if entries
+                .iter()
+                .any(|entry| entry.environment.as_str() == "prod")
+            {
+                return Err(RegistryError::Conflict("prod is frozen".to_string()));
+            }
+            Ok(())
+        }
+
+        fn post_change(&self, event: &RegistryEvent) {
+            self.changes.lock().unwrap().push(event.kind);
+        }
+    }
+
+    fn entry(environment: &str, tags: &[(&str, &str)]) -> ServiceEntry {
+        ServiceEntry::new(
+            "checkout".parse().unwrap(),
+            environment.parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            tags.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_hooks_guard_writes() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = HookedRegistry::new(InMemoryRegistry::new()).with_hook(TeamPolicy {
+            changes: changes.clone(),
+        });
+
+        assert!(matches!(
+            registry.register(entry("dev", &[])),
+            Err(RegistryError::Validation(_))
+        ));
+        registry
+            .register(entry("dev", &[("team", "payments")]))
+            .unwrap();
+        registry
+            .register(entry("prod", &[("team", "payments")]))
+            .unwrap();
+        assert_eq!(registry.list()[0].tags["region"], "eu-west-1");
+
+        assert!(
+            registry
+                .deregister(&"checkout".parse().unwrap(), None)
+                .is_err()
+        );
+        registry
+            .deregister(&"checkout".parse().unwrap(), Some(&"dev".parse().unwrap()))
+            .unwrap();
+        assert_eq!(registry.list().len(), 1);
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                RegistryEventKind::Registered,
+                RegistryEventKind::Registered,
+                RegistryEventKind::Deregistered
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hooks_guard_promotions() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = HookedRegistry::new(InMemoryRegistry::new()).with_hook(TeamPolicy {
+            changes: changes.clone(),
+        });
+        let service = "checkout".parse().unwrap();
+        registry
+            .register(entry("staging", &[("team", "payments")]))
+            .unwrap();
+        registry
+            .register(entry("prod", &[("team", "payments")]))
+            .unwrap();
+
+        // Promoting into prod would replace its frozen instances
+        assert!(matches!(
+            registry.promote(
+                &service,
+                &"staging".parse().unwrap(),
+                &"prod".parse().unwrap(),
+                false
+            ),
+            Err(RegistryError::Conflict(_))
+        ));
+        // Moving prod out would deregister it
+        assert!(
+            registry
+                .promote(
+                    &service,
+                    &"prod".parse().unwrap(),
+                    &"dr".parse().unwrap(),
+                    true
+                )
+                .is_err()
+        );
+        assert_eq!(registry.list().len(), 2);
+
+        registry
+            .promote(
+                &service,
+                &"staging".parse().unwrap(),
+                &"qa".parse().unwrap(),
+                true,
+            )
+            .unwrap();
+        assert_eq!(
+            changes.lock().unwrap().last(),
+            Some(&RegistryEventKind::Deregistered)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+/// Keys are remembered for a day unless configured otherwise
+pub const DEFAULT_IDEMPOTENCY_TTL: u64 = 86_400_000;
+
+/// Millis a key stays claimed by a request that has not completed, so a retry
+/// can run again if the first attempt never finishes
+pub const IN_FLIGHT_TTL: u64 = 60_000;
+
+/// Most keys remembered at once, the one closest to expiring is dropped first
+const MAX_KEYS: usize = 10_000;
+
+/// Response recorded for an idempotency key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyRecord {
+    /// The first request with the key is still being handled
+    InFlight,
+    Completed(StoredResponse),
+}
+
+/// Outcome of claiming a key for a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// The key is new, the request should be handled and its response stored
+    New,
+    Existing(IdempotencyRecord),
+    /// The key was used before for a different request
+    Mismatch,
+}
+
+struct Entry {
+    fingerprint: u64,
+    expires_at: u64,
+    record: IdempotencyRecord,
+}
+
+/// Responses of mutating requests by `Idempotency-Key`, so retried requests
+/// are answered with the original response instead of being applied twice
+pub struct IdempotencyStore {
+    entries: HashMap<String, Entry>,
+    ttl: u64,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: u64) -> Self {
+        IdempotencyStore {
+            entries: HashMap::new(),
+            ttl,
+        }
+    }
+
+    /// Claims `key` at time `at` for a request identified by `fingerprint`
+    pub fn claim(&mut self, key: &str, fingerprint: u64, at: u64) -> Claim {
+        match self.entries.get(key) {
+            Some(entry) if entry.expires_at > at && entry.fingerprint != fingerprint => {
+                Claim::Mismatch
+            }
+            Some(entry) if entry.expires_at > at => Claim::Existing(entry.record.clone()),
+            _ => {
+                self.make_room(at);
+                self.entries.insert(
+                    key.to_string(),
+                    Entry {
+                        fingerprint,
+                        expires_at: at.saturating_add(self.ttl.min(IN_FLIGHT_TTL)),
+                        record: IdempotencyRecord::InFlight,
+                    },
+                );
+                Claim::New
+            }
+        }
+    }
+
+    /// Drops expired keys once the store is full, and the key closest to
+    /// expiring if none has
+    fn make_room(&mut self, at: u64) {
+        if self.entries.len() < MAX_KEYS {
+            return;
+        }
+        self.entries.retain(|_, entry| entry.expires_at > at);
+        if self.entries.len() >= MAX_KEYS
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone())
+        {
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// Stores the response of a claimed key at time `at`, remembered for the full TTL
+    pub fn complete(&mut self, key: &str, response: StoredResponse, at: u64) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.expires_at = at.saturating_add(self.ttl);
+            entry.record = IdempotencyRecord::Completed(response);
+        }
+    }
+
+    /// Forgets a key still in flight, letting a retry run the request again
+    pub fn release(&mut self, key: &str) {
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.record == IdempotencyRecord::InFlight)
+        {
+            self.entries.remove(key);
+        }
+    }
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> StoredResponse {
+        StoredResponse {
+            status: 200,
+            content_type: None,
+            body: b"ok".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_claim_and_replay() {
+        let mut store = IdempotencyStore::new(1_000);
+
+        assert_eq!(store.claim("key", 1, 0), Claim::New);
+        assert_eq!(
+            store.claim("key", 1, 10),
+            Claim::Existing(IdempotencyRecord::InFlight)
+        );
+
+        store.complete("key", response(), 20);
+        assert_eq!(
+            store.claim("key", 1, 20),
+            Claim::Existing(IdempotencyRecord::Completed(response()))
+        );
+        assert_eq!(store.claim("key", 2, 20), Claim::Mismatch);
+    }
+
+    #[test]
+    fn test_keys_expire_and_release() {
+        let mut store = IdempotencyStore::new(1_000);
+
+        store.claim("key", 1, 0);
+        store.complete("key", response(), 0);
+        store.release("key");
+        assert_eq!(
+            store.claim("key", 1, 999),
+            Claim::Existing(IdempotencyRecord::Completed(response()))
+        );
+        assert_eq!(store.claim("key", 2, 1_000), Claim::New);
+
+        store.release("key");
+        assert_eq!(store.claim("key", 1, 1_000), Claim::New);
+    }
+
+    #[test]
+    fn test_in_flight_claims_expire_early() {
+        let mut store = IdempotencyStore::new(DEFAULT_IDEMPOTENCY_TTL);
+
+        assert_eq!(store.claim("key", 1, 0), Claim::New);
+        assert_eq!(
+            store.claim("key", 1, IN_FLIGHT_TTL - 1),
+            Claim::Existing(IdempotencyRecord::InFlight)
+        );
+        assert_eq!(store.claim("key", 1, IN_FLIGHT_TTL), Claim::New);
+    }
+
+    #[test]
+    fn test_store_is_bounded() {
+        let mut store = IdempotencyStore::new(1_000);
+
+        for i in 0..MAX_KEYS as u64 {
+            store.claim(&i.to_string(), 1, i);
+        }
+        assert_eq!(store.claim("new", 1, 10), Claim::New);
+        assert_eq!(store.entries.len(), MAX_KEYS);
+        // The key closest to expiring made room
+        assert_eq!(store.claim("0", 1, 10), Claim::New);
+        assert_eq!(
+            store.claim("new", 1, 10),
+            Claim::Existing(IdempotencyRecord::InFlight)
+        );
+    }
+}
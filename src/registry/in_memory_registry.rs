@@ -1,53 +1,135 @@
-use crate::model::service_registry::{RegistryError, ServiceEntry, ServiceRegistry, now};
-use std::collections::HashMap;
+use crate::model::annotation::{self, Annotation};
+use crate::model::clock::{SharedClock, system_clock};
+use crate::model::identifiers::{Environment, InstanceId, ServiceName};
+use crate::model::instance_state::InstanceState;
+use crate::model::registry_event::{RegistryEvent, RegistryEventKind};
+use crate::model::service_registry::{RegistryError, RegistryReader, RegistryWriter, ServiceEntry};
+use crate::model::tag_selector::TagSelectors;
+use crate::registry::tag_index::TagIndex;
+use std::collections::{HashMap, VecDeque};
+
+/// Maximum number of events retained for `events` queries, and of changes kept to rebuild past states
+const MAX_EVENTS: usize = 1000;
+
+/// An instance as it was right after a change, `None` once it was removed
+struct Revision {
+    index: u64,
+    id: InstanceId,
+    after: Option<ServiceEntry>,
+}
 
 pub struct InMemoryRegistry {
-    services: HashMap<String, ServiceEntry>,
+    services: HashMap<InstanceId, ServiceEntry>,
+    events: VecDeque<RegistryEvent>,
+    last_index: u64,
+    /// Index of the last event of every service, kept after its events are dropped
+    modified: HashMap<ServiceName, u64>,
+    revisions: VecDeque<Revision>,
+    /// The instances as of `base_index`, the change right before the oldest revision kept
+    base: HashMap<InstanceId, ServiceEntry>,
+    base_index: u64,
+    clock: SharedClock,
+    environment_tags: HashMap<Environment, HashMap<String, String>>,
+    tag_index: TagIndex,
 }
 
 impl InMemoryRegistry {
     pub fn new() -> Self {
         InMemoryRegistry {
             services: HashMap::new(),
+            events: VecDeque::new(),
+            last_index: 0,
+            modified: HashMap::new(),
+            revisions: VecDeque::new(),
+            base: HashMap::new(),
+            base_index: 0,
+            clock: system_clock(),
+            environment_tags: HashMap::new(),
+            tag_index: TagIndex::default(),
         }
     }
-}
 
-impl ServiceRegistry for InMemoryRegistry {
-    fn list(&self) -> Vec<ServiceEntry> {
-        self.services.values().cloned().collect()
+    /// Takes heartbeat and event timestamps from `clock` instead of the system time
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
-    fn register(&mut self, entry: ServiceEntry) -> Result<(), RegistryError> {
-        if self.services.contains_key(&entry.id) {
-            return Err(RegistryError::AlreadyExists);
-        }
-
-        self.services.insert(entry.id.clone(), entry);
-        Ok(())
+    /// Merges per-environment default tags into every entry read from the
+    /// registry, without storing them on the entries
+    pub fn with_environment_tags(
+        mut self,
+        environment_tags: HashMap<Environment, HashMap<String, String>>,
+    ) -> Self {
+        self.environment_tags = environment_tags;
+        self
     }
 
-    fn resolve(&self, service_name: &str, environment: &str) -> Vec<ServiceEntry> {
+    /// Instances of a service in an environment as stored, without inherited tags
+    fn stored(&self, service_name: &ServiceName, environment: &Environment) -> Vec<ServiceEntry> {
         self.services
             .values()
             .filter(|service| {
-                service.service_name == service_name && service.environment == environment
+                &service.service_name == service_name && &service.environment == environment
             })
             .cloned()
             .collect()
     }
 
-    fn deregister(
+    /// Stores an instance, indexing the tags it is read with
+    fn insert(&mut self, entry: ServiceEntry) {
+        self.tag_index.insert(&self.read(&entry));
+        self.services.insert(entry.id.clone(), entry);
+    }
+
+    fn remove(&mut self, id: &InstanceId) -> Option<ServiceEntry> {
+        let entry = self.services.remove(id)?;
+        self.tag_index.remove(&self.read(&entry));
+        Some(entry)
+    }
+
+    fn read(&self, entry: &ServiceEntry) -> ServiceEntry {
+        let mut entry = entry.clone();
+        if let Some(defaults) = self.environment_tags.get(&entry.environment) {
+            entry.inherit_tags(defaults);
+        }
+        entry
+    }
+
+    fn record(&mut self, kind: RegistryEventKind, entry: &ServiceEntry) {
+        self.last_index += 1;
+        let event = RegistryEvent::new(self.last_index, kind, entry, self.clock.now());
+        self.revise(&event, entry);
+        self.push_event(event);
+    }
+
+    fn record_with_detail(
+        &mut self,
+        kind: RegistryEventKind,
+        entry: &ServiceEntry,
+        detail: String,
+    ) {
+        self.last_index += 1;
+        let event =
+            RegistryEvent::new(self.last_index, kind, entry, self.clock.now()).with_detail(detail);
+        self.revise(&event, entry);
+        self.push_event(event);
+    }
+
+    /// Removes the instances of a service, in one environment or in all of them,
+    /// recording a `kind` event for each
+    fn remove_matching(
         &mut self,
-        service_name: &str,
-        environment: Option<&str>,
+        service_name: &ServiceName,
+        environment: Option<&Environment>,
+        kind: RegistryEventKind,
     ) -> Result<(), RegistryError> {
-        let ids_to_remove: Vec<String> = if let Some(env) = environment {
+        let ids_to_remove: Vec<InstanceId> = if let Some(env) = environment {
             // Remove services matching specific service name and environment
             self.services
                 .iter()
                 .filter(|(_, service)| {
-                    service.service_name == service_name && service.environment == env
+                    &service.service_name == service_name && &service.environment == env
                 })
                 .map(|(id, _)| id.clone())
                 .collect()
@@ -55,7 +137,7 @@ impl ServiceRegistry for InMemoryRegistry {
             // Remove all services matching the service name across all environments
             self.services
                 .iter()
-                .filter(|(_, service)| service.service_name == service_name)
+                .filter(|(_, service)| &service.service_name == service_name)
                 .map(|(id, _)| id.clone())
                 .collect()
         };
@@ -65,18 +147,174 @@ impl ServiceRegistry for InMemoryRegistry {
         }
 
         for id in ids_to_remove {
-            self.services.remove(&id);
+            if let Some(entry) = self.remove(&id) {
+                self.record(kind, &entry);
+            }
         }
 
         Ok(())
     }
 
-    fn heartbeat(&mut self, service_name: &str, environment: &str) -> Result<(), RegistryError> {
+    /// Keeps the state of the instance changed by `event` for rebuilding past states,
+    /// folding the oldest revision into the base once the history is full
+    fn revise(&mut self, event: &RegistryEvent, entry: &ServiceEntry) {
+        let after = match event.kind {
+            RegistryEventKind::Deregistered | RegistryEventKind::Tombstoned => None,
+            _ => Some(entry.clone()),
+        };
+        if self.revisions.len() == MAX_EVENTS
+            && let Some(oldest) = self.revisions.pop_front()
+        {
+            apply(&mut self.base, oldest.id, oldest.after);
+            self.base_index = oldest.index;
+        }
+        self.revisions.push_back(Revision {
+            index: event.index,
+            id: entry.id.clone(),
+            after,
+        });
+    }
+
+    fn push_event(&mut self, event: RegistryEvent) {
+        self.modified
+            .insert(event.service_name.clone(), event.index);
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+fn apply(
+    services: &mut HashMap<InstanceId, ServiceEntry>,
+    id: InstanceId,
+    after: Option<ServiceEntry>,
+) {
+    match after {
+        Some(entry) => services.insert(id, entry),
+        None => services.remove(&id),
+    };
+}
+
+impl Default for InMemoryRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegistryReader for InMemoryRegistry {
+    fn list(&self) -> Vec<ServiceEntry> {
+        self.services
+            .values()
+            .map(|entry| self.read(entry))
+            .collect()
+    }
+
+    fn get(&self, id: &InstanceId) -> Option<ServiceEntry> {
+        self.services.get(id).map(|entry| self.read(entry))
+    }
+
+    fn resolve(&self, service_name: &ServiceName, environment: &Environment) -> Vec<ServiceEntry> {
+        self.stored(service_name, environment)
+            .iter()
+            .map(|entry| self.read(entry))
+            .collect()
+    }
+
+    fn events(&self, since: u64) -> Vec<RegistryEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.index > since)
+            .cloned()
+            .collect()
+    }
+
+    fn list_at(&self, index: u64) -> Option<Vec<ServiceEntry>> {
+        if index >= self.last_index {
+            return Some(self.list());
+        }
+        if index < self.base_index {
+            return None;
+        }
+        let mut services = self.base.clone();
+        for revision in self
+            .revisions
+            .iter()
+            .take_while(|revision| revision.index <= index)
+        {
+            apply(&mut services, revision.id.clone(), revision.after.clone());
+        }
+        Some(services.values().map(|entry| self.read(entry)).collect())
+    }
+
+    fn last_index(&self) -> u64 {
+        self.last_index
+    }
+
+    fn modify_index(&self, service_name: &ServiceName) -> u64 {
+        self.modified.get(service_name).copied().unwrap_or(0)
+    }
+
+    fn find_by_tags(&self, selectors: &TagSelectors) -> Vec<ServiceEntry> {
+        match self.tag_index.find(selectors) {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|id| self.services.get(id))
+                .map(|entry| self.read(entry))
+                .collect(),
+            None => self.list(),
+        }
+    }
+}
+
+impl RegistryWriter for InMemoryRegistry {
+    fn register(&mut self, entry: ServiceEntry) -> Result<(), RegistryError> {
+        if self.services.contains_key(&entry.id) {
+            return Err(RegistryError::AlreadyExists);
+        }
+
+        self.record(RegistryEventKind::Registered, &entry);
+        self.insert(entry);
+        Ok(())
+    }
+
+    fn deregister(
+        &mut self,
+        service_name: &ServiceName,
+        environment: Option<&Environment>,
+    ) -> Result<(), RegistryError> {
+        self.remove_matching(service_name, environment, RegistryEventKind::Deregistered)
+    }
+
+    fn tombstone(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Result<(), RegistryError> {
+        self.remove_matching(
+            service_name,
+            Some(environment),
+            RegistryEventKind::Tombstoned,
+        )
+    }
+
+    fn deregister_instance(&mut self, id: &InstanceId) -> Result<(), RegistryError> {
+        let entry = self.remove(id).ok_or(RegistryError::NotFound)?;
+        self.record(RegistryEventKind::Deregistered, &entry);
+        Ok(())
+    }
+
+    fn heartbeat(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Result<(), RegistryError> {
         let mut found = false;
+        let now = self.clock.now();
 
         for service in self.services.values_mut() {
-            if service.service_name == service_name && service.environment == environment {
-                service.last_heartbeat = now();
+            if &service.service_name == service_name && &service.environment == environment {
+                service.record_heartbeat(now);
                 found = true;
             }
         }
@@ -86,23 +324,141 @@ impl ServiceRegistry for InMemoryRegistry {
         }
         Ok(())
     }
+
+    fn heartbeat_instance(&mut self, id: &InstanceId) -> Result<(), RegistryError> {
+        let entry = self.services.get_mut(id).ok_or(RegistryError::NotFound)?;
+        entry.record_heartbeat(self.clock.now());
+        Ok(())
+    }
+
+    fn set_state(
+        &mut self,
+        id: &InstanceId,
+        state: InstanceState,
+    ) -> Result<ServiceEntry, RegistryError> {
+        let entry = self.services.get_mut(id).ok_or(RegistryError::NotFound)?;
+        let previous = entry.current_state(self.clock.now());
+        if !previous.can_transition_to(state) {
+            return Err(RegistryError::Conflict(format!(
+                "Cannot move instance {} from {} to {}",
+                id, previous, state
+            )));
+        }
+
+        entry.state = state;
+        entry.warmup_until = None;
+        let entry = entry.clone();
+        if previous != state {
+            self.record_with_detail(
+                RegistryEventKind::StateChanged,
+                &entry,
+                format!("{} -> {}", previous, state),
+            );
+        }
+        Ok(entry)
+    }
+
+    fn annotate(
+        &mut self,
+        id: &InstanceId,
+        annotation: Annotation,
+    ) -> Result<ServiceEntry, RegistryError> {
+        let entry = self.services.get_mut(id).ok_or(RegistryError::NotFound)?;
+        let note = annotation.note.clone();
+        annotation::append(&mut entry.annotations, annotation);
+        let entry = entry.clone();
+        self.record_with_detail(RegistryEventKind::Annotated, &entry, note);
+        Ok(entry)
+    }
+
+    fn mark_expiring(&mut self, id: &InstanceId, percent: u64) -> Result<(), RegistryError> {
+        let entry = self
+            .services
+            .get(id)
+            .ok_or(RegistryError::NotFound)?
+            .clone();
+        self.record_with_detail(
+            RegistryEventKind::Expiring,
+            &entry,
+            format!("silent for {}% of the time allowed", percent),
+        );
+        Ok(())
+    }
+
+    fn promote(
+        &mut self,
+        service_name: &ServiceName,
+        from: &Environment,
+        to: &Environment,
+        remove_source: bool,
+    ) -> Result<Vec<ServiceEntry>, RegistryError> {
+        let sources = self.stored(service_name, from);
+        if sources.is_empty() {
+            return Err(RegistryError::NotFound);
+        }
+
+        // The promoted set replaces whatever was running in the target environment
+        if !self.resolve(service_name, to).is_empty() {
+            self.deregister(service_name, Some(to))?;
+        }
+
+        let mut promoted = Vec::with_capacity(sources.len());
+        for source in sources {
+            // Copies carry every setting of their source, only as a new instance of `to`
+            let entry = ServiceEntry {
+                id: InstanceId::generate(),
+                environment: to.clone(),
+                recent_heartbeats: Vec::new(),
+                ..source.clone()
+            }
+            .at(self.clock.now());
+            self.record_with_detail(
+                RegistryEventKind::Promoted,
+                &entry,
+                format!("Promoted from {} instance {}", from, source.id),
+            );
+            self.insert(entry.clone());
+            promoted.push(entry);
+
+            if remove_source {
+                self.deregister_instance(&source.id)?;
+            }
+        }
+
+        Ok(promoted)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{sync::Arc, thread::sleep, time::Duration};
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::service_registry::now;
+    use std::{sync::Arc, time::Duration};
     use tokio::sync::RwLock;
 
+    fn name(value: &str) -> ServiceName {
+        value.parse().unwrap()
+    }
+
+    fn env(value: &str) -> Environment {
+        value.parse().unwrap()
+    }
+
+    fn id(value: &str) -> InstanceId {
+        value.parse().unwrap()
+    }
+
     fn create_test_entry(name: &str, env: &str) -> ServiceEntry {
         let mut tags = HashMap::new();
         tags.insert("type".to_string(), "test".to_string());
 
         ServiceEntry::new(
-            name.to_string(),
-            env.to_string(),
+            name.parse().unwrap(),
+            env.parse().unwrap(),
             format!("http://{}_{}.example.com", name, env),
             tags,
+            now(),
         )
     }
 
@@ -168,18 +524,76 @@ mod tests {
 
         registry.register(entry.clone()).unwrap();
 
-        let result = registry.resolve("service1", "dev");
+        let result = registry.resolve(&name("service1"), &env("dev"));
         assert_eq!(result.len(), 1);
         let resolved = &result[0];
         assert_eq!(resolved.service_name, "service1");
         assert_eq!(resolved.environment, "dev");
     }
 
+    #[test]
+    fn test_environment_tags_merged_on_read() {
+        let mut registry = InMemoryRegistry::new().with_environment_tags(HashMap::from([(
+            env("prod-eu"),
+            HashMap::from([
+                ("region".to_string(), "eu-west-1".to_string()),
+                ("type".to_string(), "default".to_string()),
+            ]),
+        )]));
+        registry
+            .register(create_test_entry("service1", "prod-eu"))
+            .unwrap();
+        registry
+            .register(create_test_entry("service1", "dev"))
+            .unwrap();
+
+        let resolved = registry.resolve(&name("service1"), &env("prod-eu"));
+        assert_eq!(resolved[0].tags["region"], "eu-west-1");
+        assert_eq!(resolved[0].tags["type"], "test");
+        let dev = registry.resolve(&name("service1"), &env("dev"));
+        assert!(!dev[0].tags.contains_key("region"));
+
+        let promoted = registry
+            .promote(&name("service1"), &env("prod-eu"), &env("staging"), false)
+            .unwrap();
+        assert!(!promoted[0].tags.contains_key("region"));
+    }
+
+    #[test]
+    fn test_find_by_tags() {
+        let mut registry = InMemoryRegistry::new().with_environment_tags(HashMap::from([(
+            env("prod-eu"),
+            HashMap::from([("region".to_string(), "eu-west-1".to_string())]),
+        )]));
+        registry
+            .register(create_test_entry("service1", "prod-eu"))
+            .unwrap();
+        registry
+            .register(create_test_entry("service2", "dev"))
+            .unwrap();
+        let selectors = |value: &str| TagSelectors::parse(value).unwrap();
+
+        assert_eq!(registry.find_by_tags(&selectors("type=test")).len(), 2);
+        let eu = registry.find_by_tags(&selectors("type=test,region=eu-west-1"));
+        assert_eq!(eu.len(), 1);
+        assert_eq!(eu[0].tags["region"], "eu-west-1");
+        assert!(registry.find_by_tags(&selectors("type=other")).is_empty());
+        assert_eq!(registry.find_by_tags(&TagSelectors::default()).len(), 2);
+
+        registry
+            .promote(&name("service2"), &env("dev"), &env("staging"), true)
+            .unwrap();
+        registry.deregister(&name("service1"), None).unwrap();
+        let remaining = registry.find_by_tags(&selectors("type=test"));
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].environment, "staging");
+    }
+
     #[test]
     fn test_resolve_not_found() {
         let registry = InMemoryRegistry::new();
 
-        let result = registry.resolve("nonexistent", "dev");
+        let result = registry.resolve(&name("nonexistent"), &env("dev"));
         assert!(result.is_empty());
     }
 
@@ -196,12 +610,12 @@ mod tests {
             .unwrap();
 
         // Deregister specific environment
-        let result = registry.deregister("service1", Some("dev"));
+        let result = registry.deregister(&name("service1"), Some(&env("dev")));
         assert!(result.is_ok());
 
         // Verify only the dev environment was removed
-        assert!(registry.resolve("service1", "dev").is_empty());
-        assert_eq!(registry.resolve("service1", "prod").len(), 1);
+        assert!(registry.resolve(&name("service1"), &env("dev")).is_empty());
+        assert_eq!(registry.resolve(&name("service1"), &env("prod")).len(), 1);
     }
 
     #[test]
@@ -220,15 +634,15 @@ mod tests {
             .unwrap();
 
         // Deregister all environments for service1
-        let result = registry.deregister("service1", None);
+        let result = registry.deregister(&name("service1"), None);
         assert!(result.is_ok());
 
         // Verify all service1 entries were removed
-        assert!(registry.resolve("service1", "dev").is_empty());
-        assert!(registry.resolve("service1", "prod").is_empty());
+        assert!(registry.resolve(&name("service1"), &env("dev")).is_empty());
+        assert!(registry.resolve(&name("service1"), &env("prod")).is_empty());
 
         // Verify service2 still exists
-        assert_eq!(registry.resolve("service2", "dev").len(), 1);
+        assert_eq!(registry.resolve(&name("service2"), &env("dev")).len(), 1);
     }
 
     #[test]
@@ -236,7 +650,7 @@ mod tests {
         let mut registry = InMemoryRegistry::new();
 
         // Try to deregister a service that doesn't exist
-        let result = registry.deregister("nonexistent", Some("dev"));
+        let result = registry.deregister(&name("nonexistent"), Some(&env("dev")));
         assert!(result.is_err());
         match result {
             Err(RegistryError::NotFound) => {}
@@ -272,13 +686,13 @@ mod tests {
         assert_eq!(services.len(), 3);
 
         // Verify all expected services are in the list
-        let names: Vec<String> = services.iter().map(|s| s.service_name.clone()).collect();
-        assert!(names.contains(&"service1".to_string()));
-        assert!(names.contains(&"service2".to_string()));
+        let names: Vec<&str> = services.iter().map(|s| s.service_name.as_str()).collect();
+        assert!(names.contains(&"service1"));
+        assert!(names.contains(&"service2"));
 
-        let envs: Vec<String> = services.iter().map(|s| s.environment.clone()).collect();
-        assert!(envs.contains(&"dev".to_string()));
-        assert!(envs.contains(&"prod".to_string()));
+        let envs: Vec<&str> = services.iter().map(|s| s.environment.as_str()).collect();
+        assert!(envs.contains(&"dev"));
+        assert!(envs.contains(&"prod"));
     }
 
     #[tokio::test]
@@ -323,10 +737,11 @@ mod tests {
         tags.insert("special-key".to_string(), "special@value#123".to_string());
 
         let entry = ServiceEntry::new(
-            "service-with-dashes_and_underscores".to_string(),
-            "dev-environment_v1.2".to_string(),
+            "service-with-dashes_and_underscores".parse().unwrap(),
+            "dev-environment_v1.2".parse().unwrap(),
             "http://my-service.example.com:8080/api/v1".to_string(),
             tags,
+            now(),
         );
 
         let result = registry.register(entry.clone());
@@ -346,10 +761,11 @@ mod tests {
         let mut registry = InMemoryRegistry::new();
 
         let entry = ServiceEntry::new(
-            "no-tags-service".to_string(),
-            "prod".to_string(),
+            "no-tags-service".parse().unwrap(),
+            "prod".parse().unwrap(),
             "http://simple.example.com".to_string(),
             HashMap::new(),
+            now(),
         );
 
         let result = registry.register(entry.clone());
@@ -369,10 +785,11 @@ mod tests {
         tags.insert("owner".to_string(), "José María".to_string());
 
         let entry = ServiceEntry::new(
-            "unicode-service".to_string(),
-            "测试环境".to_string(),
+            "unicode-service".parse().unwrap(),
+            "测试环境".parse().unwrap(),
             "http://unicode.example.com".to_string(),
             tags.clone(),
+            now(),
         );
 
         let result = registry.register(entry.clone());
@@ -400,34 +817,258 @@ mod tests {
             .unwrap();
 
         // Deregister only "service" - should not affect others
-        let result = registry.deregister("service", Some("dev"));
+        let result = registry.deregister(&name("service"), Some(&env("dev")));
         assert!(result.is_ok());
 
         // Verify only the exact match was removed
-        assert!(registry.resolve("service", "dev").is_empty());
-        assert_eq!(registry.resolve("service1", "dev").len(), 1);
-        assert_eq!(registry.resolve("service-extended", "dev").len(), 1);
+        assert!(registry.resolve(&name("service"), &env("dev")).is_empty());
+        assert_eq!(registry.resolve(&name("service1"), &env("dev")).len(), 1);
+        assert_eq!(
+            registry
+                .resolve(&name("service-extended"), &env("dev"))
+                .len(),
+            1
+        );
     }
 
     #[test]
     fn test_heartbeat_update() {
+        let clock = VirtualClock::new(1_000);
+        let mut registry = InMemoryRegistry::new().with_clock(Arc::new(clock.clone()));
+
+        registry
+            .register(create_test_entry("service", "dev").at(clock.now()))
+            .unwrap();
+
+        let resolved_service = registry.resolve(&name("service"), &env("dev"));
+        assert_eq!(resolved_service[0].last_heartbeat, 1_000);
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(
+            resolved_service[0].time_since_last_heartbeat(clock.now()),
+            100
+        );
+
+        let _ = registry.heartbeat(&name("service"), &env("dev"));
+        let resolved_service = registry.resolve(&name("service"), &env("dev"));
+
+        assert_eq!(resolved_service[0].last_heartbeat, 1_100);
+    }
+
+    #[test]
+    fn test_deregister_instance() {
+        let mut registry = InMemoryRegistry::new();
+        let first = create_test_entry("service", "dev");
+        let second = create_test_entry("service", "dev");
+
+        registry.register(first.clone()).unwrap();
+        registry.register(second.clone()).unwrap();
+
+        assert!(registry.deregister_instance(&first.id).is_ok());
+
+        let remaining = registry.resolve(&name("service"), &env("dev"));
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, second.id);
+
+        match registry.deregister_instance(&first.id) {
+            Err(RegistryError::NotFound) => {}
+            _ => panic!("Expected NotFound error"),
+        }
+    }
+
+    #[test]
+    fn test_events_recorded_in_order() {
         let mut registry = InMemoryRegistry::new();
+        let entry = create_test_entry("service", "dev");
 
+        registry.register(entry.clone()).unwrap();
+        registry.heartbeat(&name("service"), &env("dev")).unwrap();
+        registry.deregister(&name("service"), None).unwrap();
+
+        let events = registry.events(0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].index, 1);
+        assert_eq!(events[0].kind, RegistryEventKind::Registered);
+        assert_eq!(events[0].instance_id, entry.id);
+        assert_eq!(events[1].index, 2);
+        assert_eq!(events[1].kind, RegistryEventKind::Deregistered);
+
+        let events = registry.events(1);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].index, 2);
+    }
+
+    #[test]
+    fn test_events_bounded() {
+        let mut registry = InMemoryRegistry::new();
+
+        for i in 0..MAX_EVENTS + 10 {
+            registry
+                .register(create_test_entry(&format!("service{}", i), "dev"))
+                .unwrap();
+        }
+
+        let events = registry.events(0);
+        assert_eq!(events.len(), MAX_EVENTS);
+        assert_eq!(events[0].index, 11);
+    }
+
+    #[test]
+    fn test_modify_index() {
+        let mut registry = InMemoryRegistry::new();
         registry
             .register(create_test_entry("service", "dev"))
             .unwrap();
+        registry
+            .register(create_test_entry("other", "dev"))
+            .unwrap();
+        registry.heartbeat(&name("service"), &env("dev")).unwrap();
+
+        assert_eq!(registry.last_index(), 2);
+        assert_eq!(registry.modify_index(&name("service")), 1);
+        assert_eq!(registry.modify_index(&name("other")), 2);
+        assert_eq!(registry.modify_index(&name("missing")), 0);
+
+        for i in 0..MAX_EVENTS {
+            registry
+                .register(create_test_entry(&format!("service{}", i), "dev"))
+                .unwrap();
+        }
+        assert_eq!(registry.modify_index(&name("service")), 1);
+    }
+
+    #[test]
+    fn test_heartbeat_instance() {
+        let mut registry = InMemoryRegistry::new();
+        let mut entry = create_test_entry("service", "dev");
+        entry.last_heartbeat = 0;
+        registry.register(entry.clone()).unwrap();
+
+        registry.heartbeat_instance(&entry.id).unwrap();
+
+        assert!(registry.resolve(&name("service"), &env("dev"))[0].last_heartbeat > 0);
+        match registry.heartbeat_instance(&id("missing")) {
+            Err(RegistryError::NotFound) => {}
+            _ => panic!("Expected NotFound error"),
+        }
+    }
+
+    #[test]
+    fn test_promote_copy_replaces_target() {
+        let mut registry = InMemoryRegistry::new();
+        let mut staging = create_test_entry("service", "staging");
+        staging.ttl_seconds = Some(10);
+        staging.health_check = Some("http://10.0.0.1/health".to_string());
+        registry.register(staging.clone()).unwrap();
+        registry
+            .register(create_test_entry("service", "prod"))
+            .unwrap();
+
+        let promoted = registry
+            .promote(&name("service"), &env("staging"), &env("prod"), false)
+            .unwrap();
+
+        assert_eq!(promoted.len(), 1);
+        assert_ne!(promoted[0].id, staging.id);
+        assert_eq!(promoted[0].tags, staging.tags);
+        assert_eq!(promoted[0].ttl_seconds, Some(10));
+        assert_eq!(promoted[0].health_check, staging.health_check);
+
+        let prod = registry.resolve(&name("service"), &env("prod"));
+        assert_eq!(prod.len(), 1);
+        assert_eq!(prod[0].address_str(), staging.address_str());
+        assert_eq!(registry.resolve(&name("service"), &env("staging")).len(), 1);
+
+        let last = registry.events(0).pop().unwrap();
+        assert_eq!(last.kind, RegistryEventKind::Promoted);
+        assert_eq!(last.environment, "prod");
+        assert!(last.detail.unwrap().contains(staging.id.as_str()));
+    }
+
+    #[test]
+    fn test_promote_move_removes_source() {
+        let mut registry = InMemoryRegistry::new();
+        registry
+            .register(create_test_entry("service", "staging"))
+            .unwrap();
+
+        registry
+            .promote(&name("service"), &env("staging"), &env("prod"), true)
+            .unwrap();
+
+        assert!(
+            registry
+                .resolve(&name("service"), &env("staging"))
+                .is_empty()
+        );
+        assert_eq!(registry.resolve(&name("service"), &env("prod")).len(), 1);
+    }
+
+    #[test]
+    fn test_promote_missing_source() {
+        let mut registry = InMemoryRegistry::new();
+        registry
+            .register(create_test_entry("service", "prod"))
+            .unwrap();
 
-        let resolved_service = registry.resolve("service", "dev");
-        let pre_heartbeat_time = resolved_service[0].last_heartbeat;
+        match registry.promote(&name("service"), &env("staging"), &env("prod"), false) {
+            Err(RegistryError::NotFound) => {}
+            _ => panic!("Expected NotFound error"),
+        }
+        assert_eq!(registry.resolve(&name("service"), &env("prod")).len(), 1);
+    }
 
-        sleep(Duration::from_millis(100));
+    #[test]
+    fn test_set_state() {
+        let mut registry = InMemoryRegistry::new();
+        let entry = create_test_entry("service", "dev");
+        registry.register(entry.clone()).unwrap();
 
-        assert!(resolved_service[0].time_since_last_heartbeat() > 0);
+        let updated = registry
+            .set_state(&entry.id, InstanceState::Draining)
+            .unwrap();
+        assert_eq!(updated.state, InstanceState::Draining);
 
-        let _ = registry.heartbeat("service", "dev");
-        let resolved_service = registry.resolve("service", "dev");
-        let post_heartbeat_time = resolved_service[0].last_heartbeat;
+        let last = registry.events(0).pop().unwrap();
+        assert_eq!(last.kind, RegistryEventKind::StateChanged);
+        assert_eq!(last.detail.unwrap(), "Up -> Draining");
 
-        assert!(pre_heartbeat_time < post_heartbeat_time);
+        match registry.set_state(&entry.id, InstanceState::Maintenance) {
+            Err(RegistryError::Conflict(_)) => {}
+            _ => panic!("Expected Conflict error"),
+        }
+        match registry.set_state(&id("missing"), InstanceState::Up) {
+            Err(RegistryError::NotFound) => {}
+            _ => panic!("Expected NotFound error"),
+        }
+    }
+
+    #[test]
+    fn test_list_at_rebuilds_past_states() {
+        let mut registry = InMemoryRegistry::new();
+        let entry = create_test_entry("service", "dev");
+        registry.register(entry.clone()).unwrap();
+        registry
+            .set_state(&entry.id, InstanceState::Draining)
+            .unwrap();
+        registry.deregister_instance(&entry.id).unwrap();
+
+        assert!(registry.list_at(0).unwrap().is_empty());
+        assert_eq!(registry.list_at(1).unwrap()[0].state, InstanceState::Up);
+        assert_eq!(
+            registry.list_at(2).unwrap()[0].state,
+            InstanceState::Draining
+        );
+        assert!(registry.list_at(3).unwrap().is_empty());
+
+        // Once the history is full the oldest states are folded away
+        for _ in 0..MAX_EVENTS {
+            registry
+                .register(create_test_entry("service", "dev"))
+                .unwrap();
+        }
+        assert!(registry.list_at(2).is_none());
+        assert_eq!(registry.list_at(4).unwrap().len(), 1);
+        assert_eq!(registry.list_at(500).unwrap().len(), 497);
     }
 }
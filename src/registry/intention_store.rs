@@ -0,0 +1,145 @@
+use crate::model::intention::{Intention, IntentionAction, WILDCARD};
+use crate::model::service_registry::RegistryError;
+use std::collections::HashMap;
+
+#[derive(Clone)]
+pub struct IntentionStore {
+    intentions: HashMap<(String, String), Intention>,
+}
+
+impl IntentionStore {
+    pub fn new() -> Self {
+        IntentionStore {
+            intentions: HashMap::new(),
+        }
+    }
+
+    pub fn list(&self) -> Vec<Intention> {
+        self.intentions.values().cloned().collect()
+    }
+
+    /// Creates or replaces the intention for its source and destination pair
+    pub fn upsert(&mut self, intention: Intention) {
+        let key = (intention.source.clone(), intention.destination.clone());
+        self.intentions.insert(key, intention);
+    }
+
+    pub fn remove(&mut self, source: &str, destination: &str) -> Result<(), RegistryError> {
+        self.intentions
+            .remove(&(source.to_string(), destination.to_string()))
+            .map(|_| ())
+            .ok_or(RegistryError::NotFound)
+    }
+
+    /// Returns the most specific intention governing a call from `source` to `destination`
+    pub fn lookup(&self, source: &str, destination: &str) -> Option<&Intention> {
+        self.intentions
+            .values()
+            .filter(|intention| intention.matches(source, destination))
+            .max_by_key(|intention| intention.precedence())
+    }
+
+    /// Decides whether `source` may call `destination`, allowing when no intention matches
+    pub fn is_allowed(&self, source: &str, destination: &str) -> bool {
+        self.lookup(source, destination)
+            .is_none_or(|intention| intention.action == IntentionAction::Allow)
+    }
+
+    /// Decides whether a caller that did not identify itself may call `destination`.
+    /// Only intentions from any source apply to it, and once intentions are
+    /// configured it is denied unless one of those allows the call
+    pub fn is_allowed_anonymously(&self, destination: &str) -> bool {
+        match self.lookup(WILDCARD, destination) {
+            Some(intention) => intention.action == IntentionAction::Allow,
+            None => self.intentions.is_empty(),
+        }
+    }
+}
+
+impl Default for IntentionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_intention(source: &str, destination: &str, action: IntentionAction) -> Intention {
+        Intention {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            action,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_default_allows() {
+        let store = IntentionStore::new();
+
+        assert!(store.lookup("web", "db").is_none());
+        assert!(store.is_allowed("web", "db"));
+    }
+
+    #[test]
+    fn test_upsert_replaces_existing_pair() {
+        let mut store = IntentionStore::new();
+
+        store.upsert(create_intention("web", "db", IntentionAction::Allow));
+        store.upsert(create_intention("web", "db", IntentionAction::Deny));
+
+        assert_eq!(store.list().len(), 1);
+        assert!(!store.is_allowed("web", "db"));
+    }
+
+    #[test]
+    fn test_exact_intention_overrides_wildcard() {
+        let mut store = IntentionStore::new();
+
+        store.upsert(create_intention("*", "*", IntentionAction::Deny));
+        store.upsert(create_intention("web", "db", IntentionAction::Allow));
+
+        assert!(store.is_allowed("web", "db"));
+        assert!(!store.is_allowed("api", "db"));
+        assert!(!store.is_allowed("web", "cache"));
+    }
+
+    #[test]
+    fn test_source_wildcard_overrides_destination_wildcard() {
+        let mut store = IntentionStore::new();
+
+        store.upsert(create_intention("web", "*", IntentionAction::Allow));
+        store.upsert(create_intention("*", "db", IntentionAction::Deny));
+
+        assert!(!store.is_allowed("web", "db"));
+        assert!(store.is_allowed("web", "cache"));
+    }
+
+    #[test]
+    fn test_anonymous_callers_need_a_wildcard_intention() {
+        let mut store = IntentionStore::new();
+        assert!(store.is_allowed_anonymously("db"));
+
+        store.upsert(create_intention("web", "db", IntentionAction::Allow));
+        assert!(!store.is_allowed_anonymously("db"));
+
+        store.upsert(create_intention("*", "docs", IntentionAction::Allow));
+        assert!(store.is_allowed_anonymously("docs"));
+        assert!(!store.is_allowed_anonymously("db"));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut store = IntentionStore::new();
+        store.upsert(create_intention("web", "db", IntentionAction::Deny));
+
+        assert!(store.remove("web", "db").is_ok());
+        assert!(store.is_allowed("web", "db"));
+        assert!(matches!(
+            store.remove("web", "db"),
+            Err(RegistryError::NotFound)
+        ));
+    }
+}
@@ -1 +1,15 @@
+pub mod agent_hub;
+pub mod deletion_store;
+pub mod environment_store;
+pub mod expectation_store;
+pub mod history_store;
+pub mod hooked_registry;
+pub mod idempotency_store;
 pub mod in_memory_registry;
+pub mod intention_store;
+pub mod profile_store;
+pub mod resolve_cache;
+pub mod service_meta_store;
+pub mod snapshot_store;
+pub mod tag_index;
+pub mod token_store;
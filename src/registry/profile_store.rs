@@ -0,0 +1,103 @@
+use crate::model::gc_policy::GcPolicy;
+use crate::model::identifiers::ServiceName;
+use crate::model::service_profile::{ServiceProfile, Visibility};
+use crate::model::service_registry::RegistryError;
+use std::collections::BTreeMap;
+
+pub struct ProfileStore {
+    profiles: BTreeMap<ServiceName, ServiceProfile>,
+}
+
+impl ProfileStore {
+    pub fn new() -> Self {
+        ProfileStore {
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    /// Returns every profile ordered by service name
+    pub fn list(&self) -> Vec<(ServiceName, ServiceProfile)> {
+        self.profiles
+            .iter()
+            .map(|(name, profile)| (name.clone(), profile.clone()))
+            .collect()
+    }
+
+    pub fn get(&self, service_name: &ServiceName) -> Option<&ServiceProfile> {
+        self.profiles.get(service_name)
+    }
+
+    /// Visibility of a service, public unless its profile says otherwise
+    pub fn visibility(&self, service_name: &ServiceName) -> Visibility {
+        self.get(service_name)
+            .map(|profile| profile.visibility)
+            .unwrap_or_default()
+    }
+
+    /// Garbage-collection policy of a service, which removes nothing unless its profile says otherwise
+    pub fn gc(&self, service_name: &ServiceName) -> GcPolicy {
+        self.get(service_name)
+            .map(|profile| profile.gc.clone())
+            .unwrap_or_default()
+    }
+
+    /// Creates or replaces the profile of a service
+    pub fn put(&mut self, service_name: ServiceName, profile: ServiceProfile) {
+        self.profiles.insert(service_name, profile);
+    }
+
+    pub fn remove(&mut self, service_name: &ServiceName) -> Result<(), RegistryError> {
+        self.profiles
+            .remove(service_name)
+            .map(|_| ())
+            .ok_or(RegistryError::NotFound)
+    }
+}
+
+impl Default for ProfileStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str) -> ServiceName {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn test_put_and_list() {
+        let mut store = ProfileStore::new();
+        store.put(service("search"), ServiceProfile::default());
+        store.put(
+            service("payments"),
+            ServiceProfile {
+                ttl_seconds: Some(10),
+                ..ServiceProfile::default()
+            },
+        );
+
+        let profiles = store.list();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].0, "payments");
+        assert_eq!(
+            store.get(&service("payments")).unwrap().ttl_seconds,
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut store = ProfileStore::new();
+        store.put(service("payments"), ServiceProfile::default());
+
+        assert!(store.remove(&service("payments")).is_ok());
+        assert!(matches!(
+            store.remove(&service("payments")),
+            Err(RegistryError::NotFound)
+        ));
+    }
+}
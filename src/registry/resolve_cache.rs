@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_registry::{RegistryReader, ServiceEntry};
+
+/// Millis a cached resolution is served for, bounding how far behind heartbeats
+/// it can be, since heartbeats do not move the modification index
+pub const DEFAULT_RESOLVE_CACHE_MILLIS: u64 = 1_000;
+
+/// Most resolutions kept at once, expired ones are dropped first
+const MAX_CACHED: usize = 10_000;
+
+struct Cached {
+    index: u64,
+    cached_at: u64,
+    entries: Vec<ServiceEntry>,
+}
+
+/// Counters describing the resolve cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResolveCacheStats {
+    pub max_age_millis: u64,
+    pub cached: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Instances of the most resolved services, reused until the service is
+/// modified or the entry is older than `max_age` millis
+pub struct ResolveCache {
+    max_age: u64,
+    cached: Mutex<HashMap<(ServiceName, Environment), Cached>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResolveCache {
+    /// Caches resolutions for `max_age` millis, 0 disables the cache
+    pub fn new(max_age: u64) -> Self {
+        ResolveCache {
+            max_age,
+            cached: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Instances of `service_name` in `environment` as `registry.resolve` returns
+    /// them, from the cache when they were resolved at the current modification
+    /// index of the service less than `max_age` millis before `at`
+    pub fn resolve(
+        &self,
+        registry: &dyn RegistryReader,
+        service_name: &ServiceName,
+        environment: &Environment,
+        at: u64,
+    ) -> Vec<ServiceEntry> {
+        if self.max_age == 0 {
+            return registry.resolve(service_name, environment);
+        }
+        let index = registry.modify_index(service_name);
+        let key = (service_name.clone(), environment.clone());
+        if let Some(hit) = self.cached.lock().unwrap().get(&key)
+            && hit.index == index
+            && at.saturating_sub(hit.cached_at) < self.max_age
+        {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return hit.entries.clone();
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let entries = registry.resolve(service_name, environment);
+        let mut cached = self.cached.lock().unwrap();
+        if cached.len() >= MAX_CACHED {
+            cached.retain(|_, entry| at.saturating_sub(entry.cached_at) < self.max_age);
+            if cached.len() >= MAX_CACHED {
+                cached.clear();
+            }
+        }
+        cached.insert(
+            key,
+            Cached {
+                index,
+                cached_at: at,
+                entries: entries.clone(),
+            },
+        );
+        entries
+    }
+
+    pub fn stats(&self) -> ResolveCacheStats {
+        ResolveCacheStats {
+            max_age_millis: self.max_age,
+            cached: self.cached.lock().unwrap().len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::{RegistryWriter, now};
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    fn create_entry() -> ServiceEntry {
+        ServiceEntry::new(
+            "payments".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_resolve_cache() {
+        let cache = ResolveCache::new(1_000);
+        let mut registry = InMemoryRegistry::new();
+        registry.register(create_entry()).unwrap();
+        let name = "payments".parse().unwrap();
+        let environment = "prod".parse().unwrap();
+
+        assert_eq!(cache.resolve(&registry, &name, &environment, 0).len(), 1);
+        assert_eq!(cache.resolve(&registry, &name, &environment, 500).len(), 1);
+        assert_eq!(cache.stats().hits, 1);
+
+        // A write to the service invalidates it right away
+        registry.register(create_entry()).unwrap();
+        assert_eq!(cache.resolve(&registry, &name, &environment, 600).len(), 2);
+        // And so does age, for heartbeats that leave the index alone
+        cache.resolve(&registry, &name, &environment, 1_600);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 3));
+    }
+}
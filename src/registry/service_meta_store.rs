@@ -0,0 +1,129 @@
+use crate::model::identifiers::{Environment, ServiceName};
+use crate::model::service_meta::ServiceMeta;
+use crate::model::service_registry::RegistryError;
+use std::collections::HashMap;
+
+pub struct ServiceMetaStore {
+    documents: HashMap<(ServiceName, Environment), ServiceMeta>,
+}
+
+impl ServiceMetaStore {
+    pub fn new() -> Self {
+        ServiceMetaStore {
+            documents: HashMap::new(),
+        }
+    }
+
+    pub fn get(
+        &self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Option<&ServiceMeta> {
+        self.documents
+            .get(&(service_name.clone(), environment.clone()))
+    }
+
+    /// Replaces the metadata document of a service in an environment
+    pub fn put(&mut self, service_name: ServiceName, environment: Environment, meta: ServiceMeta) {
+        self.documents.insert((service_name, environment), meta);
+    }
+
+    /// Returns the service and environment pairs whose document names `team` as owner
+    pub fn owned_by(&self, team: &str) -> Vec<(ServiceName, Environment)> {
+        self.documents
+            .iter()
+            .filter(|(_, meta)| meta.owner.as_deref() == Some(team))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    pub fn remove(
+        &mut self,
+        service_name: &ServiceName,
+        environment: &Environment,
+    ) -> Result<(), RegistryError> {
+        self.documents
+            .remove(&(service_name.clone(), environment.clone()))
+            .map(|_| ())
+            .ok_or(RegistryError::NotFound)
+    }
+}
+
+impl Default for ServiceMetaStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str) -> ServiceName {
+        name.parse().unwrap()
+    }
+
+    fn env(name: &str) -> Environment {
+        name.parse().unwrap()
+    }
+
+    fn create_meta(owner: &str) -> ServiceMeta {
+        ServiceMeta {
+            owner: Some(owner.to_string()),
+            ..ServiceMeta::default()
+        }
+    }
+
+    #[test]
+    fn test_put_is_scoped_to_environment() {
+        let mut store = ServiceMetaStore::new();
+        store.put(service("payments"), env("prod"), create_meta("team-a"));
+
+        assert_eq!(
+            store.get(&service("payments"), &env("prod")),
+            Some(&create_meta("team-a"))
+        );
+        assert!(store.get(&service("payments"), &env("staging")).is_none());
+    }
+
+    #[test]
+    fn test_put_replaces_document() {
+        let mut store = ServiceMetaStore::new();
+        store.put(service("payments"), env("prod"), create_meta("team-a"));
+        store.put(service("payments"), env("prod"), create_meta("team-b"));
+
+        assert_eq!(
+            store
+                .get(&service("payments"), &env("prod"))
+                .unwrap()
+                .owner
+                .as_deref(),
+            Some("team-b")
+        );
+    }
+
+    #[test]
+    fn test_owned_by() {
+        let mut store = ServiceMetaStore::new();
+        store.put(service("payments"), env("prod"), create_meta("team-a"));
+        store.put(service("search"), env("prod"), create_meta("team-b"));
+
+        assert_eq!(
+            store.owned_by("team-a"),
+            vec![(service("payments"), env("prod"))]
+        );
+        assert!(store.owned_by("team-c").is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut store = ServiceMetaStore::new();
+        store.put(service("payments"), env("prod"), create_meta("team-a"));
+
+        assert!(store.remove(&service("payments"), &env("prod")).is_ok());
+        assert!(matches!(
+            store.remove(&service("payments"), &env("prod")),
+            Err(RegistryError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::model::service_registry::ServiceEntry;
+
+/// Snapshots are kept for five minutes after they were last paged through
+pub const SNAPSHOT_TTL: u64 = 300_000;
+
+/// Longest a snapshot is kept however often it is paged through, as heartbeats
+/// do not move the modification index it is pinned at
+pub const MAX_SNAPSHOT_AGE: u64 = 900_000;
+
+/// Most snapshots kept at once, the least recently used is dropped first
+const MAX_SNAPSHOTS: usize = 16;
+
+struct Snapshot {
+    entries: Arc<Vec<ServiceEntry>>,
+    pinned_at: u64,
+    expires_at: u64,
+}
+
+impl Snapshot {
+    fn is_kept(&self, at: u64) -> bool {
+        self.expires_at > at && at.saturating_sub(self.pinned_at) < MAX_SNAPSHOT_AGE
+    }
+}
+
+/// Copies of the registry pinned at a modification index, so paging through
+/// them while the registry changes never skips or repeats an instance
+#[derive(Default)]
+pub struct SnapshotStore {
+    snapshots: BTreeMap<u64, Snapshot>,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        SnapshotStore::default()
+    }
+
+    /// Pins `entries`, the registry at `index`, at time `at`, sorted by instance id.
+    /// An existing snapshot of the same index is reused
+    pub fn pin(
+        &mut self,
+        index: u64,
+        entries: impl FnOnce() -> Vec<ServiceEntry>,
+        at: u64,
+    ) -> Arc<Vec<ServiceEntry>> {
+        self.snapshots.retain(|_, snapshot| snapshot.is_kept(at));
+        if let Some(snapshot) = self.snapshots.get_mut(&index) {
+            snapshot.expires_at = at + SNAPSHOT_TTL;
+            return snapshot.entries.clone();
+        }
+
+        if self.snapshots.len() >= MAX_SNAPSHOTS
+            && let Some(oldest) = self
+                .snapshots
+                .iter()
+                .min_by_key(|(_, snapshot)| snapshot.expires_at)
+                .map(|(index, _)| *index)
+        {
+            self.snapshots.remove(&oldest);
+        }
+
+        let mut entries = entries();
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        let entries = Arc::new(entries);
+        self.snapshots.insert(
+            index,
+            Snapshot {
+                entries: entries.clone(),
+                pinned_at: at,
+                expires_at: at + SNAPSHOT_TTL,
+            },
+        );
+        entries
+    }
+
+    /// Snapshot at `index` if it is still kept at time `at`
+    pub fn get(&mut self, index: u64, at: u64) -> Option<Arc<Vec<ServiceEntry>>> {
+        self.snapshots.retain(|_, snapshot| snapshot.is_kept(at));
+        let snapshot = self.snapshots.get_mut(&index)?;
+        snapshot.expires_at = at + SNAPSHOT_TTL;
+        Some(snapshot.entries.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+    use std::collections::HashMap;
+
+    fn entry(name: &str) -> ServiceEntry {
+        ServiceEntry::new(
+            name.parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_pin_and_expire() {
+        let mut store = SnapshotStore::new();
+        let pinned = store.pin(3, || vec![entry("search"), entry("payments")], 0);
+        assert!(pinned.windows(2).all(|pair| pair[0].id < pair[1].id));
+
+        let reused = store.pin(3, || panic!("Snapshot should be reused"), 1_000);
+        assert!(Arc::ptr_eq(&pinned, &reused));
+
+        assert!(store.get(3, 1_000 + SNAPSHOT_TTL - 1).is_some());
+        assert!(store.get(3, 1_000 + 2 * SNAPSHOT_TTL).is_none());
+        assert!(store.get(4, 0).is_none());
+    }
+
+    #[test]
+    fn test_age_is_capped() {
+        let mut store = SnapshotStore::new();
+        store.pin(3, || vec![entry("search")], 0);
+        // Paging keeps the snapshot alive, but only up to its maximum age
+        let mut at = 0;
+        while at + SNAPSHOT_TTL / 2 < MAX_SNAPSHOT_AGE {
+            at += SNAPSHOT_TTL / 2;
+            assert!(store.get(3, at).is_some());
+        }
+        assert!(store.get(3, MAX_SNAPSHOT_AGE).is_none());
+        assert!(store.pin(3, Vec::new, MAX_SNAPSHOT_AGE).is_empty());
+    }
+}
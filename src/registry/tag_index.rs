@@ -0,0 +1,101 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::model::identifiers::InstanceId;
+use crate::model::service_registry::ServiceEntry;
+use crate::model::tag_selector::TagSelectors;
+
+/// Inverted index from a tag `key=value` pair to the instances carrying it, so
+/// tag selections do not scan the whole registry
+#[derive(Default)]
+pub struct TagIndex {
+    postings: HashMap<(String, String), HashSet<InstanceId>>,
+}
+
+impl TagIndex {
+    pub fn insert(&mut self, entry: &ServiceEntry) {
+        for (key, value) in &entry.tags {
+            self.postings
+                .entry((key.clone(), value.clone()))
+                .or_default()
+                .insert(entry.id.clone());
+        }
+    }
+
+    pub fn remove(&mut self, entry: &ServiceEntry) {
+        for (key, value) in &entry.tags {
+            let pair = (key.clone(), value.clone());
+            if let Some(ids) = self.postings.get_mut(&pair) {
+                ids.remove(&entry.id);
+                if ids.is_empty() {
+                    self.postings.remove(&pair);
+                }
+            }
+        }
+    }
+
+    /// Instances carrying every selected tag, `None` when nothing is selected.
+    /// Intersects from the rarest pair so the work is bounded by the smallest match
+    pub fn find(&self, selectors: &TagSelectors) -> Option<HashSet<InstanceId>> {
+        let mut postings = Vec::new();
+        for pair in selectors.iter() {
+            match self.postings.get(pair) {
+                Some(ids) => postings.push(ids),
+                None => return Some(HashSet::new()),
+            }
+        }
+        postings.sort_by_key(|ids| ids.len());
+        let (rarest, rest) = postings.split_first()?;
+        Some(
+            rarest
+                .iter()
+                .filter(|id| rest.iter().all(|ids| ids.contains(*id)))
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::service_registry::now;
+
+    fn create_entry(tags: &[(&str, &str)]) -> ServiceEntry {
+        ServiceEntry::new(
+            "payments".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            tags.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            now(),
+        )
+    }
+
+    #[test]
+    fn test_tag_index() {
+        let mut index = TagIndex::default();
+        let eu = create_entry(&[("team", "billing"), ("region", "eu")]);
+        let us = create_entry(&[("team", "billing"), ("region", "us")]);
+        index.insert(&eu);
+        index.insert(&us);
+
+        let billing = TagSelectors::parse("team=billing").unwrap();
+        assert_eq!(index.find(&billing).unwrap().len(), 2);
+        let billing_eu = TagSelectors::parse("team=billing,region=eu").unwrap();
+        assert_eq!(
+            index.find(&billing_eu).unwrap(),
+            HashSet::from([eu.id.clone()])
+        );
+        let search = TagSelectors::parse("team=search").unwrap();
+        assert!(index.find(&search).unwrap().is_empty());
+        assert!(index.find(&TagSelectors::default()).is_none());
+
+        index.remove(&eu);
+        assert!(index.find(&billing_eu).unwrap().is_empty());
+        assert_eq!(
+            index.find(&billing).unwrap(),
+            HashSet::from([us.id.clone()])
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ring::digest::{SHA256, digest};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+
+use crate::model::service_registry::RegistryError;
+
+/// Millis before its expiry from which using a token is reported
+pub const EXPIRY_WARNING: u64 = 24 * 60 * 60 * 1000;
+
+/// Millis between two reports of the same expiring token being used
+const WARNING_INTERVAL: u64 = 60 * 60 * 1000;
+
+/// Most token events kept, older ones are dropped first
+const MAX_EVENTS: usize = 1000;
+
+/// Admin token issued at runtime, in addition to the one the server started with
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedToken {
+    pub id: String,
+    pub label: String,
+    /// SHA-256 of the secret, only the hash is kept once the token is issued
+    #[serde(skip)]
+    secret_digest: Vec<u8>,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Token that replaced this one, which stays valid until its grace window ends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub successor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<u64>,
+    #[serde(skip)]
+    warned_at: Option<u64>,
+}
+
+impl IssuedToken {
+    fn is_expired(&self, at: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= at)
+    }
+
+    /// Whether clients should have moved off this token already
+    fn is_expiring(&self, at: u64) -> bool {
+        self.successor.is_some()
+            || self
+                .expires_at
+                .is_some_and(|expires_at| expires_at.saturating_sub(at) < EXPIRY_WARNING)
+    }
+}
+
+/// A token together with its secret, only ever returned when it is issued
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedSecret {
+    #[serde(flatten)]
+    pub token: IssuedToken,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TokenEventKind {
+    Issued,
+    Rotated,
+    Revoked,
+    /// A token past its rotation or close to its expiry authenticated a request
+    UsedWhileExpiring,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenEvent {
+    pub at: u64,
+    pub kind: TokenEventKind,
+    pub token_id: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+/// Admin tokens issued, rotated and revoked through `/admin/tokens`
+#[derive(Default)]
+pub struct TokenStore {
+    tokens: Vec<IssuedToken>,
+    events: VecDeque<TokenEvent>,
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("System random source is available");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+impl TokenStore {
+    fn record(&mut self, kind: TokenEventKind, token: &IssuedToken, at: u64) {
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(TokenEvent {
+            at,
+            kind,
+            token_id: token.id.clone(),
+            label: token.label.clone(),
+            expires_at: token.expires_at,
+        });
+    }
+
+    /// Tokens that have not expired at `at`, oldest first
+    pub fn list(&mut self, at: u64) -> Vec<IssuedToken> {
+        self.tokens.retain(|token| !token.is_expired(at));
+        self.tokens.clone()
+    }
+
+    pub fn events(&self) -> Vec<TokenEvent> {
+        self.events.iter().cloned().collect()
+    }
+
+    /// Issues a new token valid until `expires_at`, or until revoked when unset
+    pub fn issue(&mut self, label: &str, expires_at: Option<u64>, at: u64) -> IssuedSecret {
+        let secret = generate_secret();
+        let token = IssuedToken {
+            id: uuid::Uuid::new_v4().to_string(),
+            label: label.to_string(),
+            secret_digest: digest(&SHA256, secret.as_bytes()).as_ref().to_vec(),
+            created_at: at,
+            expires_at,
+            successor: None,
+            last_used_at: None,
+            warned_at: None,
+        };
+        self.record(TokenEventKind::Issued, &token, at);
+        self.tokens.push(token.clone());
+        IssuedSecret { secret, token }
+    }
+
+    /// Issues a successor to token `id` valid until `expires_at`, keeping `id`
+    /// valid for `grace` more millis so clients can move over
+    pub fn rotate(
+        &mut self,
+        id: &str,
+        grace: u64,
+        expires_at: Option<u64>,
+        at: u64,
+    ) -> Result<IssuedSecret, RegistryError> {
+        let position = self
+            .tokens
+            .iter()
+            .position(|token| token.id == id && !token.is_expired(at))
+            .ok_or(RegistryError::NotFound)?;
+        if let Some(successor) = &self.tokens[position].successor {
+            return Err(RegistryError::Conflict(format!(
+                "Token {} was already rotated to {}",
+                id, successor
+            )));
+        }
+
+        let label = self.tokens[position].label.clone();
+        let successor = self.issue(&label, expires_at, at);
+        let token = &mut self.tokens[position];
+        token.successor = Some(successor.token.id.clone());
+        let grace_ends = at.saturating_add(grace);
+        token.expires_at = Some(
+            token
+                .expires_at
+                .map_or(grace_ends, |expires_at| expires_at.min(grace_ends)),
+        );
+        let token = token.clone();
+        self.record(TokenEventKind::Rotated, &token, at);
+        Ok(successor)
+    }
+
+    pub fn revoke(&mut self, id: &str, at: u64) -> Result<(), RegistryError> {
+        let position = self
+            .tokens
+            .iter()
+            .position(|token| token.id == id)
+            .ok_or(RegistryError::NotFound)?;
+        let token = self.tokens.remove(position);
+        self.record(TokenEventKind::Revoked, &token, at);
+        Ok(())
+    }
+
+    /// Whether `secret` is a valid token at `at`
+    pub fn authenticate(&mut self, secret: &str, at: u64) -> bool {
+        self.identify(secret, at).is_some()
+    }
+
+    /// The token whose secret is `secret`, if valid at `at`. Uses of a rotated
+    /// token or of one about to expire are recorded, at most once an hour per token
+    pub fn identify(&mut self, secret: &str, at: u64) -> Option<IssuedToken> {
+        // Hashes are compared instead of secrets so response timings do not leak secret prefixes
+        let secret_digest = digest(&SHA256, secret.as_bytes());
+        let position = self.tokens.iter().position(|token| {
+            token.secret_digest == secret_digest.as_ref() && !token.is_expired(at)
+        })?;
+
+        let token = &mut self.tokens[position];
+        token.last_used_at = Some(at);
+        if token.is_expiring(at)
+            && token
+                .warned_at
+                .is_none_or(|warned_at| at.saturating_sub(warned_at) >= WARNING_INTERVAL)
+        {
+            token.warned_at = Some(at);
+            let token = token.clone();
+            eprintln!(
+                "Admin token {} ({}) is still in use although it expires at {}",
+                token.id,
+                token.label,
+                token.expires_at.unwrap_or_default()
+            );
+            self.record(TokenEventKind::UsedWhileExpiring, &token, at);
+        }
+        Some(self.tokens[position].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60 * 1000;
+
+    #[test]
+    fn test_rotation_keeps_old_token_for_grace_window() {
+        let mut store = TokenStore::default();
+        let old = store.issue("ci", None, 0);
+        assert!(store.authenticate(&old.secret, HOUR));
+
+        let new = store.rotate(&old.token.id, 2 * HOUR, None, HOUR).unwrap();
+        assert!(store.rotate(&old.token.id, HOUR, None, HOUR).is_err());
+        assert!(store.authenticate(&new.secret, 2 * HOUR));
+        // The old token still works during the grace window, and its use is reported once an hour
+        assert!(store.authenticate(&old.secret, 2 * HOUR));
+        assert!(store.authenticate(&old.secret, 2 * HOUR + 1));
+        assert!(!store.authenticate(&old.secret, 3 * HOUR));
+
+        let kinds: Vec<TokenEventKind> = store.events().iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenEventKind::Issued,
+                TokenEventKind::Issued,
+                TokenEventKind::Rotated,
+                TokenEventKind::UsedWhileExpiring,
+            ]
+        );
+        assert_eq!(store.list(3 * HOUR).len(), 1);
+
+        store.revoke(&new.token.id, 3 * HOUR).unwrap();
+        assert!(!store.authenticate(&new.secret, 3 * HOUR));
+    }
+
+    #[test]
+    fn test_use_close_to_expiry_is_reported() {
+        let mut store = TokenStore::default();
+        let token = store.issue("agent", Some(EXPIRY_WARNING + HOUR), 0);
+        assert!(store.authenticate(&token.secret, 0));
+        assert!(store.authenticate(&token.secret, 2 * HOUR));
+        assert_eq!(
+            store.events().last().unwrap().kind,
+            TokenEventKind::UsedWhileExpiring
+        );
+        assert!(!store.authenticate(&token.secret, EXPIRY_WARNING + HOUR));
+        assert!(!store.authenticate("unknown", 0));
+    }
+}
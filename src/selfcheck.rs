@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use tokio::sync::RwLock;
+
+use crate::client::{ClientError, XolotlClient};
+use crate::model::clock::SharedClock;
+use crate::model::selfcheck::SelfCheckReport;
+
+pub const SELFCHECK_SERVICE: &str = "xolotl-selfcheck";
+pub const SELFCHECK_ENVIRONMENT: &str = "selfcheck";
+
+/// Heartbeats and resolves the synthetic service through the public API,
+/// registering it first when the registry does not know it
+pub async fn run_selfcheck(client: &XolotlClient, address: &str) -> Result<Duration, ClientError> {
+    let started = Instant::now();
+
+    match client
+        .heartbeat(SELFCHECK_SERVICE, SELFCHECK_ENVIRONMENT)
+        .await
+    {
+        Err(ClientError::Status(StatusCode::NOT_FOUND)) => {
+            let tags = HashMap::from([("synthetic".to_string(), "true".to_string())]);
+            client
+                .register(SELFCHECK_SERVICE, SELFCHECK_ENVIRONMENT, address, tags)
+                .await?;
+        }
+        result => {
+            result?;
+        }
+    }
+    client
+        .resolve(SELFCHECK_SERVICE, SELFCHECK_ENVIRONMENT)
+        .await?;
+
+    Ok(started.elapsed())
+}
+
+/// Runs `run_selfcheck` against the server at `url` every interval for the
+/// lifetime of the process, recording each outcome in `report`
+pub fn spawn_selfcheck(
+    url: String,
+    admin_token: Option<String>,
+    interval: Duration,
+    report: Arc<RwLock<SelfCheckReport>>,
+    clock: SharedClock,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let client = XolotlClient::new(&url, admin_token);
+        report.write().await.enabled = true;
+
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let result = run_selfcheck(&client, &url).await;
+
+            let mut report = report.write().await;
+            match result {
+                Ok(latency) => report.record_success(clock.now(), latency.as_millis() as u64),
+                Err(e) => {
+                    eprintln!("Self check failed: {}", e);
+                    report.record_failure(clock.now(), e.to_string());
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+
+    #[tokio::test]
+    async fn test_run_selfcheck_registers_once() {
+        let server = TestServer::start().await;
+
+        let client = server.client();
+        run_selfcheck(&client, server.url()).await.unwrap();
+        run_selfcheck(&client, server.url()).await.unwrap();
+
+        let instances = server.registry().read().await.list();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].service_name, SELFCHECK_SERVICE);
+        assert_eq!(instances[0].tags["synthetic"], "true");
+    }
+
+    #[tokio::test]
+    async fn test_run_selfcheck_reports_unreachable_server() {
+        let client = XolotlClient::new("http://127.0.0.1:1", None);
+
+        assert!(run_selfcheck(&client, "http://127.0.0.1:1").await.is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! Self-registration for Rust services built on axum.
+//!
+//! ```ignore
+//! let service = XolotlService::builder("http://xolotl:8000")
+//!     .name("payments")
+//!     .environment("prod")
+//!     .bind("0.0.0.0:8080")
+//!     .address("http://payments-1:8080")
+//!     .tag("team", "core")
+//!     .spawn_with(app)
+//!     .await?;
+//! // Serving, registered and heartbeating until `service` is shut down or dropped
+//! ```
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::Router;
+use reqwest::StatusCode;
+use tokio::task::JoinHandle;
+
+use crate::app_health::AppHealth;
+use crate::client::{ClientError, XolotlClient};
+
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Shortest time between heartbeats, shorter intervals are raised to it
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What a service registers itself as, built by `XolotlService::builder`
+pub struct XolotlServiceBuilder {
+    client: XolotlClient,
+    name: Option<String>,
+    environment: Option<String>,
+    bind: String,
+    address: Option<String>,
+    tags: HashMap<String, String>,
+    heartbeat_interval: Duration,
+    health: Option<AppHealth>,
+}
+
+/// An axum app being served and kept registered in Xolotl.
+///
+/// The instance is deregistered by `shutdown`, or in the background when the
+/// handle is dropped inside a Tokio runtime.
+pub struct XolotlService {
+    registration: Option<Registration>,
+    address: String,
+    server: JoinHandle<()>,
+    heartbeat: JoinHandle<()>,
+}
+
+/// The registered instance, shared with the heartbeat loop
+#[derive(Clone)]
+struct Registration {
+    client: std::sync::Arc<XolotlClient>,
+    name: String,
+    environment: String,
+    address: String,
+    tags: HashMap<String, String>,
+    health: Option<AppHealth>,
+}
+
+impl Registration {
+    /// Registers the instance and looks up the id the registry gave it
+    async fn register(&self) -> Result<String, ClientError> {
+        self.client
+            .register(
+                &self.name,
+                &self.environment,
+                &self.address,
+                self.tags.clone(),
+            )
+            .await?;
+        self.instance_id()
+            .await?
+            .ok_or(ClientError::Status(StatusCode::NOT_FOUND))
+    }
+
+    /// Id of the latest instance of the service registered at its address
+    async fn instance_id(&self) -> Result<Option<String>, ClientError> {
+        let instances = self.client.by_address(&self.address).await?;
+        Ok(instances
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|instance| {
+                instance["service_name"] == self.name.as_str()
+                    && instance["environment"] == self.environment.as_str()
+            })
+            .filter_map(|instance| instance["id"].as_str())
+            .next_back()
+            .map(str::to_string))
+    }
+
+    /// Heartbeats every `interval`, or as often as the server suggests when it paces
+    /// heartbeats, registering again whenever the registry lost the instance and
+    /// marking it `Down` while its app is unhealthy
+    async fn keep_alive(self, mut id: String, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        let mut period = interval;
+        let mut reported_healthy = true;
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            match self.client.heartbeat_instance(&id).await {
+                Ok(response) => {
+                    let suggested = response["heartbeat_interval_seconds"]
+                        .as_u64()
+                        .map_or(interval, Duration::from_secs)
+                        .max(MIN_HEARTBEAT_INTERVAL);
+                    if suggested != period {
+                        period = suggested;
+                        ticker =
+                            tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                    }
+
+                    let healthy = self.health.as_ref().is_none_or(AppHealth::is_healthy);
+                    if healthy != reported_healthy {
+                        let state = if healthy { "Up" } else { "Down" };
+                        match self.client.set_state(&id, state).await {
+                            Ok(_) => reported_healthy = healthy,
+                            Err(e) => eprintln!("Failed to report {} {}: {}", self.name, state, e),
+                        }
+                    }
+                }
+                Err(ClientError::Status(StatusCode::NOT_FOUND)) => match self.register().await {
+                    Ok(new_id) => {
+                        id = new_id;
+                        reported_healthy = true;
+                    }
+                    Err(e) => eprintln!("Failed to register {} again: {}", self.name, e),
+                },
+                Err(e) => eprintln!("Heartbeat for {} failed: {}", self.name, e),
+            }
+        }
+    }
+
+    async fn deregister(&self) {
+        // The instance may have been registered again under another id since
+        let result = match self.instance_id().await {
+            Ok(Some(id)) => self.client.deregister_instance(&id).await.map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to deregister {}: {}", self.name, e);
+        }
+    }
+}
+
+impl XolotlService {
+    /// Starts describing a service registering with the Xolotl server at `url`
+    pub fn builder(url: &str) -> XolotlServiceBuilder {
+        XolotlServiceBuilder {
+            client: XolotlClient::new(url, None),
+            name: None,
+            environment: None,
+            bind: "127.0.0.1:0".to_string(),
+            address: None,
+            tags: HashMap::new(),
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            health: None,
+        }
+    }
+
+    /// The address the service registered, where its app is reachable
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Deregisters the instance and stops serving
+    pub async fn shutdown(mut self) {
+        self.heartbeat.abort();
+        if let Some(registration) = self.registration.take() {
+            registration.deregister().await;
+        }
+        self.server.abort();
+    }
+}
+
+impl Drop for XolotlService {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        self.server.abort();
+        if let Some(registration) = self.registration.take()
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            runtime.spawn(async move { registration.deregister().await });
+        }
+    }
+}
+
+impl XolotlServiceBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.environment = Some(environment.to_string());
+        self
+    }
+
+    /// Admin token sent to the registry, needed to deregister when the server has one
+    pub fn admin_token(mut self, token: &str) -> Self {
+        self.client = self.client.with_admin_token(token);
+        self
+    }
+
+    /// Shared secret signing registrations and heartbeats, for registries
+    /// started with `--signing-secrets`
+    pub fn signing_secret(mut self, secret: &str) -> Self {
+        self.client = self.client.with_signing_secret(secret);
+        self
+    }
+
+    /// Socket address the app listens on, `127.0.0.1:0` by default
+    pub fn bind(mut self, bind: &str) -> Self {
+        self.bind = bind.to_string();
+        self
+    }
+
+    /// Address registered for the instance, `http://` and the bound socket address by default
+    pub fn address(mut self, address: &str) -> Self {
+        self.address = Some(address.to_string());
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Time between heartbeats, 10 seconds by default and at least 10 millis
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval.max(MIN_HEARTBEAT_INTERVAL);
+        self
+    }
+
+    /// Reports the instance `Down` while `health`, usually fed by a
+    /// `HealthLayer` on the app, is unhealthy and `Up` once it recovers
+    pub fn health(mut self, health: AppHealth) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Serves `app`, registers the instance once it is listening and starts heartbeating
+    pub async fn spawn_with(self, app: Router) -> Result<XolotlService, ClientError> {
+        let name = self.name.ok_or(ClientError::Incomplete("name"))?;
+        let environment = self
+            .environment
+            .ok_or(ClientError::Incomplete("environment"))?;
+        let listener = tokio::net::TcpListener::bind(&self.bind).await?;
+        let address = match self.address {
+            Some(address) => address,
+            None => format!("http://{}", listener.local_addr()?),
+        };
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                eprintln!("Server stopped: {}", e);
+            }
+        });
+
+        let registration = Registration {
+            client: std::sync::Arc::new(self.client),
+            name,
+            environment,
+            address: address.clone(),
+            tags: self.tags,
+            health: self.health,
+        };
+        let id = match registration.register().await {
+            Ok(id) => id,
+            Err(e) => {
+                server.abort();
+                return Err(e);
+            }
+        };
+        let heartbeat = tokio::spawn(registration.clone().keep_alive(id, self.heartbeat_interval));
+
+        Ok(XolotlService {
+            registration: Some(registration),
+            address,
+            server,
+            heartbeat,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_health::HealthLayer;
+    use crate::testing::TestServer;
+    use axum::routing::get;
+
+    #[tokio::test]
+    async fn test_registers_serves_and_deregisters() {
+        let registry = TestServer::start().await;
+        let app = Router::new().route("/hello", get(|| async { "hello" }));
+
+        let service = XolotlService::builder(registry.url())
+            .name("payments")
+            .environment("prod")
+            .tag("team", "core")
+            .heartbeat_interval(Duration::from_millis(50))
+            .spawn_with(app)
+            .await
+            .unwrap();
+
+        let instances = registry.client().resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["address"], service.address());
+        assert_eq!(instances[0]["tags"]["team"], "core");
+        let body = reqwest::get(format!("{}/hello", service.address()))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "hello");
+
+        // The heartbeat loop registers the instance again once the registry lost it
+        let id = instances[0]["id"].as_str().unwrap().to_string();
+        registry.client().deregister_instance(&id).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(registry.client().resolve("payments", "prod").await.is_ok());
+
+        service.shutdown().await;
+        assert!(matches!(
+            registry.client().resolve("payments", "prod").await,
+            Err(ClientError::Status(StatusCode::NOT_FOUND))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reports_app_health() {
+        let registry = TestServer::start().await;
+        let health = AppHealth::new(10, 0.5);
+        let app = Router::new()
+            .route("/fail", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .layer(HealthLayer::new(health.clone()));
+
+        let service = XolotlService::builder(registry.url())
+            .name("payments")
+            .environment("prod")
+            .heartbeat_interval(Duration::from_millis(50))
+            .health(health)
+            .spawn_with(app)
+            .await
+            .unwrap();
+        for _ in 0..10 {
+            reqwest::get(format!("{}/fail", service.address()))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let instances = registry.client().list().await.unwrap();
+        assert_eq!(instances[0]["state"], "Down");
+        assert!(matches!(
+            registry.client().resolve("payments", "prod").await,
+            Err(ClientError::Status(StatusCode::NOT_FOUND))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_requires_a_name() {
+        let registry = TestServer::start().await;
+
+        assert!(matches!(
+            XolotlService::builder(registry.url())
+                .environment("prod")
+                .spawn_with(Router::new())
+                .await,
+            Err(ClientError::Incomplete("name"))
+        ));
+    }
+
+    #[test]
+    fn test_clamps_zero_heartbeat_interval() {
+        let builder =
+            XolotlService::builder("http://127.0.0.1:8000").heartbeat_interval(Duration::ZERO);
+        assert_eq!(builder.heartbeat_interval, MIN_HEARTBEAT_INTERVAL);
+    }
+}
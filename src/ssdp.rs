@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+
+pub const SSDP_PORT: u16 = 1900;
+const SSDP_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+/// Search target identifying a Xolotl registry among other UPnP devices
+pub const SEARCH_TARGET: &str = "urn:xolotl:service:registry:1";
+const MAX_AGE: u64 = 1800;
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(300);
+
+fn group() -> SocketAddr {
+    SocketAddr::from((SSDP_GROUP, SSDP_PORT))
+}
+
+/// Splits an SSDP message into its start line and headers, with header names lowercased
+fn parse_message(message: &str) -> Option<(&str, HashMap<String, &str>)> {
+    let mut lines = message.split("\r\n");
+    let start = lines.next()?;
+    let headers = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+    Some((start, headers))
+}
+
+/// Builds the unicast answer to an `M-SEARCH` looking for a Xolotl registry,
+/// or `None` when the message is anything else
+pub fn search_response(message: &str, location: &str, usn: &str) -> Option<String> {
+    let (start, headers) = parse_message(message)?;
+    if !start.starts_with("M-SEARCH ") || headers.get("man") != Some(&"\"ssdp:discover\"") {
+        return None;
+    }
+    let target = *headers.get("st")?;
+    if target != SEARCH_TARGET && target != "ssdp:all" {
+        return None;
+    }
+
+    Some(format!(
+        "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age={}\r\nEXT:\r\nLOCATION: {}\r\nSERVER: xolotl/{}\r\nST: {}\r\nUSN: {}::{}\r\n\r\n",
+        MAX_AGE,
+        location,
+        env!("CARGO_PKG_VERSION"),
+        SEARCH_TARGET,
+        usn,
+        SEARCH_TARGET
+    ))
+}
+
+fn notify_alive(location: &str, usn: &str) -> String {
+    format!(
+        "NOTIFY * HTTP/1.1\r\nHOST: {}\r\nCACHE-CONTROL: max-age={}\r\nLOCATION: {}\r\nNT: {}\r\nNTS: ssdp:alive\r\nSERVER: xolotl/{}\r\nUSN: {}::{}\r\n\r\n",
+        group(),
+        MAX_AGE,
+        location,
+        SEARCH_TARGET,
+        env!("CARGO_PKG_VERSION"),
+        usn,
+        SEARCH_TARGET
+    )
+}
+
+fn search_request(wait: Duration) -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {}\r\n\r\n",
+        group(),
+        wait.as_secs().clamp(1, 5),
+        SEARCH_TARGET
+    )
+}
+
+/// Reads the API location out of a search response or alive notification for a registry
+pub fn registry_location(message: &str) -> Option<String> {
+    let (_, headers) = parse_message(message)?;
+    let target = headers.get("st").or_else(|| headers.get("nt"))?;
+    if *target != SEARCH_TARGET || headers.get("nts").is_some_and(|nts| *nts != "ssdp:alive") {
+        return None;
+    }
+    headers.get("location").map(|location| location.to_string())
+}
+
+/// Address of the interface this host uses to reach `peer`, which is what a
+/// server listening on every interface should advertise to it
+fn local_ip_towards(peer: SocketAddr) -> Option<IpAddr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect(peer).ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// API location of a server bound to `address` and `port` as seen from `peer`
+pub fn location_for(address: &str, port: u16, peer: SocketAddr) -> String {
+    let host = match address.parse::<IpAddr>() {
+        Ok(ip) if !ip.is_unspecified() => ip,
+        _ => local_ip_towards(peer).unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    };
+    format!("http://{}", SocketAddr::new(host, port))
+}
+
+/// Joins the SSDP multicast group, sharing the port with other UPnP stacks on the host
+fn bind_multicast() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, SSDP_PORT)).into())?;
+    socket.join_multicast_v4(&SSDP_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Announces the API of the server bound to `address` and `port` over SSDP and
+/// answers searches for it for the lifetime of the process
+pub fn spawn_ssdp(address: String, port: u16) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    let socket = bind_multicast()?;
+    let usn = format!("uuid:{}", uuid::Uuid::new_v4());
+
+    Ok(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(NOTIFY_INTERVAL);
+        let mut buffer = [0u8; 2048];
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let notify = notify_alive(&location_for(&address, port, group()), &usn);
+                    if let Err(e) = socket.send_to(notify.as_bytes(), group()).await {
+                        eprintln!("Failed to send SSDP announcement: {}", e);
+                    }
+                }
+                received = socket.recv_from(&mut buffer) => {
+                    let Ok((length, source)) = received else {
+                        continue;
+                    };
+                    let message = String::from_utf8_lossy(&buffer[..length]);
+                    let location = location_for(&address, port, source);
+                    let Some(response) = search_response(&message, &location, &usn) else {
+                        continue;
+                    };
+                    if let Err(e) = socket.send_to(response.as_bytes(), source).await {
+                        eprintln!("Failed to answer SSDP search from {}: {}", source, e);
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Searches the local network for Xolotl registries, returning the API URL of
+/// every one that answered within `timeout`
+pub async fn discover(timeout: Duration) -> std::io::Result<Vec<String>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket
+        .send_to(search_request(timeout).as_bytes(), group())
+        .await?;
+
+    let mut locations = Vec::new();
+    let mut buffer = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + timeout;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+    {
+        let (length, _) = received?;
+        if let Some(location) = registry_location(&String::from_utf8_lossy(&buffer[..length]))
+            && !locations.contains(&location)
+        {
+            locations.push(location);
+        }
+    }
+    Ok(locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_response() {
+        let search = search_request(Duration::from_secs(3));
+
+        let response = search_response(&search, "http://10.0.0.5:8000", "uuid:abc").unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(&format!("USN: uuid:abc::{}\r\n", SEARCH_TARGET)));
+        assert_eq!(
+            registry_location(&response).as_deref(),
+            Some("http://10.0.0.5:8000")
+        );
+
+        let all = search.replace(SEARCH_TARGET, "ssdp:all");
+        assert!(search_response(&all, "http://10.0.0.5:8000", "uuid:abc").is_some());
+    }
+
+    #[test]
+    fn test_ignores_other_messages() {
+        let other =
+            search_request(Duration::from_secs(1)).replace(SEARCH_TARGET, "upnp:rootdevice");
+        assert!(search_response(&other, "http://x", "uuid:abc").is_none());
+
+        let notify = notify_alive("http://10.0.0.5:8000", "uuid:abc");
+        assert!(search_response(&notify, "http://x", "uuid:abc").is_none());
+        assert_eq!(
+            registry_location(&notify).as_deref(),
+            Some("http://10.0.0.5:8000")
+        );
+        let byebye = notify.replace("ssdp:alive", "ssdp:byebye");
+        assert!(registry_location(&byebye).is_none());
+        assert!(registry_location(&other).is_none());
+    }
+
+    #[test]
+    fn test_location_for() {
+        let peer = SocketAddr::from(([127, 0, 0, 1], 40000));
+
+        assert_eq!(location_for("10.0.0.5", 8000, peer), "http://10.0.0.5:8000");
+        assert_eq!(location_for("0.0.0.0", 8000, peer), "http://127.0.0.1:8000");
+    }
+}
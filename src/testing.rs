@@ -0,0 +1,215 @@
+//! In-process Xolotl server for integration tests of crates talking to the registry.
+//!
+//! ```ignore
+//! let server = xolotl::testing::TestServer::start().await;
+//! server.register("payments", "prod", "http://localhost:8080", &[("team", "billing")]).await;
+//! let instances = server.client().resolve("payments", "prod").await?;
+//! ```
+//!
+//! A server started with `TestServer::simulated` runs on a virtual clock, so
+//! heartbeat expiry and reaping can be tested without waiting:
+//!
+//! ```ignore
+//! let server = xolotl::testing::TestServer::simulated().await;
+//! server.register("payments", "prod", "http://localhost:8080", &[]).await;
+//! server.clock().unwrap().advance(Duration::from_secs(90));
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::api::AppState;
+use crate::client::XolotlClient;
+use crate::create_app;
+use crate::model::clock::VirtualClock;
+use crate::model::service_registry::{HealthPolicy, ServiceEntry, ServiceRegistry};
+use crate::registry::in_memory_registry::InMemoryRegistry;
+
+/// Server bound to an ephemeral local port, stopped when dropped
+pub struct TestServer {
+    url: String,
+    state: AppState,
+    clock: Option<VirtualClock>,
+    handle: JoinHandle<()>,
+}
+
+impl TestServer {
+    /// Starts a server with an empty registry, the default health policy and no admin token
+    pub async fn start() -> Self {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        Self::with_state(AppState::new(registry, HealthPolicy::default(), None)).await
+    }
+
+    /// Starts a server whose registry and handlers read the time from a virtual
+    /// clock, which only moves through `clock().advance(..)`
+    pub async fn simulated() -> Self {
+        let clock = VirtualClock::starting_now();
+        let registry = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let state = AppState::new(registry, HealthPolicy::default(), None)
+            .with_clock(Arc::new(clock.clone()));
+
+        let mut server = Self::with_state(state).await;
+        server.clock = Some(clock);
+        server
+    }
+
+    /// Starts a server around a prepared state, e.g. with an admin token or tag schema
+    pub async fn with_state(state: AppState) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind test server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = create_app(state.clone());
+        let handle = tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        TestServer {
+            url,
+            state,
+            clock: None,
+            handle,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    /// Virtual clock of a simulated server
+    pub fn clock(&self) -> Option<&VirtualClock> {
+        self.clock.as_ref()
+    }
+
+    /// Moves the clock of a simulated server forward
+    pub fn advance(&self, by: Duration) {
+        self.clock
+            .as_ref()
+            .expect("Only simulated servers can advance time")
+            .advance(by);
+    }
+
+    /// Registry behind the server, for arranging state the API cannot express
+    pub fn registry(&self) -> &Arc<RwLock<dyn ServiceRegistry>> {
+        &self.state.registry
+    }
+
+    /// Client pointed at the server, using its admin token when one is set
+    pub fn client(&self) -> XolotlClient {
+        XolotlClient::new(
+            &self.url,
+            self.state.admin_token.0.as_deref().map(String::from),
+        )
+    }
+
+    /// Registers an instance directly, bypassing validation
+    pub async fn register(
+        &self,
+        service_name: &str,
+        environment: &str,
+        address: &str,
+        tags: &[(&str, &str)],
+    ) -> ServiceEntry {
+        let entry = ServiceEntry::new(
+            service_name.parse().expect("Invalid service name"),
+            environment.parse().expect("Invalid environment"),
+            address.to_string(),
+            tags.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+            self.state.clock.now(),
+        );
+        self.registry()
+            .write()
+            .await
+            .register(entry.clone())
+            .expect("Failed to register test instance");
+        entry
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_server_serves_arranged_state() {
+        let server = TestServer::start().await;
+        let entry = server
+            .register(
+                "payments",
+                "prod",
+                "http://localhost:8080",
+                &[("team", "billing")],
+            )
+            .await;
+
+        let instances = server.client().resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["id"], entry.id.to_string());
+        assert_eq!(instances[0]["tags"]["team"], "billing");
+    }
+
+    #[tokio::test]
+    async fn test_simulated_server_expires_instances() {
+        let server = TestServer::simulated().await;
+        server
+            .register("payments", "prod", "http://localhost:8080", &[])
+            .await;
+        let client = server.client();
+
+        client.heartbeat("payments", "prod").await.unwrap();
+        server.advance(Duration::from_secs(30));
+        let instances = client.resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["health"], "Stale");
+
+        server.advance(Duration::from_secs(60));
+        let instances = client.resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["health"], "Unhealthy");
+
+        client.heartbeat("payments", "prod").await.unwrap();
+        let instances = client.resolve("payments", "prod").await.unwrap();
+        assert_eq!(instances[0]["health"], "Healthy");
+    }
+
+    #[tokio::test]
+    async fn test_client_uses_admin_token() {
+        let registry = Arc::new(RwLock::new(InMemoryRegistry::new()));
+        let state = AppState::new(
+            registry,
+            HealthPolicy::default(),
+            Some("secret".to_string()),
+        );
+        let server = TestServer::with_state(state).await;
+        server
+            .register("payments", "prod", "http://localhost:8080", &[])
+            .await;
+
+        server
+            .client()
+            .deregister("payments", Some("prod"))
+            .await
+            .unwrap();
+        assert!(server.registry().read().await.list().is_empty());
+    }
+}
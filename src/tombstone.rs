@@ -0,0 +1,130 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::service_registry::ServiceRegistry;
+use crate::model::stale_report::{StaleService, find_stale};
+use crate::registry::profile_store::ProfileStore;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Removes every service and environment whose instances have all been silent
+/// for at least `older_than` millis at time `at`, returning what was removed.
+/// Services whose profile sets `never_reap` are kept
+pub async fn tombstone_stale(
+    registry: &Arc<RwLock<dyn ServiceRegistry>>,
+    profiles: &Arc<RwLock<ProfileStore>>,
+    older_than: u64,
+    at: u64,
+) -> Vec<StaleService> {
+    let profiles = profiles.read().await;
+    let mut registry = registry.write().await;
+    let stale: Vec<StaleService> = find_stale(&registry.list(), older_than, at)
+        .into_iter()
+        .filter(|service| !profiles.gc(&service.service_name).never_reap)
+        .collect();
+
+    for service in &stale {
+        if let Err(e) = registry.tombstone(&service.service_name, &service.environment) {
+            eprintln!(
+                "Failed to tombstone service {} in {}: {:?}",
+                service.service_name, service.environment, e
+            );
+        }
+    }
+
+    stale
+}
+
+/// Runs `tombstone_stale` periodically for the lifetime of the process
+pub fn spawn_tombstoning(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    profiles: Arc<RwLock<ProfileStore>>,
+    clock: SharedClock,
+    older_than: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for service in tombstone_stale(&registry, &profiles, older_than, clock.now()).await {
+                println!(
+                    "Tombstoned service {} in {}",
+                    service.service_name, service.environment
+                );
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::{Clock, VirtualClock};
+    use crate::model::gc_policy::GcPolicy;
+    use crate::model::registry_event::RegistryEventKind;
+    use crate::model::service_profile::ServiceProfile;
+    use crate::model::service_registry::ServiceEntry;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_tombstone_stale() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry: Arc<RwLock<dyn ServiceRegistry>> = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+
+        let profiles = Arc::new(RwLock::new(ProfileStore::new()));
+        profiles.write().await.put(
+            "pinned".parse().unwrap(),
+            ServiceProfile {
+                gc: GcPolicy {
+                    never_reap: true,
+                    ..GcPolicy::default()
+                },
+                ..ServiceProfile::default()
+            },
+        );
+
+        for name in ["dead", "alive", "pinned"] {
+            let entry = ServiceEntry::new(
+                name.parse().unwrap(),
+                "prod".parse().unwrap(),
+                "http://localhost:8080".to_string(),
+                HashMap::new(),
+                clock.now(),
+            );
+            registry.write().await.register(entry).unwrap();
+        }
+        clock.advance(Duration::from_secs(60));
+        registry
+            .write()
+            .await
+            .heartbeat(&"alive".parse().unwrap(), &"prod".parse().unwrap())
+            .unwrap();
+
+        assert!(
+            tombstone_stale(&registry, &profiles, 60_001, clock.now())
+                .await
+                .is_empty()
+        );
+        let removed = tombstone_stale(&registry, &profiles, 30_000, clock.now()).await;
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].service_name, "dead");
+        let mut remaining: Vec<_> = registry
+            .read()
+            .await
+            .list()
+            .into_iter()
+            .map(|entry| entry.service_name)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, ["alive", "pinned"]);
+        let last = registry.read().await.events(0).pop().unwrap();
+        assert_eq!(last.kind, RegistryEventKind::Tombstoned);
+    }
+}
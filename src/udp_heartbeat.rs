@@ -0,0 +1,173 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use ring::hmac;
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+
+use crate::model::clock::SharedClock;
+use crate::model::identifiers::{InstanceId, InvalidIdentifier};
+use crate::model::service_registry::ServiceRegistry;
+
+/// How far, in millis, a datagram's timestamp may be from the server's time,
+/// which bounds how long a captured datagram can be replayed
+const MAX_SKEW: u64 = 30_000;
+
+fn sign(key: &[u8], message: &str) -> String {
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), message.as_bytes());
+    tag.as_ref()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// Builds the heartbeat datagram for instance `id` sent at time `at`:
+/// `<id> <at> <hex HMAC-SHA256 of "<id> <at>">`
+pub fn datagram(id: &InstanceId, key: &[u8], at: u64) -> String {
+    let message = format!("{} {}", id, at);
+    let signature = sign(key, &message);
+    format!("{} {}", message, signature)
+}
+
+/// Checks a heartbeat datagram received at time `at`, returning the instance it is for
+pub fn verify(datagram: &str, key: &[u8], at: u64) -> Result<InstanceId, String> {
+    let (message, signature) = datagram
+        .trim_end()
+        .rsplit_once(' ')
+        .ok_or("Malformed heartbeat datagram")?;
+    let (id, sent_at) = message
+        .split_once(' ')
+        .ok_or("Malformed heartbeat datagram")?;
+    let sent_at: u64 = sent_at.parse().map_err(|_| "Invalid heartbeat timestamp")?;
+
+    let signature: Vec<u8> = (0..signature.len())
+        .step_by(2)
+        .map(|i| {
+            signature
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<_>>()
+        .ok_or("Invalid heartbeat signature")?;
+    hmac::verify(
+        &hmac::Key::new(hmac::HMAC_SHA256, key),
+        message.as_bytes(),
+        &signature,
+    )
+    .map_err(|_| "Invalid heartbeat signature")?;
+
+    if sent_at.abs_diff(at) > MAX_SKEW {
+        return Err(format!(
+            "Heartbeat sent at {} is too far from {}",
+            sent_at, at
+        ));
+    }
+    id.parse().map_err(|e: InvalidIdentifier| e.to_string())
+}
+
+/// Accepts signed heartbeat datagrams on `address` for the lifetime of the process
+pub async fn spawn_udp_heartbeats(
+    registry: Arc<RwLock<dyn ServiceRegistry>>,
+    clock: SharedClock,
+    address: SocketAddr,
+    key: String,
+) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    let socket = UdpSocket::bind(address).await?;
+
+    Ok(tokio::spawn(async move {
+        let mut buffer = [0u8; 512];
+        loop {
+            let Ok((length, source)) = socket.recv_from(&mut buffer).await else {
+                continue;
+            };
+            let datagram = String::from_utf8_lossy(&buffer[..length]);
+            let id = match verify(&datagram, key.as_bytes(), clock.now()) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("Rejected heartbeat datagram from {}: {}", source, e);
+                    continue;
+                }
+            };
+            if let Err(e) = registry.write().await.heartbeat_instance(&id) {
+                eprintln!("Failed to record heartbeat of instance {}: {:?}", id, e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::clock::VirtualClock;
+    use crate::model::service_registry::ServiceEntry;
+    use crate::registry::in_memory_registry::InMemoryRegistry;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    const KEY: &[u8] = b"fleet-secret";
+
+    #[test]
+    fn test_verify() {
+        let id: InstanceId = "b7c1a3e2".parse().unwrap();
+        let signed = datagram(&id, KEY, 1_000_000);
+
+        assert_eq!(verify(&signed, KEY, 1_010_000), Ok(id.clone()));
+        assert!(verify(&signed, b"other-secret", 1_000_000).is_err());
+        assert!(
+            verify(&signed, KEY, 1_031_000)
+                .unwrap_err()
+                .contains("too far")
+        );
+
+        let forged = signed.replacen("b7c1a3e2", "aaaaaaaa", 1);
+        assert!(verify(&forged, KEY, 1_000_000).is_err());
+        assert!(verify("b7c1a3e2", KEY, 1_000_000).is_err());
+        assert!(verify("b7c1a3e2 1000000 zz", KEY, 1_000_000).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_datagram_records_heartbeat() {
+        let clock = VirtualClock::new(1_000_000);
+        let registry: Arc<RwLock<dyn ServiceRegistry>> = Arc::new(RwLock::new(
+            InMemoryRegistry::new().with_clock(Arc::new(clock.clone())),
+        ));
+        let entry = ServiceEntry::new(
+            "worker".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            1_000_000,
+        );
+        registry.write().await.register(entry.clone()).unwrap();
+
+        let probe = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = probe.local_addr().unwrap();
+        drop(probe);
+        spawn_udp_heartbeats(
+            registry.clone(),
+            Arc::new(clock.clone()),
+            address,
+            "fleet-secret".to_string(),
+        )
+        .await
+        .unwrap();
+
+        clock.advance(Duration::from_secs(20));
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        sender
+            .send_to(datagram(&entry.id, KEY, 1_020_000).as_bytes(), address)
+            .await
+            .unwrap();
+
+        for _ in 0..50 {
+            if registry.read().await.list()[0].last_heartbeat == 1_020_000 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Heartbeat datagram was not recorded");
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::model::registry_hook::RegistryHook;
+use crate::model::service_address::ServiceAddress;
+use crate::model::service_registry::{RegistryError, ServiceEntry};
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Instructions a policy may run for a single decision, so a runaway module
+/// fails the registration instead of hanging the registry
+const FUEL_PER_DECISION: u64 = 10_000_000;
+
+/// Linear memory a policy may grow to, so a module cannot exhaust the registry's memory
+const MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Largest decision a policy may answer, read only once it fits in the module's memory
+const MAX_DECISION_BYTES: usize = 64 * 1024;
+
+/// What a policy module is handed for every registration
+#[derive(Serialize)]
+struct AdmissionRequest<'a> {
+    service_name: &'a str,
+    environment: &'a str,
+    address: &'a str,
+    tags: &'a HashMap<String, String>,
+}
+
+/// What a policy module answers
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+enum Decision {
+    Allow,
+    Deny {
+        reason: String,
+    },
+    /// Admits the registration after merging `tags` into its tags and replacing its address
+    Mutate {
+        #[serde(default)]
+        tags: HashMap<String, String>,
+        address: Option<String>,
+    },
+}
+
+/// Admission control by a WebAssembly module, reloaded whenever its file changes.
+///
+/// The module exports its `memory`, `alloc(len: i32) -> i32` returning where to
+/// write `len` bytes, and `admit(ptr: i32, len: i32) -> i64`, which reads the
+/// registration as JSON and returns the location of its JSON decision packed
+/// as `ptr << 32 | len`.
+#[derive(Clone)]
+pub struct WasmPolicy {
+    path: PathBuf,
+    engine: Engine,
+    module: Arc<RwLock<Module>>,
+    modified: Arc<Mutex<Option<SystemTime>>>,
+}
+
+impl WasmPolicy {
+    /// Compiles the module at `path`, either binary WebAssembly or its text format
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| e.to_string())?;
+        let module = compile(&engine, path)?;
+        Ok(WasmPolicy {
+            path: path.to_path_buf(),
+            engine,
+            module: Arc::new(RwLock::new(module)),
+            modified: Arc::new(Mutex::new(modified_at(path))),
+        })
+    }
+
+    /// Recompiles the module when its file changed since it was last loaded,
+    /// keeping the previous one when the new one does not compile
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let modified = modified_at(&self.path);
+        let mut last = self.modified.lock().unwrap();
+        if modified == *last {
+            return Ok(false);
+        }
+        *last = modified;
+        let module = compile(&self.engine, &self.path)?;
+        *self.module.write().unwrap() = module;
+        Ok(true)
+    }
+
+    fn decide(&self, request: &AdmissionRequest) -> Result<Decision, String> {
+        let module = self.module.read().unwrap().clone();
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store
+            .set_fuel(FUEL_PER_DECISION)
+            .map_err(|e| e.to_string())?;
+        let instance = Instance::new(&mut store, &module, &[]).map_err(|e| e.to_string())?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or("the module does not export its memory")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(|e| e.to_string())?;
+        let admit = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "admit")
+            .map_err(|e| e.to_string())?;
+
+        let input = serde_json::to_vec(request).map_err(|e| e.to_string())?;
+        let len = i32::try_from(input.len()).map_err(|e| e.to_string())?;
+        let ptr = alloc.call(&mut store, len).map_err(|e| e.to_string())?;
+        memory
+            .write(&mut store, ptr as u32 as usize, &input)
+            .map_err(|e| e.to_string())?;
+        let packed = admit
+            .call(&mut store, (ptr, len))
+            .map_err(|e| e.to_string())?;
+
+        let (offset, len) = ((packed >> 32) as u32 as usize, packed as u32 as usize);
+        if len > MAX_DECISION_BYTES {
+            return Err(format!(
+                "the decision is {} bytes, past the {} allowed",
+                len, MAX_DECISION_BYTES
+            ));
+        }
+        if offset + len > memory.data_size(&store) {
+            return Err("the decision lies outside the module's memory".to_string());
+        }
+        let mut output = vec![0; len];
+        memory
+            .read(&store, offset, &mut output)
+            .map_err(|e| e.to_string())?;
+        serde_json::from_slice(&output).map_err(|e| format!("invalid decision: {}", e))
+    }
+}
+
+fn compile(engine: &Engine, path: &Path) -> Result<Module, String> {
+    Module::from_file(engine, path)
+        .map_err(|e| format!("Failed to compile policy {}: {:#}", path.display(), e))
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl RegistryHook for WasmPolicy {
+    fn pre_register(&self, entry: &mut ServiceEntry) -> Result<(), RegistryError> {
+        let request = AdmissionRequest {
+            service_name: entry.service_name.as_str(),
+            environment: entry.environment.as_str(),
+            address: entry.address_str(),
+            tags: &entry.tags,
+        };
+        // A broken policy refuses registrations rather than letting everything through
+        let decision = self
+            .decide(&request)
+            .map_err(|e| RegistryError::InternalError(format!("Admission policy failed: {}", e)))?;
+        match decision {
+            Decision::Allow => Ok(()),
+            Decision::Deny { reason } => Err(RegistryError::Validation(format!(
+                "Denied by admission policy: {}",
+                reason
+            ))),
+            Decision::Mutate { tags, address } => {
+                entry.tags.extend(tags);
+                if let Some(address) = address {
+                    entry.address = ServiceAddress::from_string(address);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reloads the policy whenever its file changes for the lifetime of the process
+pub fn spawn_policy_reload(policy: WasmPolicy) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(RELOAD_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match policy.reload_if_changed() {
+                Ok(true) => println!("Reloaded admission policy {}", policy.path.display()),
+                Ok(false) => {}
+                Err(e) => eprintln!("{}, keeping the previous one", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module answering `decision` to every registration
+    fn answering(decision: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{}")
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "admit") (param i32 i32) (result i64) (i64.const {})))"#,
+            decision.replace('"', "\\\""),
+            decision.len()
+        )
+    }
+
+    fn write_policy(path: &Path, module: &str) {
+        std::fs::write(path, module).unwrap();
+    }
+
+    fn entry() -> ServiceEntry {
+        ServiceEntry::new(
+            "checkout".parse().unwrap(),
+            "prod".parse().unwrap(),
+            "http://10.0.0.1:8080".to_string(),
+            HashMap::new(),
+            1_000,
+        )
+    }
+
+    #[test]
+    fn test_decisions_and_reload() {
+        let path = std::env::temp_dir().join(format!("xolotl-policy-{}.wat", uuid::Uuid::new_v4()));
+        write_policy(
+            &path,
+            &answering(r#"{"decision":"mutate","tags":{"team":"payments"}}"#),
+        );
+        let policy = WasmPolicy::load(&path).unwrap();
+
+        let mut admitted = entry();
+        policy.pre_register(&mut admitted).unwrap();
+        assert_eq!(admitted.tags["team"], "payments");
+
+        write_policy(
+            &path,
+            &answering(r#"{"decision":"deny","reason":"prod is frozen"}"#),
+        );
+        // Make sure the new file is seen as changed on coarse grained filesystems
+        *policy.modified.lock().unwrap() = None;
+        assert!(policy.reload_if_changed().unwrap());
+        assert!(matches!(
+            policy.pre_register(&mut entry()),
+            Err(RegistryError::Validation(reason)) if reason.contains("prod is frozen")
+        ));
+
+        write_policy(&path, "(module");
+        *policy.modified.lock().unwrap() = None;
+        assert!(policy.reload_if_changed().is_err());
+        assert!(policy.pre_register(&mut entry()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_runaway_policy_fails_closed() {
+        let path = std::env::temp_dir().join(format!("xolotl-policy-{}.wat", uuid::Uuid::new_v4()));
+        write_policy(
+            &path,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "admit") (param i32 i32) (result i64) (loop (br 0)) (i64.const 0)))"#,
+        );
+        let policy = WasmPolicy::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            policy.pre_register(&mut entry()),
+            Err(RegistryError::InternalError(_))
+        ));
+    }
+
+    #[test]
+    fn test_oversized_decisions_and_memory_fail_closed() {
+        for module in [
+            // Claims a 4 GiB decision
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "admit") (param i32 i32) (result i64) (i64.const 0xffffffff)))"#,
+            // Claims a decision past the end of its memory
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "admit") (param i32 i32) (result i64) (i64.const 0xfff000000100)))"#,
+            // Grows its memory past the limit
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "admit") (param i32 i32) (result i64)
+                    (if (i32.eq (memory.grow (i32.const 1024)) (i32.const -1))
+                        (then unreachable))
+                    (i64.const 0)))"#,
+        ] {
+            let path =
+                std::env::temp_dir().join(format!("xolotl-policy-{}.wat", uuid::Uuid::new_v4()));
+            write_policy(&path, module);
+            let policy = WasmPolicy::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(matches!(
+                policy.pre_register(&mut entry()),
+                Err(RegistryError::InternalError(_))
+            ));
+        }
+    }
+}
@@ -25,3 +25,49 @@ fn test_binary_version() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("xolotl 0.1.0"));
 }
+
+#[test]
+fn test_binary_client_subcommands() {
+    let output = Command::new("cargo")
+        .args(["run", "--bin", "xolotl", "--", "--help"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for subcommand in [
+        "server",
+        "register",
+        "resolve",
+        "list",
+        "deregister",
+        "heartbeat",
+        "watch",
+    ] {
+        assert!(
+            stdout.contains(subcommand),
+            "Missing {} subcommand",
+            subcommand
+        );
+    }
+}
+
+#[test]
+fn test_binary_client_unreachable_server() {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--bin",
+            "xolotl",
+            "--",
+            "list",
+            "--url",
+            "http://127.0.0.1:1",
+        ])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Request to Xolotl failed"));
+}
@@ -0,0 +1,213 @@
+const REFRESH_INTERVAL_MS = 5000;
+const MAX_EVENTS = 25;
+
+const tokenInput = document.getElementById("admin-token");
+const serviceFilter = document.getElementById("filter-service");
+const environmentFilter = document.getElementById("filter-environment");
+const statusLine = document.getElementById("status");
+
+let instances = [];
+
+tokenInput.value = localStorage.getItem("xolotl-admin-token") || "";
+tokenInput.addEventListener("change", () => {
+  localStorage.setItem("xolotl-admin-token", tokenInput.value);
+});
+serviceFilter.addEventListener("input", renderInstances);
+environmentFilter.addEventListener("change", renderInstances);
+
+function authHeaders() {
+  return tokenInput.value ? { Authorization: `Bearer ${tokenInput.value}` } : {};
+}
+
+function formatAge(millis) {
+  const seconds = Math.max(0, Math.floor(millis / 1000));
+  if (seconds < 60) return `${seconds}s`;
+  if (seconds < 3600) return `${Math.floor(seconds / 60)}m ${seconds % 60}s`;
+  return `${Math.floor(seconds / 3600)}h ${Math.floor((seconds % 3600) / 60)}m`;
+}
+
+function cell(row, content) {
+  const td = document.createElement("td");
+  if (content instanceof Node) {
+    td.appendChild(content);
+  } else {
+    td.textContent = content;
+  }
+  row.appendChild(td);
+  return td;
+}
+
+function renderEnvironments() {
+  const selected = environmentFilter.value;
+  const environments = [...new Set(instances.map((i) => i.environment))].sort();
+  environmentFilter.replaceChildren(new Option("All environments", ""));
+  for (const environment of environments) {
+    environmentFilter.appendChild(new Option(environment, environment));
+  }
+  environmentFilter.value = environments.includes(selected) ? selected : "";
+}
+
+function renderInstances() {
+  const tbody = document.getElementById("instances");
+  const service = serviceFilter.value.trim();
+  const environment = environmentFilter.value;
+
+  const visible = instances
+    .filter((i) => !service || i.service_name.includes(service))
+    .filter((i) => !environment || i.environment === environment)
+    .sort((a, b) =>
+      a.service_name.localeCompare(b.service_name) || a.environment.localeCompare(b.environment));
+
+  tbody.replaceChildren();
+  for (const instance of visible) {
+    const row = document.createElement("tr");
+    cell(row, instance.service_name);
+    cell(row, instance.environment);
+    cell(row, instance.address);
+    cell(row, instance.health).className = `health health-${instance.health}`;
+    cell(row, instance.state);
+    cell(row, formatAge(Date.now() - instance.last_heartbeat));
+
+    const tags = document.createElement("div");
+    for (const [key, value] of Object.entries(instance.tags)) {
+      const tag = document.createElement("span");
+      tag.className = "tag";
+      tag.textContent = `${key}=${value}`;
+      tags.appendChild(tag);
+    }
+    cell(row, tags);
+
+    const annotations = instance.annotations || [];
+    const latest = annotations[annotations.length - 1];
+    const notes = cell(row, latest ? latest.note : "");
+    notes.className = "notes";
+    notes.title = annotations
+      .map((a) => `${new Date(a.created_at).toLocaleString()}${a.author ? ` ${a.author}` : ""}: ${a.note}`)
+      .join("\n");
+
+    const actions = document.createElement("div");
+    const maintenance = document.createElement("button");
+    const next = instance.state === "Maintenance" ? "Up" : "Maintenance";
+    maintenance.textContent = next === "Up" ? "Resume" : "Maintenance";
+    maintenance.addEventListener("click", () => setState(instance, next));
+    actions.appendChild(maintenance);
+
+    const annotate = document.createElement("button");
+    annotate.textContent = "Annotate";
+    annotate.addEventListener("click", () => addAnnotation(instance));
+    actions.appendChild(annotate);
+
+    const button = document.createElement("button");
+    button.textContent = "Deregister";
+    button.addEventListener("click", () => deregister(instance));
+    actions.appendChild(button);
+    cell(row, actions);
+
+    tbody.appendChild(row);
+  }
+}
+
+async function deregister(instance) {
+  if (!confirm(`Deregister ${instance.service_name} (${instance.environment}) at ${instance.address}?`)) {
+    return;
+  }
+  const response = await fetch(`/services/instances/${encodeURIComponent(instance.id)}`, {
+    method: "DELETE",
+    headers: authHeaders(),
+  });
+  if (response.status === 401) {
+    statusLine.textContent = "A valid admin token is required for this action";
+    return;
+  }
+  await refresh();
+}
+
+async function setState(instance, state) {
+  const response = await fetch(`/services/instances/${encodeURIComponent(instance.id)}/state`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json", ...authHeaders() },
+    body: JSON.stringify({ state }),
+  });
+  if (response.status === 401) {
+    statusLine.textContent = "A valid admin token is required for this action";
+    return;
+  }
+  if (!response.ok) {
+    const error = await response.json();
+    statusLine.textContent = error.message;
+    return;
+  }
+  await refresh();
+}
+
+async function addAnnotation(instance) {
+  const note = prompt(`Note for ${instance.service_name} (${instance.environment}) at ${instance.address}`);
+  if (!note) {
+    return;
+  }
+  const response = await fetch(`/services/instances/${encodeURIComponent(instance.id)}/annotations`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json", ...authHeaders() },
+    body: JSON.stringify({ note }),
+  });
+  if (response.status === 401) {
+    statusLine.textContent = "A valid admin or caller token is required for this action";
+    return;
+  }
+  if (!response.ok) {
+    const error = await response.json();
+    statusLine.textContent = error.message;
+    return;
+  }
+  await refresh();
+}
+
+async function refreshEvents() {
+  const response = await fetch("/events");
+  const events = await response.json();
+  const list = document.getElementById("events");
+  list.replaceChildren();
+  for (const event of events.slice(-MAX_EVENTS).reverse()) {
+    const item = document.createElement("li");
+    item.value = event.index;
+    const time = new Date(event.timestamp).toLocaleTimeString();
+    item.textContent =
+      `${time} ${event.kind} ${event.service_name}/${event.environment} (${event.instance_id})`;
+    list.appendChild(item);
+  }
+}
+
+async function refreshExpectations() {
+  const response = await fetch("/expectations");
+  const expectations = await response.json();
+  const list = document.getElementById("expectations");
+  document.getElementById("expectations-section").hidden = expectations.length === 0;
+  list.replaceChildren();
+  for (const expectation of expectations) {
+    const item = document.createElement("li");
+    const scope = expectation.environment || "any environment";
+    const seen = expectation.last_healthy_at
+      ? `healthy ${formatAge(Date.now() - expectation.last_healthy_at)} ago`
+      : "never seen healthy";
+    item.textContent = `${expectation.service_name} in ${scope}: ${seen}`;
+    item.className = expectation.violated ? "health health-Unhealthy" : "health health-Healthy";
+    list.appendChild(item);
+  }
+}
+
+async function refresh() {
+  try {
+    const response = await fetch("/services");
+    instances = await response.json();
+    statusLine.textContent = "";
+    renderEnvironments();
+    renderInstances();
+    await refreshExpectations();
+    await refreshEvents();
+  } catch (error) {
+    statusLine.textContent = `Failed to reach the registry: ${error}`;
+  }
+}
+
+refresh();
+setInterval(refresh, REFRESH_INTERVAL_MS);